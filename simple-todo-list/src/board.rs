@@ -0,0 +1,206 @@
+use crate::color::paint;
+use crate::i18n::t;
+use crate::{json_output, print_json, track, Todo};
+use ratatui::crossterm::terminal;
+use serde_json::json;
+use std::cmp::Reverse;
+use std::env;
+
+/// The board column a todo is in.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Status {
+    Backlog,
+    InProgress,
+    Done,
+}
+
+impl Status {
+    pub fn parse(s: &str) -> Result<Status, String> {
+        match s.to_lowercase().as_str() {
+            "backlog" | "todo" => Ok(Status::Backlog),
+            "in-progress" | "doing" | "wip" => Ok(Status::InProgress),
+            "done" => Ok(Status::Done),
+            _ => Err(t!(
+                "Invalid status '{}': use backlog, in-progress or done",
+                s
+            )),
+        }
+    }
+
+    /// Open todos are in progress when marked so (`edit --status`) or while
+    /// their timer runs.
+    pub fn of(t: &Todo) -> Status {
+        if t.completed {
+            Status::Done
+        } else if t.in_progress || track::is_running(t) {
+            Status::InProgress
+        } else {
+            Status::Backlog
+        }
+    }
+
+    fn title(self) -> &'static str {
+        match self {
+            Status::Backlog => t!("Backlog"),
+            Status::InProgress => t!("In progress"),
+            Status::Done => t!("Done"),
+        }
+    }
+}
+
+/// Cards shown in the Done column, the most recently completed first.
+const DONE_SHOWN: usize = 10;
+
+/// Columns get narrower to fit the terminal, but not narrower than this.
+const MIN_WIDTH: usize = 16;
+
+struct Column<'a> {
+    name: String,
+    todos: Vec<&'a Todo>,
+    /// Cards printed at most; the rest are counted.
+    shown: usize,
+}
+
+/// Prints the todos as a board with a column per status, or with
+/// `by_tag` a column per tag of the open todos: those in `tags` in that
+/// order, or all of them and the untagged ones when `tags` is empty.
+pub fn show(todos: &[Todo], by_tag: bool, tags: &[String]) {
+    let columns = if by_tag || !tags.is_empty() {
+        tag_columns(todos, tags)
+    } else {
+        status_columns(todos)
+    };
+    if json_output() {
+        let rows: Vec<_> = columns
+            .iter()
+            .map(|c| json!({ "name": c.name, "todos": c.todos }))
+            .collect();
+        print_json(&rows);
+    } else if columns.is_empty() {
+        println!(
+            "{}",
+            t!("No open todos have tags. Add one with: edit <id> --tag <tag>")
+        );
+    } else {
+        render(&columns);
+    }
+}
+
+fn status_columns(todos: &[Todo]) -> Vec<Column<'_>> {
+    [Status::Backlog, Status::InProgress, Status::Done]
+        .into_iter()
+        .map(|status| {
+            let mut cards: Vec<&Todo> = todos.iter().filter(|t| Status::of(t) == status).collect();
+            let mut shown = cards.len();
+            if status == Status::Done {
+                cards.sort_by_key(|t| Reverse(t.completed_at));
+                shown = shown.min(DONE_SHOWN);
+            }
+            Column {
+                name: status.title().to_string(),
+                todos: cards,
+                shown,
+            }
+        })
+        .collect()
+}
+
+fn tag_columns<'a>(todos: &'a [Todo], tags: &[String]) -> Vec<Column<'a>> {
+    let open: Vec<&Todo> = todos.iter().filter(|t| !t.completed).collect();
+    let mut names: Vec<&String> = tags.iter().collect();
+    if names.is_empty() {
+        names = open.iter().flat_map(|t| &t.tags).collect();
+        names.sort();
+        names.dedup();
+    }
+    let mut columns: Vec<Column> = names
+        .into_iter()
+        .map(|tag| {
+            let cards: Vec<&Todo> = open
+                .iter()
+                .copied()
+                .filter(|t| t.tags.contains(tag))
+                .collect();
+            Column {
+                name: format!("#{}", tag),
+                shown: cards.len(),
+                todos: cards,
+            }
+        })
+        .collect();
+    let untagged: Vec<&Todo> = open.iter().copied().filter(|t| t.tags.is_empty()).collect();
+    if tags.is_empty() && !columns.is_empty() && !untagged.is_empty() {
+        columns.push(Column {
+            name: t!("No tag").to_string(),
+            shown: untagged.len(),
+            todos: untagged,
+        });
+    }
+    columns
+}
+
+/// The terminal's width, or `$COLUMNS` when not printing to one.
+fn screen_width() -> usize {
+    terminal::size()
+        .ok()
+        .map(|(w, _)| w as usize)
+        .or_else(|| env::var("COLUMNS").ok()?.parse().ok())
+        .unwrap_or(80)
+}
+
+/// `text` cut or padded to exactly `width` characters.
+fn fit(text: &str, width: usize) -> String {
+    let len = text.chars().count();
+    if len > width {
+        let cut: String = text.chars().take(width.saturating_sub(1)).collect();
+        format!("{}…", cut)
+    } else {
+        format!("{}{}", text, " ".repeat(width - len))
+    }
+}
+
+fn card(t: &Todo, width: usize) -> String {
+    let id = format!("#{}", t.id);
+    let title = fit(&t.title, width.saturating_sub(id.len() + 1));
+    format!("{} {}", paint(&id, |c| c.id), title)
+}
+
+fn render(columns: &[Column]) {
+    const GAP: &str = "  ";
+    let width = ((screen_width() + GAP.len()) / columns.len())
+        .saturating_sub(GAP.len())
+        .max(MIN_WIDTH);
+    let print = |cells: Vec<String>| println!("{}", cells.join(GAP).trim_end());
+    print(
+        columns
+            .iter()
+            .map(|c| fit(&format!("{} ({})", c.name, c.todos.len()), width))
+            .collect(),
+    );
+    print(
+        columns
+            .iter()
+            .map(|_| paint(&"─".repeat(width), |c| c.muted))
+            .collect(),
+    );
+    let rows = columns
+        .iter()
+        .map(|c| c.shown + usize::from(c.shown < c.todos.len()))
+        .max()
+        .unwrap_or(0);
+    for row in 0..rows {
+        print(
+            columns
+                .iter()
+                .map(|c| match c.todos.get(row) {
+                    Some(t) if row < c.shown => card(t, width),
+                    Some(_) if row == c.shown => {
+                        let more = format!("+{} more", c.todos.len() - c.shown);
+                        paint(&fit(&more, width), |c| c.muted)
+                    }
+                    _ => " ".repeat(width),
+                })
+                .collect(),
+        );
+    }
+}
@@ -0,0 +1,226 @@
+use crate::due::describe_due;
+use crate::i18n::t;
+use crate::{
+    complete, load_db, lock, next_id, now, position_of, remove_todo, today, tree_order, Todo,
+};
+use std::collections::BTreeMap;
+use std::env;
+use std::sync::Mutex;
+use teloxide::prelude::*;
+use teloxide::utils::command::BotCommands;
+use todo_core::{list_path, save_list};
+
+/// Messages are handled one at a time, each under the DB lock like a CLI
+/// command.
+static SERIAL: Mutex<()> = Mutex::new(());
+
+#[derive(BotCommands, Clone)]
+#[command(
+    rename_rule = "lowercase",
+    description = "Manage this chat's todo list:"
+)]
+enum BotCommand {
+    #[command(description = "show this help")]
+    Help,
+    #[command(description = "show this help")]
+    Start,
+    #[command(description = "add a todo: /add Buy milk")]
+    Add(String),
+    #[command(description = "show the open todos, or every one with /list all")]
+    List(String),
+    #[command(description = "mark a todo as done: /done 3")]
+    Done(String),
+    #[command(description = "remove a todo and its subtasks: /remove 3")]
+    Remove(String),
+}
+
+/// The list a chat's todos are kept in, e.g. `chat-123456`; group chats
+/// have negative ids, so `chat--100123456`.
+pub fn list_for(chat: ChatId) -> String {
+    format!("chat-{}", chat.0)
+}
+
+/// Answers commands sent to the bot whose token is in `TELOXIDE_TOKEN`
+/// until interrupted. With `allow`, only those chats get an answer.
+pub fn run(allow: Vec<i64>) -> Result<(), String> {
+    if env::var("TELOXIDE_TOKEN").is_err() {
+        return Err(t!("Set TELOXIDE_TOKEN to the token BotFather gave you").to_string());
+    }
+    // Ask for an encrypted DB's passphrase now rather than on a message.
+    load_db();
+    let runtime = tokio::runtime::Runtime::new().map_err(|e| e.to_string())?;
+    runtime.block_on(async move {
+        let bot = Bot::from_env();
+        let me = bot
+            .get_me()
+            .await
+            .map_err(|e| t!("Can't reach Telegram: {}", e))?;
+        println!("{}", t!("Answering as @{} (Ctrl-C to stop)", me.username()));
+        let handler = Update::filter_message()
+            .filter(move |msg: Message| {
+                let allowed = allow.is_empty() || allow.contains(&msg.chat.id.0);
+                if !allowed {
+                    eprintln!("{}", t!("Ignored a message from chat {}", msg.chat.id));
+                }
+                allowed
+            })
+            .branch(
+                dptree::entry()
+                    .filter_command::<BotCommand>()
+                    .endpoint(answer),
+            )
+            .branch(dptree::endpoint(unknown));
+        Dispatcher::builder(bot, handler)
+            .enable_ctrlc_handler()
+            .build()
+            .dispatch()
+            .await;
+        Ok(())
+    })
+}
+
+async fn answer(bot: Bot, msg: Message, cmd: BotCommand) -> ResponseResult<()> {
+    let list = list_for(msg.chat.id);
+    let reply = match cmd {
+        BotCommand::Help | BotCommand::Start => BotCommand::descriptions().to_string(),
+        cmd => tokio::task::spawn_blocking(move || {
+            let _serial = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+            let _lock = lock::exclusive()?;
+            let mut todos = todo_core::load(&list_path(&list))?;
+            handle(&list, &mut todos, cmd)
+        })
+        .await
+        .unwrap_or_else(|e| Err(e.to_string()))
+        .unwrap_or_else(|e| e),
+    };
+    bot.send_message(msg.chat.id, reply).await?;
+    Ok(())
+}
+
+async fn unknown(bot: Bot, msg: Message) -> ResponseResult<()> {
+    bot.send_message(msg.chat.id, t!("Unknown command. Send /help to see them."))
+        .await?;
+    Ok(())
+}
+
+/// Runs `cmd` on the chat's todos, saving them if it changed any.
+/// Returns the reply.
+fn handle(list: &str, todos: &mut Vec<Todo>, cmd: BotCommand) -> Result<String, String> {
+    match cmd {
+        BotCommand::Help | BotCommand::Start => Ok(BotCommand::descriptions().to_string()),
+        BotCommand::Add(title) => {
+            let title = title.trim().to_string();
+            if title.is_empty() {
+                return Err(t!("Usage: /add <title>").to_string());
+            }
+            let id = next_id(todos);
+            todos.push(Todo {
+                id,
+                title,
+                description: String::new(),
+                completed: false,
+                due: None,
+                priority: None,
+                parent: None,
+                repeat: None,
+                tags: Vec::new(),
+                contexts: Vec::new(),
+                snoozed: None,
+                created_at: Some(now()),
+                completed_at: None,
+                time: Vec::new(),
+                pomodoros: 0,
+                in_progress: false,
+                pinned: false,
+                estimate: None,
+                blocked_by: Vec::new(),
+                notes: Vec::new(),
+                meta: BTreeMap::new(),
+            });
+            save_list(list, todos)?;
+            Ok(t!("Added todo (#{})", id))
+        }
+        BotCommand::List(which) => {
+            let all = match which.trim() {
+                "" => false,
+                "all" => true,
+                _ => return Err(t!("Usage: /list or /list all").to_string()),
+            };
+            Ok(show(todos, all))
+        }
+        BotCommand::Done(id) => {
+            let id = parse_id(&id, "/done")?;
+            let idx = position_of(todos, id).ok_or_else(|| no_todo(id))?;
+            if todos[idx].completed {
+                return Ok(t!("#{} is already done.", id));
+            }
+            let title = todos[idx].title.clone();
+            let done = complete(todos, idx).map_err(|b| {
+                t!("#{} is blocked by {}; finish those first.", id, b.ids()).to_string()
+            })?;
+            save_list(list, todos)?;
+            let mut lines = vec![t!("Marked as done (#{}): {}", id, title)];
+            if let Some(n) = done.next {
+                if let Some(d) = todos[n].due {
+                    lines.push(t!("Next occurrence (#{}) due {}", todos[n].id, d));
+                }
+            }
+            if done.pending > 0 {
+                lines.push(t!("Warning: {} subtask(s) still pending.", done.pending));
+            }
+            for p in done.parents_done {
+                lines.push(t!(
+                    "All subtasks done; marked parent as done (#{}): {}",
+                    todos[p].id,
+                    todos[p].title
+                ));
+            }
+            Ok(lines.join("\n"))
+        }
+        BotCommand::Remove(id) => {
+            let id = parse_id(&id, "/remove")?;
+            let idx = position_of(todos, id).ok_or_else(|| no_todo(id))?;
+            let title = todos[idx].title.clone();
+            let gone = remove_todo(todos, id);
+            save_list(list, todos)?;
+            let mut reply = t!("Removed (#{}): {}", id, title);
+            if gone.len() > 1 {
+                reply.push('\n');
+                reply.push_str(&t!("Also removed {} subtask(s).", gone.len() - 1));
+            }
+            Ok(reply)
+        }
+    }
+}
+
+fn parse_id(s: &str, command: &str) -> Result<u64, String> {
+    s.trim()
+        .trim_start_matches('#')
+        .parse()
+        .map_err(|_| t!("Usage: {} <id>", command))
+}
+
+fn no_todo(id: u64) -> String {
+    t!("No todo with id {}. Send /list to see them.", id)
+}
+
+/// The chat's todos as text, subtasks indented under their parents.
+fn show(todos: &[Todo], all: bool) -> String {
+    let shown: Vec<&Todo> = todos.iter().filter(|t| all || !t.completed).collect();
+    if shown.is_empty() {
+        return t!("Nothing to do. Add a todo with /add <title>").to_string();
+    }
+    let today = today();
+    tree_order(&shown)
+        .into_iter()
+        .map(|(t, depth)| {
+            let mark = if t.completed { "✔" } else { "•" };
+            let mut line = format!("{}{} #{} {}", "    ".repeat(depth), mark, t.id, t.title);
+            if let Some(d) = t.due.filter(|_| !t.completed) {
+                line.push_str(&format!(" ({})", describe_due(d, today)));
+            }
+            line
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
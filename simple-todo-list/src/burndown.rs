@@ -0,0 +1,181 @@
+use crate::i18n::t;
+use crate::query::Query;
+use crate::{config, json_output, load_archive, load_db, print_json, today, Todo};
+use chrono::{Duration, NaiveDate};
+use serde_json::json;
+use std::sync::OnceLock;
+
+/// What estimates count, from the `estimate_unit` setting.
+#[derive(Clone, Copy)]
+pub enum Unit {
+    Points,
+    Hours,
+}
+
+impl Unit {
+    pub fn configured() -> Unit {
+        static UNIT: OnceLock<Unit> = OnceLock::new();
+        *UNIT.get_or_init(|| match config::get().estimate_unit.as_deref() {
+            None | Some("points") => Unit::Points,
+            Some("hours") => Unit::Hours,
+            Some(other) => {
+                eprintln!("{}", t!("Unknown estimate unit '{}', using points", other));
+                Unit::Points
+            }
+        })
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Unit::Points => "points",
+            Unit::Hours => "hours",
+        }
+    }
+
+    /// e.g. "1 point" or "2.5 hours".
+    pub fn describe(self, n: f64) -> String {
+        let n = round(n);
+        let name = self.name();
+        if n == 1.0 {
+            format!("1 {}", &name[..name.len() - 1])
+        } else {
+            format!("{} {}", n, name)
+        }
+    }
+
+    /// e.g. "3p" or "2.5h", as `list` shows estimates.
+    pub fn short(self, n: f64) -> String {
+        match self {
+            Unit::Points => format!("{}p", round(n)),
+            Unit::Hours => format!("{}h", round(n)),
+        }
+    }
+}
+
+/// Two decimals at most; adding 0 turns the -0 of an empty sum into 0.
+fn round(n: f64) -> f64 {
+    (n * 100.0).round() / 100.0 + 0.0
+}
+
+/// Estimated work still open at the end of `day`: todos added by then
+/// (or at an unknown time) and not yet done. Todos done at an unknown time
+/// count as done all along.
+pub fn remaining(todos: &[&Todo], day: NaiveDate) -> f64 {
+    todos
+        .iter()
+        .filter(|t| t.created_at.is_none_or(|c| c.date() <= day))
+        .filter(|t| !t.completed || t.completed_at.is_some_and(|c| c.date() > day))
+        .filter_map(|t| t.estimate)
+        .sum()
+}
+
+/// Charts the estimated work left at the end of each of the last `days`
+/// days, active list and archive together, and how long the rest takes at
+/// the pace of those days.
+pub fn show(days: u32, query: Option<&Query>) {
+    let todos = load_db();
+    let archived = load_archive();
+    let all: Vec<&Todo> = todos
+        .iter()
+        .chain(&archived)
+        .filter(|t| query.is_none_or(|q| q.matches(t)))
+        .collect();
+    let unit = Unit::configured();
+    let today = today();
+    let first = today - Duration::days(i64::from(days.max(1)) - 1);
+    let chart: Vec<(NaiveDate, f64)> = first
+        .iter_days()
+        .take_while(|d| *d <= today)
+        .map(|d| (d, remaining(&all, d)))
+        .collect();
+    let left = remaining(&all, today);
+    let done: f64 = all
+        .iter()
+        .filter(|t| t.completed)
+        .filter(|t| t.completed_at.is_some_and(|c| c.date() >= first))
+        .filter_map(|t| t.estimate)
+        .sum();
+    let per_day = done / chart.len() as f64;
+    let days_left = (per_day > 0.0).then(|| (left / per_day).ceil() as i64);
+    let unestimated = all
+        .iter()
+        .filter(|t| !t.completed && t.estimate.is_none())
+        .count();
+
+    if json_output() {
+        print_json(&json!({
+            "unit": unit.name(),
+            "from": first,
+            "to": today,
+            "remaining": round(left),
+            "done": round(done),
+            "per_day": round(per_day),
+            "days_left": days_left,
+            "unestimated": unestimated,
+            "days": chart
+                .iter()
+                .map(|(day, n)| json!({ "date": day, "remaining": round(*n) }))
+                .collect::<Vec<_>>(),
+        }));
+        return;
+    }
+
+    if all.iter().all(|t| t.estimate.is_none()) {
+        println!(
+            "{}",
+            t!("No todos have an estimate yet. Add one with: edit <id> --estimate 3")
+        );
+        return;
+    }
+    println!(
+        "{}",
+        t!("Work left ({}) at the end of each day:", unit.name())
+    );
+    let widest = chart.iter().map(|(_, n)| *n).fold(0.0, f64::max);
+    for (day, n) in &chart {
+        // Bars are scaled to at most 30 columns, as in `stats`.
+        let width = if widest > 0.0 {
+            (n / widest * 30.0).ceil() as usize
+        } else {
+            0
+        };
+        let line = format!(
+            "  {}  {:>6} {}",
+            day.format("%a %Y-%m-%d"),
+            round(*n),
+            "█".repeat(width)
+        );
+        println!("{}", line.trim_end());
+    }
+    println!(
+        "{}",
+        t!(
+            "Done since {}: {} ({} a day)",
+            first,
+            unit.describe(done),
+            round(per_day)
+        )
+    );
+    match days_left {
+        _ if left == 0.0 => println!("{}", t!("Nothing estimated is left to do.")),
+        Some(n) => println!(
+            "{}",
+            t!(
+                "Left: {}; at this pace it takes about {} more day(s), until {}.",
+                unit.describe(left),
+                n,
+                today + Duration::days(n)
+            )
+        ),
+        None => println!(
+            "{}",
+            t!(
+                "Left: {}; nothing estimated was done lately to tell a pace.",
+                unit.describe(left)
+            )
+        ),
+    }
+    if unestimated > 0 {
+        println!("{}", t!("{} open todo(s) have no estimate.", unestimated));
+    }
+}
@@ -0,0 +1,141 @@
+use crate::color::paint;
+use crate::due::parse_due;
+use crate::i18n::t;
+use crate::{json_output, print_json, print_tree, today, Todo};
+use chrono::{Datelike, Months, NaiveDate};
+use serde_json::json;
+
+const MONTHS: [&str; 12] = [
+    "january",
+    "february",
+    "march",
+    "april",
+    "may",
+    "june",
+    "july",
+    "august",
+    "september",
+    "october",
+    "november",
+    "december",
+];
+
+/// Reads the month `calendar` shows as its first day: `2026-03`, `march`
+/// or `mar` (this year), `this`, `next`, `last`, or any date `--due` takes.
+pub fn parse_month(s: &str) -> Result<NaiveDate, String> {
+    let today = today();
+    let this = today.with_day(1).unwrap_or(today);
+    let s = s.trim().to_lowercase();
+    if let Ok(first) = NaiveDate::parse_from_str(&format!("{}-01", s), "%Y-%m-%d") {
+        return Ok(first);
+    }
+    let month = match s.as_str() {
+        "this" => Some(this),
+        "next" => this.checked_add_months(Months::new(1)),
+        "last" | "previous" | "prev" => this.checked_sub_months(Months::new(1)),
+        _ => MONTHS
+            .iter()
+            .position(|m| s.len() >= 3 && m.starts_with(&s))
+            .and_then(|n| NaiveDate::from_ymd_opt(today.year(), n as u32 + 1, 1)),
+    };
+    match month {
+        Some(first) => Ok(first),
+        None => parse_due(&s, today)
+            .map(|d| d.with_day(1).unwrap_or(d))
+            .map_err(|_| {
+                t!(
+                    "Invalid month '{}': use YYYY-MM, a month name, this, next or last",
+                    s
+                )
+            }),
+    }
+}
+
+/// Reads `--day`: a day of `month` (`16`) or a date (`2026-03-16`, `friday`).
+pub fn parse_day(s: &str, month: Option<NaiveDate>) -> Result<NaiveDate, String> {
+    let Ok(day) = s.trim().parse::<u32>() else {
+        return parse_due(s, today());
+    };
+    let month = month.unwrap_or_else(|| today().with_day(1).unwrap_or(today()));
+    month
+        .with_day(day)
+        .ok_or_else(|| format!("{} has no day {}", month.format("%B %Y"), day))
+}
+
+fn days_in(month: NaiveDate) -> u32 {
+    match month.checked_add_months(Months::new(1)) {
+        Some(next) => next.signed_duration_since(month).num_days() as u32,
+        None => 31,
+    }
+}
+
+/// Prints the month starting at `month` as a grid, Monday first, with the
+/// number of open todos due each day next to it; overdue days and today
+/// stand out. With `day`, the todos due that day follow the grid.
+pub fn show(todos: &[Todo], month: NaiveDate, day: Option<NaiveDate>) {
+    let today = today();
+    let days = days_in(month);
+    let due_on = |date: NaiveDate| todos.iter().filter(move |t| t.due == Some(date));
+    let dates: Vec<NaiveDate> = (1..=days).filter_map(|d| month.with_day(d)).collect();
+    if json_output() {
+        let rows: Vec<_> = dates
+            .iter()
+            .filter(|&&d| due_on(d).next().is_some())
+            .map(|&d| {
+                let done = due_on(d).filter(|t| t.completed).count();
+                json!({ "date": d, "open": due_on(d).count() - done, "done": done })
+            })
+            .collect();
+        let mut out = json!({ "month": month.format("%Y-%m").to_string(), "days": rows });
+        if let Some(day) = day {
+            out["todos"] = json!(due_on(day).collect::<Vec<_>>());
+        }
+        print_json(&out);
+        return;
+    }
+    let title = format!("{:^39}", month.format("%B %Y").to_string());
+    println!("{}", title.trim_end());
+    println!(
+        "{}",
+        paint(" Mo    Tu    We    Th    Fr    Sa    Su", |c| c.muted)
+    );
+    let mut line = " ".repeat(6 * month.weekday().num_days_from_monday() as usize);
+    for &date in &dates {
+        let open = due_on(date).filter(|t| !t.completed).count();
+        let number = date.day().to_string();
+        let mut cell = " ".repeat(3 - number.len());
+        cell.push_str(&if date == today {
+            paint(&number, |c| c.today)
+        } else if open > 0 && date < today {
+            paint(&number, |c| c.overdue)
+        } else if open > 0 {
+            paint(&number, |c| c.due)
+        } else {
+            number
+        });
+        cell.push_str(&match open {
+            0 => "   ".to_string(),
+            1..=99 => paint(&format!("·{:<2}", open), |c| c.muted),
+            _ => paint("·99", |c| c.muted),
+        });
+        line.push_str(&cell);
+        if date.weekday().num_days_from_monday() == 6 {
+            println!("{}", line.trim_end());
+            line.clear();
+        }
+    }
+    if !line.is_empty() {
+        println!("{}", line.trim_end());
+    }
+    let Some(day) = day else {
+        return;
+    };
+    let shown: Vec<&Todo> = due_on(day).collect();
+    println!();
+    if shown.is_empty() {
+        println!("{}", t!("Nothing due on {}.", day.format("%A %Y-%m-%d")));
+    } else {
+        println!("{}", t!("Due on {}:", day.format("%A %Y-%m-%d")));
+        print_tree(todos, &shown, today);
+    }
+}
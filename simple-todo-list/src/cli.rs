@@ -0,0 +1,908 @@
+use crate::board::Status;
+use crate::calendar;
+use crate::color::ColorChoice;
+use crate::config;
+use crate::due::{self, Repeat};
+use crate::export::{Format, Group};
+use crate::i18n::t;
+use crate::import::Source;
+use crate::query::{self, Query};
+use crate::search;
+use crate::sort::Order;
+use crate::{
+    normalize_context, normalize_tag, parse_estimate, parse_meta, today, validate_list_name,
+    Priority,
+};
+use chrono::NaiveDate;
+use clap::{CommandFactory, Parser, Subcommand};
+use std::path::PathBuf;
+
+const AFTER_HELP: &str = "\
+Due dates:
+  YYYY-MM-DD, today, tomorrow, friday, next friday, in 3 days|weeks|months
+
+Repeats:
+  daily, weekly, monthly, every 3 days|weeks|months
+
+Environment:
+  TODO_DB=path/to/file.json   Override DB path (default: ./todos.json)
+                              A .db/.sqlite file uses SQLite storage
+                              A .jsonl file stores one todo per line
+  TODO_LIST=name              Select a list (overrides `use`)
+  TODO_CONFIG=path            Config file (default: ~/.config/todo-cli/config.toml)
+  TODO_PASSPHRASE=secret      Passphrase of an encrypted DB (instead of asking)
+  NO_COLOR=1                  Disable colors (unless --color always)";
+
+#[derive(Parser)]
+#[command(
+    name = "todo",
+    version,
+    about = "Todo CLI (JSON-backed)",
+    after_help = AFTER_HELP,
+    help_template = "{name} {version}\n{about-with-newline}\n{usage-heading} {usage}\n\n{all-args}{after-help}",
+    arg_required_else_help = true
+)]
+pub struct Cli {
+    /// Run the command against another list
+    #[arg(long, global = true, value_name = "NAME", value_parser = parse_list_name)]
+    pub list: Option<String>,
+
+    /// Print results and errors as JSON
+    #[arg(long, global = true)]
+    pub json: bool,
+
+    /// Color `list` output
+    #[arg(
+        long,
+        global = true,
+        value_enum,
+        value_name = "WHEN",
+        default_value = "auto"
+    )]
+    pub color: ColorChoice,
+
+    /// Show what a command would change without saving anything
+    #[arg(long, global = true)]
+    pub dry_run: bool,
+
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Add a new todo (or one per line of stdin with `-` or --stdin)
+    Add {
+        #[arg(required_unless_present = "stdin")]
+        title: Option<String>,
+        /// Description (the remaining words)
+        description: Vec<String>,
+        /// Add a todo for each line read from stdin
+        #[arg(long, conflicts_with = "title")]
+        stdin: bool,
+        /// Read !priority, #tag and @due tokens out of the title
+        #[arg(long)]
+        parse: bool,
+        /// Due date, e.g. 2024-05-01, tomorrow or "in 3 days"
+        #[arg(long, value_name = "DATE", value_parser = parse_due)]
+        due: Option<NaiveDate>,
+        /// low, medium or high
+        #[arg(long, value_parser = Priority::parse)]
+        priority: Option<Priority>,
+        /// Add as a subtask of this todo
+        #[arg(long, value_name = "ID", value_parser = parse_id)]
+        parent: Option<u64>,
+        /// Recur after completion, e.g. weekly or "every 2 days"
+        #[arg(long, value_name = "EVERY", value_parser = Repeat::parse)]
+        repeat: Option<Repeat>,
+        /// Tag the todo (repeatable)
+        #[arg(long = "tag", value_name = "TAG", value_parser = parse_tag)]
+        tags: Vec<String>,
+        /// Where it can be done, e.g. @home (repeatable)
+        #[arg(long = "context", value_name = "@CONTEXT", value_parser = parse_context)]
+        contexts: Vec<String>,
+        /// A field of your own, e.g. ticket=JIRA-123 (repeatable)
+        #[arg(long, value_name = "KEY=VALUE", value_parser = parse_meta_value)]
+        meta: Vec<(String, String)>,
+        /// Expected effort in points or hours, e.g. 3 or 1.5
+        #[arg(long, value_name = "N", value_parser = parse_estimate)]
+        estimate: Option<f64>,
+    },
+
+    /// List todos (default: all)
+    List {
+        /// Show every todo
+        #[arg(long, conflicts_with_all = ["pending", "done"])]
+        all: bool,
+        /// Show only open todos
+        #[arg(long, conflicts_with = "done")]
+        pending: bool,
+        /// Show only completed todos
+        #[arg(long)]
+        done: bool,
+        /// Show only open todos that aren't blocked
+        #[arg(long, conflicts_with_all = ["all", "done"])]
+        ready: bool,
+        /// List the archive instead
+        #[arg(long)]
+        archived: bool,
+        /// Only todos due on or before this date
+        #[arg(long, value_name = "DATE", value_parser = parse_due)]
+        due: Option<NaiveDate>,
+        /// Keys to sort by, e.g. 'overdue, priority desc, due', or none
+        #[arg(long, value_name = "KEYS", value_parser = Order::parse)]
+        sort: Option<Order>,
+        /// Reverse the order
+        #[arg(long)]
+        reverse: bool,
+        /// Only todos matching a query, e.g. 'priority >= high AND tag = work'
+        #[arg(long = "where", value_name = "QUERY", value_parser = query::parse)]
+        query: Option<Query>,
+        /// Only todos with this context, e.g. @home
+        #[arg(long, value_name = "@CONTEXT", value_parser = parse_context)]
+        context: Option<String>,
+        /// Show the list again whenever the DB file changes
+        #[arg(long)]
+        watch: bool,
+        /// Show at most this many todos
+        #[arg(long, value_name = "N")]
+        limit: Option<usize>,
+        /// Skip this many todos first
+        #[arg(long, value_name = "N", default_value_t = 0, conflicts_with = "page")]
+        offset: usize,
+        /// Show page N (1 is the first) of --limit todos, 20 by default
+        #[arg(long, value_name = "N", value_parser = clap::value_parser!(u64).range(1..))]
+        page: Option<u64>,
+    },
+
+    /// Show open todos due today or overdue
+    Today,
+
+    /// Show open todos due in the next 7 days or overdue
+    Week,
+
+    /// Show open todos past their due date
+    Overdue,
+
+    /// Show todos side by side in Backlog, In progress and Done columns
+    Board {
+        /// A column per tag of the open todos instead
+        #[arg(long)]
+        by_tag: bool,
+        /// A column for this tag, in the order given (repeatable)
+        #[arg(long = "tag", value_name = "TAG", value_parser = parse_tag)]
+        tags: Vec<String>,
+    },
+
+    /// Show a month with how many open todos are due each day
+    Calendar {
+        /// YYYY-MM, a month name, next or last (default: this month)
+        #[arg(value_parser = calendar::parse_month)]
+        month: Option<NaiveDate>,
+        /// Also list the todos due on this day, e.g. 16 or 2026-03-16
+        #[arg(long)]
+        day: Option<String>,
+    },
+
+    /// Mark todos as done
+    Done {
+        /// Ids, ranges or words of a title, e.g. 2 4 7-9 or groceries
+        #[arg(value_name = "ID", required = true, value_parser = parse_targets)]
+        targets: Vec<Target>,
+        /// Complete it even if todos it waits for are still open
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// Find todos by text, or by a regular expression with --regex
+    Search {
+        /// Text to look for, ignoring case
+        pattern: String,
+        /// Read the pattern as a regular expression
+        #[arg(long)]
+        regex: bool,
+        /// Only look in the title, description or tags
+        #[arg(long, value_parser = search::Field::parse)]
+        field: Option<search::Field>,
+    },
+
+    /// Mark todos as not done
+    Undone {
+        /// Ids, ranges or words of a title, e.g. 2 4 7-9 or groceries
+        #[arg(value_name = "ID", required = true, value_parser = parse_targets)]
+        targets: Vec<Target>,
+    },
+
+    /// Remove todos and their subtasks
+    #[command(visible_aliases = ["rm", "del"])]
+    Remove {
+        /// Ids, ranges or words of a title, e.g. 2 4 7-9 or groceries
+        #[arg(value_name = "ID", required = true, value_parser = parse_targets)]
+        targets: Vec<Target>,
+    },
+
+    /// Edit a todo
+    Edit {
+        /// Id or words of the title
+        #[arg(value_name = "ID", value_parser = parse_target)]
+        target: Target,
+        /// New title
+        title: Option<String>,
+        /// New description (the remaining words)
+        description: Vec<String>,
+        /// New due date, or `none` to clear it
+        #[arg(long, value_name = "DATE", value_parser = clearable(parse_due))]
+        due: Option<Clearable<NaiveDate>>,
+        /// New priority, or `none` to clear it
+        #[arg(long, value_parser = clearable(Priority::parse))]
+        priority: Option<Clearable<Priority>>,
+        /// New repeat, or `none` to stop repeating
+        #[arg(long, value_name = "EVERY", value_parser = clearable(Repeat::parse))]
+        repeat: Option<Clearable<Repeat>>,
+        /// Add a tag (repeatable)
+        #[arg(long = "tag", value_name = "TAG", value_parser = parse_tag)]
+        tags: Vec<String>,
+        /// Remove a tag (repeatable)
+        #[arg(long = "untag", value_name = "TAG", value_parser = parse_tag)]
+        untags: Vec<String>,
+        /// Add a context, e.g. @errands (repeatable)
+        #[arg(long = "context", value_name = "@CONTEXT", value_parser = parse_context)]
+        contexts: Vec<String>,
+        /// Remove a context (repeatable)
+        #[arg(long = "uncontext", value_name = "@CONTEXT", value_parser = parse_context)]
+        uncontexts: Vec<String>,
+        /// Set a field of your own, or remove it with key= (repeatable)
+        #[arg(long, value_name = "KEY=VALUE", value_parser = parse_meta)]
+        meta: Vec<(String, String)>,
+        /// New estimate in points or hours, or `none` to clear it
+        #[arg(long, value_name = "N", value_parser = clearable(parse_estimate))]
+        estimate: Option<Clearable<f64>>,
+        /// Move it on the board: backlog, in-progress or done
+        #[arg(long, value_parser = Status::parse)]
+        status: Option<Status>,
+    },
+
+    /// Copy a todo as a new open one
+    Clone {
+        /// Id or words of the title
+        #[arg(value_name = "ID", value_parser = parse_target)]
+        target: Target,
+        /// Copy its subtasks too
+        #[arg(long)]
+        with_subtasks: bool,
+        /// Due date of the copy; copied subtasks' due dates move along
+        #[arg(long, value_name = "DATE", value_parser = parse_due)]
+        due: Option<NaiveDate>,
+    },
+
+    /// Put a todo at another place among its siblings (1 is the top)
+    Move {
+        /// Id or words of the title
+        #[arg(value_name = "ID", value_parser = parse_target)]
+        target: Target,
+        /// 1, 2, ... or top or bottom
+        #[arg(value_parser = parse_position)]
+        position: usize,
+    },
+
+    /// Swap the places of two todos with the same parent
+    Swap {
+        /// Id or words of the title
+        #[arg(value_name = "A", value_parser = parse_target)]
+        a: Target,
+        /// Id or words of the title
+        #[arg(value_name = "B", value_parser = parse_target)]
+        b: Target,
+    },
+
+    /// Make a todo wait for others to be done first
+    Block {
+        #[arg(value_parser = parse_id)]
+        id: u64,
+        /// The todo it waits for (repeatable)
+        #[arg(long, value_name = "ID", required = true, value_parser = parse_id)]
+        on: Vec<u64>,
+    },
+
+    /// Stop a todo waiting for others (all of them without --on)
+    Unblock {
+        #[arg(value_parser = parse_id)]
+        id: u64,
+        /// The todo it no longer waits for (repeatable)
+        #[arg(long, value_name = "ID", value_parser = parse_id)]
+        on: Vec<u64>,
+    },
+
+    /// Add a note to a todo (in $EDITOR when no text is given)
+    Note {
+        /// Id or words of the title
+        #[arg(value_name = "ID", value_parser = parse_target)]
+        target: Target,
+        /// The note (the remaining words)
+        text: Vec<String>,
+        /// Write the note in $EDITOR, starting from the text if any
+        #[arg(long, short)]
+        edit: bool,
+    },
+
+    /// Show everything about a todo, notes included
+    Show {
+        /// Id or words of the title
+        #[arg(value_name = "ID", value_parser = parse_target)]
+        target: Target,
+    },
+
+    /// Show who changed what and when, for the list or one todo
+    History {
+        /// Only this todo's changes (removed todos included)
+        id: Option<u64>,
+        /// How many of the latest changes to show for the whole list
+        #[arg(long, default_value_t = 50)]
+        limit: usize,
+    },
+
+    /// Move completed todos to a file per month of completion
+    Archive {
+        #[command(subcommand)]
+        action: Option<ArchiveAction>,
+    },
+
+    /// Show all lists with counts
+    Lists,
+
+    /// Make a list the default for later commands
+    Use {
+        #[arg(value_parser = parse_list_name)]
+        list: String,
+    },
+
+    /// Export todos (to stdout without a path)
+    Export {
+        /// csv, md or ics
+        #[arg(long, value_parser = Format::parse)]
+        format: Format,
+        /// Markdown sections: status or tag
+        #[arg(long, default_value = "status", value_parser = Group::parse)]
+        group: Group,
+        /// iCalendar: write all-day events instead of tasks
+        #[arg(long)]
+        events: bool,
+        path: Option<PathBuf>,
+    },
+
+    /// Import todos, skipping duplicates
+    Import {
+        /// Only csv is supported
+        #[arg(
+            long,
+            value_parser = Format::parse,
+            required_unless_present = "from",
+            conflicts_with = "from"
+        )]
+        format: Option<Format>,
+        /// Read another app's export: todoist (CSV) or trello (board JSON)
+        #[arg(long, value_parser = Source::parse)]
+        from: Option<Source>,
+        path: PathBuf,
+        /// Import todos even if an identical one exists
+        #[arg(long)]
+        allow_duplicates: bool,
+    },
+
+    /// Check the list for problems, and fix them or restore a backup
+    Doctor {
+        /// Fix what is found without asking
+        #[arg(long)]
+        repair: bool,
+    },
+
+    /// Merge another todo file into this list, skipping todos it already has
+    Merge {
+        /// A todo file in any format the DB can be stored in
+        path: PathBuf,
+        /// Take the other file's version of todos both have changed
+        #[arg(long)]
+        theirs: bool,
+    },
+
+    /// Open the full-screen interface
+    Tui,
+
+    /// Enter commands at a prompt
+    #[command(visible_alias = "shell")]
+    Interactive,
+
+    /// List or restore automatic backups of the active list
+    Backup {
+        #[command(subcommand)]
+        action: BackupAction,
+    },
+
+    /// Save todos as a reusable checklist, or add a saved one's todos
+    Template {
+        #[command(subcommand)]
+        action: TemplateAction,
+    },
+
+    /// Save, restore and compare named copies of the whole DB
+    Snapshot {
+        #[command(subcommand)]
+        action: SnapshotAction,
+    },
+
+    /// Copy all lists to another DB file/format
+    Migrate {
+        /// New DB file, e.g. todos.jsonl
+        path: PathBuf,
+    },
+
+    /// Encrypt every list with a passphrase (JSON DBs only)
+    Encrypt,
+
+    /// Turn an encrypted DB back into plain JSON
+    Decrypt,
+
+    /// Remember the passphrase until `lock` or logout
+    Unlock,
+
+    /// Forget the passphrase remembered by `unlock`
+    Lock,
+
+    /// Commit the DB to git and push/pull the configured remote
+    Sync {
+        /// Sync the active list with a `todo serve` server instead
+        #[arg(long, value_name = "URL")]
+        remote: Option<String>,
+    },
+
+    /// Show what `sync` couldn't merge, or pick a side
+    Resolve {
+        /// Only this todo's conflicts
+        #[arg(value_parser = parse_id)]
+        id: Option<u64>,
+        /// Keep this side's values
+        #[arg(long, requires = "id", conflicts_with = "theirs")]
+        ours: bool,
+        /// Take the other side's values
+        #[arg(long, requires = "id")]
+        theirs: bool,
+        /// Only this field, e.g. due
+        #[arg(long, value_name = "NAME")]
+        field: Option<String>,
+    },
+
+    /// Show counts, completions per week and overdue todos
+    Stats,
+
+    /// Show daily completion streaks and completions per weekday
+    Streak,
+
+    /// Chart the estimated work left over the last days
+    Burndown {
+        /// How many days back to chart, today included
+        #[arg(long, default_value_t = 14)]
+        days: u32,
+        /// Only todos matching a query, e.g. 'tag = release'
+        #[arg(long = "where", value_name = "QUERY", value_parser = query::parse)]
+        query: Option<Query>,
+    },
+
+    /// Show each context with how many todos it has
+    Contexts,
+
+    /// Start timing a todo (stops the timer that was running)
+    Start {
+        /// Id or words of the title
+        #[arg(value_name = "ID", value_parser = parse_target)]
+        target: Target,
+    },
+
+    /// Keep a todo at the top of `list`
+    Pin {
+        /// Id or words of the title
+        #[arg(value_name = "ID", value_parser = parse_target)]
+        target: Target,
+    },
+
+    /// Stop keeping a todo at the top of `list`
+    Unpin {
+        /// Id or words of the title
+        #[arg(value_name = "ID", value_parser = parse_target)]
+        target: Target,
+    },
+
+    /// Stop the running timer
+    Stop,
+
+    /// Work on a todo in focus/break cycles
+    Pomodoro {
+        #[arg(value_parser = parse_id)]
+        id: u64,
+        /// Minutes of focus per cycle
+        #[arg(long, default_value_t = 25, value_parser = clap::value_parser!(u32).range(1..))]
+        work: u32,
+        /// Minutes of break between cycles
+        #[arg(long = "break", default_value_t = 5)]
+        rest: u32,
+        /// Number of focus periods
+        #[arg(long, default_value_t = 4, value_parser = clap::value_parser!(u32).range(1..))]
+        cycles: u32,
+    },
+
+    /// Show tracked time per day and per tag
+    Timesheet {
+        /// How many days back to include, today included
+        #[arg(long, default_value_t = 7)]
+        days: u32,
+    },
+
+    /// Notify about todos coming due (desktop notifications)
+    Remind {
+        /// Keep running and check every minute
+        #[arg(long)]
+        daemon: bool,
+        /// Minutes before the deadline to notify (default 15)
+        #[arg(long, value_name = "MINUTES")]
+        before: Option<u32>,
+    },
+
+    /// Silence reminders for a todo for a while
+    Snooze {
+        /// Id or words of the title
+        #[arg(value_name = "ID", value_parser = parse_target)]
+        target: Target,
+        /// e.g. 30m, 2h or 1d
+        #[arg(
+            long = "for",
+            value_name = "DURATION",
+            default_value = "1h",
+            value_parser = due::parse_duration
+        )]
+        duration: chrono::Duration,
+    },
+
+    /// Serve the active list over HTTP (a REST API for scripts and sync)
+    Serve {
+        #[arg(long, default_value_t = 8080)]
+        port: u16,
+        /// Address to listen on; 0.0.0.0 exposes the list to the network
+        #[arg(long, default_value = "127.0.0.1")]
+        host: String,
+    },
+
+    /// Run a Telegram bot that keeps a list per chat (token in TELOXIDE_TOKEN)
+    Bot {
+        /// Only answer this chat (repeatable); by default every chat
+        #[arg(long = "allow", value_name = "CHAT_ID", allow_negative_numbers = true)]
+        allow: Vec<i64>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum ArchiveAction {
+    /// Show the archived months, or the todos archived in one
+    List {
+        /// YYYY-MM, a month name, next or last
+        #[arg(long, value_parser = calendar::parse_month)]
+        month: Option<NaiveDate>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum BackupAction {
+    /// Show the backups, newest (1) first
+    List,
+    /// Replace the list with a backup (default: the newest)
+    Restore {
+        #[arg(default_value_t = 1)]
+        n: usize,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum TemplateAction {
+    /// Save todos (default: the open ones) with their subtasks as a template
+    Save {
+        name: String,
+        /// Ids, ranges or words of a title, e.g. 2 4 7-9
+        #[arg(value_name = "ID", value_parser = parse_targets)]
+        targets: Vec<Target>,
+        /// Only todos matching a query, e.g. 'tag = release'
+        #[arg(long = "where", value_name = "QUERY", value_parser = query::parse)]
+        query: Option<Query>,
+        /// Replace a template with the same name
+        #[arg(long)]
+        force: bool,
+    },
+    /// Add the template's todos, due relative to today or --on
+    Apply {
+        name: String,
+        /// Day the due dates count from
+        #[arg(long, value_name = "DATE", value_parser = parse_due)]
+        on: Option<NaiveDate>,
+    },
+    /// Show the saved templates
+    List,
+    /// Delete a template
+    Remove { name: String },
+}
+
+#[derive(Subcommand)]
+pub enum SnapshotAction {
+    /// Copy every list and archive to a new snapshot
+    Save { name: String },
+    /// Put every list and archive back as the snapshot has them
+    Restore { name: String },
+    /// Show what changed between two snapshots (default: up to now)
+    Diff { a: String, b: Option<String> },
+    /// Show the snapshots, oldest first
+    List,
+}
+
+/// Value of an `edit` flag that also accepts `none` to clear the field.
+#[derive(Clone)]
+pub struct Clearable<T>(pub Option<T>);
+
+fn clearable<T>(
+    parse: fn(&str) -> Result<T, String>,
+) -> impl Fn(&str) -> Result<Clearable<T>, String> + Clone {
+    move |s| match s {
+        "none" => Ok(Clearable(None)),
+        _ => parse(s).map(|v| Clearable(Some(v))),
+    }
+}
+
+/// What `done`, `edit` and similar commands act on: ids (several for a
+/// range like `7-9`) or words to look for in the titles.
+#[derive(Clone)]
+pub enum Target {
+    Ids(Vec<u64>),
+    Title(String),
+}
+
+impl Target {
+    /// Whether `targets` name one todo, so results print as for one.
+    pub fn is_single(targets: &[Target]) -> bool {
+        match targets {
+            [Target::Ids(ids)] => ids.len() == 1,
+            [Target::Title(_)] => true,
+            _ => false,
+        }
+    }
+}
+
+/// Longest range accepted, so a typo like `1-100000` isn't taken literally.
+const MAX_RANGE: u64 = 1000;
+
+fn is_id(s: &str) -> bool {
+    let s = s.strip_prefix('#').unwrap_or(s);
+    !s.is_empty() && s.chars().all(|c| c.is_ascii_digit())
+}
+
+/// An id, or anything else as words of a title.
+fn parse_target(s: &str) -> Result<Target, String> {
+    if is_id(s) {
+        return parse_id(s).map(|id| Target::Ids(vec![id]));
+    }
+    if s.trim().is_empty() {
+        return Err(t!("Expected an id or words of a title").to_string());
+    }
+    Ok(Target::Title(s.trim().to_string()))
+}
+
+/// Like `parse_target`, also accepting a range of ids.
+fn parse_targets(s: &str) -> Result<Target, String> {
+    let Some((from, to)) = s.split_once('-').filter(|(f, t)| is_id(f) && is_id(t)) else {
+        return parse_target(s);
+    };
+    let (from, to) = (parse_id(from)?, parse_id(to)?);
+    if from > to {
+        return Err(t!("Invalid range '{}': {} is after {}", s, from, to));
+    }
+    if to - from >= MAX_RANGE {
+        return Err(t!(
+            "Invalid range '{}': at most {} ids at once",
+            s,
+            MAX_RANGE
+        ));
+    }
+    Ok(Target::Ids((from..=to).collect()))
+}
+
+fn parse_due(s: &str) -> Result<NaiveDate, String> {
+    due::parse_due(s, today())
+}
+
+fn parse_tag(s: &str) -> Result<String, String> {
+    normalize_tag(s)
+}
+
+fn parse_context(s: &str) -> Result<String, String> {
+    normalize_context(s)
+}
+
+/// `key=value` with a value, as `add` takes it.
+fn parse_meta_value(s: &str) -> Result<(String, String), String> {
+    let (key, value) = parse_meta(s)?;
+    if value.is_empty() {
+        return Err(t!("'{}' needs a value, e.g. {}=42", key, key));
+    }
+    Ok((key, value))
+}
+
+/// A title as `add --parse` reads it, with the tokens taken out.
+pub struct Inline {
+    pub title: String,
+    pub priority: Option<Priority>,
+    pub due: Option<NaiveDate>,
+    pub tags: Vec<String>,
+    pub contexts: Vec<String>,
+}
+
+/// Takes `!high`, `#tag` and `@friday` tokens out of `line`. Due dates
+/// written with dashes for spaces work too, e.g. `@next-friday`; an `@word`
+/// that isn't a date is a context, e.g. `@errands`.
+pub fn parse_inline(line: &str) -> Result<Inline, String> {
+    let mut inline = Inline {
+        title: String::new(),
+        priority: None,
+        due: None,
+        tags: Vec::new(),
+        contexts: Vec::new(),
+    };
+    let mut title = Vec::new();
+    for word in line.split_whitespace() {
+        if let Some(p) = word.strip_prefix('!').filter(|p| !p.is_empty()) {
+            inline.priority = Some(Priority::parse(p)?);
+        } else if let Some(tag) = word.strip_prefix('#').filter(|t| !t.is_empty()) {
+            let tag = normalize_tag(tag)?;
+            if !inline.tags.contains(&tag) {
+                inline.tags.push(tag);
+            }
+        } else if let Some(d) = word.strip_prefix('@').filter(|d| !d.is_empty()) {
+            if let Ok(due) = parse_due(d).or_else(|_| parse_due(&d.replace('-', " "))) {
+                inline.due = Some(due);
+                continue;
+            }
+            let context = normalize_context(d)?;
+            if !inline.contexts.contains(&context) {
+                inline.contexts.push(context);
+            }
+        } else {
+            title.push(word);
+        }
+    }
+    inline.title = title.join(" ");
+    Ok(inline)
+}
+
+fn parse_list_name(s: &str) -> Result<String, String> {
+    validate_list_name(s).map(|()| s.to_string())
+}
+
+/// A place in the list, counted from 1; `bottom` is past the last one.
+fn parse_position(s: &str) -> Result<usize, String> {
+    match s.to_lowercase().as_str() {
+        "top" | "first" => Ok(1),
+        "bottom" | "last" => Ok(usize::MAX),
+        n => match n.parse() {
+            Ok(n) if n > 0 => Ok(n),
+            _ => Err(t!(
+                "Invalid position '{}': use a number from 1, top or bottom",
+                s
+            )),
+        },
+    }
+}
+
+/// Parses a todo id as shown by `list`; a leading `#` is allowed.
+fn parse_id(s: &str) -> Result<u64, String> {
+    match s.strip_prefix('#').unwrap_or(s).parse() {
+        Ok(id) if id > 0 => Ok(id),
+        _ => Err(t!("Invalid id '{}': must be a positive number", s)),
+    }
+}
+
+/// Replaces a command that is an alias from the config with its
+/// definition, e.g. `today` -> `list --due today`, keeping the arguments
+/// after it. Aliases may use other aliases but can't shadow a command.
+pub fn expand_aliases(mut args: Vec<String>) -> Result<Vec<String>, String> {
+    let aliases = &config::get().aliases;
+    let Some(pos) = command_position(&args) else {
+        return Ok(args);
+    };
+    let builtins: Vec<String> = Cli::command()
+        .get_subcommands()
+        .flat_map(|c| std::iter::once(c.get_name()).chain(c.get_all_aliases()))
+        .chain(["help"])
+        .map(|name| name.to_string())
+        .collect();
+    let mut seen = Vec::new();
+    while let Some(definition) = aliases.get(&args[pos]) {
+        let name = args[pos].clone();
+        if builtins.contains(&name) {
+            break;
+        }
+        if seen.contains(&name) {
+            return Err(t!("Alias '{}' expands to itself", name));
+        }
+        let words = split_words(definition)?;
+        if words.is_empty() {
+            return Err(t!("Alias '{}' is empty", name));
+        }
+        args.splice(pos..=pos, words);
+        seen.push(name);
+    }
+    Ok(args)
+}
+
+/// Index of the command word, skipping global flags in front of it.
+fn command_position(args: &[String]) -> Option<usize> {
+    let mut i = 0;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--list" | "--color" => i += 2,
+            a if a.starts_with('-') => i += 1,
+            _ => return Some(i),
+        }
+    }
+    None
+}
+
+/// Splits a command line into words like a shell would: whitespace
+/// separates words, quotes group them and `\` escapes the next character.
+pub fn split_words(line: &str) -> Result<Vec<String>, String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut in_word = false;
+    let mut quote = None;
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some('"') | None, '\\') => {
+                if let Some(next) = chars.next() {
+                    word.push(next);
+                }
+                in_word = true;
+            }
+            (Some(_), c) => word.push(c),
+            (None, '"' | '\'') => {
+                quote = Some(c);
+                in_word = true;
+            }
+            (None, c) if c.is_whitespace() => {
+                if in_word {
+                    words.push(std::mem::take(&mut word));
+                    in_word = false;
+                }
+            }
+            (None, c) => {
+                word.push(c);
+                in_word = true;
+            }
+        }
+    }
+    if quote.is_some() {
+        return Err(t!("Unterminated quote.").to_string());
+    }
+    if in_word {
+        words.push(word);
+    }
+    Ok(words)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn inline_at_words_are_due_dates_or_contexts() {
+        let inline = parse_inline("buy milk @errands !high #home @2026-03-20 @Errands").unwrap();
+        assert_eq!(inline.title, "buy milk");
+        assert_eq!(inline.priority, Some(Priority::High));
+        assert_eq!(inline.due, NaiveDate::from_ymd_opt(2026, 3, 20));
+        assert_eq!(inline.tags, ["home"]);
+        assert_eq!(inline.contexts, ["errands"]);
+    }
+}
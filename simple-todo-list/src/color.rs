@@ -0,0 +1,124 @@
+use crate::config;
+use std::env;
+use std::io::{self, IsTerminal};
+use std::sync::Mutex;
+
+/// When `list` output is colored (`--color`).
+#[derive(Clone, Copy, clap::ValueEnum)]
+pub enum ColorChoice {
+    /// Only when printing to a terminal and `NO_COLOR` is not set
+    Auto,
+    Always,
+    Never,
+}
+
+/// ANSI SGR codes for each part of a `list` row (and of `calendar`).
+pub struct Theme {
+    pub check: &'static str,
+    pub muted: &'static str,
+    pub id: &'static str,
+    pub high: &'static str,
+    pub medium: &'static str,
+    pub low: &'static str,
+    pub due: &'static str,
+    pub overdue: &'static str,
+    pub repeat: &'static str,
+    pub tag: &'static str,
+    pub context: &'static str,
+    pub today: &'static str,
+    pub timer: &'static str,
+}
+
+const THEMES: &[(&str, Theme)] = &[
+    (
+        "default",
+        Theme {
+            check: "32",
+            muted: "2",
+            id: "2",
+            high: "1;31",
+            medium: "33",
+            low: "36",
+            due: "36",
+            overdue: "1;31",
+            repeat: "34",
+            tag: "35",
+            context: "94",
+            today: "7",
+            timer: "1;32",
+        },
+    ),
+    (
+        "solarized",
+        Theme {
+            check: "38;5;64",
+            muted: "38;5;245",
+            id: "38;5;245",
+            high: "1;38;5;160",
+            medium: "38;5;136",
+            low: "38;5;37",
+            due: "38;5;33",
+            overdue: "1;38;5;160",
+            repeat: "38;5;61",
+            tag: "38;5;125",
+            context: "38;5;166",
+            today: "7",
+            timer: "1;38;5;64",
+        },
+    ),
+    (
+        "mono",
+        Theme {
+            check: "1",
+            muted: "2",
+            id: "2",
+            high: "1",
+            medium: "",
+            low: "2",
+            due: "",
+            overdue: "1;4",
+            repeat: "",
+            tag: "4",
+            context: "3",
+            today: "7",
+            timer: "1",
+        },
+    ),
+];
+
+/// The theme used by `paint`, or `None` for plain output.
+static ACTIVE: Mutex<Option<&'static Theme>> = Mutex::new(None);
+
+/// Decides whether output is colored and with which theme from the config.
+pub fn configure(choice: ColorChoice) {
+    let enabled = match choice {
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+        ColorChoice::Auto => {
+            env::var_os("NO_COLOR").is_none_or(|v| v.is_empty())
+                && env::var("TERM").map_or(true, |t| t != "dumb")
+                && io::stdout().is_terminal()
+        }
+    };
+    let theme = enabled.then(|| {
+        let name = config::get().theme.as_deref().unwrap_or("default");
+        match THEMES.iter().find(|(n, _)| *n == name) {
+            Some((_, theme)) => theme,
+            None => {
+                eprintln!("Unknown theme '{}', using the default one", name);
+                &THEMES[0].1
+            }
+        }
+    });
+    *ACTIVE.lock().unwrap() = theme;
+}
+
+/// Wraps `text` in the style `pick` selects from the active theme.
+pub fn paint(text: &str, pick: fn(&Theme) -> &'static str) -> String {
+    match *ACTIVE.lock().unwrap() {
+        Some(theme) if !pick(theme).is_empty() => {
+            format!("\x1b[{}m{}\x1b[0m", pick(theme), text)
+        }
+        _ => text.to_string(),
+    }
+}
@@ -0,0 +1,184 @@
+use crate::error::Error;
+use crate::i18n::t;
+use crate::merge::FieldConflict;
+use crate::storage::write_atomic;
+use crate::{
+    announce, crypto, db_path, json_output, load_db, not_found, position_of, print_json, save_db,
+    session, stem_and_ext, Todo,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// A field `sync` couldn't merge because both sides changed it. This side's
+/// value was kept; the other one waits here until `resolve` picks.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Conflict {
+    pub id: u64,
+    pub field: String,
+    pub ours: Value,
+    pub theirs: Value,
+    /// Where the other value came from: `git` or the server's URL.
+    pub from: String,
+}
+
+/// `todos.json` -> `todos.conflicts.json`, one file per list.
+pub fn path(list: &Path) -> PathBuf {
+    let (stem, _) = stem_and_ext(list);
+    list.with_file_name(format!("{}.conflicts.json", stem))
+}
+
+/// The conflicts waiting in `list`, encrypted like the DB when it is.
+pub fn load(list: &Path) -> Result<Vec<Conflict>, String> {
+    let path = path(list);
+    let Ok(mut data) = fs::read(&path) else {
+        return Ok(Vec::new());
+    };
+    if let Some(salt) = crypto::file_salt(&path) {
+        data = crypto::decrypt(&data, &crypto::key_for(&salt)?)?;
+    }
+    serde_json::from_slice(&data).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+/// Does nothing in a dry run, which only shows how the lists would change.
+fn save(list: &Path, conflicts: &[Conflict]) -> Result<(), String> {
+    if session::dry_run() {
+        return Ok(());
+    }
+    let path = path(list);
+    if conflicts.is_empty() {
+        return match fs::remove_file(&path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.to_string()),
+            _ => Ok(()),
+        };
+    }
+    let mut data = serde_json::to_vec_pretty(conflicts).map_err(|e| e.to_string())?;
+    if let Some(salt) = crypto::db_salt() {
+        data = crypto::encrypt(&data, &salt, &crypto::key_for(&salt)?)?;
+    }
+    write_atomic(&path, |file| file.write_all(&data))
+}
+
+/// Adds what a sync of `list` couldn't merge, replacing what was waiting for
+/// the same fields.
+pub fn record(list: &Path, found: &[FieldConflict], from: &str) -> Result<(), String> {
+    if found.is_empty() {
+        return Ok(());
+    }
+    let mut conflicts = load(list)?;
+    conflicts.retain(|c| !found.iter().any(|f| f.id == c.id && f.field == c.field));
+    conflicts.extend(found.iter().map(|f| Conflict {
+        id: f.id,
+        field: f.field.clone(),
+        ours: f.ours.clone(),
+        theirs: f.theirs.clone(),
+        from: from.to_string(),
+    }));
+    save(list, &conflicts)
+}
+
+/// How many conflicts of the active list wait for `resolve`, for `list`.
+pub fn count() -> usize {
+    load(&db_path()).map_or(0, |c| c.len())
+}
+
+fn describe(value: &Value) -> String {
+    match value {
+        Value::Null => "none".to_string(),
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// Which side `resolve` keeps.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Side {
+    Ours,
+    Theirs,
+}
+
+/// Lists the active list's conflicts (only `id`'s with one), or settles
+/// `id`'s, or just its `field`, in favour of `side`.
+pub fn resolve(id: Option<u64>, side: Option<Side>, field: Option<String>) -> Result<(), Error> {
+    let list = db_path();
+    let mut conflicts = load(&list).map_err(Error::Storage)?;
+    let mut todos = load_db();
+    let picked = |c: &Conflict| {
+        id.is_none_or(|id| c.id == id) && field.as_ref().is_none_or(|f| *f == c.field)
+    };
+    let (Some(id), Some(side)) = (id, side) else {
+        let mut shown: Vec<&Conflict> = conflicts.iter().filter(|c| picked(c)).collect();
+        shown.sort_by_key(|c| c.id);
+        if json_output() {
+            print_json(&shown);
+        } else if shown.is_empty() {
+            println!("{}", t!("No conflicts."));
+        } else {
+            show(&todos, &shown);
+        }
+        return Ok(());
+    };
+    let settled: Vec<Conflict> = conflicts.iter().filter(|c| picked(c)).cloned().collect();
+    if settled.is_empty() {
+        return Err(Error::NotFound(match &field {
+            Some(field) => t!("#{} has no conflict in {}.", id, field),
+            None => t!("#{} has no conflicts.", id),
+        }));
+    }
+    conflicts.retain(|c| !picked(c));
+    let Some(idx) = position_of(&todos, id) else {
+        save(&list, &conflicts).map_err(Error::Storage)?;
+        return Err(not_found(id));
+    };
+    if side == Side::Theirs {
+        let mut todo = serde_json::to_value(&todos[idx])
+            .map_err(|e| Error::Failed(t!("Failed to serialize output: {}", e)))?;
+        if let Value::Object(fields) = &mut todo {
+            for c in &settled {
+                match &c.theirs {
+                    Value::Null => fields.remove(&c.field),
+                    value => fields.insert(c.field.clone(), value.clone()),
+                };
+            }
+        }
+        todos[idx] = serde_json::from_value(todo)
+            .map_err(|e| Error::Failed(t!("Can't take the other side's value: {}", e)))?;
+        save_db(&todos).map_err(|e| Error::Storage(t!("Failed to save: {}", e)))?;
+    }
+    save(&list, &conflicts).map_err(Error::Storage)?;
+    let names: Vec<&str> = settled.iter().map(|c| c.field.as_str()).collect();
+    if json_output() {
+        print_json(&todos[idx]);
+    } else if side == Side::Ours {
+        announce(t!("Kept this side's {} of #{}.", names.join(", "), id));
+    } else {
+        announce(t!(
+            "Took the other side's {} for #{}.",
+            names.join(", "),
+            id
+        ));
+    }
+    Ok(())
+}
+
+fn show(todos: &[Todo], conflicts: &[&Conflict]) {
+    let mut last = None;
+    for c in conflicts {
+        if last != Some(c.id) {
+            let title = position_of(todos, c.id).map_or("", |i| todos[i].title.as_str());
+            println!("#{} {} ({})", c.id, title, c.from);
+            last = Some(c.id);
+        }
+        println!(
+            "  {}: {}",
+            c.field,
+            t!("here {}, there {}", describe(&c.ours), describe(&c.theirs))
+        );
+    }
+    println!(
+        "{}",
+        t!("Keep this side's value with 'resolve <id> --ours', or 'resolve <id> --theirs'.")
+    );
+}
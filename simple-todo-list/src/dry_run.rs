@@ -0,0 +1,117 @@
+use crate::cli::{Command, SnapshotAction, TemplateAction};
+use crate::error::Error;
+use crate::i18n::t;
+use crate::{audit, base_db_path, fail, history, json_output, run_command, session, Todo};
+use serde_json::json;
+use std::path::{Path, PathBuf};
+use todo_core::load;
+
+/// Whether `command` can change todos, the only kind `--dry-run` makes
+/// sense for.
+fn changes_todos(command: &Command) -> bool {
+    matches!(
+        command,
+        Command::Add { .. }
+            | Command::Done { .. }
+            | Command::Undone { .. }
+            | Command::Remove { .. }
+            | Command::Edit { .. }
+            | Command::Clone { .. }
+            | Command::Move { .. }
+            | Command::Swap { .. }
+            | Command::Block { .. }
+            | Command::Unblock { .. }
+            | Command::Note { .. }
+            | Command::Pin { .. }
+            | Command::Unpin { .. }
+            | Command::Start { .. }
+            | Command::Stop
+            | Command::Snooze { .. }
+            | Command::Archive { action: None }
+            | Command::Template {
+                action: TemplateAction::Apply { .. },
+            }
+            | Command::Import { .. }
+            | Command::Merge { .. }
+            | Command::Resolve { ours: true, .. }
+            | Command::Resolve { theirs: true, .. }
+            | Command::Snapshot {
+                action: SnapshotAction::Restore { .. },
+            }
+            | Command::Doctor { repair: true }
+    )
+}
+
+/// Runs `command` against lists held in memory, then prints how each file
+/// would change. Nothing is written, and no history or hooks are recorded.
+pub fn run(command: Command) {
+    if !changes_todos(&command) {
+        let e = t!("--dry-run only works with commands that change todos, like add or edit.");
+        fail(Error::Usage(e.to_string()));
+        return;
+    }
+    if session::active() {
+        let e = t!("Interactive mode saves nothing until 'save' anyway; drop --dry-run.");
+        fail(Error::Usage(e.to_string()));
+        return;
+    }
+    session::start_dry_run();
+    run_command(command);
+    if let Err(e) = show(session::discard()) {
+        fail(Error::Storage(e));
+    }
+}
+
+/// Prints the todos each of `changed`'s files would gain, lose or see
+/// changed, field by field, compared to what the file holds now. With
+/// `--json` the changes go to stderr, so stdout stays the command's own
+/// output.
+fn show(changed: Vec<(PathBuf, Vec<Todo>)>) -> Result<(), String> {
+    let base = base_db_path();
+    let dir = base.parent().unwrap_or(Path::new(""));
+    let mut rows = Vec::new();
+    let mut differ = false;
+    let human = !json_output();
+    for (path, after) in &changed {
+        let before = load(path)?;
+        let changes = audit::compare(&before, after);
+        let file = path.strip_prefix(dir).unwrap_or(path).display().to_string();
+        if !human {
+            rows.extend(changes.into_iter().map(|(change, t, fields)| {
+                json!({
+                    "file": file,
+                    "id": t.id,
+                    "change": change,
+                    "title": t.title,
+                    "fields": fields,
+                })
+            }));
+            continue;
+        }
+        if changes.is_empty() {
+            continue;
+        }
+        if !differ {
+            println!(
+                "{}",
+                t!("Dry run, nothing was saved. The changes would be:")
+            );
+        }
+        differ = true;
+        println!("{}:", file);
+        for (change, t, fields) in changes {
+            println!("  #{} {} {}", t.id, change.as_str(), t.title);
+            history::print_fields(&fields);
+        }
+    }
+    if !human {
+        let out = json!({ "dry_run": true, "changes": rows });
+        match serde_json::to_string_pretty(&out) {
+            Ok(out) => eprintln!("{}", out),
+            Err(e) => return Err(t!("Failed to serialize output: {}", e)),
+        }
+    } else if !differ {
+        println!("{}", t!("Dry run: nothing would change."));
+    }
+    Ok(())
+}
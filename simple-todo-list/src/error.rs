@@ -0,0 +1,81 @@
+use std::fmt;
+use todo_core::lock::LockError;
+
+/// Why a command failed. Each kind exits with its own status, so scripts
+/// can tell a missing todo from a broken DB without reading the message.
+#[derive(Debug)]
+pub enum Error {
+    /// The command couldn't do what was asked, e.g. the todo is blocked.
+    Failed(String),
+    /// The arguments don't make sense: unknown flags, an ambiguous title.
+    Usage(String),
+    /// No todo, list or file by that id or name.
+    NotFound(String),
+    /// The DB or another file couldn't be read or written.
+    Storage(String),
+    /// Another todo command kept the DB locked.
+    Locked(String),
+}
+
+impl Error {
+    /// The process exit status; 2 matches what clap exits with for bad
+    /// arguments.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Error::Failed(_) => 1,
+            Error::Usage(_) => 2,
+            Error::NotFound(_) => 3,
+            Error::Storage(_) => 4,
+            Error::Locked(_) => 5,
+        }
+    }
+
+    /// The kind as given in `--json` output, e.g. `{"code": "not_found"}`.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Error::Failed(_) => "failed",
+            Error::Usage(_) => "usage",
+            Error::NotFound(_) => "not_found",
+            Error::Storage(_) => "storage",
+            Error::Locked(_) => "locked",
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            Error::Failed(m)
+            | Error::Usage(m)
+            | Error::NotFound(m)
+            | Error::Storage(m)
+            | Error::Locked(m) => m,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.message())
+    }
+}
+
+/// Errors that are only text are plain failures.
+impl From<String> for Error {
+    fn from(message: String) -> Error {
+        Error::Failed(message)
+    }
+}
+
+impl From<&str> for Error {
+    fn from(message: &str) -> Error {
+        Error::Failed(message.to_string())
+    }
+}
+
+impl From<LockError> for Error {
+    fn from(e: LockError) -> Error {
+        match e {
+            LockError::Busy(m) => Error::Locked(m),
+            LockError::Failed(m) => Error::Storage(m),
+        }
+    }
+}
@@ -0,0 +1,274 @@
+use crate::due::Repeat;
+use crate::i18n::t;
+use crate::{Priority, Todo};
+use chrono::Utc;
+use std::collections::BTreeSet;
+
+/// Formats understood by `export` (and, where it makes sense, `import`).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Csv,
+    Markdown,
+    ICalendar,
+}
+
+impl Format {
+    pub fn parse(s: &str) -> Result<Format, String> {
+        match s.to_lowercase().as_str() {
+            "csv" => Ok(Format::Csv),
+            "md" | "markdown" => Ok(Format::Markdown),
+            "ics" | "ical" => Ok(Format::ICalendar),
+            _ => Err(t!("Unknown format '{}': use csv, md or ics", s)),
+        }
+    }
+}
+
+/// How Markdown output is split into sections.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Group {
+    Status,
+    Tag,
+}
+
+impl Group {
+    pub fn parse(s: &str) -> Result<Group, String> {
+        match s {
+            "status" => Ok(Group::Status),
+            "tag" => Ok(Group::Tag),
+            _ => Err(t!("Unknown grouping '{}': use status or tag", s)),
+        }
+    }
+}
+
+pub struct Options {
+    /// Heading for document formats, usually the list name.
+    pub title: String,
+    /// Name of the exported list, used to keep calendar UIDs unique.
+    pub list: String,
+    pub group: Group,
+    /// Emit calendar events instead of tasks, for calendar apps that
+    /// ignore VTODO.
+    pub ics_events: bool,
+}
+
+/// Columns written by `export --format csv`, and the names `import`
+/// recognises in a header row. Metadata follows in a `meta.<key>` column
+/// per key.
+pub const CSV_COLUMNS: [&str; 11] = [
+    "id",
+    "title",
+    "description",
+    "completed",
+    "due",
+    "priority",
+    "parent",
+    "repeat",
+    "tags",
+    "contexts",
+    "estimate",
+];
+
+pub fn export(todos: &[Todo], format: Format, options: &Options) -> Result<String, String> {
+    match format {
+        Format::Csv => to_csv(todos),
+        Format::Markdown => Ok(to_markdown(todos, options)),
+        Format::ICalendar => Ok(to_icalendar(todos, options)),
+    }
+}
+
+fn to_csv(todos: &[Todo]) -> Result<String, String> {
+    let keys: BTreeSet<&String> = todos.iter().flat_map(|t| t.meta.keys()).collect();
+    let mut w = csv::Writer::from_writer(Vec::new());
+    let header = CSV_COLUMNS
+        .iter()
+        .map(|c| c.to_string())
+        .chain(keys.iter().map(|k| format!("meta.{}", k)));
+    w.write_record(header).map_err(|e| e.to_string())?;
+    for t in todos {
+        let meta = keys
+            .iter()
+            .map(|k| t.meta.get(*k).cloned().unwrap_or_default());
+        let fields = [
+            t.id.to_string(),
+            t.title.clone(),
+            t.description.clone(),
+            t.completed.to_string(),
+            t.due.map(|d| d.to_string()).unwrap_or_default(),
+            t.priority
+                .map(|p| p.as_str().to_string())
+                .unwrap_or_default(),
+            t.parent.map(|p| p.to_string()).unwrap_or_default(),
+            t.repeat.map(|r| r.to_string()).unwrap_or_default(),
+            t.tags.join(" "),
+            t.contexts.join(" "),
+            t.estimate.map(|n| n.to_string()).unwrap_or_default(),
+        ];
+        w.write_record(fields.into_iter().chain(meta))
+            .map_err(|e| e.to_string())?;
+    }
+    let bytes = w.into_inner().map_err(|e| e.to_string())?;
+    String::from_utf8(bytes).map_err(|e| e.to_string())
+}
+
+/// A checkbox list, one section per status or per tag. A todo with several
+/// tags shows up under each of them.
+fn to_markdown(todos: &[Todo], options: &Options) -> String {
+    let mut out = format!("# {}\n", options.title);
+    let sections: Vec<(String, Vec<&Todo>)> = match options.group {
+        Group::Status => vec![
+            (
+                "Pending".to_string(),
+                todos.iter().filter(|t| !t.completed).collect(),
+            ),
+            (
+                "Done".to_string(),
+                todos.iter().filter(|t| t.completed).collect(),
+            ),
+        ],
+        Group::Tag => {
+            let mut tags: Vec<&String> = todos.iter().flat_map(|t| &t.tags).collect();
+            tags.sort();
+            tags.dedup();
+            let mut sections: Vec<(String, Vec<&Todo>)> = tags
+                .into_iter()
+                .map(|tag| {
+                    let items = todos.iter().filter(|t| t.tags.contains(tag)).collect();
+                    (format!("#{}", tag), items)
+                })
+                .collect();
+            sections.push((
+                "Untagged".to_string(),
+                todos.iter().filter(|t| t.tags.is_empty()).collect(),
+            ));
+            sections
+        }
+    };
+    for (heading, items) in sections {
+        if items.is_empty() {
+            continue;
+        }
+        out.push_str(&format!("\n## {}\n\n", heading));
+        for t in items {
+            out.push_str(&markdown_item(t));
+        }
+    }
+    out
+}
+
+fn markdown_item(t: &Todo) -> String {
+    let check = if t.completed { "x" } else { " " };
+    let mut line = format!("- [{}] {}", check, escape_markdown(&t.title));
+    let description = t
+        .description
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+    if !description.is_empty() {
+        line.push_str(&format!(" — {}", escape_markdown(&description)));
+    }
+    if let Some(d) = t.due {
+        line.push_str(&format!(" (due {})", d));
+    }
+    line.push('\n');
+    line
+}
+
+/// Keeps titles like `*urgent*` or `[draft]` from turning into formatting.
+fn escape_markdown(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        if matches!(c, '\\' | '*' | '_' | '`' | '[' | ']' | '<' | '>') {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// RFC 5545 calendar with one all-day entry per todo that has a due date.
+fn to_icalendar(todos: &[Todo], options: &Options) -> String {
+    let stamp = Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//todo_cli//EN".to_string(),
+        format!("X-WR-CALNAME:{}", escape_ics(&options.title)),
+    ];
+    for t in todos {
+        let Some(due) = t.due else { continue };
+        let date = due.format("%Y%m%d").to_string();
+        let kind = if options.ics_events {
+            "VEVENT"
+        } else {
+            "VTODO"
+        };
+        lines.push(format!("BEGIN:{}", kind));
+        lines.push(format!("UID:{}-{}@todo-cli", options.list, t.id));
+        lines.push(format!("DTSTAMP:{}", stamp));
+        if options.ics_events {
+            let next_day = due.succ_opt().unwrap_or(due).format("%Y%m%d");
+            lines.push(format!("DTSTART;VALUE=DATE:{}", date));
+            lines.push(format!("DTEND;VALUE=DATE:{}", next_day));
+        } else {
+            lines.push(format!("DUE;VALUE=DATE:{}", date));
+            let status = if t.completed {
+                "COMPLETED"
+            } else {
+                "NEEDS-ACTION"
+            };
+            lines.push(format!("STATUS:{}", status));
+        }
+        lines.push(format!("SUMMARY:{}", escape_ics(&t.title)));
+        if !t.description.trim().is_empty() {
+            lines.push(format!("DESCRIPTION:{}", escape_ics(&t.description)));
+        }
+        if let Some(p) = t.priority {
+            // 1 is highest in iCalendar, 9 lowest.
+            let level = match p {
+                Priority::High => 1,
+                Priority::Medium => 5,
+                Priority::Low => 9,
+            };
+            lines.push(format!("PRIORITY:{}", level));
+        }
+        if !t.tags.is_empty() {
+            let tags: Vec<String> = t.tags.iter().map(|t| escape_ics(t)).collect();
+            lines.push(format!("CATEGORIES:{}", tags.join(",")));
+        }
+        if let Some(r) = t.repeat {
+            let (freq, n) = match r {
+                Repeat::Days(n) => ("DAILY", n),
+                Repeat::Weeks(n) => ("WEEKLY", n),
+                Repeat::Months(n) => ("MONTHLY", n),
+            };
+            lines.push(format!("RRULE:FREQ={};INTERVAL={}", freq, n));
+        }
+        lines.push(format!("END:{}", kind));
+    }
+    lines.push("END:VCALENDAR".to_string());
+    lines.iter().map(|l| fold_ics(l)).collect()
+}
+
+fn escape_ics(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+/// Content lines longer than 75 octets are continued on the next line
+/// after a space, never splitting a UTF-8 character.
+fn fold_ics(line: &str) -> String {
+    let mut out = String::new();
+    let mut len = 0;
+    for c in line.chars() {
+        if len + c.len_utf8() > 75 {
+            out.push_str("\r\n ");
+            len = 1;
+        }
+        out.push(c);
+        len += c.len_utf8();
+    }
+    out.push_str("\r\n");
+    out
+}
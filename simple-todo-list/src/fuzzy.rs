@@ -0,0 +1,101 @@
+use crate::error::Error;
+use crate::i18n::t;
+use crate::{json_output, Todo};
+use std::io::{self, BufRead, IsTerminal, Write};
+
+/// Matches whose scores are this close to the best one are offered as
+/// alternatives instead of picking the best silently.
+const CLOSE: i64 = 50;
+
+/// Most candidates listed when a title is ambiguous.
+const SHOWN: usize = 9;
+
+/// How well `query` matches `title`, higher being better, or `None` if the
+/// letters of `query` don't all appear in `title` in order. Exact titles beat
+/// substrings, which beat scattered letters; starting at a word and being
+/// close in length to the query count for more.
+pub fn score(query: &str, title: &str) -> Option<i64> {
+    let query: Vec<char> = query.trim().to_lowercase().chars().collect();
+    let title: Vec<char> = title.to_lowercase().chars().collect();
+    if query.is_empty() {
+        return None;
+    }
+    if query == title {
+        return Some(1000);
+    }
+    let extra = (title.len() - query.len().min(title.len())) as i64;
+    let word_start = |i: usize| i == 0 || !title[i - 1].is_alphanumeric();
+    if let Some(i) = (0..title.len())
+        .filter(|&i| title[i..].starts_with(&query))
+        .min_by_key(|&i| !word_start(i))
+    {
+        return Some(600 + if word_start(i) { 100 } else { 0 } - extra.min(99));
+    }
+    // Each query letter at its first place after the previous one.
+    let mut score = 300;
+    let mut last: Option<usize> = None;
+    for &c in &query {
+        let from = last.map_or(0, |l| l + 1);
+        let i = from + title[from..].iter().position(|&t| t == c)?;
+        score += match last {
+            Some(l) if i == l + 1 => 10,
+            _ if word_start(i) => 5,
+            Some(l) => -((i - l - 1) as i64).min(10),
+            None => -(i as i64).min(10),
+        };
+        last = Some(i);
+    }
+    Some(score - extra.min(99))
+}
+
+/// The id of the todo among `todos` whose title best matches `query`. When
+/// several match about as well, asks which one is meant on a terminal and
+/// fails elsewhere, listing them.
+pub fn find(query: &str, todos: &[&Todo]) -> Result<u64, Error> {
+    let mut matches: Vec<(i64, &Todo)> = todos
+        .iter()
+        .filter_map(|t| score(query, &t.title).map(|s| (s, *t)))
+        .collect();
+    matches.sort_by_key(|(s, _)| std::cmp::Reverse(*s));
+    let Some(&(best, todo)) = matches.first() else {
+        return Err(Error::NotFound(t!(
+            "No todo matches '{}'. Use 'list' to see items.",
+            query
+        )));
+    };
+    let close: Vec<&Todo> = matches
+        .iter()
+        .take_while(|(s, _)| *s > best - CLOSE)
+        .take(SHOWN)
+        .map(|(_, t)| *t)
+        .collect();
+    if close.len() == 1 {
+        return Ok(todo.id);
+    }
+    let names: Vec<String> = close
+        .iter()
+        .map(|t| format!("#{} {}", t.id, t.title))
+        .collect();
+    if json_output() || !io::stdin().is_terminal() {
+        return Err(Error::Usage(t!(
+            "'{}' matches several todos ({}); give an id instead",
+            query,
+            names.join(", ")
+        )));
+    }
+    println!("{}", t!("'{}' matches several todos:", query));
+    for (n, name) in names.iter().enumerate() {
+        println!("  {}) {}", n + 1, name);
+    }
+    print!("{} ", t!("Which one? [1-{}, Enter to cancel]", close.len()));
+    let _ = io::stdout().flush();
+    let mut answer = String::new();
+    io::stdin()
+        .lock()
+        .read_line(&mut answer)
+        .map_err(|e| e.to_string())?;
+    match answer.trim().parse::<usize>() {
+        Ok(n) if (1..=close.len()).contains(&n) => Ok(close[n - 1].id),
+        _ => Err(Error::Failed(t!("Cancelled.").to_string())),
+    }
+}
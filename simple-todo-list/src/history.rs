@@ -0,0 +1,55 @@
+use crate::audit::{self, FieldChange};
+use crate::i18n::t;
+use crate::{json_output, print_json};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+/// Prints changed fields as `name: from -> to`, or only the name when the
+/// values weren't kept.
+pub fn print_fields(fields: &BTreeMap<String, FieldChange>) {
+    for (name, f) in fields {
+        if f.from.is_none() && f.to.is_none() {
+            println!("    {}", name);
+        } else {
+            println!("    {}: {} -> {}", name, describe(&f.from), describe(&f.to));
+        }
+    }
+}
+
+/// A short, readable form of a field's value.
+fn describe(v: &Option<Value>) -> String {
+    match v {
+        None | Some(Value::Null) => "none".to_string(),
+        Some(Value::String(s)) => format!("{:?}", s),
+        Some(Value::Array(items)) => format!("[{} item(s)]", items.len()),
+        Some(v) => v.to_string(),
+    }
+}
+
+/// Prints the changes to `list`, oldest first: the last `limit` of them, or
+/// only those to todo `id`.
+pub fn show(list: &str, id: Option<u64>, limit: usize) -> Result<(), String> {
+    let entries = audit::entries(list, id, limit)?;
+    if json_output() {
+        print_json(&entries);
+        return Ok(());
+    }
+    if entries.is_empty() {
+        println!("{}", t!("No recorded changes yet."));
+        return Ok(());
+    }
+    for e in entries {
+        let title = e.title.map(|t| format!(" {}", t)).unwrap_or_default();
+        println!(
+            "{}  {}  #{} {}{}  ({})",
+            e.at.format("%Y-%m-%d %H:%M"),
+            e.user,
+            e.id,
+            e.change.as_str(),
+            title,
+            e.command
+        );
+        print_fields(&e.fields);
+    }
+    Ok(())
+}
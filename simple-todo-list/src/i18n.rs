@@ -0,0 +1,599 @@
+use crate::config;
+use std::env;
+use std::fmt::{Display, Write};
+use std::sync::OnceLock;
+
+/// Languages messages are printed in.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Lang {
+    English,
+    Spanish,
+}
+
+impl Lang {
+    /// Reads `es`, `es_ES.UTF-8`, `en_GB` and the like.
+    fn parse(s: &str) -> Option<Lang> {
+        let code = s.split(['_', '-', '.', '@']).next().unwrap_or_default();
+        match code.to_lowercase().as_str() {
+            "en" | "c" | "posix" => Some(Lang::English),
+            "es" => Some(Lang::Spanish),
+            _ => None,
+        }
+    }
+}
+
+static LANG: OnceLock<Lang> = OnceLock::new();
+
+/// `language` from the config, else the first of `LC_ALL`, `LC_MESSAGES`
+/// and `LANG` that is set; English when neither names a language there
+/// are messages for.
+pub fn lang() -> Lang {
+    *LANG.get_or_init(|| {
+        if let Some(name) = &config::get().language {
+            match Lang::parse(name) {
+                Some(lang) => return lang,
+                None => eprintln!("Unknown language '{}', using English", name),
+            }
+        }
+        ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|var| env::var(var).ok())
+            .find(|value| !value.is_empty())
+            .and_then(|value| Lang::parse(&value))
+            .unwrap_or(Lang::English)
+    })
+}
+
+/// `message` in the user's language. Messages are looked up by their
+/// English text, which is used as is when there is no translation.
+pub fn tr(message: &'static str) -> &'static str {
+    let catalog = match lang() {
+        Lang::English => return message,
+        Lang::Spanish => SPANISH,
+    };
+    catalog
+        .iter()
+        .find(|(english, _)| *english == message)
+        .map_or(message, |(_, translated)| translated)
+}
+
+/// `message` with each `{}` replaced by the next of `args`.
+pub fn fill(message: &str, args: &[&dyn Display]) -> String {
+    let mut parts = message.split("{}");
+    let mut args = args.iter();
+    let mut out = parts.next().unwrap_or_default().to_string();
+    for part in parts {
+        if let Some(arg) = args.next() {
+            let _ = write!(out, "{}", arg);
+        }
+        out.push_str(part);
+    }
+    out
+}
+
+/// A message in the user's language: `t!("Removed (#{}): {}", id, title)`
+/// works like `format!` with `{}` placeholders only.
+macro_rules! t {
+    ($message:literal) => {
+        $crate::i18n::tr($message)
+    };
+    ($message:literal, $($arg:expr),+ $(,)?) => {
+        $crate::i18n::fill($crate::i18n::tr($message), &[$(&$arg),+])
+    };
+}
+pub(crate) use t;
+
+/// Spanish for each message, by its English text. A translation has the
+/// same `{}` placeholders, filled in the same order.
+const SPANISH: &[(&str, &str)] = &[
+    ("Failed to serialize output: {}", "No se pudo serializar la salida: {}"),
+    (
+        "Can't read {}: {}\nRun 'doctor' to repair it from a backup.",
+        "No se puede leer {}: {}\nEjecuta 'doctor' para repararlo desde una copia de seguridad.",
+    ),
+    (
+        "No todo with id {}. Use 'list' to see items.",
+        "No hay ninguna tarea con id {}. Usa 'list' para verlas.",
+    ),
+    ("Failed to save: {}", "No se pudo guardar: {}"),
+    ("{} is already the DB file", "{} ya es el archivo de la base de datos"),
+    ("{} already exists", "{} ya existe"),
+    (
+        "Only JSON, TOML and YAML DBs can be encrypted; `migrate` {} to a .json file first",
+        "Solo se pueden cifrar bases JSON, TOML y YAML; haz antes `migrate` de {} a un .json",
+    ),
+    ("The DB is already encrypted", "La base de datos ya está cifrada"),
+    ("Failed to read {}: {}", "No se pudo leer {}: {}"),
+    ("The DB is not encrypted", "La base de datos no está cifrada"),
+    (
+        "No todos yet. Add one with: add <title> [description]",
+        "Todavía no hay tareas. Añade una con: add <título> [descripción]",
+    ),
+    ("Nothing past {} todo(s).", "No hay nada más allá de {} tarea(s)."),
+    ("Showing {}-{} of {}.", "Mostrando {}-{} de {}."),
+    (" (in progress)", " (en curso)"),
+    (" (blocked by {})", " (bloqueada por {})"),
+    (
+        "Todos read from stdin can't share a description.",
+        "Las tareas leídas de stdin no pueden compartir una descripción.",
+    ),
+    ("Failed to read stdin: {}", "No se pudo leer stdin: {}"),
+    ("Nothing to add: stdin had no lines.", "Nada que añadir: stdin no tenía líneas."),
+    ("A todo needs a title.", "Una tarea necesita un título."),
+    ("Added todo (#{})", "Tarea añadida (#{})"),
+    ("The archive is empty.", "El archivo está vacío."),
+    ("Nothing due today.", "Nada vence hoy."),
+    ("Nothing due this week.", "Nada vence esta semana."),
+    ("Nothing is overdue.", "Nada está vencido."),
+    ("{} {} ({} todos, {} pending, {} done)", "{} {} ({} tareas, {} pendientes, {} hechas)"),
+    ("Now using list '{}'", "Usando ahora la lista '{}'"),
+    ("Failed to export: {}", "No se pudo exportar: {}"),
+    ("Failed to write {}: {}", "No se pudo escribir {}: {}"),
+    ("Exported {} todo(s) to {}", "Exportadas {} tarea(s) a {}"),
+    ("Only --format csv can be imported.", "Solo se puede importar --format csv."),
+    ("Failed to import {}: {}", "No se pudo importar {}: {}"),
+    ("Warning: {}", "Aviso: {}"),
+    (
+        "Imported {} todo(s), skipped {} duplicate(s)",
+        "Importadas {} tarea(s), omitidas {} duplicada(s)",
+    ),
+    ("{} can't be read: {}", "No se puede leer {}: {}"),
+    ("Restore backup {} from {} ({} todos)?", "¿Restaurar la copia {} de {} ({} tareas)?"),
+    ("the unreadable file is now backup 1", "el archivo ilegible es ahora la copia 1"),
+    ("Restored backup {}; {}.", "Restaurada la copia {}; {}."),
+    (
+        "Run 'doctor --repair' to restore backup {}.",
+        "Ejecuta 'doctor --repair' para restaurar la copia {}.",
+    ),
+    ("No backup of it can be read either.", "Tampoco se puede leer ninguna copia de seguridad."),
+    ("No problems in {} ({} todos).", "Ningún problema en {} ({} tareas)."),
+    ("Fixed {} problem(s).", "Corregido(s) {} problema(s)."),
+    ("Run 'doctor --repair' to fix them.", "Ejecuta 'doctor --repair' para corregirlos."),
+    ("No such file: {}", "No existe el archivo: {}"),
+    (
+        "That is the current list; give another todo file to merge.",
+        "Esa es la lista actual; indica otro archivo de tareas para fusionar.",
+    ),
+    ("Added", "Añadidas"),
+    (
+        "{} {} todo(s), skipped {} already here, {} conflict(s).",
+        "{} {} tarea(s), omitidas {} que ya estaban, {} conflicto(s).",
+    ),
+    ("took theirs", "se tomó la suya"),
+    ("kept ours", "se mantuvo la nuestra"),
+    ("  {} {} (theirs {}): {} — {}", "  {} {} (la suya {}): {} — {}"),
+    ("Nothing to archive.", "Nada que archivar."),
+    ("Nothing was archived in {}.", "No se archivó nada en {}."),
+    ("Failed to save archive: {}", "No se pudo guardar el archivo: {}"),
+    ("Archived {} completed todo(s) to {}", "Archivadas {} tarea(s) completadas en {}"),
+    (
+        "#{} is blocked by {}; finish those first or use --force.",
+        "#{} está bloqueada por {}; termina esas primero o usa --force.",
+    ),
+    (
+        "#{} is blocked by {}; finish those first.",
+        "#{} está bloqueada por {}; termina esas primero.",
+    ),
+    ("Marked as done (#{}): {}", "Marcada como hecha (#{}): {}"),
+    ("Next occurrence (#{}) due {}", "Siguiente repetición (#{}) vence el {}"),
+    ("Warning: {} subtask(s) still pending.", "Aviso: {} subtarea(s) siguen pendientes."),
+    (
+        "All subtasks done; marked parent as done (#{}): {}",
+        "Todas las subtareas hechas; tarea padre marcada como hecha (#{}): {}",
+    ),
+    ("Marked as not done (#{}): {}", "Marcada como no hecha (#{}): {}"),
+    ("Reopened parent (#{}): {}", "Tarea padre reabierta (#{}): {}"),
+    ("Removed (#{}): {}", "Eliminada (#{}): {}"),
+    ("Also removed {} subtask(s).", "También se eliminaron {} subtarea(s)."),
+    (
+        "Error: 'edit' requires a new <title> or a flag to change.",
+        "Error: 'edit' necesita un nuevo <título> o una opción que cambiar.",
+    ),
+    ("Updated (#{}).", "Actualizada (#{})."),
+    ("Cloned #{} as #{} with {} subtask(s).", "Clonada #{} como #{} con {} subtarea(s)."),
+    ("Cloned #{} as #{}: {}", "Clonada #{} como #{}: {}"),
+    ("Moved #{} to position {}.", "Movida #{} a la posición {}."),
+    ("#{} can't swap with itself.", "#{} no se puede intercambiar consigo misma."),
+    (
+        "#{} and #{} have different parents; use 'move' within one of them.",
+        "#{} y #{} tienen padres distintos; usa 'move' dentro de uno de ellos.",
+    ),
+    ("Swapped #{} and #{}.", "Intercambiadas #{} y #{}."),
+    ("A todo can't wait for itself.", "Una tarea no puede esperarse a sí misma."),
+    (
+        "#{} already waits for #{}; that would be a cycle.",
+        "#{} ya espera a #{}; eso sería un ciclo.",
+    ),
+    ("#{} now waits for {}.", "#{} espera ahora a {}."),
+    ("Removed {} blocker(s) from #{}.", "Quitado(s) {} bloqueo(s) de #{}."),
+    ("Terminal error: {}", "Error de terminal: {}"),
+    ("No backups of {} yet.", "Todavía no hay copias de seguridad de {}."),
+    ("{}  {}  {} todo(s)", "{}  {}  {} tarea(s)"),
+    ("{}  {}  unreadable: {}", "{}  {}  ilegible: {}"),
+    ("No backup {} of {}", "No existe la copia {} de {}"),
+    ("Restored backup {} of {}.", "Restaurada la copia {} de {}."),
+    ("No todos to save in the template.", "No hay tareas que guardar en la plantilla."),
+    ("Saved template '{}' with {} todo(s).", "Guardada la plantilla '{}' con {} tarea(s)."),
+    ("Added todo #{} from '{}'.", "Añadida la tarea #{} desde '{}'."),
+    ("Added {} todos from '{}' (#{}-#{}).", "Añadidas {} tareas desde '{}' (#{}-#{})."),
+    ("No templates yet.", "Todavía no hay plantillas."),
+    ("{}  {} todo(s)", "{}  {} tarea(s)"),
+    ("Removed template '{}'.", "Eliminada la plantilla '{}'."),
+    ("Saved snapshot '{}' of {} list(s).", "Guardada la instantánea '{}' de {} lista(s)."),
+    ("Restored snapshot '{}' ({} todo(s)).", "Restaurada la instantánea '{}' ({} tarea(s))."),
+    ("No snapshots yet.", "Todavía no hay instantáneas."),
+    ("{}  {}  {} list(s)", "{}  {}  {} lista(s)"),
+    ("Copied {} todo(s) to {}.", "Copiadas {} tarea(s) a {}."),
+    ("Set TODO_DB={} to use it.", "Define TODO_DB={} para usarla."),
+    ("Migration failed: {}", "La migración falló: {}"),
+    (
+        "Encrypted {} file(s); plaintext backups were deleted.",
+        "Cifrado(s) {} archivo(s); se borraron las copias sin cifrar.",
+    ),
+    (
+        "Run `todo unlock` to avoid typing the passphrase every time.",
+        "Ejecuta `todo unlock` para no escribir la contraseña cada vez.",
+    ),
+    ("Encryption failed: {}", "El cifrado falló: {}"),
+    ("Decrypted {} file(s).", "Descifrado(s) {} archivo(s)."),
+    ("Decryption failed: {}", "El descifrado falló: {}"),
+    (
+        "Unlocked until `todo lock` or logout.",
+        "Desbloqueada hasta `todo lock` o el cierre de sesión.",
+    ),
+    ("Locked.", "Bloqueada."),
+    ("Not unlocked.", "No estaba desbloqueada."),
+    ("Failed to forget the key: {}", "No se pudo olvidar la clave: {}"),
+    (
+        "Synced with {}: {} sent, {} received, {} deleted here, {} deleted there.",
+        "Sincronizado con {}: {} enviadas, {} recibidas, {} borradas aquí, {} borradas allí.",
+    ),
+    (
+        "#{} changed on both sides; kept the server's version.",
+        "#{} cambió en ambos lados; se mantuvo la versión del servidor.",
+    ),
+    ("Sync failed: {}", "La sincronización falló: {}"),
+    (
+        "No contexts yet. Add one with: edit <id> --context @home",
+        "Todavía no hay contextos. Añade uno con: edit <id> --context @casa",
+    ),
+    ("{}  {} open, {} done", "{}  {} abiertas, {} hechas"),
+    ("{} open todo(s) without a context", "{} tarea(s) abiertas sin contexto"),
+    ("#{} is already done.", "#{} ya está hecha."),
+    ("The timer of #{} is already running.", "El temporizador de #{} ya está en marcha."),
+    ("Stopped (#{}) after {}: {}", "Detenida (#{}) tras {}: {}"),
+    ("Started timer (#{}): {}", "Temporizador iniciado (#{}): {}"),
+    ("No timer is running.", "No hay ningún temporizador en marcha."),
+    ("Total tracked: {}", "Total registrado: {}"),
+    ("Snoozed (#{}) until {}.", "Pospuesta (#{}) hasta el {}."),
+    ("Committed changes in {}.", "Cambios confirmados en {}."),
+    ("No local changes to commit.", "No hay cambios locales que confirmar."),
+    ("Pulled from the remote.", "Traído del remoto."),
+    ("Pushed to the remote.", "Enviado al remoto."),
+    (
+        "No remote configured; set `remote` under [sync] in the config.",
+        "No hay remoto configurado; define `remote` en [sync] en la configuración.",
+    ),
+    (
+        "Set TELOXIDE_TOKEN to the token BotFather gave you",
+        "Define TELOXIDE_TOKEN con el token que te dio BotFather",
+    ),
+    ("Can't reach Telegram: {}", "No se puede contactar con Telegram: {}"),
+    ("Answering as @{} (Ctrl-C to stop)", "Respondiendo como @{} (Ctrl-C para parar)"),
+    ("Ignored a message from chat {}", "Ignorado un mensaje del chat {}"),
+    ("Unknown command. Send /help to see them.", "Comando desconocido. Envía /help para verlos."),
+    ("Usage: /add <title>", "Uso: /add <título>"),
+    ("Usage: /list or /list all", "Uso: /list o /list all"),
+    ("Usage: {} <id>", "Uso: {} <id>"),
+    (
+        "No todo with id {}. Send /list to see them.",
+        "No hay ninguna tarea con id {}. Envía /list para verlas.",
+    ),
+    (
+        "Nothing to do. Add a todo with /add <title>",
+        "Nada que hacer. Añade una tarea con /add <título>",
+    ),
+    (
+        "No todo matches '{}'. Use 'list' to see items.",
+        "Ninguna tarea coincide con '{}'. Usa 'list' para verlas.",
+    ),
+    (
+        "'{}' matches several todos ({}); give an id instead",
+        "'{}' coincide con varias tareas ({}); indica un id",
+    ),
+    ("'{}' matches several todos:", "'{}' coincide con varias tareas:"),
+    ("Which one? [1-{}, Enter to cancel]", "¿Cuál? [1-{}, Enter para cancelar]"),
+    ("Cancelled.", "Cancelado."),
+    ("Empty note; nothing was saved.", "Nota vacía; no se guardó nada."),
+    ("#{} was removed meanwhile.", "#{} fue eliminada mientras tanto."),
+    ("Added a note to (#{}): {}", "Nota añadida a (#{}): {}"),
+    ("{} exited with {}", "{} terminó con {}"),
+    ("Failed to run {}: {}", "No se pudo ejecutar {}: {}"),
+    ("done", "hecha"),
+    ("in progress", "en curso"),
+    ("open", "abierta"),
+    ("Status", "Estado"),
+    ("Priority", "Prioridad"),
+    ("Due", "Vence"),
+    ("Repeats", "Se repite"),
+    ("Tags", "Etiquetas"),
+    ("Contexts", "Contextos"),
+    ("Parent", "Padre"),
+    ("Subtasks", "Subtareas"),
+    ("Waits for", "Espera a"),
+    ("Created", "Creada"),
+    ("Completed", "Completada"),
+    ("Snoozed", "Pospuesta"),
+    ("until {}", "hasta el {}"),
+    (" (timer running)", " (temporizador en marcha)"),
+    ("Tracked", "Registrado"),
+    ("Pomodoros", "Pomodoros"),
+    ("Notes:", "Notas:"),
+    ("Nothing matches.", "Nada coincide."),
+    (" (pinned)", " (fijada)"),
+    ("Pinned (#{}): {}", "Fijada (#{}): {}"),
+    ("Unpinned (#{}): {}", "Desfijada (#{}): {}"),
+    ("Pinned", "Fijada"),
+    ("yes", "sí"),
+    ("Estimate", "Estimación"),
+    ("No conflicts.", "No hay conflictos."),
+    ("#{} has no conflict in {}.", "#{} no tiene ningún conflicto en {}."),
+    ("#{} has no conflicts.", "#{} no tiene conflictos."),
+    (
+        "Can't take the other side's value: {}",
+        "No se puede tomar el valor del otro lado: {}",
+    ),
+    ("Kept this side's {} of #{}.", "Se mantuvo {} de este lado en #{}."),
+    ("Took the other side's {} for #{}.", "Se tomó {} del otro lado para #{}."),
+    ("here {}, there {}", "aquí {}, allá {}"),
+    (
+        "Keep this side's value with 'resolve <id> --ours', or 'resolve <id> --theirs'.",
+        "Mantén el valor de este lado con 'resolve <id> --ours', o usa 'resolve <id> --theirs'.",
+    ),
+    (
+        "{} conflict(s) from sync; see 'resolve'.",
+        "{} conflicto(s) de la sincronización; mira 'resolve'.",
+    ),
+    (
+        "Merged {} todo(s) changed on both sides.",
+        "Se combinaron {} tarea(s) cambiadas en ambos lados.",
+    ),
+    (
+        "{} field(s) changed differently on both sides kept this side's value; see 'resolve'.",
+        "{} campo(s) cambiados de forma distinta en ambos lados mantienen el valor de este lado; \
+         mira 'resolve'.",
+    ),
+    (
+        "Invalid regular expression '{}': {}",
+        "Expresión regular no válida '{}': {}",
+    ),
+    (
+        "--dry-run only works with commands that change todos, like add or edit.",
+        "--dry-run solo funciona con comandos que cambian tareas, como add o edit.",
+    ),
+    (
+        "Interactive mode saves nothing until 'save' anyway; drop --dry-run.",
+        "El modo interactivo no guarda nada hasta 'save'; quita --dry-run.",
+    ),
+    (
+        "Dry run, nothing was saved. The changes would be:",
+        "Simulación, no se guardó nada. Los cambios serían:",
+    ),
+    ("Dry run: nothing would change.", "Simulación: nada cambiaría."),
+    (
+        "Restoring a backup can't be previewed; drop --dry-run.",
+        "No se puede simular la restauración de una copia; quita --dry-run.",
+    ),
+    (
+        "{} todo(s): {} open, {} done",
+        "{} tarea(s): {} abiertas, {} hechas",
+    ),
+    (" ({} more archived)", " ({} más archivadas)"),
+    ("Open by priority: {}", "Abiertas por prioridad: {}"),
+    ("Tags:", "Etiquetas:"),
+    ("  {}  {} open, {} done", "  {}  {} abiertas, {} hechas"),
+    ("Completed per week:", "Completadas por semana:"),
+    (
+        "Average time to complete: {} (over {} todo(s))",
+        "Tiempo medio hasta completar: {} (sobre {} tarea(s))",
+    ),
+    (
+        "Average time to complete: no completed todos with timestamps yet",
+        "Tiempo medio hasta completar: todavía no hay tareas completadas con fechas",
+    ),
+    ("Streak: {} (longest {})", "Racha: {} (la más larga {})"),
+    ("Overdue ({}):", "Vencidas ({}):"),
+    (
+        "No streak going. Finish a todo today to start one.",
+        "No hay ninguna racha. Termina una tarea hoy para empezar una.",
+    ),
+    (
+        "Current streak: {}, today included.",
+        "Racha actual: {}, hoy incluido.",
+    ),
+    (
+        "Current streak: {}. Finish a todo today to keep it going.",
+        "Racha actual: {}. Termina una tarea hoy para mantenerla.",
+    ),
+    (
+        "Longest streak: {} ({} to {})",
+        "Racha más larga: {} ({} a {})",
+    ),
+    (
+        "Completed per weekday:",
+        "Completadas por día de la semana:",
+    ),
+    (
+        "No time tracked since {}. Start a timer with: start <id>",
+        "No hay tiempo registrado desde {}. Inicia un temporizador con: start <id>",
+    ),
+    ("Tracked since {}: {}", "Registrado desde {}: {}"),
+    ("Per day:", "Por día:"),
+    ("Per tag:", "Por etiqueta:"),
+    ("(untagged)", "(sin etiqueta)"),
+    ("Per todo:", "Por tarea:"),
+    (
+        "Unknown estimate unit '{}', using points",
+        "Unidad de estimación desconocida '{}', se usan puntos",
+    ),
+    (
+        "No todos have an estimate yet. Add one with: edit <id> --estimate 3",
+        "Ninguna tarea tiene estimación todavía. Añade una con: edit <id> --estimate 3",
+    ),
+    (
+        "Work left ({}) at the end of each day:",
+        "Trabajo restante ({}) al final de cada día:",
+    ),
+    (
+        "Done since {}: {} ({} a day)",
+        "Hecho desde {}: {} ({} al día)",
+    ),
+    (
+        "Nothing estimated is left to do.",
+        "No queda nada estimado por hacer.",
+    ),
+    (
+        "Left: {}; at this pace it takes about {} more day(s), until {}.",
+        "Queda: {}; a este ritmo lleva unos {} día(s) más, hasta {}.",
+    ),
+    (
+        "Left: {}; nothing estimated was done lately to tell a pace.",
+        "Queda: {}; no se hizo nada estimado últimamente para saber el ritmo.",
+    ),
+    (
+        "{} open todo(s) have no estimate.",
+        "{} tarea(s) abiertas no tienen estimación.",
+    ),
+    (
+        "Invalid status '{}': use backlog, in-progress or done",
+        "Estado no válido '{}': usa backlog, in-progress o done",
+    ),
+    ("Backlog", "Pendientes"),
+    ("In progress", "En curso"),
+    ("Done", "Hechas"),
+    (
+        "No open todos have tags. Add one with: edit <id> --tag <tag>",
+        "Ninguna tarea abierta tiene etiquetas. Añade una con: edit <id> --tag <etiqueta>",
+    ),
+    ("No tag", "Sin etiqueta"),
+    (
+        "Invalid month '{}': use YYYY-MM, a month name, this, next or last",
+        "Mes no válido '{}': usa AAAA-MM, el nombre de un mes, this, next o last",
+    ),
+    ("Nothing due on {}.", "Nada vence el {}."),
+    ("Due on {}:", "Vencen el {}:"),
+    ("No todo with id {}", "No hay ninguna tarea con id {}"),
+    ("{} for {} minute(s)...", "{} durante {} minuto(s)..."),
+    ("{} over.", "{} terminado."),
+    (
+        "Stopped the timer of #{}.",
+        "Detenido el temporizador de #{}.",
+    ),
+    (
+        "Pomodoro on (#{}): {} (Ctrl-C to give up)",
+        "Pomodoro en (#{}): {} (Ctrl-C para abandonar)",
+    ),
+    ("Focus {}/{}", "Concentración {}/{}"),
+    ("Break", "Descanso"),
+    ("Mark (#{}) {} as done?", "¿Marcar (#{}) {} como hecha?"),
+    (
+        "{} pomodoro(s) on #{} so far ({} total).",
+        "{} pomodoro(s) en #{} hasta ahora ({} en total).",
+    ),
+    (
+        "Type a command (e.g. `add milk`, `done 2`, `list`), `help`, or `exit`.",
+        "Escribe un comando (p. ej. `add leche`, `done 2`, `list`), `help` o `exit`.",
+    ),
+    ("Saved.", "Guardado."),
+    (
+        "Already in interactive mode.",
+        "Ya estás en modo interactivo.",
+    ),
+    (
+        "No recorded changes yet.",
+        "Todavía no hay cambios registrados.",
+    ),
+    (
+        "Invalid snapshot name '{}': use letters, digits, '-' or '_'",
+        "Nombre de instantánea no válido '{}': usa letras, dígitos, '-' o '_'",
+    ),
+    (
+        "No snapshot '{}'. Use 'snapshot list' to see them.",
+        "No hay ninguna instantánea '{}'. Usa 'snapshot list' para verlas.",
+    ),
+    (
+        "Snapshot '{}' already exists",
+        "La instantánea '{}' ya existe",
+    ),
+    ("Failed to create {}: {}", "No se pudo crear {}: {}"),
+    ("Failed to copy {}: {}", "No se pudo copiar {}: {}"),
+    ("No differences.", "No hay diferencias."),
+    (
+        "Invalid template name '{}': use letters, digits, '-' or '_'",
+        "Nombre de plantilla no válido '{}': usa letras, dígitos, '-' o '_'",
+    ),
+    (
+        "No template '{}'. Use 'template list' to see them.",
+        "No hay ninguna plantilla '{}'. Usa 'template list' para verlas.",
+    ),
+    (
+        "Template '{}' already exists; use --force to replace it",
+        "La plantilla '{}' ya existe; usa --force para reemplazarla",
+    ),
+    ("No template '{}'", "No hay ninguna plantilla '{}'"),
+    ("Failed to remove {}: {}", "No se pudo eliminar {}: {}"),
+    (
+        "Unknown format '{}': use csv, md or ics",
+        "Formato desconocido '{}': usa csv, md o ics",
+    ),
+    (
+        "Unknown grouping '{}': use status or tag",
+        "Agrupación desconocida '{}': usa status o tag",
+    ),
+    (
+        "Unknown source '{}': use todoist or trello",
+        "Origen desconocido '{}': usa todoist o trello",
+    ),
+    (
+        "Expected an id or words of a title",
+        "Se esperaba un id o palabras de un título",
+    ),
+    (
+        "Invalid range '{}': {} is after {}",
+        "Rango no válido '{}': {} va después de {}",
+    ),
+    (
+        "Invalid range '{}': at most {} ids at once",
+        "Rango no válido '{}': como mucho {} ids a la vez",
+    ),
+    (
+        "'{}' needs a value, e.g. {}=42",
+        "'{}' necesita un valor, p. ej. {}=42",
+    ),
+    (
+        "Invalid position '{}': use a number from 1, top or bottom",
+        "Posición no válida '{}': usa un número desde 1, top o bottom",
+    ),
+    (
+        "Invalid id '{}': must be a positive number",
+        "Id no válido '{}': debe ser un número positivo",
+    ),
+    (
+        "Alias '{}' expands to itself",
+        "El alias '{}' se expande a sí mismo",
+    ),
+    ("Alias '{}' is empty", "El alias '{}' está vacío"),
+    ("Unterminated quote.", "Comillas sin cerrar."),
+    ("Failed to run git: {}", "No se pudo ejecutar git: {}"),
+    (
+        "{} is inside the git repository {}; keep the DB in a directory of its own \
+         (see TODO_DB) to sync it",
+        "{} está dentro del repositorio git {}; pon la base de datos en un directorio \
+         propio (mira TODO_DB) para sincronizarla",
+    ),
+    ("{} at {}: {}", "{} en {}: {}"),
+    (
+        "{}\nThe pull was undone; merge the changes with git in {}",
+        "{}\nSe deshizo el pull; combina los cambios con git en {}",
+    ),
+];
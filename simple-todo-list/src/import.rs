@@ -0,0 +1,421 @@
+use crate::due::{parse_due, Repeat};
+use crate::export::CSV_COLUMNS;
+use crate::i18n::t;
+use crate::Note;
+use crate::{
+    next_id, normalize_context, normalize_meta_key, normalize_tag, now, parse_estimate, Priority,
+    Todo,
+};
+use chrono::{DateTime, Local, NaiveDate};
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+
+/// Apps whose exports `import --from` understands.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Source {
+    Todoist,
+    Trello,
+}
+
+impl Source {
+    pub fn parse(s: &str) -> Result<Source, String> {
+        match s.to_lowercase().as_str() {
+            "todoist" => Ok(Source::Todoist),
+            "trello" => Ok(Source::Trello),
+            _ => Err(t!("Unknown source '{}': use todoist or trello", s)),
+        }
+    }
+}
+
+/// Column order for hand-written files without a header row.
+const HEADERLESS_COLUMNS: [&str; 7] = [
+    "title",
+    "description",
+    "completed",
+    "due",
+    "priority",
+    "repeat",
+    "tags",
+];
+
+/// Reads todos from CSV. A first row made only of known column names is
+/// treated as a header and columns are matched by name, in any order;
+/// without one, columns are taken in `HEADERLESS_COLUMNS` order. Ids in
+/// the result are the ones from the file (0 when absent) and are remapped
+/// by `merge`.
+pub fn parse_csv(content: &str) -> Result<Vec<Todo>, String> {
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .trim(csv::Trim::All)
+        .from_reader(content.as_bytes());
+    let mut records = reader.records().peekable();
+
+    let is_header = match records.peek() {
+        Some(Ok(first)) => {
+            !first.is_empty()
+                && first.iter().all(|f| {
+                    let f = f.to_lowercase();
+                    CSV_COLUMNS.contains(&f.as_str()) || f.starts_with("meta.")
+                })
+        }
+        _ => false,
+    };
+    let columns: Vec<String> = if is_header {
+        let header = records.next().unwrap().map_err(|e| e.to_string())?;
+        header.iter().map(|f| f.to_lowercase()).collect()
+    } else {
+        HEADERLESS_COLUMNS.iter().map(|c| c.to_string()).collect()
+    };
+    if !columns.iter().any(|c| c == "title") {
+        return Err("CSV header has no 'title' column".to_string());
+    }
+
+    let mut todos = Vec::new();
+    for (n, record) in records.enumerate() {
+        let line = n + 1 + usize::from(is_header);
+        let record = record.map_err(|e| format!("line {}: {}", line, e))?;
+        let field = |name: &str| -> &str {
+            columns
+                .iter()
+                .position(|c| c == name)
+                .and_then(|i| record.get(i))
+                .unwrap_or("")
+        };
+        let title = field("title");
+        if title.is_empty() {
+            continue;
+        }
+        let err = |e: String| format!("line {}: {}", line, e);
+        let mut meta = BTreeMap::new();
+        for (column, value) in columns.iter().zip(record.iter()) {
+            if let Some(key) = column.strip_prefix("meta.").filter(|_| !value.is_empty()) {
+                meta.insert(normalize_meta_key(key).map_err(err)?, value.to_string());
+            }
+        }
+        todos.push(Todo {
+            id: field("id").parse().unwrap_or(0),
+            title: title.to_string(),
+            description: field("description").to_string(),
+            completed: parse_bool(field("completed")),
+            due: optional(field("due"), |s| {
+                NaiveDate::parse_from_str(s, "%Y-%m-%d")
+                    .map_err(|_| format!("invalid due date '{}'", s))
+            })
+            .map_err(err)?,
+            priority: optional(field("priority"), Priority::parse).map_err(err)?,
+            parent: field("parent").parse().ok(),
+            repeat: optional(field("repeat"), Repeat::parse).map_err(err)?,
+            tags: field("tags")
+                .split(|c: char| c == ',' || c == ';' || c.is_whitespace())
+                .filter(|t| !t.is_empty())
+                .map(normalize_tag)
+                .collect::<Result<_, _>>()
+                .map_err(err)?,
+            contexts: field("contexts")
+                .split(|c: char| c == ',' || c == ';' || c.is_whitespace())
+                .filter(|c| !c.is_empty())
+                .map(normalize_context)
+                .collect::<Result<_, _>>()
+                .map_err(err)?,
+            snoozed: None,
+            created_at: None,
+            completed_at: None,
+            time: Vec::new(),
+            pomodoros: 0,
+            in_progress: false,
+            pinned: false,
+            estimate: optional(field("estimate"), parse_estimate).map_err(err)?,
+            blocked_by: Vec::new(),
+            notes: Vec::new(),
+            meta,
+        });
+    }
+    Ok(todos)
+}
+
+fn blank(title: &str) -> Todo {
+    Todo {
+        id: 0,
+        title: title.to_string(),
+        description: String::new(),
+        completed: false,
+        due: None,
+        priority: None,
+        parent: None,
+        repeat: None,
+        tags: Vec::new(),
+        contexts: Vec::new(),
+        snoozed: None,
+        created_at: None,
+        completed_at: None,
+        time: Vec::new(),
+        pomodoros: 0,
+        in_progress: false,
+        pinned: false,
+        estimate: None,
+        blocked_by: Vec::new(),
+        notes: Vec::new(),
+        meta: BTreeMap::new(),
+    }
+}
+
+/// A Todoist project or Trello list name as a tag: `In Progress` -> `in-progress`.
+fn name_tag(name: &str) -> Option<String> {
+    let words: Vec<&str> = name.split_whitespace().collect();
+    normalize_tag(&words.join("-")).ok()
+}
+
+/// Reads a Todoist CSV export (`TYPE,CONTENT,DESCRIPTION,PRIORITY,INDENT,...,DATE`).
+/// Tasks keep their `@labels` as tags and the section they are in as one
+/// more tag; `INDENT` makes subtasks and `note` rows become notes. Priority
+/// 1 is high, 2 medium and 3 low. `DATE` is read as a due date or a repeat
+/// where this tool understands it; others are returned as warnings.
+pub fn parse_todoist(content: &str, today: NaiveDate) -> Result<(Vec<Todo>, Vec<String>), String> {
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .trim(csv::Trim::All)
+        .from_reader(content.as_bytes());
+    let columns: Vec<String> = reader
+        .headers()
+        .map_err(|e| e.to_string())?
+        .iter()
+        .map(|h| h.to_uppercase())
+        .collect();
+    if !columns.iter().any(|c| c == "CONTENT") {
+        return Err("not a Todoist export: no CONTENT column".to_string());
+    }
+    let mut todos: Vec<Todo> = Vec::new();
+    let mut warnings = Vec::new();
+    let mut section = None;
+    // Ids of the latest task at each indentation level, for subtasks.
+    let mut ancestors: Vec<u64> = Vec::new();
+    for (n, record) in reader.records().enumerate() {
+        let line = n + 2;
+        let record = record.map_err(|e| format!("line {}: {}", line, e))?;
+        let field = |name: &str| -> &str {
+            columns
+                .iter()
+                .position(|c| c == name)
+                .and_then(|i| record.get(i))
+                .unwrap_or("")
+        };
+        let content = field("CONTENT");
+        match field("TYPE").to_lowercase().as_str() {
+            "section" => {
+                section = name_tag(content);
+                continue;
+            }
+            "note" => {
+                if let Some(t) = todos.last_mut().filter(|_| !content.is_empty()) {
+                    t.notes.push(Note {
+                        at: now(),
+                        text: content.to_string(),
+                    });
+                }
+                continue;
+            }
+            "task" | "" if !content.is_empty() => {}
+            _ => continue,
+        }
+        let mut title = Vec::new();
+        let mut tags = Vec::new();
+        for word in content.split_whitespace() {
+            match word.strip_prefix('@').map(normalize_tag) {
+                Some(Ok(tag)) => tags.push(tag),
+                _ => title.push(word),
+            }
+        }
+        let mut t = blank(&title.join(" "));
+        t.id = todos.len() as u64 + 1;
+        t.description = field("DESCRIPTION").to_string();
+        t.priority = match field("PRIORITY") {
+            "1" => Some(Priority::High),
+            "2" => Some(Priority::Medium),
+            "3" => Some(Priority::Low),
+            _ => None,
+        };
+        t.tags = tags;
+        t.tags
+            .extend(section.clone().filter(|s| !t.tags.contains(s)));
+        let date = field("DATE");
+        if !date.is_empty() {
+            if let Ok(r) = Repeat::parse(date) {
+                t.repeat = Some(r);
+                t.due = Some(today);
+            } else if let Ok(d) = parse_due(date, today) {
+                t.due = Some(d);
+            } else {
+                warnings.push(format!("line {}: kept no due date for '{}'", line, date));
+            }
+        }
+        let depth = field("INDENT").parse::<usize>().unwrap_or(1).max(1);
+        ancestors.truncate(depth - 1);
+        t.parent = ancestors.last().copied();
+        ancestors.push(t.id);
+        todos.push(t);
+    }
+    Ok((todos, warnings))
+}
+
+#[derive(Deserialize)]
+struct TrelloBoard {
+    #[serde(default)]
+    lists: Vec<TrelloList>,
+    #[serde(default)]
+    cards: Vec<TrelloCard>,
+    #[serde(default)]
+    checklists: Vec<TrelloChecklist>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TrelloList {
+    id: String,
+    name: String,
+    #[serde(default)]
+    closed: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TrelloCard {
+    id: String,
+    name: String,
+    #[serde(default)]
+    desc: String,
+    #[serde(default)]
+    due: Option<DateTime<chrono::Utc>>,
+    #[serde(default)]
+    due_complete: bool,
+    #[serde(default)]
+    closed: bool,
+    #[serde(default)]
+    id_list: String,
+    #[serde(default)]
+    labels: Vec<TrelloLabel>,
+}
+
+#[derive(Deserialize)]
+struct TrelloLabel {
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    color: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TrelloChecklist {
+    id_card: String,
+    #[serde(default)]
+    check_items: Vec<TrelloCheckItem>,
+}
+
+#[derive(Deserialize)]
+struct TrelloCheckItem {
+    name: String,
+    #[serde(default)]
+    state: String,
+}
+
+/// Reads a Trello board exported as JSON. Cards become todos tagged with
+/// their list and labels (a label without a name by its color), checklist
+/// items become subtasks, and archived cards or lists, like cards whose due
+/// date is marked complete, come in as done.
+pub fn parse_trello(content: &str) -> Result<Vec<Todo>, String> {
+    let board: TrelloBoard =
+        serde_json::from_str(content).map_err(|e| format!("not a Trello board export: {}", e))?;
+    let mut todos = Vec::new();
+    for card in &board.cards {
+        let list = board.lists.iter().find(|l| l.id == card.id_list);
+        let mut t = blank(&card.name);
+        t.id = todos.len() as u64 + 1;
+        t.description = card.desc.clone();
+        t.due = card.due.map(|d| d.with_timezone(&Local).date_naive());
+        t.completed = card.closed || card.due_complete || list.is_some_and(|l| l.closed);
+        // Trello ids start with the creation time in seconds, in hex.
+        t.created_at = card
+            .id
+            .get(..8)
+            .and_then(|hex| i64::from_str_radix(hex, 16).ok())
+            .and_then(|secs| DateTime::from_timestamp(secs, 0))
+            .map(|d| d.with_timezone(&Local).naive_local());
+        let labels = card.labels.iter().map(|l| match l.name.trim() {
+            "" => l.color.as_deref().unwrap_or_default(),
+            name => name,
+        });
+        for tag in list.map(|l| l.name.as_str()).into_iter().chain(labels) {
+            if let Some(tag) = name_tag(tag).filter(|tag| !t.tags.contains(tag)) {
+                t.tags.push(tag);
+            }
+        }
+        let parent = t.id;
+        todos.push(t);
+        for item in board
+            .checklists
+            .iter()
+            .filter(|c| c.id_card == card.id)
+            .flat_map(|c| &c.check_items)
+        {
+            let mut sub = blank(&item.name);
+            sub.id = todos.len() as u64 + 1;
+            sub.parent = Some(parent);
+            sub.completed = item.state == "complete";
+            todos.push(sub);
+        }
+    }
+    Ok(todos)
+}
+
+fn optional<T>(s: &str, parse: impl Fn(&str) -> Result<T, String>) -> Result<Option<T>, String> {
+    if s.is_empty() {
+        Ok(None)
+    } else {
+        parse(s).map(Some)
+    }
+}
+
+fn parse_bool(s: &str) -> bool {
+    matches!(
+        s.to_lowercase().as_str(),
+        "true" | "yes" | "y" | "1" | "x" | "done"
+    )
+}
+
+pub struct MergeReport {
+    pub added: usize,
+    pub duplicates: usize,
+}
+
+/// Appends `incoming` to `todos` with fresh ids, keeping parent links that
+/// point inside the imported set. Unless `allow_duplicates` is set, a todo
+/// whose title and description match an existing one (ignoring case) is
+/// skipped.
+pub fn merge(todos: &mut Vec<Todo>, incoming: Vec<Todo>, allow_duplicates: bool) -> MergeReport {
+    let key = |t: &Todo| (t.title.to_lowercase(), t.description.to_lowercase());
+    let mut report = MergeReport {
+        added: 0,
+        duplicates: 0,
+    };
+    let mut new_ids: HashMap<u64, u64> = HashMap::new();
+    let mut added = Vec::new();
+    for mut t in incoming {
+        if !allow_duplicates && todos.iter().any(|e| key(e) == key(&t)) {
+            report.duplicates += 1;
+            continue;
+        }
+        let id = next_id(todos);
+        if t.id != 0 {
+            new_ids.insert(t.id, id);
+        }
+        t.id = id;
+        todos.push(t);
+        added.push(todos.len() - 1);
+        report.added += 1;
+    }
+    for i in added {
+        todos[i].parent = todos[i].parent.and_then(|p| new_ids.get(&p).copied());
+    }
+    report
+}
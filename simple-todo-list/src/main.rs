@@ -1,266 +1,2288 @@
-use serde::{Deserialize, Serialize};
+mod board;
+mod bot;
+mod burndown;
+mod calendar;
+mod cli;
+mod color;
+mod conflicts;
+mod dry_run;
+mod error;
+mod export;
+mod fuzzy;
+mod history;
+mod i18n;
+mod import;
+mod notes;
+mod pomodoro;
+mod remote;
+mod remind;
+mod repl;
+mod search;
+mod serve;
+mod snapshot;
+mod stats;
+mod streak;
+mod sync;
+mod template;
+mod timesheet;
+mod tui;
+mod watch;
+
+use clap::Parser;
+use cli::{
+    ArchiveAction, BackupAction, Clearable, Cli, Command, SnapshotAction, Target, TemplateAction,
+};
+use color::paint;
+use error::Error;
+use i18n::t;
+use chrono::{Datelike, Local, NaiveDate};
+use serde::Serialize;
+use serde_json::json;
+use std::collections::BTreeMap;
 use std::env;
-use std::fs::{self, File};
-use std::io::{Read, Write};
-use std::path::PathBuf;
+use std::fs;
+use std::io::{self, IsTerminal};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicI32, Ordering};
+use todo_core::{
+    all_lists, append_db, archivable, archive_dir, archive_file, archive_files, archive_month,
+    archive_month_file, archive_months, base_db_path, complete, current_list, current_list_file,
+    db_path, depends_on, duplicate, force_complete, list_file, list_path, lists_at, move_to,
+    next_id, normalize_context, normalize_meta_key, normalize_tag, now, open_blockers,
+    override_list, parse_estimate, parse_meta, position_of, remove_todo, reopen, save_db, save_to,
+    stem_and_ext, today, validate_list_name, Completion, Filter, Note, Priority, Todo,
+    DEFAULT_LIST,
+};
+use todo_core::{
+    audit, backup, config, crypto, doctor, due, lock, merge, query, session, sort, storage, track,
+};
+
+/// The rows `list --offset/--page/--limit` shows, counted in printed order.
+#[derive(Clone, Copy)]
+struct Page {
+    offset: usize,
+    limit: Option<usize>,
+}
+
+/// Set by the global `--json` flag: commands print JSON instead of text.
+static JSON_OUTPUT: AtomicBool = AtomicBool::new(false);
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
-struct Todo {
-    title: String,
-    description: String,
-    completed: bool,
+fn json_output() -> bool {
+    JSON_OUTPUT.load(Ordering::Relaxed)
 }
 
-enum Filter {
-    All,
-    Pending,
-    Done,
+fn print_json(value: &impl Serialize) {
+    match serde_json::to_string_pretty(value) {
+        Ok(json) => println!("{}", json),
+        Err(e) => eprintln!("{}", t!("Failed to serialize output: {}", e)),
+    }
+}
+
+/// The exit status of the first failure, 0 while nothing has failed.
+static EXIT_CODE: AtomicI32 = AtomicI32::new(0);
+
+/// Makes the process exit with `e`'s status, unless something failed
+/// before.
+fn set_failed(e: &Error) {
+    let _ = EXIT_CODE.compare_exchange(0, e.exit_code(), Ordering::Relaxed, Ordering::Relaxed);
 }
 
-fn db_path() -> PathBuf {
-    if let Ok(path) = env::var("TODO_DB") {
-        PathBuf::from(path)
+/// Reports why a command failed: on stderr, or as `{"error": ...,
+/// "code": ...}` on stdout with `--json` so scripts see it in the same
+/// stream.
+fn fail(e: impl Into<Error>) {
+    let e = e.into();
+    set_failed(&e);
+    if json_output() {
+        print_json(&json!({ "error": e.message(), "code": e.code() }));
     } else {
-        PathBuf::from("todos.json")
+        eprintln!("{}", e);
+    }
+}
+
+/// Reports `e` and exits right away.
+fn exit_with(e: Error) -> ! {
+    let code = e.exit_code();
+    fail(e);
+    std::process::exit(code);
+}
+
+/// Prints a line saying what a command changed. A dry run leaves these out,
+/// since the diff it prints says the same.
+fn announce(line: impl std::fmt::Display) {
+    if !session::dry_run() {
+        println!("{}", line);
+    }
+}
+
+/// Asks a yes/no question on stdin; anything but "y"/"yes" (or end of
+/// input) is no.
+fn confirm(question: &str) -> bool {
+    use std::io::{BufRead, Write};
+    if !json_output() {
+        print!("{} [y/N] ", question);
+        let _ = io::stdout().flush();
     }
+    let mut answer = String::new();
+    if io::stdin().lock().read_line(&mut answer).is_err() {
+        return false;
+    }
+    matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
 }
 
 fn load_db() -> Vec<Todo> {
-    let path = db_path();
-    if !path.exists() {
-        return Vec::new();
+    load_from(&db_path())
+}
+
+/// Loads `path`, stopping if it can't be read: going on with an empty list
+/// would overwrite the file.
+fn load_from(path: &Path) -> Vec<Todo> {
+    match todo_core::load(path) {
+        Ok(todos) => todos,
+        Err(e) if crypto::file_salt(path).is_some() => exit_with(Error::Storage(e)),
+        Err(e) => exit_with(Error::Storage(t!(
+            "Can't read {}: {}\nRun 'doctor' to repair it from a backup.",
+            path.display(),
+            e
+        ))),
+    }
+}
+
+/// Every archived todo of the active list, oldest month first.
+fn load_archive() -> Vec<Todo> {
+    archive_files(&db_path())
+        .iter()
+        .flat_map(|f| load_from(f))
+        .collect()
+}
+
+/// Why `path` couldn't be read: missing, or unreadable.
+fn read_error(path: &Path, e: io::Error) -> Error {
+    let message = format!("Failed to read {}: {}", path.display(), e);
+    if e.kind() == io::ErrorKind::NotFound {
+        Error::NotFound(message)
+    } else {
+        Error::Storage(message)
     }
-    let mut file = match File::open(&path) {
-        Ok(f) => f,
-        Err(_) => return Vec::new(),
+}
+
+/// What `done`, `undone` or `remove` did to one target: its JSON result and
+/// the lines to print, or the target (`{"id": ...}` or `{"title": ...}`) and
+/// why it was skipped.
+type ItemResult = Result<(serde_json::Value, Vec<String>), (serde_json::Value, Error)>;
+
+fn no_todo(id: u64) -> (serde_json::Value, Error) {
+    (json!({ "id": id }), not_found(id))
+}
+
+fn not_found(id: u64) -> Error {
+    Error::NotFound(t!("No todo with id {}. Use 'list' to see items.", id))
+}
+
+/// Ids of the todos `targets` name, in the order given and without repeats.
+/// Titles are matched among the todos `pick` accepts; those that match none
+/// (or where the user cancelled the choice) go to `results` as failures.
+fn resolve_targets(
+    todos: &[Todo],
+    targets: Vec<Target>,
+    pick: fn(&Todo) -> bool,
+    results: &mut Vec<ItemResult>,
+) -> Vec<u64> {
+    let candidates: Vec<&Todo> = todos.iter().filter(|t| pick(t)).collect();
+    let mut ids = Vec::new();
+    for target in targets {
+        let found = match target {
+            Target::Ids(ids) => ids,
+            Target::Title(title) => match fuzzy::find(&title, &candidates) {
+                Ok(id) => vec![id],
+                Err(e) => {
+                    results.push(Err((json!({ "title": title }), e)));
+                    continue;
+                }
+            },
+        };
+        for id in found {
+            if !ids.contains(&id) {
+                ids.push(id);
+            }
+        }
+    }
+    ids
+}
+
+/// Index of the todo `target` names, matching titles among the todos `pick`
+/// accepts.
+fn find_target(todos: &[Todo], target: Target, pick: fn(&Todo) -> bool) -> Result<usize, Error> {
+    let id = match target {
+        Target::Ids(ids) => ids[0],
+        Target::Title(title) => {
+            let candidates: Vec<&Todo> = todos.iter().filter(|t| pick(t)).collect();
+            fuzzy::find(&title, &candidates)?
+        }
     };
-    let mut content = String::new();
-    if file.read_to_string(&mut content).is_err() {
-        return Vec::new();
+    position_of(todos, id).ok_or_else(|| not_found(id))
+}
+
+/// Like `find_target`, reporting the failure and returning `None` if there
+/// is no such todo.
+fn resolve_target(todos: &[Todo], target: Target, pick: fn(&Todo) -> bool) -> Option<usize> {
+    find_target(todos, target, pick).map_err(fail).ok()
+}
+
+/// Saves the list if any target changed, then prints every target's result.
+/// With `--json`, a single target prints its object and several print an
+/// array of them, skipped ones as `{"id": ..., "error": ...}`.
+fn save_and_report(todos: &[Todo], results: Vec<ItemResult>, single: bool) {
+    if results.iter().any(|r| r.is_ok()) {
+        if let Err(e) = save_db(todos) {
+            fail(Error::Storage(t!("Failed to save: {}", e)));
+            return;
+        }
     }
-    if content.trim().is_empty() {
-        return Vec::new();
+    if let Some(Err((_, e))) = results.iter().find(|r| r.is_err()) {
+        set_failed(e);
+    }
+    if json_output() {
+        let mut values: Vec<serde_json::Value> = results
+            .into_iter()
+            .map(|r| match r {
+                Ok((value, _)) => value,
+                Err((mut target, e)) => {
+                    target["error"] = json!(e.message());
+                    target["code"] = json!(e.code());
+                    target
+                }
+            })
+            .collect();
+        if single && values.len() == 1 {
+            print_json(&values.remove(0));
+        } else {
+            print_json(&values);
+        }
+        return;
+    }
+    for r in results {
+        match r {
+            Ok((_, lines)) => lines.iter().for_each(announce),
+            Err((_, e)) => eprintln!("{}", e),
+        }
     }
-    serde_json::from_str(&content).unwrap_or_else(|_| Vec::<Todo>::new())
 }
 
-fn save_db(todos: &Vec<Todo>) -> Result<(), String> {
-    let path = db_path();
-    let json = serde_json::to_string_pretty(todos).map_err(|e| e.to_string())?;
-    if let Some(parent) = path.parent() {
-        if !parent.as_os_str().is_empty() {
-            if let Err(e) = fs::create_dir_all(parent) {
-                return Err(format!("Failed to create directory: {}", e));
+/// Copies every list and archive to `target`'s naming and backend. The old
+/// files are left alone until `TODO_DB` points at the new ones.
+fn migrate_to(target: &Path) -> Result<usize, String> {
+    session::flush()?;
+    let base = base_db_path();
+    if base == target {
+        return Err(t!("{} is already the DB file", target.display()));
+    }
+    let mut copied = 0;
+    for name in all_lists() {
+        let from = list_file(&base, &name);
+        let to = list_file(target, &name);
+        let mut pairs = vec![(archive_file(&from), archive_file(&to))];
+        for month in archive_months(&from) {
+            pairs.push((
+                archive_month_file(&from, month),
+                archive_month_file(&to, month),
+            ));
+        }
+        pairs.push((from, to));
+        for (from, to) in pairs {
+            if !from.exists() {
+                continue;
             }
+            if to.exists() {
+                return Err(t!("{} already exists", to.display()));
+            }
+            let todos = todo_core::load(&from)?;
+            save_to(&to, &todos)?;
+            copied += todos.len();
         }
     }
-    let mut file = File::create(&path).map_err(|e| e.to_string())?;
-    file.write_all(json.as_bytes()).map_err(|e| e.to_string())
+    Ok(copied)
 }
 
-fn print_usage() {
-    let exe = env::args().next().unwrap_or_else(|| "todo".to_string());
-    println!("Todo CLI (JSON-backed)\n");
-    println!("Usage:");
-    println!("  {} add <title> [description]        Add a new todo", exe);
-    println!("  {} list [--all|--pending|--done]    List todos (default: --all)", exe);
-    println!("  {} done <index>                     Mark todo as done", exe);
-    println!("  {} undone <index>                   Mark todo as not done", exe);
-    println!("  {} remove <index>                   Remove a todo", exe);
-    println!("  {} edit <index> <title> [desc]      Edit a todo", exe);
-    println!("\nEnvironment:");
-    println!("  TODO_DB=path/to/file.json           Override DB path (default: ./todos.json)");
+/// Every list and archive file of the DB; the default list even before it
+/// has been written.
+fn db_files() -> Vec<PathBuf> {
+    let base = base_db_path();
+    let mut files = vec![base.clone()];
+    for name in all_lists() {
+        let path = list_file(&base, &name);
+        let archives = archive_files(&path);
+        if path != base {
+            files.push(path);
+        }
+        files.extend(archives);
+    }
+    files.retain(|f| *f == base || f.exists());
+    files
+}
+
+/// Files next to the lists that hold copies of their todos: the state of
+/// `sync --remote` and the conflicts waiting for `resolve`.
+fn side_files() -> Vec<PathBuf> {
+    let base = base_db_path();
+    all_lists()
+        .iter()
+        .map(|name| list_file(&base, name))
+        .flat_map(|list| [remote::state_path(&list), conflicts::path(&list)])
+        .filter(|f| f.exists())
+        .collect()
 }
 
-fn list_todos(todos: &Vec<Todo>, filter: Filter) {
-    if todos.is_empty() {
-        println!("No todos yet. Add one with: add <title> [description]");
+/// Only the JSON backend can encrypt.
+fn check_encryptable() -> Result<(), String> {
+    let base = base_db_path();
+    match base.extension().and_then(|e| e.to_str()) {
+        Some("jsonl" | "ndjson" | "txt" | "db" | "sqlite" | "sqlite3") => Err(t!(
+            "Only JSON, TOML and YAML DBs can be encrypted; `migrate` {} to a .json file first",
+            base.display()
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Encrypts all lists with a new passphrase, deletes their plaintext
+/// backups and strips the content from the audit log. Returns how many
+/// files were encrypted.
+fn encrypt_db() -> Result<usize, String> {
+    check_encryptable()?;
+    session::flush()?;
+    if crypto::db_salt().is_some() {
+        return Err(t!("The DB is already encrypted").to_string());
+    }
+    let mut files = db_files();
+    files.extend(snapshot::files());
+    let mut contents = Vec::new();
+    for path in &files {
+        contents.push(storage::open(path).load()?);
+    }
+    let others = read_files(template::files().into_iter().chain(side_files()).collect())?;
+    let salt = crypto::new_salt();
+    let key = crypto::derive_key(&crypto::new_passphrase()?, &salt)?;
+    for (path, todos) in files.iter().zip(&contents) {
+        let plain = storage::Format::of(path).serialize(todos)?;
+        let data = crypto::encrypt(&plain, &salt, &key)?;
+        storage::write_atomic(path, |file| std::io::Write::write_all(file, &data))?;
+        backup::remove_all(path)?;
+    }
+    for (path, plain) in &others {
+        let data = crypto::encrypt(plain, &salt, &key)?;
+        storage::write_atomic(path, |file| std::io::Write::write_all(file, &data))?;
+    }
+    audit::redact_log()?;
+    crypto::remember(&salt, &key);
+    Ok(files.len() + others.len())
+}
+
+/// The contents of `files`, e.g. templates, which aren't lists of todos.
+fn read_files(files: Vec<PathBuf>) -> Result<Vec<(PathBuf, Vec<u8>)>, String> {
+    files
+        .into_iter()
+        .map(|path| match fs::read(&path) {
+            Ok(data) => Ok((path, data)),
+            Err(e) => Err(t!("Failed to read {}: {}", path.display(), e)),
+        })
+        .collect()
+}
+
+/// Writes every encrypted list back as plain JSON. Returns how many files
+/// were decrypted.
+fn decrypt_db() -> Result<usize, String> {
+    session::flush()?;
+    let Some(salt) = crypto::db_salt() else {
+        return Err(t!("The DB is not encrypted").to_string());
+    };
+    let key = crypto::key_for(&salt)?;
+    let files: Vec<PathBuf> = db_files()
+        .into_iter()
+        .chain(snapshot::files())
+        .filter(|f| crypto::file_salt(f).is_some())
+        .collect();
+    let mut contents = Vec::new();
+    for path in &files {
+        contents.push(storage::open(path).load()?);
+    }
+    let mut others = Vec::new();
+    for (path, data) in read_files(template::files().into_iter().chain(side_files()).collect())? {
+        if crypto::file_salt(&path).is_some() {
+            others.push((path, crypto::decrypt(&data, &key)?));
+        }
+    }
+    for (path, todos) in files.iter().zip(&contents) {
+        let plain = storage::Format::of(path).serialize(todos)?;
+        storage::write_atomic(path, |file| std::io::Write::write_all(file, &plain))?;
+    }
+    for (path, plain) in &others {
+        storage::write_atomic(path, |file| std::io::Write::write_all(file, plain))?;
+    }
+    crypto::forget_key()?;
+    Ok(files.len() + others.len())
+}
+
+/// Checks the passphrase against the default list and caches the key.
+fn unlock_db() -> Result<PathBuf, String> {
+    let base = base_db_path();
+    let Some(salt) = crypto::db_salt() else {
+        return Err(t!("The DB is not encrypted").to_string());
+    };
+    let key = crypto::derive_key(&crypto::passphrase("Passphrase: ")?, &salt)?;
+    let data = fs::read(&base).map_err(|e| e.to_string())?;
+    crypto::decrypt(&data, &key)?;
+    crypto::remember(&salt, &key);
+    crypto::save_key(&salt, &key)
+}
+
+fn list_todos(
+    todos: &[Todo],
+    filter: Filter,
+    due_by: Option<NaiveDate>,
+    order: &sort::Order,
+    reverse: bool,
+    query: Option<&query::Query>,
+    page: Page,
+) {
+    let today = today();
+    if todos.is_empty() && !json_output() {
+        println!("{}", t!("No todos yet. Add one with: add <title> [description]"));
         return;
     }
-    for (i, t) in todos.iter().enumerate() {
-        let idx = i + 1;
-        let status = if t.completed { "✔" } else { " " };
-        let show = match filter {
-            Filter::All => true,
-            Filter::Pending => !t.completed,
-            Filter::Done => t.completed,
-        };
-        if show {
-            if t.description.trim().is_empty() {
-                println!("[{}] {} - {}", status, idx, t.title);
-            } else {
-                println!("[{}] {} - {}\n    {}", status, idx, t.title, t.description);
+    let mut shown: Vec<&Todo> = todos
+        .iter()
+        .filter(|t| filter.matches(todos, t))
+        .filter(|t| due_by.is_none_or(|by| t.due.is_some_and(|d| d <= by)))
+        .filter(|t| query.is_none_or(|q| q.matches(t)))
+        .collect();
+    order.sort(&mut shown, today);
+    if reverse {
+        shown.reverse();
+    }
+    // Pinned todos come first whatever the order; the sort is stable, so
+    // both groups keep it.
+    shown.sort_by_key(|t| !t.pinned);
+    if page.offset == 0 && page.limit.is_none() {
+        if json_output() {
+            print_json(&shown);
+        } else {
+            print_tree(todos, &shown, today);
+        }
+        return;
+    }
+    // Pages follow the printed order, so subtasks stay with their parent
+    // unless the page ends between them.
+    let rows = tree_order(&shown);
+    let end = page
+        .limit
+        .map_or(rows.len(), |n| (page.offset + n).min(rows.len()));
+    let start = page.offset.min(end);
+    if json_output() {
+        let page: Vec<&Todo> = rows[start..end].iter().map(|(t, _)| *t).collect();
+        print_json(&page);
+        return;
+    }
+    for &(t, depth) in &rows[start..end] {
+        print_row(todos, t, depth, today);
+    }
+    let footer = if start == end {
+        t!("Nothing past {} todo(s).", rows.len())
+    } else {
+        t!("Showing {}-{} of {}.", start + 1, end, rows.len())
+    };
+    println!("{}", paint(&footer, |c| c.muted));
+}
+
+/// `pin` and `unpin`.
+fn set_pinned(target: Target, pinned: bool) {
+    let mut todos = load_db();
+    // Titles are matched among the todos it would change.
+    let pick: fn(&Todo) -> bool = if pinned { |t| !t.pinned } else { |t| t.pinned };
+    let Some(idx) = resolve_target(&todos, target, pick) else {
+        return;
+    };
+    let id = todos[idx].id;
+    if todos[idx].pinned != pinned {
+        todos[idx].pinned = pinned;
+        if let Err(e) = save_db(&todos) {
+            fail(Error::Storage(t!("Failed to save: {}", e)));
+            return;
+        }
+    }
+    if json_output() {
+        print_json(&todos[idx]);
+    } else if pinned {
+        announce(t!("Pinned (#{}): {}", id, todos[idx].title));
+    } else {
+        announce(t!("Unpinned (#{}): {}", id, todos[idx].title));
+    }
+}
+
+/// Prints the open todos due on or before `by`, soonest first, or `nothing`
+/// when there are none.
+fn print_due_view(by: NaiveDate, nothing: &str) {
+    let todos = load_db();
+    let mut shown: Vec<&Todo> = todos
+        .iter()
+        .filter(|t| !t.completed && t.due.is_some_and(|d| d <= by))
+        .collect();
+    sort::Order::by_due().sort(&mut shown, today());
+    if json_output() {
+        print_json(&shown);
+    } else if shown.is_empty() {
+        println!("{}", nothing);
+    } else {
+        print_tree(&todos, &shown, today());
+    }
+}
+
+/// `shown` in the order it is printed: each todo followed by its subtasks,
+/// with how deeply it is nested. Todos whose parent isn't shown are at the
+/// top level.
+fn tree_order<'a>(shown: &[&'a Todo]) -> Vec<(&'a Todo, usize)> {
+    fn visit<'a>(
+        shown: &[&'a Todo],
+        parent: Option<u64>,
+        depth: usize,
+        rows: &mut Vec<(&'a Todo, usize)>,
+    ) {
+        for &t in shown {
+            let is_child = match parent {
+                Some(pid) => t.parent == Some(pid),
+                None => t
+                    .parent
+                    .is_none_or(|pid| !shown.iter().any(|p| p.id == pid)),
+            };
+            if is_child {
+                rows.push((t, depth));
+                visit(shown, Some(t.id), depth + 1, rows);
             }
         }
     }
+    let mut rows = Vec::new();
+    visit(shown, None, 0, &mut rows);
+    rows
+}
+
+/// Prints `shown` with subtasks under their parents. Todos are numbered by
+/// id, which doesn't change when other todos are removed.
+fn print_tree(todos: &[Todo], shown: &[&Todo], today: NaiveDate) {
+    for (t, depth) in tree_order(shown) {
+        print_row(todos, t, depth, today);
+    }
 }
 
-fn parse_index(arg: &str) -> Result<usize, String> {
-    let idx: usize = arg
-        .parse()
-        .map_err(|_| format!("Invalid index '{}': must be a positive number", arg))?;
-    if idx == 0 {
-        return Err("Index must be 1-based (>= 1)".to_string());
+fn print_row(todos: &[Todo], t: &Todo, depth: usize, today: NaiveDate) {
+    let indent = "    ".repeat(depth);
+    let status = if t.completed {
+        paint("✔", |c| c.check)
+    } else {
+        " ".to_string()
+    };
+    let id = paint(&t.id.to_string(), |c| c.id);
+    let title = if t.completed {
+        paint(&t.title, |c| c.muted)
+    } else {
+        t.title.clone()
+    };
+    let priority = match t.priority {
+        Some(p) => {
+            let text = format!(" [{}]", p.as_str());
+            match p {
+                Priority::High => paint(&text, |c| c.high),
+                Priority::Medium => paint(&text, |c| c.medium),
+                Priority::Low => paint(&text, |c| c.low),
+            }
+        }
+        None => String::new(),
+    };
+    let mut due = match t.due {
+        Some(d) if t.completed => paint(&format!(" (due {})", d), |c| c.muted),
+        Some(d) if d < today => {
+            paint(&format!(" ({})", due::describe_due(d, today)), |c| c.overdue)
+        }
+        Some(d) => paint(&format!(" ({})", due::describe_due(d, today)), |c| c.due),
+        None => String::new(),
+    };
+    if let Some(r) = t.repeat {
+        due.push_str(&paint(&format!(" ↻ {}", r), |c| c.repeat));
+    }
+    for tag in &t.tags {
+        due.push_str(&paint(&format!(" #{}", tag), |c| c.tag));
+    }
+    for context in &t.contexts {
+        due.push_str(&paint(&format!(" @{}", context), |c| c.context));
+    }
+    if t.in_progress && !t.completed {
+        due.push_str(&paint(t!(" (in progress)"), |c| c.timer));
+    }
+    if t.pinned {
+        due.push_str(&paint(t!(" (pinned)"), |c| c.muted));
+    }
+    if let Some(e) = t.estimate {
+        let estimate = burndown::Unit::configured().short(e);
+        due.push_str(&paint(&format!(" ~{}", estimate), |c| c.muted));
+    }
+    if let Some(created) = t.created_at.filter(|_| !t.completed) {
+        let age = due::describe_age(created, now());
+        due.push_str(&paint(&format!(" · {}", age), |c| c.muted));
+    }
+    let blockers = if t.completed { Vec::new() } else { open_blockers(todos, t) };
+    if !blockers.is_empty() {
+        let ids: Vec<String> = blockers.iter().map(|b| format!("#{}", b)).collect();
+        due.push_str(&paint(&t!(" (blocked by {})", ids.join(", ")), |c| c.muted));
+    }
+    if track::is_running(t) {
+        let spent = track::describe(track::total(t, now()));
+        due.push_str(&paint(&format!(" ⏱ {}", spent), |c| c.timer));
+    }
+    if t.description.trim().is_empty() {
+        println!("{}[{}] {} - {}{}{}", indent, status, id, title, priority, due);
+    } else {
+        println!(
+            "{}[{}] {} - {}{}{}\n{}    {}",
+            indent, status, id, title, priority, due, indent, t.description
+        );
     }
-    Ok(idx - 1)
 }
 
 fn main() {
-    let mut args = env::args().skip(1).collect::<Vec<_>>();
+    let mut args: Vec<String> = env::args().collect();
+    let json = args.iter().any(|a| a == "--json");
+    let rest = match cli::expand_aliases(args.split_off(1)) {
+        Ok(rest) => rest,
+        Err(e) => {
+            JSON_OUTPUT.store(json, Ordering::Relaxed);
+            exit_with(Error::Usage(e));
+        }
+    };
+    audit::set_command(&rest);
+    let cli = match Cli::try_parse_from(args.into_iter().chain(rest)) {
+        Ok(cli) => cli,
+        Err(e) if e.use_stderr() && json => {
+            JSON_OUTPUT.store(true, Ordering::Relaxed);
+            let message = e.render().to_string();
+            let message = message.lines().next().unwrap_or_default();
+            fail(Error::Usage(message.trim_start_matches("error: ").to_string()));
+            std::process::exit(e.exit_code());
+        }
+        Err(e) => e.exit(),
+    };
+    run_cli(cli);
+    let code = EXIT_CODE.load(Ordering::Relaxed);
+    if code != 0 {
+        std::process::exit(code);
+    }
+}
 
-    if args.is_empty() {
-        print_usage();
-        return;
+/// Runs one line entered in `interactive`; parse errors and `--help` are
+/// printed instead of exiting.
+fn run(args: Vec<String>) {
+    let args = match cli::expand_aliases(args) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{}", e);
+            return;
+        }
+    };
+    audit::set_command(&args);
+    match Cli::try_parse_from(std::iter::once("todo".to_string()).chain(args)) {
+        Ok(cli) => run_cli(cli),
+        Err(e) => {
+            let _ = e.print();
+        }
     }
+}
+
+/// `--list`, `--json` and `--dry-run` only apply to this command.
+fn run_cli(cli: Cli) {
+    color::configure(cli.color);
+    let json = JSON_OUTPUT.load(Ordering::Relaxed);
+    JSON_OUTPUT.store(json || cli.json, Ordering::Relaxed);
+    let run_command: fn(Command) = if cli.dry_run {
+        dry_run::run
+    } else {
+        run_command
+    };
+    let Some(name) = cli.list else {
+        run_command(cli.command);
+        JSON_OUTPUT.store(json, Ordering::Relaxed);
+        return;
+    };
+    let previous = override_list(Some(name));
+    run_command(cli.command);
+    override_list(previous);
+    JSON_OUTPUT.store(json, Ordering::Relaxed);
+}
 
-    let cmd = args.remove(0).to_lowercase();
-    match cmd.as_str() {
-        "add" => {
-            if args.is_empty() {
-                eprintln!("Error: 'add' requires at least a <title>.");
-                print_usage();
+fn run_command(command: Command) {
+    // Held for the whole load-modify-save cycle, so two invocations can't
+    // both load the same list and overwrite each other's changes.
+    // Long-running commands (`serve`, `remind`, `pomodoro`, `note` in an
+    // editor, `list --watch`) lock for each change instead, so other
+    // commands can run meanwhile.
+    let lock = match command {
+        Command::List { watch: true, .. }
+        | Command::Serve { .. }
+        | Command::Bot { .. }
+        | Command::Remind { .. }
+        | Command::Pomodoro { .. }
+        | Command::Note { .. } => Ok(None),
+        Command::List { .. }
+        | Command::Lists
+        | Command::Search { .. }
+        | Command::Export { .. }
+        | Command::Today
+        | Command::Week
+        | Command::Overdue
+        | Command::Calendar { .. }
+        | Command::Board { .. }
+        | Command::Stats
+        | Command::Streak
+        | Command::Burndown { .. }
+        | Command::Contexts
+        | Command::Show { .. }
+        | Command::History { .. }
+        | Command::Timesheet { .. }
+        | Command::Archive {
+            action: Some(ArchiveAction::List { .. }),
+        }
+        | Command::Backup {
+            action: BackupAction::List,
+        }
+        | Command::Snapshot {
+            action: SnapshotAction::List | SnapshotAction::Diff { .. },
+        }
+        | Command::Template {
+            action: TemplateAction::List,
+        }
+        | Command::Unlock
+        | Command::Lock => lock::shared().map(Some),
+        _ => lock::exclusive().map(Some),
+    };
+    let _lock = match lock {
+        Ok(lock) => lock,
+        Err(e) => {
+            fail(e);
+            return;
+        }
+    };
+    match command {
+        Command::Add {
+            title,
+            description,
+            stdin,
+            parse,
+            due,
+            priority,
+            parent,
+            repeat,
+            tags,
+            contexts,
+            meta,
+            estimate,
+        } => {
+            let stdin = stdin || title.as_deref() == Some("-");
+            let titles: Vec<String> = if stdin {
+                if !description.is_empty() {
+                    fail(Error::Usage(
+                        t!("Todos read from stdin can't share a description.").to_string(),
+                    ));
+                    return;
+                }
+                match io::stdin().lines().collect::<Result<Vec<_>, _>>() {
+                    Ok(lines) => lines
+                        .iter()
+                        .map(|l| l.trim().to_string())
+                        .filter(|l| !l.is_empty())
+                        .collect(),
+                    Err(e) => {
+                        fail(t!("Failed to read stdin: {}", e));
+                        return;
+                    }
+                }
+            } else {
+                title.into_iter().collect()
+            };
+            if titles.is_empty() {
+                fail(Error::Usage(t!("Nothing to add: stdin had no lines.").to_string()));
+                return;
+            }
+            let mut todos = load_db();
+            if let Some(p) = parent.filter(|&p| position_of(&todos, p).is_none()) {
+                fail(not_found(p));
                 return;
             }
-            let title = args.remove(0);
-            let description = if !args.is_empty() {
-                args.join(" ")
+            let first = todos.len();
+            // Nothing is saved unless every line can be added.
+            for (n, line) in titles.into_iter().enumerate() {
+                let at_line = |e: String| {
+                    Error::Usage(if stdin {
+                        format!("line {}: {}", n + 1, e)
+                    } else {
+                        e
+                    })
+                };
+                let mut t = Todo {
+                    id: next_id(&todos),
+                    title: line,
+                    description: description.join(" "),
+                    completed: false,
+                    due,
+                    priority,
+                    parent,
+                    repeat,
+                    tags: tags.clone(),
+                    contexts: contexts.clone(),
+                    snoozed: None,
+                    created_at: Some(now()),
+                    completed_at: None,
+                    time: Vec::new(),
+                    pomodoros: 0,
+                    in_progress: false,
+                    pinned: false,
+                    estimate,
+                    blocked_by: Vec::new(),
+                    notes: Vec::new(),
+                    meta: meta.iter().cloned().collect(),
+                };
+                if parse {
+                    let inline = match cli::parse_inline(&t.title) {
+                        Ok(inline) => inline,
+                        Err(e) => {
+                            fail(at_line(e));
+                            return;
+                        }
+                    };
+                    t.title = inline.title;
+                    t.priority = inline.priority.or(priority);
+                    t.due = inline.due.or(due);
+                    for tag in inline.tags {
+                        if !t.tags.contains(&tag) {
+                            t.tags.push(tag);
+                        }
+                    }
+                    for context in inline.contexts {
+                        if !t.contexts.contains(&context) {
+                            t.contexts.push(context);
+                        }
+                    }
+                }
+                if t.title.is_empty() {
+                    fail(at_line(t!("A todo needs a title.").to_string()));
+                    return;
+                }
+                todos.push(t);
+            }
+            let saved = if todos.len() - first == 1 {
+                append_db(&todos)
             } else {
-                String::new()
+                save_db(&todos)
             };
-            let mut todos = load_db();
-            todos.push(Todo { title, description, completed: false });
-            if let Err(e) = save_db(&todos) {
-                eprintln!("Failed to save: {}", e);
+            if let Err(e) = saved {
+                fail(Error::Storage(t!("Failed to save: {}", e)));
                 return;
             }
-            println!("Added todo (#{})", todos.len());
+            let added = &todos[first..];
+            if json_output() && stdin {
+                print_json(&added);
+            } else if json_output() {
+                print_json(&added[0]);
+            } else {
+                for t in added {
+                    announce(t!("Added todo (#{})", t.id));
+                }
+            }
         }
 
-        "list" => {
-            let filter = if let Some(flag) = args.get(0) {
-                match flag.as_str() {
-                    "--pending" => Filter::Pending,
-                    "--done" => Filter::Done,
-                    _ => Filter::All,
-                }
+        Command::List {
+            all: _,
+            pending,
+            done,
+            ready,
+            archived,
+            due,
+            sort,
+            reverse,
+            query,
+            context,
+            watch,
+            limit,
+            offset,
+            page,
+        } => {
+            let query = [query, context.map(|c| query::context(&c))]
+                .into_iter()
+                .flatten()
+                .reduce(|a, b| query::Query::And(Box::new(a), Box::new(b)));
+            // `--page` alone shows pages of 20.
+            let limit = limit.or(page.map(|_| 20));
+            let page = Page {
+                offset: page.map_or(offset, |p| (p as usize - 1) * limit.unwrap_or(20)),
+                limit,
+            };
+            let filter = if ready {
+                Filter::Ready
+            } else if pending {
+                Filter::Pending
+            } else if done {
+                Filter::Done
             } else {
                 Filter::All
             };
+            let order = sort.unwrap_or_else(sort::Order::configured);
+            // Archiving changes the DB too, so watching it is enough.
+            let path = db_path();
+            let render = || {
+                let todos = if archived {
+                    load_archive()
+                } else {
+                    load_from(&path)
+                };
+                if archived && todos.is_empty() && !json_output() {
+                    println!("{}", t!("The archive is empty."));
+                    return;
+                }
+                list_todos(&todos, filter, due, &order, reverse, query.as_ref(), page);
+                let conflicts = if archived { 0 } else { conflicts::count() };
+                if conflicts > 0 && !json_output() {
+                    let note = t!("{} conflict(s) from sync; see 'resolve'.", conflicts);
+                    println!("{}", paint(&note, |c| c.muted));
+                }
+            };
+            if !watch {
+                render();
+            } else if let Err(e) = watch::run(&path, render) {
+                fail(e);
+            }
+        }
+
+        Command::Search {
+            pattern,
+            regex,
+            field,
+        } => match search::Pattern::new(&pattern, regex) {
+            Ok(pattern) => search::show(&load_db(), &pattern, field),
+            Err(e) => fail(e),
+        },
+
+        Command::Today => print_due_view(today(), t!("Nothing due today.")),
+        Command::Week => {
+            print_due_view(today() + chrono::Duration::days(6), t!("Nothing due this week."))
+        }
+        Command::Overdue => print_due_view(today().pred_opt().unwrap(), t!("Nothing is overdue.")),
+
+        Command::Board { by_tag, tags } => board::show(&load_db(), by_tag, &tags),
+
+        Command::Calendar { month, day } => {
+            let day = match day.map(|d| calendar::parse_day(&d, month)).transpose() {
+                Ok(day) => day,
+                Err(e) => {
+                    fail(Error::Usage(e));
+                    return;
+                }
+            };
+            let this = today().with_day(1).unwrap_or_else(today);
+            let month = month.or(day.and_then(|d| d.with_day(1))).unwrap_or(this);
+            calendar::show(&load_db(), month, day);
+        }
+
+        Command::Lists => {
+            let current = current_list();
+            let mut names = all_lists();
+            if !names.contains(&current) {
+                names.push(current.clone());
+            }
+            let mut rows = Vec::new();
+            for name in names {
+                let todos = load_from(&list_path(&name));
+                let done = todos.iter().filter(|t| t.completed).count();
+                if json_output() {
+                    rows.push(json!({
+                        "name": name,
+                        "current": name == current,
+                        "todos": todos.len(),
+                        "pending": todos.len() - done,
+                        "done": done,
+                    }));
+                    continue;
+                }
+                let marker = if name == current { "*" } else { " " };
+                println!(
+                    "{}",
+                    t!(
+                        "{} {} ({} todos, {} pending, {} done)",
+                        marker,
+                        name,
+                        todos.len(),
+                        todos.len() - done,
+                        done
+                    )
+                );
+            }
+            if json_output() {
+                print_json(&rows);
+            }
+        }
+
+        Command::Use { list: name } => {
+            let file = current_list_file();
+            let result = if name == DEFAULT_LIST {
+                match fs::remove_file(&file) {
+                    Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+                    _ => Ok(()),
+                }
+            } else {
+                fs::write(&file, format!("{}\n", name))
+            };
+            if let Err(e) = result {
+                fail(Error::Storage(t!("Failed to save: {}", e)));
+                return;
+            }
+            if json_output() {
+                print_json(&json!({ "list": name }));
+            } else {
+                println!("{}", t!("Now using list '{}'", name));
+            }
+        }
+
+        Command::Export {
+            format,
+            group,
+            events,
+            path,
+        } => {
+            let list = current_list();
+            let options = export::Options {
+                title: if list == DEFAULT_LIST { "Todos".to_string() } else { list.clone() },
+                list,
+                group,
+                ics_events: events,
+            };
             let todos = load_db();
-            list_todos(&todos, filter);
+            let out = match export::export(&todos, format, &options) {
+                Ok(out) => out,
+                Err(e) => {
+                    fail(t!("Failed to export: {}", e));
+                    return;
+                }
+            };
+            match path {
+                Some(path) => {
+                    if let Err(e) = fs::write(&path, out) {
+                        fail(Error::Storage(t!("Failed to write {}: {}", path.display(), e)));
+                        return;
+                    }
+                    if json_output() {
+                        print_json(&json!({ "exported": todos.len(), "path": path }));
+                    } else {
+                        let count = todos.len();
+                        println!("{}", t!("Exported {} todo(s) to {}", count, path.display()));
+                    }
+                }
+                None => print!("{}", out),
+            }
         }
 
-        "done" => {
-            if args.is_empty() {
-                eprintln!("Error: 'done' requires an <index>.");
+        Command::Import {
+            format,
+            from,
+            path,
+            allow_duplicates,
+        } => {
+            if format.is_some_and(|f| f != export::Format::Csv) {
+                fail(Error::Usage(t!("Only --format csv can be imported.").to_string()));
                 return;
             }
-            let idx = match parse_index(&args[0]) {
-                Ok(i) => i,
+            let content = match fs::read_to_string(&path) {
+                Ok(c) => c,
                 Err(e) => {
-                    eprintln!("{}", e);
+                    fail(read_error(&path, e));
                     return;
                 }
             };
+            let parsed = match from {
+                Some(import::Source::Todoist) => import::parse_todoist(&content, today()),
+                Some(import::Source::Trello) => import::parse_trello(&content).map(|t| (t, vec![])),
+                None => import::parse_csv(&content).map(|t| (t, vec![])),
+            };
+            let (incoming, warnings) = match parsed {
+                Ok(parsed) => parsed,
+                Err(e) => {
+                    fail(t!("Failed to import {}: {}", path.display(), e));
+                    return;
+                }
+            };
+            for warning in &warnings {
+                eprintln!("{}", t!("Warning: {}", warning));
+            }
             let mut todos = load_db();
-            if idx >= todos.len() {
-                eprintln!("Index out of range. Use 'list' to see items.");
+            let report = import::merge(&mut todos, incoming, allow_duplicates);
+            if let Err(e) = save_db(&todos) {
+                fail(Error::Storage(t!("Failed to save: {}", e)));
                 return;
             }
-            todos[idx].completed = true;
-            if let Err(e) = save_db(&todos) {
-                eprintln!("Failed to save: {}", e);
+            if json_output() {
+                print_json(&json!({ "imported": report.added, "duplicates": report.duplicates }));
+            } else {
+                announce(t!(
+                    "Imported {} todo(s), skipped {} duplicate(s)",
+                    report.added, report.duplicates
+                ));
+            }
+        }
+
+        Command::Doctor { repair } => {
+            let path = db_path();
+            // The backup put back in place of an unreadable DB, if any.
+            let mut restored = None;
+            if let Err(e) = storage::open(&path).load() {
+                if !json_output() {
+                    println!("{}", t!("{} can't be read: {}", path.display(), e));
+                }
+                let backup = doctor::readable_backup(&path);
+                let restore = backup.as_ref().is_some_and(|b| {
+                    let question = t!(
+                        "Restore backup {} from {} ({} todos)?",
+                        b.n, b.modified, b.todos
+                    );
+                    repair || (!json_output() && io::stdin().is_terminal() && confirm(&question))
+                });
+                match backup {
+                    // There is nothing readable to show a diff against.
+                    Some(_) if restore && session::dry_run() => {
+                        let e = t!("Restoring a backup can't be previewed; drop --dry-run.");
+                        fail(Error::Usage(e.to_string()));
+                        return;
+                    }
+                    Some(b) if restore => {
+                        if let Err(e) = backup::restore(&path, b.n) {
+                            fail(Error::Storage(e));
+                            return;
+                        }
+                        if !json_output() {
+                            let moved = t!("the unreadable file is now backup 1");
+                            println!("{}", t!("Restored backup {}; {}.", b.n, moved));
+                        }
+                        // The backup may have problems of its own; checked below.
+                        restored = Some(b.n);
+                    }
+                    _ if json_output() => {
+                        let e = Error::Storage(e);
+                        print_json(&json!({
+                            "path": path,
+                            "error": e.message(),
+                            "code": e.code(),
+                            "backup": backup.map(|b| b.n),
+                            "restored": null,
+                        }));
+                        set_failed(&e);
+                        return;
+                    }
+                    Some(b) => {
+                        let hint = t!("Run 'doctor --repair' to restore backup {}.", b.n);
+                        fail(Error::Storage(hint));
+                        return;
+                    }
+                    None => {
+                        fail(Error::Storage(t!("No backup of it can be read either.").to_string()));
+                        return;
+                    }
+                }
+            }
+            let mut todos = load_db();
+            let problems = doctor::check(&mut todos);
+            if repair && !problems.is_empty() {
+                if let Err(e) = save_db(&todos) {
+                    fail(Error::Storage(t!("Failed to save: {}", e)));
+                    return;
+                }
+            }
+            if json_output() {
+                print_json(&json!({
+                    "path": path,
+                    "restored": restored,
+                    "problems": problems,
+                    "repaired": repair && !problems.is_empty(),
+                }));
+            } else if problems.is_empty() {
+                println!("{}", t!("No problems in {} ({} todos).", path.display(), todos.len()));
+            } else {
+                for problem in &problems {
+                    println!("- {}", problem);
+                }
+            }
+            if problems.is_empty() {
                 return;
             }
-            println!("Marked as done (#{}): {}", idx + 1, todos[idx].title);
+            if repair {
+                if !json_output() {
+                    announce(t!("Fixed {} problem(s).", problems.len()));
+                }
+            } else {
+                let e = Error::Storage(t!("Run 'doctor --repair' to fix them.").to_string());
+                if json_output() {
+                    set_failed(&e);
+                } else {
+                    fail(e);
+                }
+            }
         }
 
-        "undone" => {
-            if args.is_empty() {
-                eprintln!("Error: 'undone' requires an <index>.");
+        Command::Merge { path, theirs } => {
+            if !path.exists() {
+                fail(Error::NotFound(t!("No such file: {}", path.display())));
+                return;
+            }
+            let same_file = fs::canonicalize(&path).ok() == fs::canonicalize(db_path()).ok();
+            if same_file {
+                let e = t!("That is the current list; give another todo file to merge.");
+                fail(Error::Usage(e.to_string()));
                 return;
             }
-            let idx = match parse_index(&args[0]) {
-                Ok(i) => i,
+            let incoming = match storage::open(&path).load() {
+                Ok(todos) => todos,
                 Err(e) => {
-                    eprintln!("{}", e);
+                    fail(Error::Storage(t!("Failed to read {}: {}", path.display(), e)));
                     return;
                 }
             };
             let mut todos = load_db();
-            if idx >= todos.len() {
-                eprintln!("Index out of range. Use 'list' to see items.");
+            let outcome = merge::merge(&mut todos, incoming, theirs);
+            if let Err(e) = save_db(&todos) {
+                fail(Error::Storage(t!("Failed to save: {}", e)));
                 return;
             }
-            todos[idx].completed = false;
-            if let Err(e) = save_db(&todos) {
-                eprintln!("Failed to save: {}", e);
+            if json_output() {
+                let conflicts: Vec<_> = outcome
+                    .conflicts
+                    .iter()
+                    .map(|c| json!({ "id": c.id, "theirs": c.theirs, "fields": c.fields }))
+                    .collect();
+                print_json(&json!({
+                    "added": outcome.added,
+                    "skipped": outcome.skipped,
+                    "conflicts": conflicts,
+                    "kept": if theirs { "theirs" } else { "ours" },
+                }));
                 return;
             }
-            println!("Marked as not done (#{}): {}", idx + 1, todos[idx].title);
+            announce(
+                t!(
+                    "{} {} todo(s), skipped {} already here, {} conflict(s).",
+                    t!("Added"),
+                    outcome.added.len(),
+                    outcome.skipped.len(),
+                    outcome.conflicts.len()
+                )
+            );
+            let kept = if theirs { t!("took theirs") } else { t!("kept ours") };
+            for c in &outcome.conflicts {
+                announce(
+                    t!(
+                        "  {} {} (theirs {}): {} — {}",
+                        paint(&format!("#{}", c.id), |c| c.id),
+                        c.title,
+                        paint(&format!("#{}", c.theirs), |c| c.id),
+                        c.fields.join(", "),
+                        kept
+                    )
+                );
+            }
         }
 
-        "remove" | "rm" | "del" => {
-            if args.is_empty() {
-                eprintln!("Error: 'remove' requires an <index>.");
+        Command::Archive { action: None } => {
+            let mut todos = load_db();
+            let movable = archivable(&todos);
+            if movable.is_empty() {
+                if json_output() {
+                    print_json(&json!({ "archived": [] }));
+                } else {
+                    println!("{}", t!("Nothing to archive."));
+                }
                 return;
             }
-            let idx = match parse_index(&args[0]) {
-                Ok(i) => i,
-                Err(e) => {
-                    eprintln!("{}", e);
+            let (moved, kept): (Vec<Todo>, Vec<Todo>) =
+                todos.drain(..).partition(|t| movable.contains(&t.id));
+            let mut months: BTreeMap<NaiveDate, Vec<Todo>> = BTreeMap::new();
+            for t in moved {
+                months.entry(archive_month(&t)).or_default().push(t);
+            }
+            let path = db_path();
+            // Write the archives first: if saving the DB then fails, the
+            // todos exist twice rather than not at all.
+            for (month, moved) in &months {
+                let file = archive_month_file(&path, *month);
+                let mut archive = load_from(&file);
+                archive.extend(moved.iter().cloned());
+                if let Err(e) = save_to(&file, &archive) {
+                    fail(Error::Storage(t!("Failed to save archive: {}", e)));
                     return;
                 }
+            }
+            if let Err(e) = save_db(&kept) {
+                fail(Error::Storage(t!("Failed to save: {}", e)));
+                return;
+            }
+            if json_output() {
+                let archived: Vec<&Todo> = months.values().flatten().collect();
+                let files: Vec<PathBuf> = months
+                    .keys()
+                    .map(|m| archive_month_file(&path, *m))
+                    .collect();
+                print_json(&json!({
+                    "archived": archived,
+                    "path": archive_dir(&path),
+                    "files": files,
+                }));
+            } else {
+                announce(
+                    t!(
+                        "Archived {} completed todo(s) to {}",
+                        movable.len(),
+                        archive_dir(&path).display()
+                    )
+                );
+            }
+        }
+
+        Command::Archive {
+            action: Some(ArchiveAction::List { month: Some(month) }),
+        } => {
+            let todos = load_from(&archive_month_file(&db_path(), month));
+            if todos.is_empty() && !json_output() {
+                println!("{}", t!("Nothing was archived in {}.", month.format("%Y-%m")));
+                return;
+            }
+            let order = sort::Order::configured();
+            let page = Page {
+                offset: 0,
+                limit: None,
+            };
+            list_todos(&todos, Filter::All, None, &order, false, None, page);
+        }
+
+        Command::Archive {
+            action: Some(ArchiveAction::List { month: None }),
+        } => {
+            let path = db_path();
+            let old = archive_file(&path);
+            let mut rows = Vec::new();
+            if old.exists() {
+                rows.push((None, load_from(&old).len(), old));
+            }
+            for month in archive_months(&path) {
+                let file = archive_month_file(&path, month);
+                rows.push((Some(month), load_from(&file).len(), file));
+            }
+            if json_output() {
+                let rows: Vec<_> = rows
+                    .iter()
+                    .map(|(month, count, file)| {
+                        let month = month.map(|m| m.format("%Y-%m").to_string());
+                        json!({ "month": month, "todos": count, "path": file })
+                    })
+                    .collect();
+                print_json(&rows);
+            } else if rows.is_empty() {
+                println!("{}", t!("The archive is empty."));
+            } else {
+                for (month, count, file) in rows {
+                    // The single file older versions wrote goes by its name.
+                    let name = match month {
+                        Some(m) => m.format("%Y-%m").to_string(),
+                        None => file.file_name().unwrap_or_default().to_string_lossy().into(),
+                    };
+                    println!("{}", t!("{}  {} todo(s)", name, count));
+                }
+            }
+        }
+
+        Command::Done { targets, force } => {
+            let single = Target::is_single(&targets);
+            let mut todos = load_db();
+            let mut results = Vec::new();
+            for id in resolve_targets(&todos, targets, |t| !t.completed, &mut results) {
+                let Some(idx) = position_of(&todos, id) else {
+                    results.push(Err(no_todo(id)));
+                    continue;
+                };
+                let done = if force {
+                    Ok(force_complete(&mut todos, idx))
+                } else {
+                    complete(&mut todos, idx)
+                };
+                let Completion {
+                    next,
+                    pending,
+                    parents_done,
+                } = match done {
+                    Ok(done) => done,
+                    Err(blocked) => {
+                        results.push(Err((
+                            json!({ "id": id }),
+                            Error::Failed(t!(
+                                "#{} is blocked by {}; finish those first or use --force.",
+                                id,
+                                blocked.ids()
+                            )),
+                        )));
+                        continue;
+                    }
+                };
+                let value = json!({
+                    "done": todos[idx],
+                    "next": next.map(|n| &todos[n]),
+                    "pending_subtasks": pending,
+                    "parents_done": parents_done.iter().map(|&p| todos[p].id).collect::<Vec<_>>(),
+                });
+                let mut lines = vec![t!("Marked as done (#{}): {}", id, todos[idx].title)];
+                if let Some(n) = next {
+                    if let Some(d) = todos[n].due {
+                        lines.push(t!("Next occurrence (#{}) due {}", todos[n].id, d));
+                    }
+                }
+                if pending > 0 {
+                    lines.push(t!("Warning: {} subtask(s) still pending.", pending));
+                }
+                for p in parents_done {
+                    lines.push(t!(
+                        "All subtasks done; marked parent as done (#{}): {}",
+                        todos[p].id, todos[p].title
+                    ));
+                }
+                results.push(Ok((value, lines)));
+            }
+            save_and_report(&todos, results, single);
+        }
+
+        Command::Undone { targets } => {
+            let single = Target::is_single(&targets);
+            let mut todos = load_db();
+            let mut results = Vec::new();
+            for id in resolve_targets(&todos, targets, |t| t.completed, &mut results) {
+                let Some(idx) = position_of(&todos, id) else {
+                    results.push(Err(no_todo(id)));
+                    continue;
+                };
+                let reopened = reopen(&mut todos, idx);
+                let value = json!({
+                    "undone": todos[idx],
+                    "reopened": reopened.iter().map(|&p| todos[p].id).collect::<Vec<_>>(),
+                });
+                let mut lines = vec![t!("Marked as not done (#{}): {}", id, todos[idx].title)];
+                for p in reopened {
+                    lines.push(t!("Reopened parent (#{}): {}", todos[p].id, todos[p].title));
+                }
+                results.push(Ok((value, lines)));
+            }
+            save_and_report(&todos, results, single);
+        }
+
+        Command::Remove { targets } => {
+            let single = Target::is_single(&targets);
+            let mut todos = load_db();
+            let mut results = Vec::new();
+            let mut removed = Vec::new();
+            for id in resolve_targets(&todos, targets, |_| true, &mut results) {
+                // Already gone as a subtask of an earlier target.
+                if removed.contains(&id) {
+                    continue;
+                }
+                let Some(idx) = position_of(&todos, id) else {
+                    results.push(Err(no_todo(id)));
+                    continue;
+                };
+                // Subtasks go with their parent.
+                let title = todos[idx].title.clone();
+                let gone = remove_todo(&mut todos, id);
+                let mut lines = vec![t!("Removed (#{}): {}", id, title)];
+                if gone.len() > 1 {
+                    lines.push(t!("Also removed {} subtask(s).", gone.len() - 1));
+                }
+                removed.extend(gone.iter().map(|t| t.id));
+                results.push(Ok((json!({ "removed": gone }), lines)));
+            }
+            save_and_report(&todos, results, single);
+        }
+
+        Command::Edit {
+            target,
+            title,
+            description,
+            due,
+            priority,
+            repeat,
+            tags,
+            untags,
+            contexts,
+            uncontexts,
+            meta,
+            estimate,
+            status,
+        } => {
+            let flags_only = due.is_some()
+                || estimate.is_some()
+                || status.is_some()
+                || priority.is_some()
+                || repeat.is_some()
+                || !tags.is_empty()
+                || !untags.is_empty()
+                || !contexts.is_empty()
+                || !uncontexts.is_empty()
+                || !meta.is_empty();
+            if title.is_none() && !flags_only {
+                let e = t!("Error: 'edit' requires a new <title> or a flag to change.");
+                fail(Error::Usage(e.to_string()));
+                return;
+            }
+            let mut todos = load_db();
+            let Some(idx) = resolve_target(&todos, target, |_| true) else {
+                return;
             };
+            let id = todos[idx].id;
+            if let Some(title) = title {
+                todos[idx].title = title;
+                todos[idx].description = description.join(" ");
+            }
+            if let Some(Clearable(d)) = due {
+                todos[idx].due = d;
+                todos[idx].snoozed = None;
+            }
+            if let Some(Clearable(p)) = priority {
+                todos[idx].priority = p;
+            }
+            if let Some(Clearable(r)) = repeat {
+                todos[idx].repeat = r;
+            }
+            if let Some(Clearable(e)) = estimate {
+                todos[idx].estimate = e;
+            }
+            for tag in tags {
+                if !todos[idx].tags.contains(&tag) {
+                    todos[idx].tags.push(tag);
+                }
+            }
+            todos[idx].tags.retain(|t| !untags.contains(t));
+            for context in contexts {
+                if !todos[idx].contexts.contains(&context) {
+                    todos[idx].contexts.push(context);
+                }
+            }
+            todos[idx].contexts.retain(|c| !uncontexts.contains(c));
+            for (key, value) in meta {
+                if value.is_empty() {
+                    todos[idx].meta.remove(&key);
+                } else {
+                    todos[idx].meta.insert(key, value);
+                }
+            }
+            match status {
+                Some(board::Status::Done) if !todos[idx].completed => {
+                    if let Err(blocked) = complete(&mut todos, idx) {
+                        fail(Error::Failed(t!(
+                            "#{} is blocked by {}; finish those first.",
+                            id,
+                            blocked.ids()
+                        )));
+                        return;
+                    }
+                }
+                Some(board::Status::Done) | None => {}
+                Some(status) => {
+                    todos[idx].completed = false;
+                    todos[idx].completed_at = None;
+                    todos[idx].in_progress = status == board::Status::InProgress;
+                }
+            }
+            if let Err(e) = save_db(&todos) {
+                fail(Error::Storage(t!("Failed to save: {}", e)));
+                return;
+            }
+            if json_output() {
+                print_json(&todos[idx]);
+            } else {
+                announce(t!("Updated (#{}).", id));
+            }
+        }
+
+        Command::Clone {
+            target,
+            with_subtasks,
+            due,
+        } => {
             let mut todos = load_db();
-            if idx >= todos.len() {
-                eprintln!("Index out of range. Use 'list' to see items.");
+            let Some(idx) = resolve_target(&todos, target, |_| true) else {
                 return;
+            };
+            let original = todos[idx].id;
+            let added = duplicate(&mut todos, idx, with_subtasks, due);
+            if let Err(e) = save_db(&todos) {
+                fail(Error::Storage(t!("Failed to save: {}", e)));
+                return;
+            }
+            let copy = &todos[todos.len() - added];
+            if json_output() && added > 1 {
+                print_json(&&todos[todos.len() - added..]);
+            } else if json_output() {
+                print_json(copy);
+            } else if added > 1 {
+                announce(
+                    t!("Cloned #{} as #{} with {} subtask(s).", original, copy.id, added - 1)
+                );
+            } else {
+                announce(t!("Cloned #{} as #{}: {}", original, copy.id, copy.title));
             }
-            let removed = todos.remove(idx);
+        }
+
+        Command::Move { target, position } => {
+            let mut todos = load_db();
+            let Some(idx) = resolve_target(&todos, target, |_| true) else {
+                return;
+            };
+            let id = todos[idx].id;
+            let position = move_to(&mut todos, idx, position);
             if let Err(e) = save_db(&todos) {
-                eprintln!("Failed to save: {}", e);
+                fail(Error::Storage(t!("Failed to save: {}", e)));
                 return;
             }
-            println!("Removed (#{}): {}", idx + 1, removed.title);
+            if json_output() {
+                print_json(&json!({ "id": id, "position": position }));
+            } else {
+                announce(t!("Moved #{} to position {}.", id, position));
+            }
         }
 
-        "edit" => {
-            if args.len() < 2 {
-                eprintln!("Error: 'edit' requires <index> <title> [description].");
+        Command::Swap { a, b } => {
+            let mut todos = load_db();
+            let Some(a) = resolve_target(&todos, a, |_| true) else {
+                return;
+            };
+            let Some(b) = resolve_target(&todos, b, |_| true) else {
+                return;
+            };
+            let (id_a, id_b) = (todos[a].id, todos[b].id);
+            if a == b {
+                fail(Error::Usage(t!("#{} can't swap with itself.", id_a)));
                 return;
             }
-            let idx = match parse_index(&args[0]) {
-                Ok(i) => i,
-                Err(e) => {
-                    eprintln!("{}", e);
+            if todos[a].parent != todos[b].parent {
+                fail(t!(
+                    "#{} and #{} have different parents; use 'move' within one of them.",
+                    id_a, id_b
+                ));
+                return;
+            }
+            todos.swap(a, b);
+            if let Err(e) = save_db(&todos) {
+                fail(Error::Storage(t!("Failed to save: {}", e)));
+                return;
+            }
+            if json_output() {
+                print_json(&json!({ "swapped": [id_a, id_b] }));
+            } else {
+                announce(t!("Swapped #{} and #{}.", id_a, id_b));
+            }
+        }
+
+        Command::Block { id, on } => {
+            let mut todos = load_db();
+            let Some(idx) = position_of(&todos, id) else {
+                fail(not_found(id));
+                return;
+            };
+            for &other in &on {
+                if position_of(&todos, other).is_none() {
+                    fail(not_found(other));
+                    return;
+                }
+                if other == id {
+                    fail(Error::Usage(t!("A todo can't wait for itself.").to_string()));
                     return;
                 }
+                if depends_on(&todos, other, id) {
+                    fail(t!("#{} already waits for #{}; that would be a cycle.", other, id));
+                    return;
+                }
+                if !todos[idx].blocked_by.contains(&other) {
+                    todos[idx].blocked_by.push(other);
+                }
+            }
+            if let Err(e) = save_db(&todos) {
+                fail(Error::Storage(t!("Failed to save: {}", e)));
+                return;
+            }
+            if json_output() {
+                print_json(&todos[idx]);
+            } else {
+                let ids: Vec<String> = on.iter().map(|b| format!("#{}", b)).collect();
+                announce(t!("#{} now waits for {}.", id, ids.join(", ")));
+            }
+        }
+
+        Command::Unblock { id, on } => {
+            let mut todos = load_db();
+            let Some(idx) = position_of(&todos, id) else {
+                fail(not_found(id));
+                return;
             };
-            let title = args[1].clone();
-            let description = if args.len() > 2 {
-                args[2..].join(" ")
+            let before = todos[idx].blocked_by.len();
+            todos[idx].blocked_by.retain(|b| !on.is_empty() && !on.contains(b));
+            let removed = before - todos[idx].blocked_by.len();
+            if let Err(e) = save_db(&todos) {
+                fail(Error::Storage(t!("Failed to save: {}", e)));
+                return;
+            }
+            if json_output() {
+                print_json(&todos[idx]);
+            } else {
+                announce(t!("Removed {} blocker(s) from #{}.", removed, id));
+            }
+        }
+
+        Command::Note { target, text, edit } => {
+            if let Err(e) = notes::add(target, text.join(" "), edit) {
+                fail(e);
+            }
+        }
+
+        Command::Show { target } => {
+            let todos = load_db();
+            let Some(idx) = resolve_target(&todos, target, |_| true) else {
+                return;
+            };
+            if json_output() {
+                print_json(&todos[idx]);
+            } else {
+                notes::show(&todos, &todos[idx], now(), today());
+            }
+        }
+
+        Command::History { id, limit } => {
+            if let Err(e) = history::show(&current_list(), id, limit) {
+                fail(e);
+            }
+        }
+
+        Command::Tui => {
+            if let Err(e) = tui::run() {
+                fail(t!("Terminal error: {}", e));
+            }
+        }
+
+        Command::Interactive => {
+            if let Err(e) = repl::run_interactive() {
+                fail(Error::Storage(t!("Failed to save: {}", e)));
+            }
+        }
+
+        Command::Backup {
+            action: BackupAction::List,
+        } => {
+            let path = db_path();
+            let backups: Vec<_> = backup::available(&path)
+                .into_iter()
+                .map(|(n, file)| {
+                    let modified = fs::metadata(&file)
+                        .and_then(|m| m.modified())
+                        .map(|t| {
+                            let t: chrono::DateTime<Local> = t.into();
+                            t.format("%Y-%m-%d %H:%M").to_string()
+                        })
+                        .unwrap_or_default();
+                    let count = storage::open_as(&file, &path).load().map(|t| t.len());
+                    (n, modified, count)
+                })
+                .collect();
+            if json_output() {
+                let rows: Vec<_> = backups
+                    .iter()
+                    .map(|(n, modified, count)| {
+                        json!({ "backup": n, "modified": modified, "todos": count.as_ref().ok() })
+                    })
+                    .collect();
+                print_json(&rows);
+                return;
+            }
+            if backups.is_empty() {
+                println!("{}", t!("No backups of {} yet.", path.display()));
+            }
+            for (n, modified, count) in backups {
+                match count {
+                    Ok(c) => println!("{}", t!("{}  {}  {} todo(s)", n, modified, c)),
+                    Err(e) => println!("{}", t!("{}  {}  unreadable: {}", n, modified, e)),
+                }
+            }
+        }
+
+        Command::Backup {
+            action: BackupAction::Restore { n },
+        } => {
+            let path = db_path();
+            if !backup::available(&path).iter().any(|&(m, _)| m == n) {
+                fail(Error::NotFound(t!("No backup {} of {}", n, path.display())));
+                return;
+            }
+            if let Err(e) = backup::restore(&path, n) {
+                fail(Error::Storage(e));
+                return;
+            }
+            if json_output() {
+                print_json(&json!({ "restored": n, "path": path }));
             } else {
-                String::new()
+                println!("{}", t!("Restored backup {} of {}.", n, path.display()));
+            }
+        }
+
+        Command::Template {
+            action:
+                TemplateAction::Save {
+                    name,
+                    targets,
+                    query,
+                    force,
+                },
+        } => {
+            let todos = load_db();
+            let mut picked: Vec<&Todo> = Vec::new();
+            for target in targets {
+                let ids = match target {
+                    Target::Ids(ids) => ids,
+                    title => match find_target(&todos, title, |_| true) {
+                        Ok(idx) => vec![todos[idx].id],
+                        Err(e) => {
+                            fail(e);
+                            return;
+                        }
+                    },
+                };
+                for id in ids {
+                    match position_of(&todos, id) {
+                        Some(idx) => picked.push(&todos[idx]),
+                        None => {
+                            fail(not_found(id));
+                            return;
+                        }
+                    }
+                }
+            }
+            if picked.is_empty() {
+                picked = todos
+                    .iter()
+                    .filter(|t| !t.completed || query.is_some())
+                    .collect();
+            }
+            picked.retain(|t| query.as_ref().is_none_or(|q| q.matches(t)));
+            if picked.is_empty() {
+                fail(t!("No todos to save in the template."));
+                return;
+            }
+            match template::save(&name, &todos, &picked, today(), force) {
+                Ok(n) if json_output() => print_json(&json!({ "template": name, "todos": n })),
+                Ok(n) => println!("{}", t!("Saved template '{}' with {} todo(s).", name, n)),
+                Err(e) => fail(e),
+            }
+        }
+
+        Command::Template {
+            action: TemplateAction::Apply { name, on },
+        } => {
+            let mut todos = load_db();
+            let added = match template::apply(&name, &mut todos, on.unwrap_or_else(today)) {
+                Ok(added) => added,
+                Err(e) => {
+                    fail(e);
+                    return;
+                }
             };
+            if let Err(e) = save_db(&todos) {
+                fail(Error::Storage(t!("Failed to save: {}", e)));
+                return;
+            }
+            if json_output() {
+                let added: Vec<&Todo> = todos.iter().filter(|t| added.contains(&t.id)).collect();
+                print_json(&added);
+            } else if let [id] = added[..] {
+                announce(t!("Added todo #{} from '{}'.", id, name));
+            } else if let (Some(first), Some(last)) = (added.first(), added.last()) {
+                announce(
+                    t!("Added {} todos from '{}' (#{}-#{}).", added.len(), name, first, last)
+                );
+            }
+        }
+
+        Command::Template {
+            action: TemplateAction::List,
+        } => match template::list() {
+            Ok(templates) if json_output() => {
+                let rows: Vec<_> = templates
+                    .iter()
+                    .map(|(name, n)| json!({ "name": name, "todos": n }))
+                    .collect();
+                print_json(&rows);
+            }
+            Ok(templates) if templates.is_empty() => println!("{}", t!("No templates yet.")),
+            Ok(templates) => {
+                for (name, n) in templates {
+                    println!("{}", t!("{}  {} todo(s)", name, n));
+                }
+            }
+            Err(e) => fail(e),
+        },
+
+        Command::Template {
+            action: TemplateAction::Remove { name },
+        } => match template::remove(&name) {
+            Ok(()) if json_output() => print_json(&json!({ "removed": name })),
+            Ok(()) => println!("{}", t!("Removed template '{}'.", name)),
+            Err(e) => fail(e),
+        },
+
+        Command::Snapshot {
+            action: SnapshotAction::Save { name },
+        } => match snapshot::save(&name) {
+            Ok(n) if json_output() => print_json(&json!({ "snapshot": name, "lists": n })),
+            Ok(n) => println!("{}", t!("Saved snapshot '{}' of {} list(s).", name, n)),
+            Err(e) => fail(e),
+        },
+
+        Command::Snapshot {
+            action: SnapshotAction::Restore { name },
+        } => match snapshot::restore(&name) {
+            Ok(n) if json_output() => print_json(&json!({ "restored": name, "todos": n })),
+            Ok(n) => announce(t!("Restored snapshot '{}' ({} todo(s)).", name, n)),
+            Err(e) => fail(e),
+        },
+
+        Command::Snapshot {
+            action: SnapshotAction::Diff { a, b },
+        } => {
+            if let Err(e) = snapshot::diff(&a, b.as_deref()) {
+                fail(e);
+            }
+        }
+
+        Command::Snapshot {
+            action: SnapshotAction::List,
+        } => match snapshot::list() {
+            Ok(snapshots) if json_output() => {
+                let rows: Vec<_> = snapshots
+                    .iter()
+                    .map(|s| json!({ "name": s.name, "created": s.created, "lists": s.lists }))
+                    .collect();
+                print_json(&rows);
+            }
+            Ok(snapshots) if snapshots.is_empty() => println!("{}", t!("No snapshots yet.")),
+            Ok(snapshots) => {
+                for s in snapshots {
+                    let created = s.created.map(|c| c.format("%Y-%m-%d %H:%M").to_string());
+                    println!(
+                        "{}",
+                        t!("{}  {}  {} list(s)", created.unwrap_or_default(), s.name, s.lists)
+                    );
+                }
+            }
+            Err(e) => fail(e),
+        },
+
+        Command::Migrate { path } => match migrate_to(&path) {
+            Ok(n) if json_output() => print_json(&json!({ "copied": n, "path": path })),
+            Ok(n) => {
+                println!("{}", t!("Copied {} todo(s) to {}.", n, path.display()));
+                println!("{}", t!("Set TODO_DB={} to use it.", path.display()));
+            }
+            Err(e) => fail(t!("Migration failed: {}", e)),
+        },
+
+        Command::Encrypt => match encrypt_db() {
+            Ok(n) if json_output() => print_json(&json!({ "encrypted": n })),
+            Ok(n) => {
+                println!("{}", t!("Encrypted {} file(s); plaintext backups were deleted.", n));
+                println!("{}", t!("Run `todo unlock` to avoid typing the passphrase every time."));
+            }
+            Err(e) => fail(t!("Encryption failed: {}", e)),
+        },
+
+        Command::Decrypt => match decrypt_db() {
+            Ok(n) if json_output() => print_json(&json!({ "decrypted": n })),
+            Ok(n) => println!("{}", t!("Decrypted {} file(s).", n)),
+            Err(e) => fail(t!("Decryption failed: {}", e)),
+        },
+
+        Command::Unlock => match unlock_db() {
+            Ok(path) if json_output() => print_json(&json!({ "unlocked": true, "key": path })),
+            Ok(_) => println!("{}", t!("Unlocked until `todo lock` or logout.")),
+            Err(e) => fail(e),
+        },
+
+        Command::Lock => match crypto::forget_key() {
+            Ok(was_unlocked) if json_output() => print_json(&json!({ "locked": was_unlocked })),
+            Ok(true) => println!("{}", t!("Locked.")),
+            Ok(false) => println!("{}", t!("Not unlocked.")),
+            Err(e) => fail(t!("Failed to forget the key: {}", e)),
+        },
+
+        Command::Sync { remote: Some(url) } => match remote::sync(&url) {
+            Ok(report) if json_output() => print_json(&json!({
+                "remote": url,
+                "pushed": report.pushed,
+                "pulled": report.pulled,
+                "deleted_here": report.deleted_here,
+                "deleted_there": report.deleted_there,
+                "merged": report.merged,
+                "conflicts": report.conflicts,
+                "overwritten": report.overwritten,
+            })),
+            Ok(report) => {
+                println!(
+                    "{}",
+                    t!(
+                        "Synced with {}: {} sent, {} received, {} deleted here, {} deleted there.",
+                        url, report.pushed, report.pulled, report.deleted_here, report.deleted_there
+                    )
+                );
+                if report.merged > 0 {
+                    println!("{}", t!("Merged {} todo(s) changed on both sides.", report.merged));
+                }
+                if !report.conflicts.is_empty() {
+                    println!(
+                        "{}",
+                        t!(
+                            "{} field(s) changed differently on both sides kept this side's value; \
+                             see 'resolve'.",
+                            report.conflicts.len()
+                        )
+                    );
+                }
+                for id in report.overwritten {
+                    println!("{}", t!("#{} changed on both sides; kept the server's version.", id));
+                }
+            }
+            Err(e) => fail(t!("Sync failed: {}", e)),
+        },
+
+        Command::Resolve {
+            id,
+            ours,
+            theirs,
+            field,
+        } => {
+            let side = match (ours, theirs) {
+                (true, _) => Some(conflicts::Side::Ours),
+                (_, true) => Some(conflicts::Side::Theirs),
+                _ => None,
+            };
+            if let Err(e) = conflicts::resolve(id, side, field) {
+                fail(e);
+            }
+        }
+
+        Command::Stats => stats::show(),
+        Command::Streak => streak::show(),
+        Command::Burndown { days, query } => burndown::show(days, query.as_ref()),
+
+        Command::Contexts => {
+            let todos = load_db();
+            let mut contexts: BTreeMap<&str, (usize, usize)> = BTreeMap::new();
+            for t in &todos {
+                for context in &t.contexts {
+                    let counts = contexts.entry(context).or_default();
+                    if t.completed {
+                        counts.1 += 1;
+                    } else {
+                        counts.0 += 1;
+                    }
+                }
+            }
+            let loose = todos
+                .iter()
+                .filter(|t| !t.completed && t.contexts.is_empty())
+                .count();
+            if json_output() {
+                let rows: Vec<_> = contexts
+                    .iter()
+                    .map(|(c, (open, done))| json!({ "context": c, "open": open, "done": done }))
+                    .collect();
+                print_json(&json!({ "contexts": rows, "open_without_context": loose }));
+                return;
+            }
+            if contexts.is_empty() {
+                println!("{}", t!("No contexts yet. Add one with: edit <id> --context @home"));
+                return;
+            }
+            let width = contexts.keys().map(|c| c.len()).max().unwrap_or(0) + 1;
+            for (context, (open, done)) in &contexts {
+                let name = format!("@{:<width$}", context, width = width - 1);
+                println!("{}", t!("{}  {} open, {} done", paint(&name, |c| c.context), open, done));
+            }
+            if loose > 0 {
+                let note = t!("{} open todo(s) without a context", loose);
+                println!("{}", paint(&note, |c| c.muted));
+            }
+        }
+
+        Command::Start { target } => {
+            let mut todos = load_db();
+            let Some(idx) = resolve_target(&todos, target, |t| !t.completed) else {
+                return;
+            };
+            let id = todos[idx].id;
+            if todos[idx].completed {
+                fail(t!("#{} is already done.", id));
+                return;
+            }
+            if track::running(&todos) == Some(idx) {
+                fail(t!("The timer of #{} is already running.", id));
+                return;
+            }
+            let stopped = track::start_timer(&mut todos, idx);
+            if let Err(e) = save_db(&todos) {
+                fail(Error::Storage(t!("Failed to save: {}", e)));
+                return;
+            }
+            if json_output() {
+                print_json(&json!({
+                    "started": todos[idx],
+                    "stopped": stopped.map(|(i, _)| &todos[i]),
+                }));
+                return;
+            }
+            if let Some((i, spent)) = stopped {
+                announce(
+                    t!(
+                        "Stopped (#{}) after {}: {}",
+                        todos[i].id,
+                        track::describe(spent),
+                        todos[i].title
+                    )
+                );
+            }
+            announce(t!("Started timer (#{}): {}", id, todos[idx].title));
+        }
+
+        Command::Pin { target } => set_pinned(target, true),
+        Command::Unpin { target } => set_pinned(target, false),
+
+        Command::Stop => {
             let mut todos = load_db();
-            if idx >= todos.len() {
-                eprintln!("Index out of range. Use 'list' to see items.");
+            let Some(idx) = track::running(&todos) else {
+                fail(t!("No timer is running."));
+                return;
+            };
+            let spent = track::stop_timer(&mut todos, idx).unwrap_or_else(chrono::Duration::zero);
+            if let Err(e) = save_db(&todos) {
+                fail(Error::Storage(t!("Failed to save: {}", e)));
                 return;
             }
-            todos[idx].title = title;
-            todos[idx].description = description;
+            if json_output() {
+                print_json(&json!({ "stopped": todos[idx], "minutes": spent.num_minutes() }));
+                return;
+            }
+            announce(
+                t!(
+                    "Stopped (#{}) after {}: {}",
+                    todos[idx].id,
+                    track::describe(spent),
+                    todos[idx].title
+                )
+            );
+            announce(
+                t!("Total tracked: {}", track::describe(track::total(&todos[idx], now())))
+            );
+        }
+
+        Command::Timesheet { days } => timesheet::show(days),
+
+        Command::Pomodoro {
+            id,
+            work,
+            rest,
+            cycles,
+        } => {
+            if let Err(e) = pomodoro::run(id, work, rest, cycles) {
+                fail(e);
+            }
+        }
+
+        Command::Remind { daemon, before } => {
+            if let Err(e) = remind::run(daemon, before) {
+                fail(e);
+            }
+        }
+
+        Command::Snooze { target, duration } => {
+            let mut todos = load_db();
+            let Some(idx) = resolve_target(&todos, target, |t| !t.completed) else {
+                return;
+            };
+            let id = todos[idx].id;
+            let until = now() + duration;
+            todos[idx].snoozed = Some(until);
             if let Err(e) = save_db(&todos) {
-                eprintln!("Failed to save: {}", e);
+                fail(Error::Storage(t!("Failed to save: {}", e)));
                 return;
             }
-            println!("Updated (#{}).", idx + 1);
+            if json_output() {
+                print_json(&todos[idx]);
+            } else {
+                announce(t!(
+                    "Snoozed (#{}) until {}.",
+                    id,
+                    until.format("%Y-%m-%d %H:%M")
+                ));
+            }
         }
 
-        _ => {
-            eprintln!("Unknown command: {}", cmd);
-            print_usage();
+        Command::Serve { port, host } => {
+            if let Err(e) = serve::run(&host, port) {
+                fail(e);
+            }
+        }
+
+        Command::Bot { allow } => {
+            if let Err(e) = bot::run(allow) {
+                fail(e);
+            }
         }
+
+        Command::Sync { remote: None } => match sync::sync() {
+            Ok(synced) if json_output() => print_json(&json!({
+                "repository": synced.dir,
+                "committed": synced.committed,
+                "pulled": synced.pulled,
+                "pushed": synced.pushed,
+                "conflicts": synced.conflicts,
+            })),
+            Ok(synced) => {
+                if synced.committed {
+                    println!("{}", t!("Committed changes in {}.", synced.dir.display()));
+                } else {
+                    println!("{}", t!("No local changes to commit."));
+                }
+                if synced.pulled {
+                    println!("{}", t!("Pulled from the remote."));
+                }
+                if synced.conflicts > 0 {
+                    println!(
+                        "{}",
+                        t!(
+                            "{} field(s) changed differently on both sides kept this side's value; \
+                             see 'resolve'.",
+                            synced.conflicts
+                        )
+                    );
+                }
+                if synced.pushed {
+                    println!("{}", t!("Pushed to the remote."));
+                } else if config::get().sync.remote.is_none() {
+                    println!(
+                        "{}",
+                        t!("No remote configured; set `remote` under [sync] in the config.")
+                    );
+                }
+            }
+            Err(e) => fail(t!("Sync failed: {}", e)),
+        },
     }
 }
-
@@ -0,0 +1,182 @@
+use crate::burndown::Unit;
+use crate::cli::Target;
+use crate::color::paint;
+use crate::due::describe_due;
+use crate::error::Error;
+use crate::i18n::t;
+use crate::{
+    announce, find_target, json_output, load_db, lock, now, open_blockers, position_of, print_json,
+    save_db, track, Note, Todo,
+};
+use chrono::{NaiveDate, NaiveDateTime};
+use std::env;
+use std::fs;
+use std::process::Command;
+
+/// Adds a note to the todo `target` names: `text`, or what the user writes
+/// in an editor when there is no text or `edit` is set. The DB isn't locked
+/// while the editor is open.
+pub fn add(target: Target, text: String, edit: bool) -> Result<(), Error> {
+    let todo = {
+        let _lock = lock::shared()?;
+        let todos = load_db();
+        todos[find_target(&todos, target, |_| true)?].clone()
+    };
+    let text = if edit || text.trim().is_empty() {
+        write_in_editor(&todo, &text)?
+    } else {
+        text.trim().to_string()
+    };
+    if text.is_empty() {
+        return Err(Error::Usage(
+            t!("Empty note; nothing was saved.").to_string(),
+        ));
+    }
+    let _lock = lock::exclusive()?;
+    let mut todos = load_db();
+    let idx = position_of(&todos, todo.id)
+        .ok_or_else(|| Error::NotFound(t!("#{} was removed meanwhile.", todo.id)))?;
+    todos[idx].notes.push(Note { at: now(), text });
+    save_db(&todos).map_err(|e| Error::Storage(t!("Failed to save: {}", e)))?;
+    if json_output() {
+        print_json(&todos[idx]);
+    } else {
+        announce(t!("Added a note to (#{}): {}", todo.id, todo.title));
+    }
+    Ok(())
+}
+
+/// Lets the user write a note, starting from `text`, in `$VISUAL` or
+/// `$EDITOR` (default `vi`). Lines starting with `#` are dropped.
+fn write_in_editor(t: &Todo, text: &str) -> Result<String, String> {
+    let editor = env::var("VISUAL")
+        .or_else(|_| env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".to_string());
+    let mut words = editor.split_whitespace();
+    let program = words.next().ok_or("$EDITOR is empty")?;
+    let path = env::temp_dir().join(format!("todo-note-{}-{}.txt", t.id, std::process::id()));
+    let template = format!(
+        "{}\n# Note for #{}: {}\n\
+         # Lines starting with '#' are ignored; an empty note is dropped.\n",
+        text, t.id, t.title
+    );
+    fs::write(&path, template).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let status = Command::new(program).args(words).arg(&path).status();
+    let text = fs::read_to_string(&path);
+    let _ = fs::remove_file(&path);
+    match status {
+        Ok(s) if s.success() => {}
+        Ok(s) => return Err(t!("{} exited with {}", editor, s)),
+        Err(e) => return Err(t!("Failed to run {}: {}", editor, e)),
+    }
+    let text = text.map_err(|e| e.to_string())?;
+    let lines: Vec<&str> = text.lines().filter(|l| !l.starts_with('#')).collect();
+    Ok(lines.join("\n").trim().to_string())
+}
+
+/// Prints every field of `t`, its subtasks and its notes.
+pub fn show(todos: &[Todo], t: &Todo, now: NaiveDateTime, today: NaiveDate) {
+    let status = if t.completed {
+        t!("done")
+    } else if t.in_progress {
+        t!("in progress")
+    } else {
+        t!("open")
+    };
+    println!("#{} {}", t.id, t.title);
+    if !t.description.trim().is_empty() {
+        println!("{}", t.description);
+    }
+    println!();
+    let mut fields: Vec<(&str, String)> = vec![(t!("Status"), status.to_string())];
+    if t.pinned {
+        fields.push((t!("Pinned"), t!("yes").to_string()));
+    }
+    if let Some(p) = t.priority {
+        fields.push((t!("Priority"), p.as_str().to_string()));
+    }
+    if let Some(d) = t.due {
+        fields.push((t!("Due"), format!("{} ({})", d, describe_due(d, today))));
+    }
+    if let Some(r) = t.repeat {
+        fields.push((t!("Repeats"), r.to_string()));
+    }
+    if !t.tags.is_empty() {
+        let tags: Vec<String> = t.tags.iter().map(|tag| format!("#{}", tag)).collect();
+        fields.push((t!("Tags"), paint(&tags.join(" "), |c| c.tag)));
+    }
+    if !t.contexts.is_empty() {
+        let contexts: Vec<String> = t.contexts.iter().map(|c| format!("@{}", c)).collect();
+        fields.push((t!("Contexts"), paint(&contexts.join(" "), |c| c.context)));
+    }
+    if let Some(p) = t.parent {
+        fields.push((t!("Parent"), format!("#{}", p)));
+    }
+    let subtasks: Vec<String> = todos
+        .iter()
+        .filter(|s| s.parent == Some(t.id))
+        .map(|s| format!("#{}{}", s.id, if s.completed { " ✔" } else { "" }))
+        .collect();
+    if !subtasks.is_empty() {
+        fields.push((t!("Subtasks"), subtasks.join(", ")));
+    }
+    if !t.blocked_by.is_empty() {
+        let open = open_blockers(todos, t);
+        let ids: Vec<String> = t
+            .blocked_by
+            .iter()
+            .map(|b| format!("#{}{}", b, if open.contains(b) { "" } else { " ✔" }))
+            .collect();
+        fields.push((t!("Waits for"), ids.join(", ")));
+    }
+    if let Some(c) = t.created_at {
+        fields.push((t!("Created"), c.format("%Y-%m-%d %H:%M").to_string()));
+    }
+    if let Some(c) = t.completed_at {
+        fields.push((t!("Completed"), c.format("%Y-%m-%d %H:%M").to_string()));
+    }
+    if let Some(s) = t.snoozed.filter(|s| *s > now) {
+        fields.push((t!("Snoozed"), t!("until {}", s.format("%Y-%m-%d %H:%M"))));
+    }
+    if !t.time.is_empty() {
+        let running = if track::is_running(t) {
+            t!(" (timer running)")
+        } else {
+            ""
+        };
+        let total = track::describe(track::total(t, now));
+        fields.push((t!("Tracked"), format!("{}{}", total, running)));
+    }
+    if t.pomodoros > 0 {
+        fields.push((t!("Pomodoros"), t.pomodoros.to_string()));
+    }
+    if let Some(e) = t.estimate {
+        fields.push((t!("Estimate"), Unit::configured().describe(e)));
+    }
+    // `--meta` fields go by their own, lowercase names.
+    for (key, value) in &t.meta {
+        fields.push((key, value.clone()));
+    }
+    // Translated names may be longer than the English ones.
+    let width = fields
+        .iter()
+        .map(|(name, _)| name.chars().count() + 1)
+        .max();
+    let width = width.unwrap_or(0).max(10);
+    for (name, value) in fields {
+        println!("{:<width$} {}", format!("{}:", name), value, width = width);
+    }
+    if t.notes.is_empty() {
+        return;
+    }
+    println!("\n{}", t!("Notes:"));
+    for note in &t.notes {
+        println!(
+            "{}",
+            paint(&note.at.format("%Y-%m-%d %H:%M").to_string(), |c| c.muted)
+        );
+        for line in note.text.lines() {
+            println!("  {}", line);
+        }
+    }
+}
@@ -0,0 +1,128 @@
+use crate::i18n::t;
+use crate::track::{self, TimeEntry};
+use crate::{
+    complete, confirm, json_output, load_db, lock, now, position_of, print_json, save_db, Todo,
+};
+use serde_json::json;
+use std::io::{self, IsTerminal, Write};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Loads the active list under the DB lock, changes todo `id` with
+/// `change` and saves it. The lock is only held for this, so other commands
+/// can run during a pomodoro.
+fn update(
+    id: u64,
+    change: impl FnOnce(&mut Vec<Todo>, usize) -> Result<(), String>,
+) -> Result<Todo, String> {
+    let _lock = lock::exclusive()?;
+    let mut todos = load_db();
+    let idx = position_of(&todos, id).ok_or_else(|| t!("No todo with id {}", id))?;
+    change(&mut todos, idx)?;
+    save_db(&todos).map_err(|e| t!("Failed to save: {}", e))?;
+    Ok(todos[idx].clone())
+}
+
+/// Waits `minutes`, counting down on the terminal, and rings the bell.
+fn countdown(label: &str, minutes: u32) {
+    let length = Duration::from_secs(u64::from(minutes) * 60);
+    let started = Instant::now();
+    let live = io::stdout().is_terminal() && !json_output();
+    if !live && !json_output() {
+        println!("{}", t!("{} for {} minute(s)...", label, minutes));
+    }
+    while let Some(left) = length.checked_sub(started.elapsed()) {
+        if live {
+            let secs = left.as_secs() + u64::from(left.subsec_nanos() > 0);
+            print!("\r{} {:02}:{:02} ", label, secs / 60, secs % 60);
+            let _ = io::stdout().flush();
+        }
+        thread::sleep(left.min(Duration::from_secs(1)));
+    }
+    if live {
+        print!("\r\x07{}      \n", t!("{} over.", label));
+    } else if !json_output() {
+        println!("\x07{}", t!("{} over.", label));
+    }
+}
+
+/// Runs `cycles` focus periods of `work` minutes on todo `id` with `rest`
+/// minute breaks between them. Each finished focus period is counted on the
+/// todo and recorded as tracked time; at the end the todo is marked done if
+/// the user confirms.
+pub fn run(id: u64, work: u32, rest: u32, cycles: u32) -> Result<(), String> {
+    let title = {
+        let _lock = lock::shared()?;
+        let todos = load_db();
+        let idx = position_of(&todos, id)
+            .ok_or_else(|| t!("No todo with id {}. Use 'list' to see items.", id))?;
+        if todos[idx].completed {
+            return Err(t!("#{} is already done.", id));
+        }
+        todos[idx].title.clone()
+    };
+    // Focus periods are recorded as tracked time, so a running timer
+    // would count them twice.
+    {
+        let _lock = lock::exclusive()?;
+        let mut todos = load_db();
+        if let Some(i) = track::running(&todos) {
+            track::stop_timer(&mut todos, i);
+            save_db(&todos).map_err(|e| t!("Failed to save: {}", e))?;
+            if !json_output() {
+                println!("{}", t!("Stopped the timer of #{}.", todos[i].id));
+            }
+        }
+    }
+    if !json_output() {
+        println!(
+            "{}",
+            t!("Pomodoro on (#{}): {} (Ctrl-C to give up)", id, title)
+        );
+    }
+
+    let mut todo = None;
+    for cycle in 1..=cycles {
+        let start = now();
+        countdown(&t!("Focus {}/{}", cycle, cycles), work);
+        todo = Some(update(id, |todos, i| {
+            todos[i].pomodoros += 1;
+            todos[i].time.push(TimeEntry {
+                start,
+                end: Some(now()),
+            });
+            Ok(())
+        })?);
+        if cycle < cycles {
+            countdown(t!("Break"), rest);
+        }
+    }
+
+    let mut done = false;
+    if confirm(&t!("Mark (#{}) {} as done?", id, title)) {
+        todo = Some(update(id, |todos, i| {
+            complete(todos, i).map(|_| ()).map_err(|b| b.to_string())
+        })?);
+        done = true;
+    }
+    let pomodoros = todo.as_ref().map_or(0, |t| t.pomodoros);
+    if json_output() {
+        print_json(&json!({ "todo": todo, "completed_cycles": cycles, "done": done }));
+    } else {
+        println!(
+            "{}",
+            t!(
+                "{} pomodoro(s) on #{} so far ({} total).",
+                pomodoros,
+                id,
+                todo.as_ref()
+                    .map(|t| track::describe(track::total(t, now())))
+                    .unwrap_or_default()
+            )
+        );
+        if done {
+            println!("{}", t!("Marked as done (#{}): {}", id, title));
+        }
+    }
+    Ok(())
+}
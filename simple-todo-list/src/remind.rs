@@ -0,0 +1,108 @@
+use crate::due::describe_due;
+use crate::{all_lists, config, list_path, load_from, lock, DEFAULT_LIST};
+use chrono::{Duration, Local, NaiveDateTime, NaiveTime};
+use notify_rust::Notification;
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+
+/// How often `remind --daemon` looks at the DB.
+const POLL: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// Set once a notification failed, so a missing notification service is
+/// reported only once.
+static NO_NOTIFICATIONS: AtomicBool = AtomicBool::new(false);
+
+/// A todo to remind about.
+struct Reminder {
+    list: String,
+    id: u64,
+    title: String,
+    deadline: NaiveDateTime,
+    snoozed: Option<NaiveDateTime>,
+}
+
+/// The time of day todos are due, from `remind.at` (default 09:00).
+fn due_time() -> Result<NaiveTime, String> {
+    match &config::get().remind.at {
+        Some(at) => NaiveTime::parse_from_str(at, "%H:%M")
+            .map_err(|_| format!("Invalid remind.at '{}' in the config: use HH:MM", at)),
+        None => Ok(NaiveTime::from_hms_opt(9, 0, 0).unwrap_or_default()),
+    }
+}
+
+/// Open todos in every list whose deadline is at most `before` away (or
+/// past) and that aren't snoozed.
+fn due_now(now: NaiveDateTime, at: NaiveTime, before: Duration) -> Result<Vec<Reminder>, String> {
+    let _lock = lock::shared()?;
+    let mut reminders = Vec::new();
+    for list in all_lists() {
+        for t in load_from(&list_path(&list)) {
+            let Some(due) = t.due.filter(|_| !t.completed) else {
+                continue;
+            };
+            let deadline = due.and_time(at);
+            if now < deadline - before || t.snoozed.is_some_and(|s| now < s) {
+                continue;
+            }
+            reminders.push(Reminder {
+                list: list.clone(),
+                id: t.id,
+                title: t.title,
+                deadline,
+                snoozed: t.snoozed,
+            });
+        }
+    }
+    Ok(reminders)
+}
+
+fn notify(r: &Reminder, now: NaiveDateTime) {
+    let mut body = format!(
+        "{} at {} (#{})",
+        describe_due(r.deadline.date(), now.date()),
+        r.deadline.format("%H:%M"),
+        r.id
+    );
+    if r.list != DEFAULT_LIST {
+        body.push_str(&format!(" in {}", r.list));
+    }
+    println!("{}: {}", r.title, body);
+    if let Err(e) = Notification::new()
+        .appname("todo")
+        .summary(&r.title)
+        .body(&body)
+        .show()
+    {
+        if !NO_NOTIFICATIONS.swap(true, Ordering::Relaxed) {
+            eprintln!("Can't show desktop notifications: {}", e);
+        }
+    }
+}
+
+/// Notifies about every todo coming due. With `daemon`, keeps checking
+/// every minute, notifying about each todo once (again after a snooze).
+pub fn run(daemon: bool, before: Option<u32>) -> Result<(), String> {
+    let at = due_time()?;
+    let before = Duration::minutes(before.or(config::get().remind.before).unwrap_or(15).into());
+    let mut shown = HashSet::new();
+    loop {
+        let now = Local::now().naive_local();
+        match due_now(now, at, before) {
+            Ok(reminders) => {
+                for r in reminders {
+                    if shown.insert((r.list.clone(), r.id, r.deadline, r.snoozed)) {
+                        notify(&r, now);
+                    }
+                }
+            }
+            // A busy DB is checked again next time.
+            Err(e) if daemon => eprintln!("{}", e),
+            Err(e) => return Err(e),
+        }
+        if !daemon {
+            return Ok(());
+        }
+        thread::sleep(POLL);
+    }
+}
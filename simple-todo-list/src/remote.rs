@@ -0,0 +1,344 @@
+use crate::conflicts;
+use crate::merge::{merge3, FieldConflict};
+use crate::storage::write_atomic;
+use crate::{crypto, db_path, load_db, position_of, save_db, stem_and_ext, Todo};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// What the list looked like at the last `sync --remote`, kept next to it
+/// in `todos.sync.json` and encrypted like the DB when it is.
+#[derive(Serialize, Deserialize, Default)]
+struct State {
+    url: String,
+    /// Synced todos by id.
+    items: BTreeMap<u64, Mark>,
+}
+
+/// The server's revision of a todo when it was last synced, and a
+/// fingerprint of its content then, to tell whether it changed here since.
+/// The content itself is the common version a change on both sides is
+/// merged from; state files written before it was kept don't have it.
+#[derive(Serialize, Deserialize, Clone)]
+struct Mark {
+    rev: u64,
+    hash: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    todo: Option<Todo>,
+}
+
+/// A todo as the server sends it: the usual fields plus its revision.
+#[derive(Deserialize)]
+struct RemoteTodo {
+    rev: u64,
+    #[serde(flatten)]
+    todo: Todo,
+}
+
+/// What `sync --remote` did.
+#[derive(Default)]
+pub struct Report {
+    pub pushed: usize,
+    pub pulled: usize,
+    pub deleted_here: usize,
+    pub deleted_there: usize,
+    /// Todos changed on both sides and merged field by field.
+    pub merged: usize,
+    /// Fields changed differently on both sides; this side's value was
+    /// kept and the server's waits for `resolve`.
+    pub conflicts: Vec<FieldConflict>,
+    /// Todos replaced by the server's version: changed there but deleted
+    /// here, or changed there again during the sync.
+    pub overwritten: Vec<u64>,
+}
+
+pub fn state_path(list: &Path) -> PathBuf {
+    let (stem, _) = stem_and_ext(list);
+    list.with_file_name(format!("{}.sync.json", stem))
+}
+
+fn load_state(path: &Path) -> Result<State, String> {
+    let Ok(mut data) = fs::read(path) else {
+        return Ok(State::default());
+    };
+    if let Some(salt) = crypto::file_salt(path) {
+        data = crypto::decrypt(&data, &crypto::key_for(&salt)?)?;
+    }
+    Ok(serde_json::from_slice(&data).unwrap_or_default())
+}
+
+fn save_state(path: &Path, state: &State) -> Result<(), String> {
+    let mut data = serde_json::to_vec_pretty(state).map_err(|e| e.to_string())?;
+    if let Some(salt) = crypto::db_salt() {
+        data = crypto::encrypt(&data, &salt, &crypto::key_for(&salt)?)?;
+    }
+    write_atomic(path, |file| std::io::Write::write_all(file, &data))
+}
+
+/// FNV-1a of the todo's JSON; `todo serve` uses it as the revision.
+pub fn fingerprint(t: &Todo) -> u64 {
+    serde_json::to_vec(t)
+        .unwrap_or_default()
+        .iter()
+        .fold(0xcbf29ce484222325, |h, &b| {
+            (h ^ b as u64).wrapping_mul(0x100000001b3)
+        })
+}
+
+fn mark(rev: u64, t: &Todo) -> Mark {
+    Mark {
+        rev,
+        hash: fingerprint(t),
+        todo: Some(t.clone()),
+    }
+}
+
+/// The HTTP API served by `todo serve`: `GET /todos`, `POST /todos`, and
+/// `PUT`/`DELETE /todos/<id>` guarded by `If-Match` with the revision the
+/// server sends as the `ETag`.
+struct Client {
+    agent: ureq::Agent,
+    base: String,
+}
+
+fn etag(rev: u64) -> String {
+    format!("\"{}\"", rev)
+}
+
+fn http_error(e: ureq::Error) -> String {
+    match e {
+        ureq::Error::Status(code, response) => {
+            let body = response.into_string().unwrap_or_default();
+            format!("server answered {}: {}", code, body.trim())
+        }
+        ureq::Error::Transport(t) => t.to_string(),
+    }
+}
+
+impl Client {
+    fn new(url: &str) -> Client {
+        Client {
+            agent: ureq::AgentBuilder::new()
+                .timeout(Duration::from_secs(30))
+                .build(),
+            base: url.trim_end_matches('/').to_string(),
+        }
+    }
+
+    fn url(&self, id: Option<u64>) -> String {
+        match id {
+            Some(id) => format!("{}/todos/{}", self.base, id),
+            None => format!("{}/todos", self.base),
+        }
+    }
+
+    fn list(&self) -> Result<Vec<RemoteTodo>, String> {
+        let response = self.agent.get(&self.url(None)).call().map_err(http_error)?;
+        response.into_json().map_err(|e| e.to_string())
+    }
+
+    /// Creates `t`; the server picks the id.
+    fn create(&self, t: &Todo) -> Result<RemoteTodo, String> {
+        let response = self
+            .agent
+            .post(&self.url(None))
+            .send_json(t)
+            .map_err(http_error)?;
+        response.into_json().map_err(|e| e.to_string())
+    }
+
+    /// Stores `t` if the server still has revision `rev` of it (or, for
+    /// `None`, doesn't have it at all). Returns the new revision, or `None`
+    /// when someone else changed it first.
+    fn put(&self, t: &Todo, rev: Option<u64>) -> Result<Option<u64>, String> {
+        let request = self.agent.put(&self.url(Some(t.id)));
+        let request = match rev {
+            Some(rev) => request.set("If-Match", &etag(rev)),
+            None => request.set("If-None-Match", "*"),
+        };
+        match request.send_json(t) {
+            Ok(response) => response
+                .header("ETag")
+                .and_then(|tag| tag.trim_matches('"').parse().ok())
+                .map(Some)
+                .ok_or_else(|| "server sent no ETag".to_string()),
+            Err(ureq::Error::Status(404 | 412, _)) => Ok(None),
+            Err(e) => Err(http_error(e)),
+        }
+    }
+
+    /// Deletes todo `id` if the server still has revision `rev` of it.
+    /// Returns false when someone else changed it first.
+    fn delete(&self, id: u64, rev: u64) -> Result<bool, String> {
+        match self
+            .agent
+            .delete(&self.url(Some(id)))
+            .set("If-Match", &etag(rev))
+            .call()
+        {
+            Ok(_) | Err(ureq::Error::Status(404, _)) => Ok(true),
+            Err(ureq::Error::Status(412, _)) => Ok(false),
+            Err(e) => Err(http_error(e)),
+        }
+    }
+}
+
+/// Number of ancestors of `todos[i]`, so parents can be sent first.
+fn depth(todos: &[Todo], i: usize) -> usize {
+    let mut depth = 0;
+    let mut cur = todos[i].parent;
+    while let Some(p) = cur.and_then(|pid| position_of(todos, pid)) {
+        depth += 1;
+        cur = todos[p].parent;
+        if depth > todos.len() {
+            break;
+        }
+    }
+    depth
+}
+
+/// Two-way sync of the active list with the server at `url`. Changes made
+/// on only one side since the last sync are copied to the other; a todo
+/// both sides changed is merged field by field, and fields changed
+/// differently on both keep this side's value and are recorded for
+/// `resolve`.
+pub fn sync(url: &str) -> Result<Report, String> {
+    let path = db_path();
+    let state_file = state_path(&path);
+    let mut state = load_state(&state_file)?;
+    if state.url != url {
+        // Another server: nothing is known to be in sync yet.
+        state = State {
+            url: url.to_string(),
+            items: BTreeMap::new(),
+        };
+    }
+    let client = Client::new(url);
+    let remote: BTreeMap<u64, RemoteTodo> =
+        client.list()?.into_iter().map(|r| (r.todo.id, r)).collect();
+    let mut todos = load_db();
+    let before: Vec<u64> = todos.iter().map(fingerprint).collect();
+    let mut report = Report::default();
+
+    // What was done before a failed request is kept, so the next sync
+    // doesn't send it again.
+    let result = exchange(&client, &remote, &mut todos, &mut state, &mut report);
+    if todos.len() != before.len() || todos.iter().map(fingerprint).ne(before) {
+        save_db(&todos)?;
+    }
+    save_state(&state_file, &state)?;
+    conflicts::record(&path, &report.conflicts, url)?;
+    result.map(|()| report)
+}
+
+fn exchange(
+    client: &Client,
+    remote: &BTreeMap<u64, RemoteTodo>,
+    todos: &mut Vec<Todo>,
+    state: &mut State,
+    report: &mut Report,
+) -> Result<(), String> {
+    // Todos added here get their ids from the server, which may differ
+    // from the local ones; parents go first so children can point to them.
+    let mut new: Vec<usize> = (0..todos.len())
+        .filter(|&i| !state.items.contains_key(&todos[i].id))
+        .collect();
+    new.sort_by_key(|&i| depth(todos, i));
+    let mut renamed: HashMap<u64, u64> = HashMap::new();
+    for &i in &new {
+        let mut t = todos[i].clone();
+        t.parent = t.parent.map(|p| renamed.get(&p).copied().unwrap_or(p));
+        let created = client.create(&t)?;
+        renamed.insert(todos[i].id, created.todo.id);
+        state
+            .items
+            .insert(created.todo.id, mark(created.rev, &created.todo));
+        todos[i] = created.todo;
+        report.pushed += 1;
+    }
+    for (i, t) in todos.iter_mut().enumerate() {
+        if new.contains(&i) {
+            continue;
+        }
+        if let Some(p) = t.parent.and_then(|p| renamed.get(&p)) {
+            t.parent = Some(*p);
+        }
+    }
+
+    // Todos both sides had at the last sync (the ones just sent aren't in
+    // the `remote` listing).
+    let mut removed = Vec::new();
+    for t in todos.iter_mut() {
+        if renamed.values().any(|&id| id == t.id) {
+            continue;
+        }
+        let Some(last) = state.items.get(&t.id).cloned() else {
+            continue;
+        };
+        let changed_here = fingerprint(t) != last.hash;
+        let Some(r) = remote.get(&t.id) else {
+            if changed_here {
+                // Deleted there but edited here: put it back.
+                if let Some(rev) = client.put(t, None)? {
+                    state.items.insert(t.id, mark(rev, t));
+                    report.pushed += 1;
+                    continue;
+                }
+            }
+            removed.push(t.id);
+            state.items.remove(&t.id);
+            report.deleted_here += 1;
+            continue;
+        };
+        let changed_there = r.rev != last.rev;
+        if changed_here && !changed_there {
+            if let Some(rev) = client.put(t, Some(last.rev))? {
+                state.items.insert(t.id, mark(rev, t));
+                report.pushed += 1;
+                continue;
+            }
+        }
+        if changed_here && changed_there {
+            let (merged, mut found) = merge3(last.todo.as_ref(), t, &r.todo);
+            if let Some(rev) = client.put(&merged, Some(r.rev))? {
+                *t = merged;
+                state.items.insert(t.id, mark(rev, t));
+                report.merged += 1;
+                report.conflicts.append(&mut found);
+                continue;
+            }
+        }
+        if changed_there || changed_here {
+            if changed_here {
+                report.overwritten.push(t.id);
+            } else {
+                report.pulled += 1;
+            }
+            *t = r.todo.clone();
+            state.items.insert(t.id, mark(r.rev, t));
+        }
+    }
+    todos.retain(|t| !removed.contains(&t.id));
+
+    // Todos only the server has: new there, or deleted here.
+    for (&id, r) in remote {
+        if position_of(todos, id).is_some() {
+            continue;
+        }
+        if let Some(last) = state.items.get(&id).cloned() {
+            if client.delete(id, last.rev)? {
+                state.items.remove(&id);
+                report.deleted_there += 1;
+                continue;
+            }
+            report.overwritten.push(id);
+        } else {
+            report.pulled += 1;
+        }
+        todos.push(r.todo.clone());
+        state.items.insert(id, mark(r.rev, &r.todo));
+    }
+    Ok(())
+}
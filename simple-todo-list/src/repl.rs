@@ -0,0 +1,56 @@
+use crate::cli::{split_words, Cli};
+use crate::i18n::t;
+use crate::{base_db_path, current_list, run, session};
+use clap::CommandFactory;
+use rustyline::error::ReadlineError;
+use rustyline::DefaultEditor;
+
+/// Reads commands until `exit` or end of input, then saves what changed.
+pub fn run_interactive() -> Result<(), String> {
+    let mut editor = DefaultEditor::new().map_err(|e| e.to_string())?;
+    let history = base_db_path().with_extension("history");
+    let _ = editor.load_history(&history);
+    session::start();
+    println!(
+        "{}",
+        t!("Type a command (e.g. `add milk`, `done 2`, `list`), `help`, or `exit`.")
+    );
+
+    loop {
+        let line = match editor.readline(&format!("todo[{}]> ", current_list())) {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => break,
+            Err(e) => {
+                eprintln!("{}", e);
+                break;
+            }
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        let _ = editor.add_history_entry(line.as_str());
+        let args = match split_words(&line) {
+            Ok(args) => args,
+            Err(e) => {
+                eprintln!("{}", e);
+                continue;
+            }
+        };
+        match args[0].as_str() {
+            "exit" | "quit" => break,
+            "help" => {
+                let _ = Cli::command().print_help();
+            }
+            "save" => match session::flush() {
+                Ok(()) => println!("{}", t!("Saved.")),
+                Err(e) => eprintln!("{}", t!("Failed to save: {}", e)),
+            },
+            "interactive" => eprintln!("{}", t!("Already in interactive mode.")),
+            _ => run(args),
+        }
+    }
+
+    let _ = editor.save_history(&history);
+    session::end()
+}
@@ -0,0 +1,86 @@
+use crate::error::Error;
+use crate::i18n::t;
+use crate::{json_output, print_json, print_tree, sort, today, Todo};
+use regex::Regex;
+
+/// The part of a todo `search --field` looks in.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Field {
+    Title,
+    Description,
+    Tag,
+}
+
+impl Field {
+    pub fn parse(s: &str) -> Result<Field, String> {
+        match s.to_lowercase().as_str() {
+            "title" => Ok(Field::Title),
+            "description" | "desc" => Ok(Field::Description),
+            "tag" | "tags" => Ok(Field::Tag),
+            _ => Err(format!(
+                "Unknown field '{}': use title, description or tag",
+                s
+            )),
+        }
+    }
+}
+
+/// What `search` looks for: text anywhere, ignoring case, or a regular
+/// expression, which is case-sensitive unless it starts with `(?i)`.
+pub enum Pattern {
+    Text(String),
+    Regex(Regex),
+}
+
+impl Pattern {
+    pub fn new(pattern: &str, regex: bool) -> Result<Pattern, Error> {
+        if !regex {
+            return Ok(Pattern::Text(pattern.to_lowercase()));
+        }
+        Regex::new(pattern).map(Pattern::Regex).map_err(|e| {
+            // The regex crate's message draws a caret under the problem;
+            // its last line says what the problem is.
+            let reason = match &e {
+                regex::Error::Syntax(s) => s
+                    .lines()
+                    .rev()
+                    .find_map(|l| l.strip_prefix("error: "))
+                    .unwrap_or(s)
+                    .to_string(),
+                _ => e.to_string(),
+            };
+            Error::Usage(t!("Invalid regular expression '{}': {}", pattern, reason))
+        })
+    }
+
+    fn is_match(&self, text: &str) -> bool {
+        match self {
+            Pattern::Text(s) => text.to_lowercase().contains(s),
+            Pattern::Regex(r) => r.is_match(text),
+        }
+    }
+
+    /// Whether `t` matches in `field`, or in any of them without one. Tags
+    /// are matched one at a time, without their `#`.
+    pub fn matches(&self, t: &Todo, field: Option<Field>) -> bool {
+        let wanted = |f: Field| field.is_none_or(|w| w == f);
+        (wanted(Field::Title) && self.is_match(&t.title))
+            || (wanted(Field::Description) && self.is_match(&t.description))
+            || (wanted(Field::Tag) && t.tags.iter().any(|tag| self.is_match(tag)))
+    }
+}
+
+/// Prints the todos matching `pattern`, in the configured order with
+/// subtasks under their parents.
+pub fn show(todos: &[Todo], pattern: &Pattern, field: Option<Field>) {
+    let today = today();
+    let mut shown: Vec<&Todo> = todos.iter().filter(|t| pattern.matches(t, field)).collect();
+    sort::Order::configured().sort(&mut shown, today);
+    if json_output() {
+        print_json(&shown);
+    } else if shown.is_empty() {
+        println!("{}", t!("Nothing matches."));
+    } else {
+        print_tree(todos, &shown, today);
+    }
+}
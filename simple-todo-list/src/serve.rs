@@ -0,0 +1,242 @@
+use crate::remote::fingerprint;
+use crate::{
+    complete, current_list, load_db, lock, next_id, now, position_of, remove_todo, reopen, save_db,
+    Todo,
+};
+use axum::extract::Path;
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::{Html, IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use serde_json::{json, Value};
+use std::sync::Mutex;
+
+/// Requests run one at a time: each loads, changes and saves the list
+/// under the DB lock, like a CLI command.
+static SERIAL: Mutex<()> = Mutex::new(());
+
+/// The page served at `/`: list, add, toggle and delete in the browser.
+const INDEX: &str = include_str!("web/index.html");
+
+type Reply = Result<Response, (StatusCode, String)>;
+
+/// Serves the active list over HTTP until interrupted. Each todo's ETag is
+/// a fingerprint of its content, so edits made with the CLI meanwhile
+/// change it too.
+pub fn run(host: &str, port: u16) -> Result<(), String> {
+    // Ask for an encrypted DB's passphrase now rather than on a request.
+    load_db();
+    let app = Router::new()
+        .route("/", get(|| async { Html(INDEX) }))
+        .route("/todos", get(list).post(create))
+        .route("/todos/{id}", get(show).put(replace).delete(remove))
+        .route("/todos/{id}/done", post(done))
+        .route("/todos/{id}/undone", post(undone));
+    let runtime = tokio::runtime::Runtime::new().map_err(|e| e.to_string())?;
+    runtime.block_on(async {
+        let listener = tokio::net::TcpListener::bind((host, port))
+            .await
+            .map_err(|e| format!("Can't listen on {}:{}: {}", host, port, e))?;
+        println!(
+            "Serving list '{}' on http://{}:{} (Ctrl-C to stop)",
+            current_list(),
+            host,
+            port
+        );
+        axum::serve(listener, app).await.map_err(|e| e.to_string())
+    })
+}
+
+/// Runs `handle` on a blocking thread while holding the DB lock.
+async fn locked(write: bool, handle: impl FnOnce() -> Reply + Send + 'static) -> Response {
+    let result = tokio::task::spawn_blocking(move || {
+        let _serial = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+        let _lock = if write {
+            lock::exclusive()
+        } else {
+            lock::shared()
+        }
+        .map_err(|e| (StatusCode::SERVICE_UNAVAILABLE, e.to_string()))?;
+        handle()
+    })
+    .await;
+    match result {
+        Ok(Ok(response)) => response,
+        Ok(Err((status, message))) => (status, Json(json!({ "error": message }))).into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({ "error": e.to_string() })),
+        )
+            .into_response(),
+    }
+}
+
+/// Revision of a todo as served: its fingerprint, cut to 53 bits so it
+/// survives a JavaScript number.
+fn rev(t: &Todo) -> u64 {
+    fingerprint(t) >> 11
+}
+
+fn etag(t: &Todo) -> String {
+    format!("\"{}\"", rev(t))
+}
+
+/// A todo as sent to clients: its fields plus `rev`, the ETag value.
+fn with_rev(t: &Todo) -> Value {
+    let mut value = json!(t);
+    value["rev"] = json!(rev(t));
+    value
+}
+
+fn todo_response(status: StatusCode, t: &Todo) -> Response {
+    (status, [(header::ETAG, etag(t))], Json(with_rev(t))).into_response()
+}
+
+fn not_found(id: u64) -> (StatusCode, String) {
+    (StatusCode::NOT_FOUND, format!("No todo with id {}", id))
+}
+
+fn save(todos: &[Todo]) -> Result<(), (StatusCode, String)> {
+    save_db(todos).map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))
+}
+
+/// Reads a todo from a request body. Only `title` is required; `id` and
+/// `rev` are ignored.
+fn parse_todo(body: &str, id: u64, todos: &[Todo]) -> Result<Todo, (StatusCode, String)> {
+    let bad = |e: String| (StatusCode::BAD_REQUEST, e);
+    let mut value: Value = serde_json::from_str(body).map_err(|e| bad(e.to_string()))?;
+    let Some(fields) = value.as_object_mut() else {
+        return Err(bad("Expected a JSON object".to_string()));
+    };
+    fields.remove("rev");
+    fields.insert("id".to_string(), json!(id));
+    fields.entry("description").or_insert(json!(""));
+    fields.entry("completed").or_insert(json!(false));
+    fields.entry("created_at").or_insert(json!(now()));
+    let todo: Todo = serde_json::from_value(value).map_err(|e| bad(e.to_string()))?;
+    if todo.title.trim().is_empty() {
+        return Err(bad("Title cannot be empty".to_string()));
+    }
+    if let Some(p) = todo.parent {
+        if p == id || position_of(todos, p).is_none() {
+            return Err(bad(format!("No parent todo with id {}", p)));
+        }
+    }
+    Ok(todo)
+}
+
+/// Checks `If-Match` / `If-None-Match: *` against the current version.
+fn check_preconditions(
+    headers: &HeaderMap,
+    current: Option<&Todo>,
+) -> Result<(), (StatusCode, String)> {
+    let failed = |message: &str| Err((StatusCode::PRECONDITION_FAILED, message.to_string()));
+    if let Some(expected) = headers.get(header::IF_MATCH) {
+        let expected = expected.to_str().unwrap_or_default();
+        match current {
+            Some(t) if expected == "*" || expected == etag(t) => {}
+            Some(_) => return failed("The todo was changed since that version"),
+            None => return failed("The todo doesn't exist"),
+        }
+    }
+    if headers.get(header::IF_NONE_MATCH).is_some_and(|v| v == "*") && current.is_some() {
+        return failed("The todo already exists");
+    }
+    Ok(())
+}
+
+async fn list() -> Response {
+    locked(false, || {
+        let todos: Vec<Value> = load_db().iter().map(with_rev).collect();
+        Ok(Json(todos).into_response())
+    })
+    .await
+}
+
+async fn show(Path(id): Path<u64>) -> Response {
+    locked(false, move || {
+        let todos = load_db();
+        let i = position_of(&todos, id).ok_or_else(|| not_found(id))?;
+        Ok(todo_response(StatusCode::OK, &todos[i]))
+    })
+    .await
+}
+
+async fn create(body: String) -> Response {
+    locked(true, move || {
+        let mut todos = load_db();
+        let todo = parse_todo(&body, next_id(&todos), &todos)?;
+        todos.push(todo.clone());
+        save(&todos)?;
+        let mut response = todo_response(StatusCode::CREATED, &todo);
+        if let Ok(location) = format!("/todos/{}", todo.id).parse() {
+            response.headers_mut().insert(header::LOCATION, location);
+        }
+        Ok(response)
+    })
+    .await
+}
+
+async fn replace(Path(id): Path<u64>, headers: HeaderMap, body: String) -> Response {
+    locked(true, move || {
+        let mut todos = load_db();
+        let pos = position_of(&todos, id);
+        check_preconditions(&headers, pos.map(|i| &todos[i]))?;
+        let todo = parse_todo(&body, id, &todos)?;
+        let status = match pos {
+            Some(i) => {
+                todos[i] = todo.clone();
+                StatusCode::OK
+            }
+            None => {
+                todos.push(todo.clone());
+                StatusCode::CREATED
+            }
+        };
+        save(&todos)?;
+        Ok(todo_response(status, &todo))
+    })
+    .await
+}
+
+/// Completes the todo like `todo done`: a recurring one gets its next
+/// occurrence and finished parents are completed too.
+async fn done(Path(id): Path<u64>) -> Response {
+    locked(true, move || {
+        let mut todos = load_db();
+        let i = position_of(&todos, id).ok_or_else(|| not_found(id))?;
+        if !todos[i].completed {
+            complete(&mut todos, i).map_err(|b| (StatusCode::CONFLICT, b.to_string()))?;
+            save(&todos)?;
+        }
+        Ok(todo_response(StatusCode::OK, &todos[i]))
+    })
+    .await
+}
+
+/// Reopens the todo like `todo undone`, along with its completed parents.
+async fn undone(Path(id): Path<u64>) -> Response {
+    locked(true, move || {
+        let mut todos = load_db();
+        let i = position_of(&todos, id).ok_or_else(|| not_found(id))?;
+        if todos[i].completed {
+            reopen(&mut todos, i);
+            save(&todos)?;
+        }
+        Ok(todo_response(StatusCode::OK, &todos[i]))
+    })
+    .await
+}
+
+/// Deletes the todo and its subtasks, like `todo remove`.
+async fn remove(Path(id): Path<u64>, headers: HeaderMap) -> Response {
+    locked(true, move || {
+        let mut todos = load_db();
+        let i = position_of(&todos, id).ok_or_else(|| not_found(id))?;
+        check_preconditions(&headers, Some(&todos[i]))?;
+        remove_todo(&mut todos, id);
+        save(&todos)?;
+        Ok(StatusCode::NO_CONTENT.into_response())
+    })
+    .await
+}
@@ -0,0 +1,261 @@
+use crate::error::Error;
+use crate::i18n::t;
+use crate::{
+    all_lists, archive_file, archive_files, archive_month_file, archive_months, audit, backup,
+    base_db_path, db_files, history, json_output, list_file, lists_at, load_from, print_json,
+    save_to, session, stem_and_ext, storage, validate_list_name, Todo,
+};
+use chrono::{DateTime, Local, NaiveDateTime};
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// A snapshot as `snapshot list` shows it.
+pub struct Info {
+    pub name: String,
+    pub created: Option<NaiveDateTime>,
+    pub lists: usize,
+}
+
+/// `todos.json` -> `todos.snapshots/`, with a directory per snapshot that
+/// holds a copy of every list and archive file.
+fn root() -> PathBuf {
+    let base = base_db_path();
+    let (stem, _) = stem_and_ext(&base);
+    base.with_file_name(format!("{}.snapshots", stem))
+}
+
+/// The copy of the default list in snapshot `name`; the other lists and
+/// the archives are named after it as they are after the DB file.
+fn snapshot_base(name: &str) -> PathBuf {
+    let base = base_db_path();
+    root().join(name).join(base.file_name().unwrap_or_default())
+}
+
+fn check_name(name: &str) -> Result<(), Error> {
+    validate_list_name(name).map_err(|_| {
+        Error::Usage(t!(
+            "Invalid snapshot name '{}': use letters, digits, '-' or '_'",
+            name
+        ))
+    })
+}
+
+fn existing(name: &str) -> Result<PathBuf, Error> {
+    check_name(name)?;
+    if !root().join(name).is_dir() {
+        return Err(Error::NotFound(t!(
+            "No snapshot '{}'. Use 'snapshot list' to see them.",
+            name
+        )));
+    }
+    Ok(snapshot_base(name))
+}
+
+/// Copies every list and archive, as stored (encrypted or not), to a new
+/// snapshot. Returns how many lists it holds.
+pub fn save(name: &str) -> Result<usize, Error> {
+    check_name(name)?;
+    session::flush()?;
+    let dir = root().join(name);
+    if dir.exists() {
+        return Err(Error::Failed(t!("Snapshot '{}' already exists", name)));
+    }
+    fs::create_dir_all(&dir)
+        .map_err(|e| Error::Storage(t!("Failed to create {}: {}", dir.display(), e)))?;
+    let base = base_db_path();
+    let top = base.parent().unwrap_or(Path::new(""));
+    for file in db_files().into_iter().filter(|f| f.exists()) {
+        // Monthly archives keep their directory.
+        let to = dir.join(file.strip_prefix(top).unwrap_or(&file));
+        let copied = to
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|()| fs::copy(&file, &to));
+        if let Err(e) = copied {
+            let _ = fs::remove_dir_all(&dir);
+            return Err(Error::Storage(t!(
+                "Failed to copy {}: {}",
+                file.display(),
+                e
+            )));
+        }
+    }
+    Ok(lists_at(&snapshot_base(name), Vec::new()).len())
+}
+
+/// The snapshots taken so far, oldest first.
+pub fn list() -> Result<Vec<Info>, String> {
+    let entries = match fs::read_dir(root()) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(t!("Failed to read {}: {}", root().display(), e)),
+    };
+    let mut snapshots: Vec<Info> = entries
+        .filter_map(|e| e.ok())
+        .filter(|e| e.path().is_dir())
+        .filter_map(|e| {
+            let name = e.file_name().into_string().ok()?;
+            let created = e.metadata().and_then(|m| m.modified()).ok().map(|t| {
+                let t: DateTime<Local> = t.into();
+                t.naive_local()
+            });
+            let lists = lists_at(&snapshot_base(&name), Vec::new()).len();
+            Some(Info {
+                name,
+                created,
+                lists,
+            })
+        })
+        .collect();
+    snapshots.sort_by(|a, b| a.created.cmp(&b.created).then(a.name.cmp(&b.name)));
+    Ok(snapshots)
+}
+
+/// Every list and archive file kept in snapshots, e.g. for `encrypt`.
+pub fn files() -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    for info in list().unwrap_or_default() {
+        let snapshot = snapshot_base(&info.name);
+        for name in lists_at(&snapshot, Vec::new()) {
+            let path = list_file(&snapshot, &name);
+            files.extend(archive_files(&path));
+            files.push(path);
+        }
+    }
+    files.retain(|f| f.exists());
+    files
+}
+
+fn load_if_exists(path: &Path) -> Result<Vec<Todo>, String> {
+    if path.exists() {
+        storage::open(path).load()
+    } else {
+        Ok(Vec::new())
+    }
+}
+
+/// Puts every list and archive back as snapshot `name` has them; lists
+/// created since are removed. The replaced files are backed up first.
+/// Returns how many todos the restored lists hold.
+pub fn restore(name: &str) -> Result<usize, Error> {
+    let snapshot = existing(name)?;
+    session::flush()?;
+    let base = base_db_path();
+    let mut names = all_lists();
+    names.extend(lists_at(&snapshot, Vec::new()));
+    names.sort();
+    names.dedup();
+    // Everything is read before anything is written, so a file that can't
+    // be read leaves the DB as it was.
+    let mut files = Vec::new();
+    for name in &names {
+        let (from, to) = (list_file(&snapshot, name), list_file(&base, name));
+        let (before, after) = (load_if_exists(&to)?, load_if_exists(&from)?);
+        let mut months = archive_months(&from);
+        months.extend(archive_months(&to));
+        months.sort();
+        months.dedup();
+        for month in months {
+            let (from, to) = (
+                archive_month_file(&from, month),
+                archive_month_file(&to, month),
+            );
+            files.push((from, to, None));
+        }
+        files.push((archive_file(&from), archive_file(&to), None));
+        files.push((from, to, Some((name, before, after))));
+    }
+    let mut restored = 0;
+    // A dry run only hands the lists to the session, which shows the diff;
+    // archives and backups are left alone.
+    if session::dry_run() {
+        for (_, to, list) in files {
+            if let Some((_, _, after)) = list {
+                save_to(&to, &after)?;
+                restored += after.len();
+            }
+        }
+        return Ok(restored);
+    }
+    for (from, to, list) in files {
+        backup::rotate(&to)?;
+        if from.exists() {
+            let data = fs::read(&from).map_err(|e| format!("{}: {}", from.display(), e))?;
+            storage::write_atomic(&to, |file| io::Write::write_all(file, &data))?;
+        } else if to.exists() {
+            fs::remove_file(&to).map_err(|e| format!("{}: {}", to.display(), e))?;
+        }
+        if let Some((name, before, after)) = list {
+            audit::record(name, &before, &after);
+            restored += after.len();
+        }
+    }
+    session::reload();
+    Ok(restored)
+}
+
+/// The lists of snapshot `name`, or of the DB as it is now.
+fn lists(name: Option<&str>) -> Result<BTreeMap<String, Vec<Todo>>, Error> {
+    let Some(name) = name else {
+        let base = base_db_path();
+        return Ok(all_lists()
+            .into_iter()
+            .map(|n| {
+                let todos = load_from(&list_file(&base, &n));
+                (n, todos)
+            })
+            .collect());
+    };
+    let snapshot = existing(name)?;
+    lists_at(&snapshot, Vec::new())
+        .into_iter()
+        .map(|n| Ok((n.clone(), load_if_exists(&list_file(&snapshot, &n))?)))
+        .collect()
+}
+
+/// Prints what changed from snapshot `a` to snapshot `b`, or to the DB as
+/// it is now when `b` is `None`, list by list.
+pub fn diff(a: &str, b: Option<&str>) -> Result<(), Error> {
+    let (old, new) = (lists(Some(a))?, lists(b)?);
+    let mut names: Vec<&String> = old.keys().chain(new.keys()).collect();
+    names.sort();
+    names.dedup();
+    let mut rows = Vec::new();
+    let mut differ = false;
+    for name in names {
+        let empty = Vec::new();
+        let before = old.get(name).unwrap_or(&empty);
+        let after = new.get(name).unwrap_or(&empty);
+        let changes = audit::compare(before, after);
+        if json_output() {
+            rows.extend(changes.into_iter().map(|(change, t, fields)| {
+                json!({
+                    "list": name,
+                    "id": t.id,
+                    "change": change,
+                    "title": t.title,
+                    "fields": fields,
+                })
+            }));
+            continue;
+        }
+        if changes.is_empty() {
+            continue;
+        }
+        differ = true;
+        println!("{}:", name);
+        for (change, t, fields) in changes {
+            println!("  #{} {} {}", t.id, change.as_str(), t.title);
+            history::print_fields(&fields);
+        }
+    }
+    if json_output() {
+        print_json(&rows);
+    } else if !differ {
+        println!("{}", t!("No differences."));
+    }
+    Ok(())
+}
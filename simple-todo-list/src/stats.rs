@@ -0,0 +1,192 @@
+use crate::color::paint;
+use crate::due::describe_due;
+use crate::i18n::t;
+use crate::streak::{self, describe_days};
+use crate::{config, json_output, load_archive, load_db, print_json, today, Priority, Todo};
+use chrono::{Datelike, Duration, NaiveDate};
+use serde_json::json;
+use std::collections::BTreeMap;
+
+/// How many weeks of completions `stats` shows.
+const WEEKS: i64 = 8;
+
+fn week_start(d: NaiveDate) -> NaiveDate {
+    d - Duration::days(d.weekday().num_days_from_monday().into())
+}
+
+/// e.g. "3.5 days" or "5.0 hours".
+fn describe_duration(d: Duration) -> String {
+    let hours = d.num_minutes() as f64 / 60.0;
+    if hours >= 24.0 {
+        format!("{:.1} days", hours / 24.0)
+    } else if hours >= 1.0 {
+        format!("{:.1} hours", hours)
+    } else {
+        format!("{} minutes", d.num_minutes())
+    }
+}
+
+/// Prints counts for the active list, completions per week and the average
+/// time from adding a todo to finishing it (archived todos included).
+pub fn show() {
+    let todos = load_db();
+    let archived = load_archive();
+    let today = today();
+
+    let open: Vec<&Todo> = todos.iter().filter(|t| !t.completed).collect();
+    let done = todos.len() - open.len();
+    let priorities = [
+        ("high", Some(Priority::High)),
+        ("medium", Some(Priority::Medium)),
+        ("low", Some(Priority::Low)),
+        ("none", None),
+    ]
+    .map(|(name, p)| (name, open.iter().filter(|t| t.priority == p).count()));
+    let mut tags: BTreeMap<&str, (usize, usize)> = BTreeMap::new();
+    for t in &todos {
+        for tag in &t.tags {
+            let counts = tags.entry(tag).or_default();
+            if t.completed {
+                counts.1 += 1;
+            } else {
+                counts.0 += 1;
+            }
+        }
+    }
+    let mut overdue: Vec<&Todo> = open
+        .iter()
+        .copied()
+        .filter(|t| t.due.is_some_and(|d| d < today))
+        .collect();
+    overdue.sort_by_key(|t| t.due);
+
+    let finished: Vec<&Todo> = todos
+        .iter()
+        .chain(&archived)
+        .filter(|t| t.completed)
+        .collect();
+    let this_week = week_start(today);
+    let weeks: Vec<(NaiveDate, usize)> = (0..WEEKS)
+        .rev()
+        .map(|i| {
+            let start = this_week - Duration::weeks(i);
+            let count = finished
+                .iter()
+                .filter(|t| {
+                    t.completed_at
+                        .is_some_and(|c| week_start(c.date()) == start)
+                })
+                .count();
+            (start, count)
+        })
+        .collect();
+    // Only todos added since timestamps were recorded count here.
+    let durations: Vec<Duration> = finished
+        .iter()
+        .filter_map(|t| Some(t.completed_at? - t.created_at?))
+        .collect();
+    let average = (!durations.is_empty()).then(|| {
+        durations.iter().fold(Duration::zero(), |sum, d| sum + *d) / durations.len() as i32
+    });
+    let streaks = streak::streaks(&finished, today);
+
+    if json_output() {
+        print_json(&json!({
+            "total": todos.len(),
+            "open": open.len(),
+            "done": done,
+            "archived": archived.len(),
+            "priority": priorities
+                .iter()
+                .map(|(p, n)| (p.to_string(), n))
+                .collect::<BTreeMap<_, _>>(),
+            "tags": tags
+                .iter()
+                .map(|(tag, (open, done))| {
+                    (tag.to_string(), json!({ "open": open, "done": done }))
+                })
+                .collect::<BTreeMap<_, _>>(),
+            "completed_per_week": weeks
+                .iter()
+                .map(|(start, n)| json!({ "week": start, "completed": n }))
+                .collect::<Vec<_>>(),
+            "average_hours_to_complete": average.map(|d| d.num_minutes() as f64 / 60.0),
+            "streak": {
+                "current": streaks.current,
+                "longest": streaks.longest.map_or(0, |l| l.0),
+            },
+            "overdue": overdue,
+        }));
+        return;
+    }
+
+    print!(
+        "{}",
+        t!(
+            "{} todo(s): {} open, {} done",
+            todos.len(),
+            open.len(),
+            done
+        )
+    );
+    if archived.is_empty() {
+        println!();
+    } else {
+        println!("{}", t!(" ({} more archived)", archived.len()));
+    }
+    let priorities: Vec<String> = priorities
+        .iter()
+        .map(|(p, n)| format!("{} {}", p, n))
+        .collect();
+    println!("{}", t!("Open by priority: {}", priorities.join(", ")));
+    if !tags.is_empty() {
+        println!("{}", t!("Tags:"));
+        for (tag, (open, done)) in &tags {
+            let name = paint(&format!("#{}", tag), |c| c.tag);
+            println!("{}", t!("  {}  {} open, {} done", name, open, done));
+        }
+    }
+
+    println!("{}", t!("Completed per week:"));
+    let widest = weeks.iter().map(|(_, n)| *n).max().unwrap_or(0).max(1);
+    for (start, n) in &weeks {
+        // Bars are scaled to at most 30 columns.
+        let bar = "█".repeat((n * 30).div_ceil(widest));
+        let line = format!("  {}  {:>3} {}", start.format("%Y-%m-%d"), n, bar);
+        println!("{}", line.trim_end());
+    }
+    match average {
+        Some(d) => println!(
+            "{}",
+            t!(
+                "Average time to complete: {} (over {} todo(s))",
+                describe_duration(d),
+                durations.len()
+            )
+        ),
+        None => println!(
+            "{}",
+            t!("Average time to complete: no completed todos with timestamps yet")
+        ),
+    }
+    if let Some((longest, ..)) = streaks.longest {
+        if config::get().stats_streak != Some(false) {
+            println!(
+                "{}",
+                t!(
+                    "Streak: {} (longest {})",
+                    describe_days(streaks.current),
+                    describe_days(longest)
+                )
+            );
+        }
+    }
+
+    if !overdue.is_empty() {
+        println!("{}", t!("Overdue ({}):", overdue.len()));
+        for t in overdue {
+            let due = t.due.map(|d| describe_due(d, today)).unwrap_or_default();
+            println!("  #{} {} ({})", t.id, t.title, paint(&due, |c| c.overdue));
+        }
+    }
+}
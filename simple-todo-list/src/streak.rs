@@ -0,0 +1,151 @@
+use crate::i18n::t;
+use crate::{json_output, load_archive, load_db, print_json, today, Todo};
+use chrono::{Datelike, Duration, NaiveDate, Weekday};
+use serde_json::json;
+use std::collections::BTreeSet;
+
+const WEEKDAYS: [Weekday; 7] = [
+    Weekday::Mon,
+    Weekday::Tue,
+    Weekday::Wed,
+    Weekday::Thu,
+    Weekday::Fri,
+    Weekday::Sat,
+    Weekday::Sun,
+];
+
+/// Runs of days on which at least one todo was completed.
+pub struct Streaks {
+    /// Days in the run that ends today, or yesterday while today has no
+    /// completion yet.
+    pub current: u32,
+    pub done_today: bool,
+    /// The longest run so far: its length, first and last day.
+    pub longest: Option<(u32, NaiveDate, NaiveDate)>,
+}
+
+/// Days with a completion; todos completed before times were recorded
+/// don't count.
+fn days(todos: &[&Todo]) -> BTreeSet<NaiveDate> {
+    todos
+        .iter()
+        .filter(|t| t.completed)
+        .filter_map(|t| t.completed_at.map(|c| c.date()))
+        .collect()
+}
+
+pub fn streaks(todos: &[&Todo], today: NaiveDate) -> Streaks {
+    let days = days(todos);
+    let done_today = days.contains(&today);
+    let mut current = 0;
+    let mut day = if done_today {
+        today
+    } else {
+        today - Duration::days(1)
+    };
+    while days.contains(&day) {
+        current += 1;
+        day -= Duration::days(1);
+    }
+    let mut longest: Option<(u32, NaiveDate, NaiveDate)> = None;
+    let mut run: Option<(u32, NaiveDate, NaiveDate)> = None;
+    for &d in &days {
+        run = match run {
+            Some((n, first, last)) if d - last == Duration::days(1) => Some((n + 1, first, d)),
+            _ => Some((1, d, d)),
+        };
+        if run.map(|r| r.0) > longest.map(|l| l.0) {
+            longest = run;
+        }
+    }
+    Streaks {
+        current,
+        done_today,
+        longest,
+    }
+}
+
+/// Completions on each weekday, Monday first.
+fn per_weekday(todos: &[&Todo]) -> [usize; 7] {
+    let mut counts = [0; 7];
+    for c in todos
+        .iter()
+        .filter(|t| t.completed)
+        .filter_map(|t| t.completed_at)
+    {
+        counts[c.weekday().num_days_from_monday() as usize] += 1;
+    }
+    counts
+}
+
+/// e.g. "1 day" or "4 days".
+pub fn describe_days(n: u32) -> String {
+    if n == 1 {
+        "1 day".to_string()
+    } else {
+        format!("{} days", n)
+    }
+}
+
+/// Prints the current and longest completion streaks of the active list
+/// (archived todos included) and how many todos were done per weekday.
+pub fn show() {
+    let todos = load_db();
+    let archived = load_archive();
+    let all: Vec<&Todo> = todos.iter().chain(&archived).collect();
+    let streaks = streaks(&all, today());
+    let weekdays = per_weekday(&all);
+
+    if json_output() {
+        print_json(&json!({
+            "current": streaks.current,
+            "done_today": streaks.done_today,
+            "longest": streaks.longest.map_or(0, |l| l.0),
+            "longest_from": streaks.longest.map(|l| l.1),
+            "longest_to": streaks.longest.map(|l| l.2),
+            "completed_per_weekday": WEEKDAYS
+                .iter()
+                .zip(weekdays)
+                .map(|(day, n)| json!({ "weekday": day.to_string(), "completed": n }))
+                .collect::<Vec<_>>(),
+        }));
+        return;
+    }
+
+    match (streaks.current, streaks.done_today) {
+        (0, _) => println!(
+            "{}",
+            t!("No streak going. Finish a todo today to start one.")
+        ),
+        (n, true) => println!(
+            "{}",
+            t!("Current streak: {}, today included.", describe_days(n))
+        ),
+        (n, false) => println!(
+            "{}",
+            t!(
+                "Current streak: {}. Finish a todo today to keep it going.",
+                describe_days(n)
+            )
+        ),
+    }
+    if let Some((n, first, last)) = streaks.longest {
+        println!(
+            "{}",
+            t!(
+                "Longest streak: {} ({} to {})",
+                describe_days(n),
+                first.format("%Y-%m-%d"),
+                last.format("%Y-%m-%d")
+            )
+        );
+    }
+    println!("{}", t!("Completed per weekday:"));
+    let widest = weekdays.iter().copied().max().unwrap_or(0).max(1);
+    for (day, n) in WEEKDAYS.iter().zip(weekdays) {
+        // Bars are scaled to at most 30 columns, as in `stats`.
+        let bar = "█".repeat((n * 30).div_ceil(widest));
+        let line = format!("  {}  {:>4} {}", day, n, bar);
+        println!("{}", line.trim_end());
+    }
+}
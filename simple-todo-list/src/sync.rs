@@ -0,0 +1,273 @@
+use crate::conflicts;
+use crate::i18n::t;
+use crate::merge::merge_lists;
+use crate::{all_lists, backup, base_db_path, config, db_files, list_file, session, storage, Todo};
+use chrono::Local;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Name the configured remote gets in the DB's repository.
+const REMOTE: &str = "origin";
+
+/// Written when `sync` creates the repository, so backups, the lock and
+/// other per-machine files stay out of it.
+const GITIGNORE: &str = "*.bak.*\n*.lock\n*.current\n*.history\n*.sync.json\n*.conflicts.json\n\
+                         *.audit.jsonl\n.*.tmp-*\n";
+
+/// What `sync` did.
+pub struct Synced {
+    pub dir: PathBuf,
+    pub committed: bool,
+    pub pulled: bool,
+    pub pushed: bool,
+    /// Fields both machines changed differently, left for `resolve`.
+    pub conflicts: usize,
+}
+
+/// Runs `git` in `dir` and returns its trimmed stdout, or stderr as the error.
+fn git(dir: &Path, args: &[&str]) -> Result<String, String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .output()
+        .map_err(|e| t!("Failed to run git: {}", e))?;
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    } else {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let message: Vec<&str> = stderr.lines().filter(|l| !l.starts_with("hint:")).collect();
+        Err(format!("git {}: {}", args[0], message.join("\n").trim()))
+    }
+}
+
+/// The directory holding the DB, which `sync` keeps as a git repository.
+/// It is created on first use; a DB inside some other repository is refused
+/// so `sync` never commits or pulls unrelated files.
+fn repo_dir() -> Result<PathBuf, String> {
+    let base = base_db_path();
+    let dir = match base.parent() {
+        Some(p) if !p.as_os_str().is_empty() => p.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+    let dir = fs::canonicalize(&dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+    match git(&dir, &["rev-parse", "--show-toplevel"]) {
+        Ok(top) if fs::canonicalize(&top).ok().as_deref() == Some(dir.as_path()) => Ok(dir),
+        Ok(top) => Err(t!(
+            "{} is inside the git repository {}; keep the DB in a directory of its own \
+             (see TODO_DB) to sync it",
+            dir.display(),
+            top
+        )),
+        Err(_) => {
+            git(&dir, &["init", "--quiet"])?;
+            fs::write(dir.join(".gitignore"), GITIGNORE).map_err(|e| e.to_string())?;
+            Ok(dir)
+        }
+    }
+}
+
+/// The content of `file` at `rev`, or `None` if it isn't there.
+fn blob(dir: &Path, rev: &str, file: &str) -> Result<Option<Vec<u8>>, String> {
+    let spec = format!("{}:{}", rev, file);
+    if git(dir, &["cat-file", "-e", &spec]).is_err() {
+        return Ok(None);
+    }
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(["cat-file", "blob", &spec])
+        .output()
+        .map_err(|e| t!("Failed to run git: {}", e))?;
+    Ok(Some(output.stdout))
+}
+
+/// The todos in `file` at `rev`, none if it isn't there. The content is
+/// read through a copy in `.git`, with the same name so the same backend
+/// (and the DB's key) reads it.
+fn todos_at(dir: &Path, rev: &str, file: &str) -> Result<Vec<Todo>, String> {
+    let Some(data) = blob(dir, rev, file)? else {
+        return Ok(Vec::new());
+    };
+    let name = Path::new(file).file_name().unwrap_or_default();
+    let copy = dir.join(".git").join("todo-merge").join(name);
+    fs::create_dir_all(copy.parent().unwrap()).map_err(|e| e.to_string())?;
+    fs::write(&copy, data).map_err(|e| e.to_string())?;
+    let todos = storage::open(&copy).load();
+    let _ = fs::remove_file(&copy);
+    todos.map_err(|e| t!("{} at {}: {}", file, rev, e))
+}
+
+/// Files changed between two revisions.
+fn changed(dir: &Path, from: &str, to: &str) -> Result<Vec<String>, String> {
+    let names = git(dir, &["diff", "--name-only", from, to])?;
+    Ok(names.lines().map(str::to_string).collect())
+}
+
+/// Merges the fetched branch into `HEAD`. A file changed on one side only
+/// takes that side's version; a list changed on both is merged todo by todo
+/// and field by field (`merge_lists`). Fields the two changed differently
+/// keep this machine's value and are recorded for `resolve`; returns how
+/// many.
+fn merge(dir: &Path) -> Result<usize, String> {
+    let born = git(dir, &["rev-parse", "--verify", "--quiet", "HEAD"]).is_ok();
+    if !born || git(dir, &["merge-base", "--is-ancestor", "HEAD", "FETCH_HEAD"]).is_ok() {
+        git(dir, &["merge", "--quiet", "--ff-only", "FETCH_HEAD"])?;
+        return Ok(0);
+    }
+    let base = git(dir, &["merge-base", "HEAD", "FETCH_HEAD"]).ok();
+    // Record the merge, keeping our files for now.
+    let mut args = vec!["merge", "--quiet", "--no-commit", "--strategy", "ours"];
+    if base.is_none() {
+        args.push("--allow-unrelated-histories");
+    }
+    args.push("FETCH_HEAD");
+    git(dir, &args)?;
+
+    let (ours, theirs) = match &base {
+        Some(base) => (
+            changed(dir, base, "HEAD")?,
+            changed(dir, base, "FETCH_HEAD")?,
+        ),
+        None => {
+            let files = |rev| git(dir, &["ls-tree", "-r", "--name-only", rev]);
+            let lines = |s: String| s.lines().map(str::to_string).collect::<Vec<_>>();
+            (lines(files("HEAD")?), lines(files("FETCH_HEAD")?))
+        }
+    };
+    let db = base_db_path();
+    let top = db.parent().unwrap_or(Path::new(""));
+    let lists: Vec<PathBuf> = all_lists()
+        .iter()
+        .filter_map(|name| {
+            list_file(&db, name)
+                .strip_prefix(top)
+                .ok()
+                .map(PathBuf::from)
+        })
+        .collect();
+    let mut conflicts = 0;
+    for file in theirs {
+        if !ours.contains(&file) {
+            if blob(dir, "FETCH_HEAD", &file)?.is_some() {
+                git(dir, &["checkout", "FETCH_HEAD", "--", &file])?;
+            } else {
+                git(dir, &["rm", "--quiet", "--", &file])?;
+            }
+            continue;
+        }
+        if file == ".gitignore" {
+            continue;
+        }
+        let common = match &base {
+            Some(base) => todos_at(dir, base, &file)?,
+            None => Vec::new(),
+        };
+        let (merged, found) = merge_lists(
+            &common,
+            &todos_at(dir, "HEAD", &file)?,
+            &todos_at(dir, "FETCH_HEAD", &file)?,
+        );
+        let path = dir.join(&file);
+        storage::open(&path).save(&merged)?;
+        git(dir, &["add", "--", &file])?;
+        // Archives keep this machine's values without asking.
+        if lists.iter().any(|l| Path::new(&file) == l) {
+            conflicts::record(&path, &found, "git")?;
+            conflicts += found.len();
+        }
+    }
+    let message = format!("Merge todos ({})", Local::now().format("%Y-%m-%d %H:%M"));
+    git(dir, &["commit", "--quiet", "-m", &message])?;
+    Ok(conflicts)
+}
+
+/// Commits the lists and archives, then, if `sync.remote` is configured,
+/// merges the remote branch in and pushes back to it.
+pub fn sync() -> Result<Synced, String> {
+    session::flush()?;
+    let dir = repo_dir()?;
+    let base = base_db_path();
+    let top = base.parent().unwrap_or(Path::new(""));
+    // Relative to the repository, so monthly archives keep their directory.
+    let mut files: Vec<String> = db_files()
+        .iter()
+        .filter(|f| f.exists())
+        .filter_map(|f| f.strip_prefix(top).ok()?.to_str().map(|n| n.to_string()))
+        .collect();
+    if dir.join(".gitignore").exists() {
+        files.push(".gitignore".to_string());
+    }
+    let mut synced = Synced {
+        dir: dir.clone(),
+        committed: false,
+        pulled: false,
+        pushed: false,
+        conflicts: 0,
+    };
+
+    if !files.is_empty() {
+        let mut add = vec!["add", "--"];
+        add.extend(files.iter().map(|f| f.as_str()));
+        git(&dir, &add)?;
+    }
+    if git(&dir, &["diff", "--cached", "--quiet"]).is_err() {
+        let message = format!("Sync todos ({})", Local::now().format("%Y-%m-%d %H:%M"));
+        git(&dir, &["commit", "--quiet", "-m", &message])?;
+        synced.committed = true;
+    }
+
+    let sync_config = &config::get().sync;
+    let Some(remote) = sync_config.remote.as_deref() else {
+        return Ok(synced);
+    };
+    match git(&dir, &["remote", "get-url", REMOTE]) {
+        Ok(url) if url == remote => {}
+        Ok(_) => {
+            git(&dir, &["remote", "set-url", REMOTE, remote])?;
+        }
+        Err(_) => {
+            git(&dir, &["remote", "add", REMOTE, remote])?;
+        }
+    }
+    let branch = match &sync_config.branch {
+        Some(branch) => branch.clone(),
+        None => git(&dir, &["symbolic-ref", "--short", "HEAD"])?,
+    };
+
+    let on_remote = git(
+        &dir,
+        &["ls-remote", "--exit-code", "--heads", REMOTE, &branch],
+    )
+    .is_ok();
+    if on_remote {
+        git(&dir, &["fetch", "--quiet", REMOTE, &branch])?;
+    }
+    let up_to_date = || git(&dir, &["merge-base", "--is-ancestor", "FETCH_HEAD", "HEAD"]).is_ok();
+    if on_remote && !up_to_date() {
+        // Merging rewrites the files without going through `save`.
+        for file in files.iter().filter(|f| *f != ".gitignore") {
+            backup::rotate(&dir.join(file))?;
+        }
+        match merge(&dir) {
+            Ok(conflicts) => synced.conflicts = conflicts,
+            Err(e) => {
+                let _ = git(&dir, &["merge", "--abort"]);
+                return Err(t!(
+                    "{}\nThe pull was undone; merge the changes with git in {}",
+                    e,
+                    dir.display()
+                ));
+            }
+        }
+        synced.pulled = true;
+        session::reload();
+    }
+    if git(&dir, &["rev-parse", "--verify", "--quiet", "HEAD"]).is_ok() {
+        let refspec = format!("HEAD:refs/heads/{}", branch);
+        git(&dir, &["push", "--quiet", REMOTE, &refspec])?;
+        synced.pushed = true;
+    }
+    Ok(synced)
+}
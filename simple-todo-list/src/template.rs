@@ -0,0 +1,200 @@
+use crate::due::Repeat;
+use crate::error::Error;
+use crate::i18n::t;
+use crate::{
+    base_db_path, crypto, next_id, now, stem_and_ext, storage, validate_list_name, Priority, Todo,
+};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+/// A todo of a template. Due dates are kept as days after the day the
+/// template is applied, and subtasks point at their parent by `key`.
+#[derive(Serialize, Deserialize)]
+pub struct Item {
+    pub key: u64,
+    pub title: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub description: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub due_in: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority: Option<Priority>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repeat: Option<Repeat>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub contexts: Vec<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub meta: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub estimate: Option<f64>,
+}
+
+/// `todos.json` -> `todos.templates/`, a file per template.
+fn dir() -> PathBuf {
+    let base = base_db_path();
+    let (stem, _) = stem_and_ext(&base);
+    base.with_file_name(format!("{}.templates", stem))
+}
+
+fn path(name: &str) -> Result<PathBuf, Error> {
+    validate_list_name(name).map_err(|_| {
+        Error::Usage(t!(
+            "Invalid template name '{}': use letters, digits, '-' or '_'",
+            name
+        ))
+    })?;
+    Ok(dir().join(format!("{}.json", name)))
+}
+
+/// Every template file, e.g. for `encrypt`.
+pub fn files() -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(dir()) else {
+        return Vec::new();
+    };
+    let mut files: Vec<PathBuf> = entries
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.extension().is_some_and(|e| e == "json"))
+        .collect();
+    files.sort();
+    files
+}
+
+/// Names of the saved templates with how many todos each holds.
+pub fn list() -> Result<Vec<(String, usize)>, Error> {
+    let mut templates = Vec::new();
+    for file in files() {
+        let name = file
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        templates.push((name.clone(), load(&name)?.len()));
+    }
+    Ok(templates)
+}
+
+/// Reads template `name`, decrypting it if the DB is encrypted.
+fn load(name: &str) -> Result<Vec<Item>, Error> {
+    let path = path(name)?;
+    let mut data = fs::read(&path).map_err(|e| match e.kind() {
+        io::ErrorKind::NotFound => Error::NotFound(t!(
+            "No template '{}'. Use 'template list' to see them.",
+            name
+        )),
+        _ => Error::Storage(t!("Failed to read {}: {}", path.display(), e)),
+    })?;
+    if let Some(salt) = crypto::file_salt(&path) {
+        data = crypto::decrypt(&data, &crypto::key_for(&salt)?)?;
+    }
+    serde_json::from_slice(&data).map_err(|e| Error::Storage(format!("{}: {}", path.display(), e)))
+}
+
+/// Saves `todos` (with their subtasks) as template `name`. Due dates are
+/// stored relative to `today`. Returns how many todos it holds.
+pub fn save(
+    name: &str,
+    all: &[Todo],
+    todos: &[&Todo],
+    today: NaiveDate,
+    replace: bool,
+) -> Result<usize, Error> {
+    let path = path(name)?;
+    if path.exists() && !replace {
+        return Err(Error::Failed(t!(
+            "Template '{}' already exists; use --force to replace it",
+            name
+        )));
+    }
+    let mut picked: Vec<&Todo> = Vec::new();
+    let mut queue: Vec<&Todo> = todos.to_vec();
+    while let Some(t) = queue.pop() {
+        if picked.iter().any(|p| p.id == t.id) {
+            continue;
+        }
+        picked.push(t);
+        queue.extend(all.iter().filter(|s| s.parent == Some(t.id)));
+    }
+    picked.sort_by_key(|t| all.iter().position(|a| a.id == t.id));
+    let items: Vec<Item> = picked
+        .iter()
+        .map(|t| Item {
+            key: t.id,
+            title: t.title.clone(),
+            description: t.description.clone(),
+            due_in: t.due.map(|d| (d - today).num_days()),
+            priority: t.priority,
+            parent: t.parent.filter(|p| picked.iter().any(|o| o.id == *p)),
+            repeat: t.repeat,
+            tags: t.tags.clone(),
+            contexts: t.contexts.clone(),
+            meta: t.meta.clone(),
+            estimate: t.estimate,
+        })
+        .collect();
+    let mut data = serde_json::to_vec_pretty(&items).map_err(|e| e.to_string())?;
+    if let Some(salt) = crypto::db_salt() {
+        data = crypto::encrypt(&data, &salt, &crypto::key_for(&salt)?)?;
+    }
+    fs::create_dir_all(dir()).map_err(|e| Error::Storage(e.to_string()))?;
+    storage::write_atomic(&path, |file| file.write_all(&data)).map_err(Error::Storage)?;
+    Ok(items.len())
+}
+
+/// Adds the todos of template `name` to `todos`, due relative to `on`.
+/// Returns the ids they got.
+pub fn apply(name: &str, todos: &mut Vec<Todo>, on: NaiveDate) -> Result<Vec<u64>, Error> {
+    let items = load(name)?;
+    let first = next_id(todos);
+    let id_of = |key: u64| {
+        items
+            .iter()
+            .position(|i| i.key == key)
+            .map(|n| first + n as u64)
+    };
+    let mut added = Vec::new();
+    for (n, item) in items.iter().enumerate() {
+        let id = first + n as u64;
+        todos.push(Todo {
+            id,
+            title: item.title.clone(),
+            description: item.description.clone(),
+            completed: false,
+            due: item.due_in.map(|days| on + chrono::Duration::days(days)),
+            priority: item.priority,
+            parent: item.parent.and_then(id_of),
+            repeat: item.repeat,
+            tags: item.tags.clone(),
+            contexts: item.contexts.clone(),
+            snoozed: None,
+            created_at: Some(now()),
+            completed_at: None,
+            time: Vec::new(),
+            pomodoros: 0,
+            in_progress: false,
+            pinned: false,
+            estimate: item.estimate,
+            blocked_by: Vec::new(),
+            notes: Vec::new(),
+            meta: item.meta.clone(),
+        });
+        added.push(id);
+    }
+    Ok(added)
+}
+
+/// Deletes template `name`.
+pub fn remove(name: &str) -> Result<(), Error> {
+    let path = path(name)?;
+    fs::remove_file(&path).map_err(|e| match e.kind() {
+        io::ErrorKind::NotFound => Error::NotFound(t!("No template '{}'", name)),
+        _ => Error::Storage(t!("Failed to remove {}: {}", path.display(), e)),
+    })
+}
@@ -0,0 +1,99 @@
+use crate::color::paint;
+use crate::i18n::t;
+use crate::track::{describe, TimeEntry};
+use crate::{json_output, load_archive, load_db, now, print_json, Todo};
+use chrono::{Duration, NaiveDate};
+use serde_json::json;
+use std::collections::BTreeMap;
+
+fn hours(d: Duration) -> f64 {
+    (d.num_minutes() as f64 / 60.0 * 100.0).round() / 100.0
+}
+
+/// Prints the time tracked in the active list (archive included) on each of
+/// the last `days` days and per tag. An entry counts on the day it started.
+pub fn show(days: u32) {
+    let todos = load_db();
+    let archived = load_archive();
+    let now = now();
+    let first = now.date() - Duration::days(i64::from(days.max(1)) - 1);
+
+    let mut by_day: BTreeMap<NaiveDate, Duration> = BTreeMap::new();
+    let mut by_tag: BTreeMap<String, Duration> = BTreeMap::new();
+    let mut by_todo: Vec<(&Todo, Duration)> = Vec::new();
+    for t in todos.iter().chain(&archived) {
+        let entries: Vec<&TimeEntry> = t.time.iter().filter(|e| e.start.date() >= first).collect();
+        if entries.is_empty() {
+            continue;
+        }
+        let mut spent = Duration::zero();
+        for e in entries {
+            *by_day.entry(e.start.date()).or_insert_with(Duration::zero) += e.length(now);
+            spent += e.length(now);
+        }
+        if t.tags.is_empty() {
+            *by_tag.entry(String::new()).or_insert_with(Duration::zero) += spent;
+        }
+        for tag in &t.tags {
+            *by_tag.entry(tag.clone()).or_insert_with(Duration::zero) += spent;
+        }
+        by_todo.push((t, spent));
+    }
+    by_todo.sort_by_key(|(_, spent)| std::cmp::Reverse(*spent));
+    let total = by_todo
+        .iter()
+        .fold(Duration::zero(), |sum, (_, spent)| sum + *spent);
+
+    if json_output() {
+        print_json(&json!({
+            "from": first,
+            "to": now.date(),
+            "total_hours": hours(total),
+            "days": by_day
+                .iter()
+                .map(|(day, d)| json!({ "date": day, "hours": hours(*d) }))
+                .collect::<Vec<_>>(),
+            "tags": by_tag
+                .iter()
+                .map(|(tag, d)| {
+                    let tag = (!tag.is_empty()).then_some(tag);
+                    json!({ "tag": tag, "hours": hours(*d) })
+                })
+                .collect::<Vec<_>>(),
+            "todos": by_todo
+                .iter()
+                .map(|(t, d)| json!({ "id": t.id, "title": t.title, "hours": hours(*d) }))
+                .collect::<Vec<_>>(),
+        }));
+        return;
+    }
+
+    if by_todo.is_empty() {
+        println!(
+            "{}",
+            t!(
+                "No time tracked since {}. Start a timer with: start <id>",
+                first
+            )
+        );
+        return;
+    }
+    println!("{}", t!("Tracked since {}: {}", first, describe(total)));
+    println!("{}", t!("Per day:"));
+    for (day, d) in &by_day {
+        println!("  {}  {:>8}", day.format("%a %Y-%m-%d"), describe(*d));
+    }
+    println!("{}", t!("Per tag:"));
+    for (tag, d) in &by_tag {
+        let name = if tag.is_empty() {
+            t!("(untagged)").to_string()
+        } else {
+            paint(&format!("#{}", tag), |c| c.tag)
+        };
+        println!("  {}  {}", name, describe(*d));
+    }
+    println!("{}", t!("Per todo:"));
+    for (t, d) in &by_todo {
+        println!("  #{} {}  {}", t.id, t.title, describe(*d));
+    }
+}
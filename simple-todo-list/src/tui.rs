@@ -0,0 +1,404 @@
+use crate::{
+    complete, due, load_db, next_id, now, position_of, remove_todo, reopen, save_db, today, Todo,
+};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, List, ListItem, ListState, Paragraph};
+use ratatui::{DefaultTerminal, Frame};
+use std::collections::BTreeMap;
+use std::io;
+
+enum Mode {
+    Normal,
+    Search,
+    Add,
+    Edit,
+    ConfirmDelete,
+}
+
+struct App {
+    todos: Vec<Todo>,
+    /// Storage indices of the rows on screen, in display order.
+    rows: Vec<usize>,
+    state: ListState,
+    mode: Mode,
+    query: String,
+    input: String,
+    message: String,
+}
+
+/// Runs the full-screen list on the active list until the user quits.
+/// Every change is saved right away, like the other commands.
+pub fn run() -> io::Result<()> {
+    let mut app = App {
+        todos: load_db(),
+        rows: Vec::new(),
+        state: ListState::default(),
+        mode: Mode::Normal,
+        query: String::new(),
+        input: String::new(),
+        message: String::new(),
+    };
+    app.refresh(None);
+    let mut terminal = ratatui::init();
+    let result = app.run(&mut terminal);
+    ratatui::restore();
+    result
+}
+
+impl App {
+    fn run(&mut self, terminal: &mut DefaultTerminal) -> io::Result<()> {
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+                return Ok(());
+            }
+            let quit = match self.mode {
+                Mode::Normal => self.on_normal_key(key.code),
+                Mode::Search => {
+                    self.on_search_key(key.code);
+                    false
+                }
+                Mode::Add | Mode::Edit => {
+                    self.on_input_key(key.code);
+                    false
+                }
+                Mode::ConfirmDelete => {
+                    if key.code == KeyCode::Char('y') {
+                        self.delete_selected();
+                    } else {
+                        self.message = "Not deleted.".to_string();
+                    }
+                    self.mode = Mode::Normal;
+                    false
+                }
+            };
+            if quit {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Rebuilds the visible rows: the whole tree, or the todos matching the
+    /// search query (title, description or tag). Selects the todo with id
+    /// `keep` if it is still shown, else stays on the same row.
+    fn refresh(&mut self, keep: Option<u64>) {
+        let query = self.query.to_lowercase();
+        self.rows = tree_order(&self.todos)
+            .into_iter()
+            .filter(|&i| query.is_empty() || matches(&self.todos[i], &query))
+            .collect();
+        let pos = keep
+            .and_then(|id| self.rows.iter().position(|&i| self.todos[i].id == id))
+            .or(self.state.selected())
+            .unwrap_or(0);
+        self.state
+            .select((!self.rows.is_empty()).then(|| pos.min(self.rows.len() - 1)));
+    }
+
+    fn selected(&self) -> Option<usize> {
+        self.state
+            .selected()
+            .and_then(|r| self.rows.get(r).copied())
+    }
+
+    fn selected_id(&self) -> Option<u64> {
+        self.selected().map(|i| self.todos[i].id)
+    }
+
+    fn save(&mut self, keep: Option<u64>) {
+        if let Err(e) = save_db(&self.todos) {
+            self.message = format!("Failed to save: {}", e);
+        }
+        self.refresh(keep);
+    }
+
+    fn on_normal_key(&mut self, code: KeyCode) -> bool {
+        self.message.clear();
+        match code {
+            KeyCode::Char('q') => return true,
+            KeyCode::Esc if !self.query.is_empty() => {
+                self.query.clear();
+                self.refresh(self.selected_id());
+            }
+            KeyCode::Esc => return true,
+            KeyCode::Down | KeyCode::Char('j') => self.state.select_next(),
+            KeyCode::Up | KeyCode::Char('k') => self.state.select_previous(),
+            KeyCode::Home | KeyCode::Char('g') => self.state.select_first(),
+            KeyCode::End | KeyCode::Char('G') => self.state.select_last(),
+            KeyCode::Char(' ') | KeyCode::Enter => self.toggle_selected(),
+            KeyCode::Char('/') => self.mode = Mode::Search,
+            KeyCode::Char('a') => {
+                self.input.clear();
+                self.mode = Mode::Add;
+            }
+            KeyCode::Char('e') => {
+                if let Some(i) = self.selected() {
+                    self.input = self.todos[i].title.clone();
+                    self.mode = Mode::Edit;
+                }
+            }
+            KeyCode::Char('d') | KeyCode::Delete if self.selected().is_some() => {
+                self.mode = Mode::ConfirmDelete;
+            }
+            _ => {}
+        }
+        false
+    }
+
+    fn on_search_key(&mut self, code: KeyCode) {
+        match code {
+            KeyCode::Enter => self.mode = Mode::Normal,
+            KeyCode::Esc => {
+                self.query.clear();
+                self.mode = Mode::Normal;
+            }
+            KeyCode::Backspace => {
+                self.query.pop();
+            }
+            KeyCode::Char(c) => self.query.push(c),
+            _ => return,
+        }
+        self.refresh(self.selected_id());
+    }
+
+    fn on_input_key(&mut self, code: KeyCode) {
+        match code {
+            KeyCode::Enter => {
+                let title = self.input.trim().to_string();
+                if !title.is_empty() {
+                    match self.mode {
+                        Mode::Add => self.add(title),
+                        _ => self.rename_selected(title),
+                    }
+                }
+                self.mode = Mode::Normal;
+            }
+            KeyCode::Esc => self.mode = Mode::Normal,
+            KeyCode::Backspace => {
+                self.input.pop();
+            }
+            KeyCode::Char(c) => self.input.push(c),
+            _ => {}
+        }
+    }
+
+    fn add(&mut self, title: String) {
+        let id = next_id(&self.todos);
+        self.todos.push(Todo {
+            id,
+            title,
+            description: String::new(),
+            completed: false,
+            due: None,
+            priority: None,
+            parent: None,
+            repeat: None,
+            tags: Vec::new(),
+            contexts: Vec::new(),
+            snoozed: None,
+            created_at: Some(now()),
+            completed_at: None,
+            time: Vec::new(),
+            pomodoros: 0,
+            in_progress: false,
+            pinned: false,
+            estimate: None,
+            blocked_by: Vec::new(),
+            notes: Vec::new(),
+            meta: BTreeMap::new(),
+        });
+        self.message = format!("Added todo (#{})", id);
+        self.save(Some(id));
+    }
+
+    fn rename_selected(&mut self, title: String) {
+        if let Some(i) = self.selected() {
+            self.todos[i].title = title;
+            let id = self.todos[i].id;
+            self.message = format!("Updated (#{}).", id);
+            self.save(Some(id));
+        }
+    }
+
+    fn toggle_selected(&mut self) {
+        let Some(i) = self.selected() else { return };
+        let id = self.todos[i].id;
+        if self.todos[i].completed {
+            reopen(&mut self.todos, i);
+            self.message = format!("Marked as not done (#{})", id);
+        } else {
+            let done = match complete(&mut self.todos, i) {
+                Ok(done) => done,
+                Err(blocked) => {
+                    self.message = blocked.to_string();
+                    return;
+                }
+            };
+            self.message = match done.next.and_then(|n| self.todos[n].due) {
+                Some(d) => format!("Marked as done (#{}); next occurrence due {}", id, d),
+                None => format!("Marked as done (#{})", id),
+            };
+        }
+        self.save(Some(id));
+    }
+
+    fn delete_selected(&mut self) {
+        let Some(i) = self.selected() else { return };
+        let id = self.todos[i].id;
+        remove_todo(&mut self.todos, id);
+        self.message = format!("Removed (#{})", id);
+        self.save(None);
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [list_area, status_area, help_area] = Layout::vertical([
+            Constraint::Min(1),
+            Constraint::Length(1),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+
+        let today = today();
+        let items: Vec<ListItem> = self
+            .rows
+            .iter()
+            .map(|&i| row(&self.todos, i, today, self.query.is_empty()))
+            .collect();
+        let done = self.todos.iter().filter(|t| t.completed).count();
+        let title = format!(
+            " {} — {}/{} done ",
+            crate::current_list(),
+            done,
+            self.todos.len()
+        );
+        let list = List::new(items)
+            .block(Block::default().borders(Borders::ALL).title(title))
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(list, list_area, &mut self.state);
+
+        let status = match self.mode {
+            Mode::Search => format!("/{}", self.query),
+            Mode::Add => format!("Add: {}", self.input),
+            Mode::Edit => format!("Edit: {}", self.input),
+            Mode::ConfirmDelete => "Delete this todo and its subtasks? (y/n)".to_string(),
+            Mode::Normal if !self.message.is_empty() => self.message.clone(),
+            Mode::Normal if !self.query.is_empty() => {
+                format!("Filter: {} (Esc to clear)", self.query)
+            }
+            Mode::Normal => String::new(),
+        };
+        if let Mode::Search | Mode::Add | Mode::Edit = self.mode {
+            let x = status_area.x + status.chars().count() as u16;
+            frame.set_cursor_position((x.min(status_area.right() - 1), status_area.y));
+        }
+        frame.render_widget(Paragraph::new(status), status_area);
+
+        let help = "j/k move  space toggle  a add  e edit  d delete  / search  q quit";
+        frame.render_widget(
+            Paragraph::new(help).style(Style::default().fg(Color::DarkGray)),
+            help_area,
+        );
+    }
+}
+
+/// Storage indices with every todo directly after its parent.
+fn tree_order(todos: &[Todo]) -> Vec<usize> {
+    fn visit(todos: &[Todo], parent: Option<u64>, out: &mut Vec<usize>) {
+        for (i, t) in todos.iter().enumerate() {
+            let is_child = match parent {
+                Some(pid) => t.parent == Some(pid),
+                None => t.parent.is_none_or(|pid| position_of(todos, pid).is_none()),
+            };
+            if is_child {
+                out.push(i);
+                visit(todos, Some(t.id), out);
+            }
+        }
+    }
+    let mut out = Vec::with_capacity(todos.len());
+    visit(todos, None, &mut out);
+    out
+}
+
+fn matches(t: &Todo, query: &str) -> bool {
+    t.title.to_lowercase().contains(query)
+        || t.description.to_lowercase().contains(query)
+        || t.tags
+            .iter()
+            .any(|tag| tag.contains(query.trim_start_matches('#')))
+        || t.contexts
+            .iter()
+            .any(|c| c.contains(query.trim_start_matches('@')))
+}
+
+fn depth<'a>(todos: &'a [Todo], mut t: &'a Todo) -> usize {
+    let mut depth = 0;
+    while let Some(p) = t.parent.and_then(|pid| position_of(todos, pid)) {
+        depth += 1;
+        t = &todos[p];
+    }
+    depth
+}
+
+fn row(todos: &[Todo], i: usize, today: chrono::NaiveDate, nested: bool) -> ListItem<'static> {
+    let t = &todos[i];
+    let indent = if nested {
+        "    ".repeat(depth(todos, t))
+    } else {
+        String::new()
+    };
+    let status = if t.completed { "✔" } else { " " };
+    let title_style = if t.completed {
+        Style::default().fg(Color::DarkGray)
+    } else {
+        Style::default()
+    };
+    let mut spans = vec![
+        Span::raw(format!("{}[{}] {} - ", indent, status, t.id)),
+        Span::styled(t.title.clone(), title_style),
+    ];
+    if let Some(p) = t.priority {
+        spans.push(Span::styled(
+            format!(" [{}]", p.as_str()),
+            Style::default().fg(Color::Yellow),
+        ));
+    }
+    if let Some(d) = t.due {
+        let text = if t.completed {
+            format!(" (due {})", d)
+        } else {
+            format!(" ({})", due::describe_due(d, today))
+        };
+        let color = if !t.completed && d < today {
+            Color::Red
+        } else {
+            Color::Cyan
+        };
+        spans.push(Span::styled(text, Style::default().fg(color)));
+    }
+    if let Some(r) = t.repeat {
+        spans.push(Span::raw(format!(" ↻ {}", r)));
+    }
+    for tag in &t.tags {
+        spans.push(Span::styled(
+            format!(" #{}", tag),
+            Style::default().fg(Color::Magenta),
+        ));
+    }
+    for context in &t.contexts {
+        spans.push(Span::styled(
+            format!(" @{}", context),
+            Style::default().fg(Color::Blue),
+        ));
+    }
+    ListItem::new(Line::from(spans))
+}
@@ -0,0 +1,66 @@
+use crate::color::paint;
+use crate::{json_output, lock};
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+use std::ffi::OsStr;
+use std::io::{self, IsTerminal, Write};
+use std::path::Path;
+use std::sync::mpsc;
+use std::time::Duration;
+
+/// Changes this close together are shown once, e.g. a save that backs the
+/// file up and then renames the new version into place.
+const SETTLE: Duration = Duration::from_millis(150);
+
+/// Whether `event` changed the file called `name`; reading it doesn't count.
+fn changes(event: &notify::Result<Event>, name: &OsStr) -> bool {
+    let Ok(event) = event else {
+        return false;
+    };
+    matches!(
+        event.kind,
+        EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)
+    ) && event.paths.iter().any(|p| p.file_name() == Some(name))
+}
+
+/// Calls `render` now and after every change to `path` until interrupted.
+/// The directory is watched rather than the file, since saves replace the
+/// file with a new one. The DB is only locked while rendering.
+pub fn run(path: &Path, render: impl Fn()) -> Result<(), String> {
+    let name = path.file_name().ok_or("Nothing to watch")?;
+    let dir = match path.parent() {
+        Some(p) if !p.as_os_str().is_empty() => p,
+        _ => Path::new("."),
+    };
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(tx).map_err(|e| e.to_string())?;
+    watcher
+        .watch(dir, RecursiveMode::NonRecursive)
+        .map_err(|e| format!("Failed to watch {}: {}", dir.display(), e))?;
+    let clear = !json_output() && io::stdout().is_terminal();
+    let mut first = true;
+    loop {
+        {
+            let _lock = lock::shared()?;
+            if clear {
+                print!("\x1b[2J\x1b[H");
+            } else if !first && !json_output() {
+                // Piped output keeps every version, a blank line apart.
+                println!();
+            }
+            render();
+        }
+        first = false;
+        if clear {
+            let footer = format!("Watching {} (Ctrl-C to stop)", path.display());
+            println!("\n{}", paint(&footer, |c| c.muted));
+        }
+        let _ = io::stdout().flush();
+        loop {
+            let event = rx.recv().map_err(|e| e.to_string())?;
+            if changes(&event, name) {
+                break;
+            }
+        }
+        while rx.recv_timeout(SETTLE).is_ok() {}
+    }
+}
@@ -0,0 +1,277 @@
+use crate::{base_db_path, crypto, now, session, stem_and_ext, storage, Todo};
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+/// The command line being run, recorded with each change it makes.
+static COMMAND: Mutex<String> = Mutex::new(String::new());
+
+/// One change to one todo, a line of `todos.audit.jsonl`.
+#[derive(Serialize, Deserialize)]
+pub struct Entry {
+    pub at: NaiveDateTime,
+    pub user: String,
+    pub list: String,
+    pub command: String,
+    pub id: u64,
+    pub change: Change,
+    /// Left out when the DB is encrypted, like the values below.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub fields: BTreeMap<String, FieldChange>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Change {
+    Added,
+    Changed,
+    Removed,
+}
+
+impl Change {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Change::Added => "added",
+            Change::Changed => "changed",
+            Change::Removed => "removed",
+        }
+    }
+}
+
+/// A field's value before and after the change.
+#[derive(Serialize, Deserialize)]
+pub struct FieldChange {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub from: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub to: Option<Value>,
+}
+
+/// The log of every list's changes, next to the default list:
+/// `todos.json` -> `todos.audit.jsonl`. Lines are only ever appended.
+pub fn log_path() -> PathBuf {
+    let base = base_db_path();
+    let (stem, _) = stem_and_ext(&base);
+    base.with_file_name(format!("{}.audit.jsonl", stem))
+}
+
+/// Remembers the command line that changes are made by, e.g. `done 3`.
+pub fn set_command(args: &[String]) {
+    *COMMAND.lock().unwrap() = args.join(" ");
+}
+
+fn user() -> String {
+    env::var("USER")
+        .or_else(|_| env::var("USERNAME"))
+        .unwrap_or_else(|_| "unknown".to_string())
+}
+
+/// `t`'s fields by their JSON names; unset ones are left out.
+pub(crate) fn fields(t: &Todo) -> BTreeMap<String, Value> {
+    match serde_json::to_value(t) {
+        Ok(Value::Object(map)) => map.into_iter().collect(),
+        _ => BTreeMap::new(),
+    }
+}
+
+/// The todos `after` adds, changes or removes compared to `before`, with
+/// the fields each change touches.
+pub fn compare<'a>(
+    before: &'a [Todo],
+    after: &'a [Todo],
+) -> Vec<(Change, &'a Todo, BTreeMap<String, FieldChange>)> {
+    let mut changes = Vec::new();
+    for t in after {
+        let Some(old) = before.iter().find(|o| o.id == t.id) else {
+            changes.push((Change::Added, t, BTreeMap::new()));
+            continue;
+        };
+        let (old_fields, new_fields) = (fields(old), fields(t));
+        let mut changed = BTreeMap::new();
+        for name in old_fields.keys().chain(new_fields.keys()) {
+            let (from, to) = (old_fields.get(name), new_fields.get(name));
+            if from != to && !changed.contains_key(name) {
+                changed.insert(
+                    name.clone(),
+                    FieldChange {
+                        from: from.cloned(),
+                        to: to.cloned(),
+                    },
+                );
+            }
+        }
+        if !changed.is_empty() {
+            changes.push((Change::Changed, t, changed));
+        }
+    }
+    for t in before
+        .iter()
+        .filter(|t| !after.iter().any(|a| a.id == t.id))
+    {
+        changes.push((Change::Removed, t, BTreeMap::new()));
+    }
+    changes
+}
+
+/// Appends an entry for every todo of `list` that `after` adds, removes or
+/// changes compared to `before`. A log that can't be written only gets a
+/// warning, so it never stops a command.
+pub fn record(list: &str, before: &[Todo], after: &[Todo]) {
+    if session::dry_run() {
+        return;
+    }
+    let private = crypto::db_salt().is_some();
+    let entries: Vec<Entry> = compare(before, after)
+        .into_iter()
+        .map(|(change, t, fields)| {
+            let mut entry = Entry {
+                at: now(),
+                user: user(),
+                list: list.to_string(),
+                command: COMMAND.lock().unwrap().clone(),
+                id: t.id,
+                change,
+                title: Some(t.title.clone()),
+                fields,
+            };
+            if private {
+                redact(&mut entry);
+            }
+            entry
+        })
+        .collect();
+    if entries.is_empty() {
+        return;
+    }
+    let mut lines = String::new();
+    for e in &entries {
+        if let Ok(line) = serde_json::to_string(e) {
+            lines.push_str(&line);
+            lines.push('\n');
+        }
+    }
+    let path = log_path();
+    let written = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .and_then(|mut file| file.write_all(lines.as_bytes()));
+    if let Err(e) = written {
+        eprintln!("Warning: failed to write {}: {}", path.display(), e);
+    }
+}
+
+/// What an encrypted DB's log keeps: ids, field names and the subcommand,
+/// not the content, which the command line can hold too.
+fn redact(entry: &mut Entry) {
+    entry.command = subcommand(&entry.command);
+    entry.title = None;
+    entry
+        .fields
+        .values_mut()
+        .for_each(|f| (f.from, f.to) = (None, None));
+}
+
+/// The subcommand of `command`, e.g. `add` for `--list work add secret`.
+fn subcommand(command: &str) -> String {
+    let mut words = command.split_whitespace();
+    while let Some(word) = words.next() {
+        match word {
+            // The global options that take a separate value.
+            "--list" | "--color" => {
+                words.next();
+            }
+            w if w.starts_with('-') => {}
+            w => return w.to_string(),
+        }
+    }
+    String::new()
+}
+
+/// Rewrites the log as an encrypted DB would have written it, for when the
+/// DB is encrypted: entries written before lose their content.
+pub fn redact_log() -> Result<(), String> {
+    let path = log_path();
+    let content = match fs::read_to_string(&path) {
+        Ok(c) => c,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(format!("Failed to read {}: {}", path.display(), e)),
+    };
+    let mut lines = String::new();
+    // A line that can't be read can't be redacted either, so it goes.
+    for mut entry in content
+        .lines()
+        .filter_map(|l| serde_json::from_str::<Entry>(l).ok())
+    {
+        redact(&mut entry);
+        if let Ok(line) = serde_json::to_string(&entry) {
+            lines.push_str(&line);
+            lines.push('\n');
+        }
+    }
+    storage::write_atomic(&path, |file| file.write_all(lines.as_bytes()))
+}
+
+/// The recorded changes to `list`, oldest first: the last `limit` of them,
+/// or all those to todo `id`.
+pub fn entries(list: &str, id: Option<u64>, limit: usize) -> Result<Vec<Entry>, String> {
+    let path = log_path();
+    let content = match fs::read_to_string(&path) {
+        Ok(c) => c,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(format!("Failed to read {}: {}", path.display(), e)),
+    };
+    let mut entries: Vec<Entry> = content
+        .lines()
+        .filter_map(|l| serde_json::from_str(l).ok())
+        .filter(|e: &Entry| e.list == list && id.is_none_or(|id| e.id == id))
+        .collect();
+    if id.is_none() && entries.len() > limit {
+        entries.drain(..entries.len() - limit);
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn redacted_entries_keep_only_the_subcommand() {
+        assert_eq!(subcommand("add secret divorce lawyer"), "add");
+        assert_eq!(subcommand("--list work --dry-run add secret"), "add");
+        assert_eq!(subcommand("--color never done 3"), "done");
+        assert_eq!(subcommand(""), "");
+
+        let mut entry = Entry {
+            at: now(),
+            user: "me".to_string(),
+            list: "todos".to_string(),
+            command: "edit 1 --title secret".to_string(),
+            id: 1,
+            change: Change::Changed,
+            title: Some("secret".to_string()),
+            fields: BTreeMap::from([(
+                "title".to_string(),
+                FieldChange {
+                    from: Some(Value::from("old")),
+                    to: Some(Value::from("secret")),
+                },
+            )]),
+        };
+        redact(&mut entry);
+        let line = serde_json::to_string(&entry).unwrap();
+        assert!(!line.contains("secret"), "{}", line);
+        assert!(!line.contains("old"), "{}", line);
+        assert_eq!(entry.command, "edit");
+        assert!(entry.fields.contains_key("title"));
+    }
+}
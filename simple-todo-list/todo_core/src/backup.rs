@@ -0,0 +1,69 @@
+use crate::{config, storage};
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// How many backups are kept per file when the config doesn't say.
+const DEFAULT_KEEP: usize = 5;
+
+fn keep() -> usize {
+    config::get().backups.unwrap_or(DEFAULT_KEEP)
+}
+
+/// `todos.json` -> `todos.json.bak.<n>`; 1 is the most recent.
+pub fn backup_path(path: &Path, n: usize) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".bak.{}", n));
+    path.with_file_name(name)
+}
+
+/// Copies `path` to `.bak.1` before it is overwritten, shifting older
+/// backups up and dropping the ones past the configured retention.
+pub fn rotate(path: &Path) -> Result<(), String> {
+    let keep = keep();
+    if keep == 0 || !path.exists() {
+        return Ok(());
+    }
+    let fail = |e: io::Error| format!("Failed to back up {}: {}", path.display(), e);
+    match fs::remove_file(backup_path(path, keep)) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(fail(e)),
+        _ => {}
+    }
+    for n in (1..keep).rev() {
+        let from = backup_path(path, n);
+        if from.exists() {
+            fs::rename(&from, backup_path(path, n + 1)).map_err(fail)?;
+        }
+    }
+    fs::copy(path, backup_path(path, 1)).map_err(fail)?;
+    Ok(())
+}
+
+/// Backups of `path` that exist, newest first.
+pub fn available(path: &Path) -> Vec<(usize, PathBuf)> {
+    (1..=keep().max(DEFAULT_KEEP))
+        .map(|n| (n, backup_path(path, n)))
+        .filter(|(_, p)| p.exists())
+        .collect()
+}
+
+/// Deletes every backup of `path`.
+pub fn remove_all(path: &Path) -> Result<(), String> {
+    for (_, file) in available(path) {
+        fs::remove_file(&file)
+            .map_err(|e| format!("Failed to remove {}: {}", file.display(), e))?;
+    }
+    Ok(())
+}
+
+/// Puts backup `n` back in place of `path`. The current file is backed up
+/// first, so a restore can itself be undone with `backup restore 1`.
+pub fn restore(path: &Path, n: usize) -> Result<(), String> {
+    let source = backup_path(path, n);
+    let data = fs::read(&source).map_err(|e| match e.kind() {
+        io::ErrorKind::NotFound => format!("No backup {} of {}", n, path.display()),
+        _ => format!("Failed to read {}: {}", source.display(), e),
+    })?;
+    rotate(path)?;
+    storage::write_atomic(path, |file: &mut File| io::Write::write_all(file, &data))
+}
@@ -0,0 +1,99 @@
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+/// Settings read from `config.toml`; every key is optional.
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Color theme for `list` output: `default`, `solarized` or `mono`.
+    pub theme: Option<String>,
+    /// Backups kept per DB file (`todos.json.bak.1` ...); 0 turns them off.
+    pub backups: Option<usize>,
+    /// Default order of `list`, e.g. `"overdue, priority desc, due"`.
+    pub sort: Option<String>,
+    /// Language of messages, `en` or `es`; otherwise taken from `LANG`.
+    pub language: Option<String>,
+    /// Whether `stats` shows the completion streak (default true).
+    pub stats_streak: Option<bool>,
+    /// What estimates count, `points` (the default) or `hours`.
+    pub estimate_unit: Option<String>,
+    /// Shorthand commands, e.g. `t = "add"` or `today = "list --due today"`.
+    pub aliases: BTreeMap<String, String>,
+    /// Where `sync` pushes and pulls.
+    pub sync: SyncConfig,
+    /// When `remind` notifies.
+    pub remind: RemindConfig,
+    /// Commands run when todos are added, done or removed.
+    pub hooks: HooksConfig,
+}
+
+/// The `[hooks]` table: shell commands per event, each given the todo as
+/// JSON on stdin.
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct HooksConfig {
+    pub add: Vec<String>,
+    pub done: Vec<String>,
+    pub remove: Vec<String>,
+}
+
+impl HooksConfig {
+    pub fn is_empty(&self) -> bool {
+        self.add.is_empty() && self.done.is_empty() && self.remove.is_empty()
+    }
+}
+
+/// The `[remind]` table.
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct RemindConfig {
+    /// Time of day a todo is due on its due date, `HH:MM` (default 09:00).
+    pub at: Option<String>,
+    /// Minutes before that to notify (default 15).
+    pub before: Option<u32>,
+}
+
+/// The `[sync]` table.
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct SyncConfig {
+    /// Git remote URL, e.g. `git@github.com:me/todos.git`.
+    pub remote: Option<String>,
+    /// Branch on the remote; defaults to the current one.
+    pub branch: Option<String>,
+}
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+/// `TODO_CONFIG`, else `$XDG_CONFIG_HOME/todo-cli/config.toml`, else
+/// `~/.config/todo-cli/config.toml`.
+pub fn path() -> Option<PathBuf> {
+    if let Ok(path) = env::var("TODO_CONFIG") {
+        return Some(PathBuf::from(path));
+    }
+    let dir = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(dir.join("todo-cli").join("config.toml"))
+}
+
+/// The config, read on first use. A missing file means the defaults; a
+/// broken one is reported once and then ignored.
+pub fn get() -> &'static Config {
+    CONFIG.get_or_init(|| {
+        let Some(path) = path() else {
+            return Config::default();
+        };
+        let Ok(content) = fs::read_to_string(&path) else {
+            return Config::default();
+        };
+        toml::from_str(&content).unwrap_or_else(|e| {
+            eprintln!("Ignoring invalid config {}: {}", path.display(), e);
+            Config::default()
+        })
+    })
+}
@@ -0,0 +1,186 @@
+use crate::base_db_path;
+use argon2::Argon2;
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use std::env;
+use std::fs::{self, File};
+use std::io::{IsTerminal, Read};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Start of every encrypted file, followed by the salt, the nonce and the
+/// ChaCha20-Poly1305 ciphertext of the usual JSON.
+const MAGIC: &[u8; 8] = b"TODOENC1";
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+const HEADER_LEN: usize = MAGIC.len() + SALT_LEN + NONCE_LEN;
+
+pub type Salt = [u8; SALT_LEN];
+pub type KeyBytes = [u8; 32];
+
+/// Key derived during this run, with the salt it belongs to.
+static KEY: Mutex<Option<(Salt, KeyBytes)>> = Mutex::new(None);
+
+pub fn is_encrypted(data: &[u8]) -> bool {
+    data.starts_with(MAGIC)
+}
+
+fn salt_of(data: &[u8]) -> Option<Salt> {
+    if data.len() < HEADER_LEN || !is_encrypted(data) {
+        return None;
+    }
+    data[MAGIC.len()..MAGIC.len() + SALT_LEN].try_into().ok()
+}
+
+/// Salt of the file at `path` if it is encrypted. Only reads the header.
+pub fn file_salt(path: &Path) -> Option<Salt> {
+    let mut header = [0u8; HEADER_LEN];
+    File::open(path).ok()?.read_exact(&mut header).ok()?;
+    salt_of(&header)
+}
+
+/// Salt new files should be encrypted with: the default list's, so every
+/// list of an encrypted DB is encrypted with the same key.
+pub fn db_salt() -> Option<Salt> {
+    file_salt(&base_db_path())
+}
+
+pub fn new_salt() -> Salt {
+    let mut salt = [0u8; SALT_LEN];
+    OsRng.fill_bytes(&mut salt);
+    salt
+}
+
+pub fn derive_key(passphrase: &str, salt: &Salt) -> Result<KeyBytes, String> {
+    let mut key = [0u8; 32];
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| format!("Failed to derive key: {}", e))?;
+    Ok(key)
+}
+
+pub fn encrypt(plaintext: &[u8], salt: &Salt, key: &KeyBytes) -> Result<Vec<u8>, String> {
+    let cipher = ChaCha20Poly1305::new(Key::from_slice(key));
+    let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let ciphertext = cipher
+        .encrypt(&nonce, plaintext)
+        .map_err(|_| "Encryption failed".to_string())?;
+    let mut out = Vec::with_capacity(HEADER_LEN + ciphertext.len());
+    out.extend_from_slice(MAGIC);
+    out.extend_from_slice(salt);
+    out.extend_from_slice(&nonce);
+    out.extend_from_slice(&ciphertext);
+    Ok(out)
+}
+
+pub fn decrypt(data: &[u8], key: &KeyBytes) -> Result<Vec<u8>, String> {
+    if data.len() < HEADER_LEN || !is_encrypted(data) {
+        return Err("Not an encrypted todo file".to_string());
+    }
+    let nonce = Nonce::from_slice(&data[MAGIC.len() + SALT_LEN..HEADER_LEN]);
+    ChaCha20Poly1305::new(Key::from_slice(key))
+        .decrypt(nonce, &data[HEADER_LEN..])
+        .map_err(|_| "Wrong passphrase or corrupted file".to_string())
+}
+
+/// The key for files encrypted with `salt`: from this run, from
+/// `todo unlock`, from `TODO_PASSPHRASE`, or by asking on the terminal.
+pub fn key_for(salt: &Salt) -> Result<KeyBytes, String> {
+    if let Some((s, key)) = *KEY.lock().unwrap() {
+        if &s == salt {
+            return Ok(key);
+        }
+    }
+    let key = match cached_key(salt) {
+        Some(key) => key,
+        None => derive_key(&passphrase("Passphrase: ")?, salt)?,
+    };
+    remember(salt, &key);
+    Ok(key)
+}
+
+/// Makes `key` the one used for `salt` for the rest of this run.
+pub fn remember(salt: &Salt, key: &KeyBytes) {
+    *KEY.lock().unwrap() = Some((*salt, *key));
+}
+
+/// `TODO_PASSPHRASE`, or a passphrase typed (without echo) on the terminal.
+pub fn passphrase(prompt: &str) -> Result<String, String> {
+    if let Ok(p) = env::var("TODO_PASSPHRASE") {
+        return Ok(p);
+    }
+    if !std::io::stdin().is_terminal() {
+        return Err("Passphrase needed: run `todo unlock` or set TODO_PASSPHRASE".to_string());
+    }
+    rpassword::prompt_password(prompt).map_err(|e| e.to_string())
+}
+
+/// A passphrase for `encrypt`, typed twice when asked for on the terminal.
+pub fn new_passphrase() -> Result<String, String> {
+    if let Ok(p) = env::var("TODO_PASSPHRASE") {
+        return Ok(p);
+    }
+    let p = passphrase("New passphrase: ")?;
+    if p.is_empty() {
+        return Err("The passphrase can't be empty".to_string());
+    }
+    if passphrase("Repeat passphrase: ")? != p {
+        return Err("Passphrases don't match".to_string());
+    }
+    Ok(p)
+}
+
+/// Where `unlock` keeps the key: a per-user runtime directory that is
+/// cleared on logout where the platform has one, else the temp directory.
+fn key_file() -> PathBuf {
+    let dir = env::var_os("XDG_RUNTIME_DIR")
+        .map(|d| PathBuf::from(d).join("todo-cli"))
+        .unwrap_or_else(|| env::temp_dir().join("todo-cli"));
+    // One key per DB, named after a hash of its path.
+    let db = fs::canonicalize(base_db_path()).unwrap_or_else(|_| base_db_path());
+    let hash = db
+        .to_string_lossy()
+        .bytes()
+        .fold(0xcbf29ce484222325u64, |h, b| {
+            (h ^ b as u64).wrapping_mul(0x100000001b3)
+        });
+    dir.join(format!("{:016x}.key", hash))
+}
+
+fn cached_key(salt: &Salt) -> Option<KeyBytes> {
+    let data = fs::read(key_file()).ok()?;
+    if data.len() != SALT_LEN + 32 || &data[..SALT_LEN] != salt {
+        return None;
+    }
+    data[SALT_LEN..].try_into().ok()
+}
+
+/// Caches the key so later commands don't ask for the passphrase.
+pub fn save_key(salt: &Salt, key: &KeyBytes) -> Result<PathBuf, String> {
+    let path = key_file();
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(&path).map_err(|e| e.to_string())?;
+    let mut data = salt.to_vec();
+    data.extend_from_slice(key);
+    std::io::Write::write_all(&mut file, &data).map_err(|e| e.to_string())?;
+    Ok(path)
+}
+
+/// Forgets the key cached by `unlock`. Returns whether there was one.
+pub fn forget_key() -> Result<bool, String> {
+    match fs::remove_file(key_file()) {
+        Ok(()) => Ok(true),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+        Err(e) => Err(e.to_string()),
+    }
+}
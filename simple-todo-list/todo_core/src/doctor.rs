@@ -0,0 +1,136 @@
+use crate::{backup, next_id, storage, Todo};
+use chrono::{DateTime, Local};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+/// A backup of the DB that can be read, to restore when it can't be.
+pub struct Readable {
+    pub n: usize,
+    pub todos: usize,
+    /// When it was saved, e.g. `2026-03-16 09:30`.
+    pub modified: String,
+}
+
+/// The newest backup of `path` that loads, if any.
+pub fn readable_backup(path: &Path) -> Option<Readable> {
+    backup::available(path).into_iter().find_map(|(n, file)| {
+        let todos = storage::open_as(&file, path).load().ok()?;
+        Some(Readable {
+            n,
+            todos: todos.len(),
+            modified: file
+                .metadata()
+                .and_then(|m| m.modified())
+                .map(|t| {
+                    DateTime::<Local>::from(t)
+                        .format("%Y-%m-%d %H:%M")
+                        .to_string()
+                })
+                .unwrap_or_default(),
+        })
+    })
+}
+
+/// Finds what is inconsistent in `todos` and fixes it in place: ids used
+/// twice get fresh ones, titles can't be empty, parents and dependencies
+/// must exist (and not loop back to the todo itself), and only completed
+/// todos have a completion time. Returns a line per problem.
+pub fn check(todos: &mut [Todo]) -> Vec<String> {
+    let mut problems = Vec::new();
+    let mut next = next_id(todos);
+    let mut seen = HashSet::new();
+    for t in todos.iter_mut() {
+        if !seen.insert(t.id) {
+            problems.push(format!(
+                "#{} ({}) has the id of another todo; it gets #{}",
+                t.id, t.title, next
+            ));
+            t.id = next;
+            next += 1;
+        }
+    }
+    let ids: HashSet<u64> = todos.iter().map(|t| t.id).collect();
+    for t in todos.iter_mut() {
+        if t.title.trim().is_empty() {
+            problems.push(format!("#{} has no title; it is now '(untitled)'", t.id));
+            t.title = "(untitled)".to_string();
+        }
+        if let Some(p) = t.parent.filter(|p| !ids.contains(p) || *p == t.id) {
+            problems.push(format!(
+                "#{}'s parent #{} doesn't exist; it is now top-level",
+                t.id, p
+            ));
+            t.parent = None;
+        }
+        let before = t.blocked_by.len();
+        t.blocked_by.retain(|b| ids.contains(b) && *b != t.id);
+        if t.blocked_by.len() < before {
+            problems.push(format!(
+                "#{} waits on {} todo(s) that don't exist; dropped",
+                t.id,
+                before - t.blocked_by.len()
+            ));
+        }
+        if !t.completed && t.completed_at.is_some() {
+            problems.push(format!(
+                "#{} is open but has a completion time; cleared",
+                t.id
+            ));
+            t.completed_at = None;
+        }
+    }
+    // A parent chain that comes back around is cut where it is found.
+    let parents: HashMap<u64, u64> = todos
+        .iter()
+        .filter_map(|t| Some((t.id, t.parent?)))
+        .collect();
+    let mut cut = HashSet::new();
+    for t in todos.iter() {
+        let mut visited = HashSet::from([t.id]);
+        let mut at = t.id;
+        while let Some(&p) = parents.get(&at) {
+            if cut.contains(&at) {
+                break;
+            }
+            if !visited.insert(p) {
+                cut.insert(at);
+                break;
+            }
+            at = p;
+        }
+    }
+    for t in todos.iter_mut().filter(|t| cut.contains(&t.id)) {
+        problems.push(format!(
+            "#{} is nested inside itself through its subtasks; it is now top-level",
+            t.id
+        ));
+        t.parent = None;
+    }
+    problems
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{child, todo};
+
+    #[test]
+    fn fixes_what_it_reports() {
+        let mut todos = vec![
+            child(1, "a", 3),
+            todo(1, "b"),
+            child(3, "c", 1),
+            child(4, " ", 42),
+        ];
+        todos[1].blocked_by = vec![9, 3];
+        let problems = check(&mut todos);
+        assert_eq!(problems.len(), 5, "{:?}", problems);
+        assert_eq!(todos[1].id, 5);
+        assert_eq!(todos[1].blocked_by, [3]);
+        assert_eq!(todos[3].title, "(untitled)");
+        assert_eq!(todos[3].parent, None);
+        // The loop 1 -> 3 -> 1 is cut once.
+        assert_eq!(todos.iter().filter(|t| t.parent.is_none()).count(), 3);
+        assert!(check(&mut todos).is_empty());
+    }
+}
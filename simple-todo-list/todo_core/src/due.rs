@@ -0,0 +1,238 @@
+use chrono::{Datelike, Days, Duration, Months, NaiveDate, NaiveDateTime, Weekday};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// Parses a due date given either as `YYYY-MM-DD` or in a few natural forms
+/// relative to `today`: `today`, `tomorrow`, `friday`, `next friday`,
+/// `in 3 days`, `in 2 weeks`, `in 1 month`.
+pub fn parse_due(input: &str, today: NaiveDate) -> Result<NaiveDate, String> {
+    let s = input.trim().to_lowercase();
+    if let Ok(date) = NaiveDate::parse_from_str(&s, "%Y-%m-%d") {
+        return Ok(date);
+    }
+    let words: Vec<&str> = s.split_whitespace().collect();
+    let date = match words.as_slice() {
+        ["today"] => Some(today),
+        ["tomorrow"] => today.succ_opt(),
+        ["yesterday"] => today.pred_opt(),
+        // A bare weekday may be today; "next" always means a later day.
+        [day] => weekday(day).map(|wd| next_weekday(today, wd, false)),
+        ["next", "week"] => Some(today + Duration::weeks(1)),
+        ["next", "month"] => today.checked_add_months(Months::new(1)),
+        ["next", day] => weekday(day).map(|wd| next_weekday(today, wd, true)),
+        ["in", n, unit] => {
+            let n: u32 = n.parse().map_err(|_| bad_date(input))?;
+            match unit.trim_end_matches('s') {
+                "day" => Some(today + Duration::days(n.into())),
+                "week" => Some(today + Duration::weeks(n.into())),
+                "month" => today.checked_add_months(Months::new(n)),
+                _ => None,
+            }
+        }
+        _ => None,
+    };
+    date.ok_or_else(|| bad_date(input))
+}
+
+fn bad_date(input: &str) -> String {
+    format!(
+        "Invalid due date '{}': use YYYY-MM-DD, today, tomorrow, <weekday>, next <weekday>, or in N days|weeks|months",
+        input
+    )
+}
+
+fn weekday(s: &str) -> Option<Weekday> {
+    let wd = match s {
+        "monday" | "mon" => Weekday::Mon,
+        "tuesday" | "tue" => Weekday::Tue,
+        "wednesday" | "wed" => Weekday::Wed,
+        "thursday" | "thu" => Weekday::Thu,
+        "friday" | "fri" => Weekday::Fri,
+        "saturday" | "sat" => Weekday::Sat,
+        "sunday" | "sun" => Weekday::Sun,
+        _ => return None,
+    };
+    Some(wd)
+}
+
+fn next_weekday(today: NaiveDate, wd: Weekday, strictly_after: bool) -> NaiveDate {
+    let mut ahead = (wd.num_days_from_monday() + 7 - today.weekday().num_days_from_monday()) % 7;
+    if ahead == 0 && strictly_after {
+        ahead = 7;
+    }
+    today + Duration::days(ahead.into())
+}
+
+/// Parses a length of time such as `30m`, `2h`, `1d` or `90 minutes`.
+pub fn parse_duration(input: &str) -> Result<Duration, String> {
+    let s = input.trim().to_lowercase();
+    let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (n, unit) = s.split_at(split);
+    let bad = || format!("Invalid duration '{}': use e.g. 30m, 2h or 1d", input);
+    let n: i64 = n.parse().map_err(|_| bad())?;
+    match unit.trim().trim_end_matches('s') {
+        "m" | "min" | "minute" => Ok(Duration::minutes(n)),
+        "h" | "hour" => Ok(Duration::hours(n)),
+        "d" | "day" => Ok(Duration::days(n)),
+        _ => Err(bad()),
+    }
+}
+
+/// Human-readable deadline relative to `today`, e.g. "due in 3 days" or
+/// "overdue 2d".
+pub fn describe_due(due: NaiveDate, today: NaiveDate) -> String {
+    let days = (due - today).num_days();
+    match days {
+        0 => "due today".to_string(),
+        1 => "due tomorrow".to_string(),
+        d if d > 1 => format!("due in {} days", d),
+        d => format!("overdue {}d", -d),
+    }
+}
+
+/// How long ago `since` was, roughly: "new", "5h", "3d", "2w" or "4mo".
+pub fn describe_age(since: NaiveDateTime, now: NaiveDateTime) -> String {
+    let age = now - since;
+    match age.num_hours() {
+        h if h < 1 => "new".to_string(),
+        h if h < 24 => format!("{}h", h),
+        _ if age.num_days() < 14 => format!("{}d", age.num_days()),
+        _ if age.num_days() < 60 => format!("{}w", age.num_weeks()),
+        _ => format!("{}mo", age.num_days() / 30),
+    }
+}
+
+/// How often a recurring todo comes back after being completed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Repeat {
+    Days(u32),
+    Weeks(u32),
+    Months(u32),
+}
+
+impl Repeat {
+    /// Accepts `daily`, `weekly`, `monthly`, or `every [N] day(s)|week(s)|month(s)`.
+    pub fn parse(input: &str) -> Result<Repeat, String> {
+        let s = input.trim().to_lowercase();
+        let words: Vec<&str> = s.split_whitespace().collect();
+        let (n, unit) = match words.as_slice() {
+            ["daily"] => (1, "day"),
+            ["weekly"] => (1, "week"),
+            ["monthly"] => (1, "month"),
+            ["every", unit] => (1, *unit),
+            ["every", n, unit] => match n.parse::<u32>() {
+                Ok(n) if n > 0 => (n, *unit),
+                _ => return Err(bad_repeat(input)),
+            },
+            _ => return Err(bad_repeat(input)),
+        };
+        match unit.trim_end_matches('s') {
+            "day" => Ok(Repeat::Days(n)),
+            "week" => Ok(Repeat::Weeks(n)),
+            "month" => Ok(Repeat::Months(n)),
+            _ => Err(bad_repeat(input)),
+        }
+    }
+
+    /// Stops at the last date chrono knows rather than overflowing, so a
+    /// huge N can't panic.
+    fn advance(self, date: NaiveDate) -> NaiveDate {
+        let next = match self {
+            Repeat::Days(n) => date.checked_add_days(Days::new(n.into())),
+            Repeat::Weeks(n) => date.checked_add_days(Days::new(u64::from(n) * 7)),
+            Repeat::Months(n) => date.checked_add_months(Months::new(n)),
+        };
+        next.unwrap_or(NaiveDate::MAX)
+    }
+
+    /// The first date after `today` on this schedule, counting from `due`
+    /// (or from today when there is no due date), so a late completion
+    /// keeps the original rhythm instead of drifting.
+    pub fn next_due(self, due: Option<NaiveDate>, today: NaiveDate) -> NaiveDate {
+        let mut next = self.advance(due.unwrap_or(today));
+        while next <= today {
+            next = self.advance(next);
+        }
+        next
+    }
+}
+
+fn bad_repeat(input: &str) -> String {
+    format!(
+        "Invalid repeat '{}': use daily, weekly, monthly or every N days|weeks|months",
+        input
+    )
+}
+
+impl std::fmt::Display for Repeat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            Repeat::Days(1) => write!(f, "daily"),
+            Repeat::Weeks(1) => write!(f, "weekly"),
+            Repeat::Months(1) => write!(f, "monthly"),
+            Repeat::Days(n) => write!(f, "every {} days", n),
+            Repeat::Weeks(n) => write!(f, "every {} weeks", n),
+            Repeat::Months(n) => write!(f, "every {} months", n),
+        }
+    }
+}
+
+impl Serialize for Repeat {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Repeat {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        Repeat::parse(&s).map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::date;
+
+    #[test]
+    fn natural_due_dates() {
+        // A Monday.
+        let today = date("2026-03-16");
+        let parse = |s| parse_due(s, today).unwrap();
+        assert_eq!(parse("2026-04-01"), date("2026-04-01"));
+        assert_eq!(parse("Tomorrow"), date("2026-03-17"));
+        assert_eq!(parse("monday"), today);
+        assert_eq!(parse("next monday"), date("2026-03-23"));
+        assert_eq!(parse("fri"), date("2026-03-20"));
+        assert_eq!(parse("in 2 weeks"), date("2026-03-30"));
+        assert_eq!(parse("in 1 month"), date("2026-04-16"));
+        assert!(parse_due("someday", today).is_err());
+        assert!(parse_due("in x days", today).is_err());
+    }
+
+    #[test]
+    fn repeats_keep_their_rhythm() {
+        let today = date("2026-03-16");
+        assert_eq!(Repeat::parse("weekly").unwrap(), Repeat::Weeks(1));
+        assert_eq!(Repeat::parse("every 3 days").unwrap(), Repeat::Days(3));
+        assert!(Repeat::parse("every 0 days").is_err());
+        let weekly = Repeat::Weeks(1);
+        // Completed late: the next date stays on the same weekday.
+        assert_eq!(
+            weekly.next_due(Some(date("2026-03-02")), today),
+            date("2026-03-23")
+        );
+        assert_eq!(weekly.next_due(None, today), date("2026-03-23"));
+        assert_eq!(Repeat::parse(&weekly.to_string()).unwrap(), weekly);
+        let huge = Repeat::parse("every 4294967295 weeks").unwrap();
+        assert_eq!(huge.next_due(Some(today), today), NaiveDate::MAX);
+        assert_eq!(Repeat::Days(u32::MAX).next_due(None, today), NaiveDate::MAX);
+    }
+
+    #[test]
+    fn durations() {
+        assert_eq!(parse_duration("90m").unwrap(), Duration::minutes(90));
+        assert_eq!(parse_duration("2h").unwrap(), Duration::hours(2));
+        assert!(parse_duration("soon").is_err());
+    }
+}
@@ -0,0 +1,128 @@
+use crate::audit::{self, Change};
+use crate::{config, session, Todo};
+use std::env;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// What happened to a todo, for the hooks of `[hooks]` in the config.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Event {
+    Add,
+    Done,
+    Remove,
+}
+
+impl Event {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Event::Add => "add",
+            Event::Done => "done",
+            Event::Remove => "remove",
+        }
+    }
+
+    fn commands(self) -> &'static [String] {
+        let hooks = &config::get().hooks;
+        match self {
+            Event::Add => &hooks.add,
+            Event::Done => &hooks.done,
+            Event::Remove => &hooks.remove,
+        }
+    }
+}
+
+/// The events in going from `before` to `after`, with the todo each is
+/// about: todos that appear were added, ones that disappear (archived
+/// ones too) removed, and open ones marked as completed done.
+pub fn events<'a>(before: &'a [Todo], after: &'a [Todo]) -> Vec<(Event, &'a Todo)> {
+    audit::compare(before, after)
+        .into_iter()
+        .filter_map(|(change, t, fields)| match change {
+            Change::Added => Some((Event::Add, t)),
+            Change::Removed => Some((Event::Remove, t)),
+            Change::Changed if t.completed && fields.contains_key("completed") => {
+                Some((Event::Done, t))
+            }
+            Change::Changed => None,
+        })
+        .collect()
+}
+
+/// Runs the configured hooks for what changed in `list`, one at a time,
+/// once per todo. A hook failing is reported but doesn't undo the change,
+/// which is already saved. Changes made by the commands of a hook don't run
+/// hooks again, so a hook can't set itself off.
+pub fn run(list: &str, before: &[Todo], after: &[Todo]) {
+    if config::get().hooks.is_empty() || env::var_os("TODO_HOOK").is_some() || session::dry_run() {
+        return;
+    }
+    for (event, t) in events(before, after) {
+        for command in event.commands() {
+            if let Err(e) = run_one(command, event, list, t) {
+                eprintln!(
+                    "Warning: {} hook '{}' failed: {}",
+                    event.as_str(),
+                    command,
+                    e
+                );
+            }
+        }
+    }
+}
+
+/// Runs `command` with the shell, the todo as JSON on its stdin and the
+/// event and list in `TODO_HOOK` and `TODO_LIST`. What it prints goes to
+/// stderr, so it doesn't mix with `--json` output.
+fn run_one(command: &str, event: Event, list: &str, t: &Todo) -> Result<(), String> {
+    let json = serde_json::to_vec(t).map_err(|e| e.to_string())?;
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .env("TODO_HOOK", event.as_str())
+        .env("TODO_LIST", list)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .map_err(|e| e.to_string())?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // A hook that doesn't read its input closes the pipe early.
+        let _ = stdin.write_all(&json);
+    }
+    let output = child.wait_with_output().map_err(|e| e.to_string())?;
+    eprint!("{}", String::from_utf8_lossy(&output.stdout));
+    match output.status.code() {
+        Some(0) => Ok(()),
+        Some(code) => Err(format!("exited with status {}", code)),
+        None => Err("killed by a signal".to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::todo;
+
+    #[test]
+    fn events_follow_the_changes() {
+        let before = vec![todo(1, "a"), todo(2, "b"), todo(3, "c")];
+        let mut after = before.clone();
+        after[0].completed = true;
+        after[1].title = "renamed".to_string();
+        after.remove(2);
+        after.push(todo(4, "d"));
+        let ids = |before, after| -> Vec<(Event, u64)> {
+            let mut found: Vec<_> = events(before, after)
+                .into_iter()
+                .map(|(e, t)| (e, t.id))
+                .collect();
+            found.sort_by_key(|(_, id)| *id);
+            found
+        };
+        assert_eq!(
+            ids(&before, &after),
+            [(Event::Done, 1), (Event::Remove, 3), (Event::Add, 4)]
+        );
+        // Reopening isn't an event.
+        assert_eq!(ids(&after, &before), [(Event::Add, 3), (Event::Remove, 4)]);
+    }
+}
@@ -0,0 +1,1087 @@
+use chrono::{Datelike, Local, NaiveDate, NaiveDateTime, SubsecRound};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+pub mod audit;
+pub mod backup;
+pub mod config;
+pub mod crypto;
+pub mod doctor;
+pub mod due;
+pub mod hooks;
+pub mod lock;
+pub mod merge;
+pub mod query;
+pub mod session;
+pub mod sort;
+pub mod storage;
+pub mod todotxt;
+pub mod track;
+
+use due::Repeat;
+use track::TimeEntry;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Todo {
+    /// Internal identity used for links between todos (e.g. subtasks);
+    /// files written before it existed get ids assigned on load.
+    #[serde(default)]
+    pub id: u64,
+    pub title: String,
+    pub description: String,
+    pub completed: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub due: Option<NaiveDate>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority: Option<Priority>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repeat: Option<Repeat>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// Where or with what it can be done, GTD style: `@home`, `@phone`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub contexts: Vec<String>,
+    /// `remind` stays quiet about this todo until then.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub snoozed: Option<NaiveDateTime>,
+    /// When it was added; unknown for todos from older files and imports.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<NaiveDateTime>,
+    /// When it was last marked as done.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub completed_at: Option<NaiveDateTime>,
+    /// Time recorded with `start`/`stop`, oldest first.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub time: Vec<TimeEntry>,
+    /// Focus periods finished with `pomodoro`.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub pomodoros: u32,
+    /// In the board's "In progress" column (`edit --status in-progress`).
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub in_progress: bool,
+    /// Always at the top of `list` (`pin`).
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub pinned: bool,
+    /// Expected effort in points or hours, as the `estimate_unit` setting
+    /// says; `burndown` charts what is left of it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub estimate: Option<f64>,
+    /// Ids of todos that have to be done first (`block`).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub blocked_by: Vec<u64>,
+    /// Added with `note`, oldest first.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub notes: Vec<Note>,
+    /// Fields of your own set with `--meta key=value`, e.g. a ticket number.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub meta: BTreeMap<String, String>,
+}
+
+fn is_zero(n: &u32) -> bool {
+    *n == 0
+}
+
+fn is_false(b: &bool) -> bool {
+    !*b
+}
+
+/// Tags are stored lowercase without the leading `#`.
+pub fn normalize_tag(tag: &str) -> Result<String, String> {
+    let tag = tag.trim().trim_start_matches('#').to_lowercase();
+    if tag.is_empty() || tag.contains(char::is_whitespace) {
+        return Err(format!("Invalid tag '{}'", tag));
+    }
+    Ok(tag)
+}
+
+/// Contexts are stored lowercase without the leading `@`.
+pub fn normalize_context(context: &str) -> Result<String, String> {
+    let context = context.trim().trim_start_matches('@').to_lowercase();
+    if context.is_empty() || context.contains(char::is_whitespace) {
+        return Err(format!("Invalid context '@{}'", context));
+    }
+    Ok(context)
+}
+
+/// Metadata keys are lowercase, start with a letter and hold only letters,
+/// digits, `-` and `_`, so they read the same in queries and todo.txt.
+pub fn normalize_meta_key(key: &str) -> Result<String, String> {
+    let key = key.trim().to_lowercase();
+    let valid = key.starts_with(|c: char| c.is_ascii_alphabetic())
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !valid {
+        return Err(format!(
+            "Invalid metadata key '{}': use letters, digits, '-' or '_'",
+            key
+        ));
+    }
+    Ok(key)
+}
+
+/// Reads `key=value` as given to `--meta`. The value may be empty.
+pub fn parse_meta(s: &str) -> Result<(String, String), String> {
+    let (key, value) = s
+        .split_once('=')
+        .ok_or_else(|| format!("Invalid metadata '{}': use key=value", s))?;
+    Ok((normalize_meta_key(key)?, value.trim().to_string()))
+}
+
+/// Reads an estimate: a positive number of points or hours, e.g. `3` or
+/// `1.5`.
+pub fn parse_estimate(s: &str) -> Result<f64, String> {
+    match s.trim().parse::<f64>() {
+        Ok(n) if n > 0.0 && n.is_finite() => Ok(n),
+        _ => Err(format!(
+            "Invalid estimate '{}': use a positive number, e.g. 3 or 1.5",
+            s
+        )),
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum Priority {
+    Low,
+    Medium,
+    High,
+}
+
+impl Priority {
+    pub fn parse(s: &str) -> Result<Priority, String> {
+        match s.to_lowercase().as_str() {
+            "low" | "l" => Ok(Priority::Low),
+            "medium" | "med" | "m" => Ok(Priority::Medium),
+            "high" | "h" => Ok(Priority::High),
+            _ => Err(format!("Invalid priority '{}': use low, medium or high", s)),
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Priority::Low => "low",
+            Priority::Medium => "medium",
+            Priority::High => "high",
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub enum Filter {
+    All,
+    Pending,
+    Done,
+    /// Open and not blocked.
+    Ready,
+}
+
+impl Filter {
+    pub fn matches(self, todos: &[Todo], t: &Todo) -> bool {
+        match self {
+            Filter::All => true,
+            Filter::Pending => !t.completed,
+            Filter::Done => t.completed,
+            Filter::Ready => !t.completed && open_blockers(todos, t).is_empty(),
+        }
+    }
+}
+
+/// A note added with `note`, kept with the time it was written.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Note {
+    pub at: NaiveDateTime,
+    pub text: String,
+}
+
+pub const DEFAULT_LIST: &str = "default";
+
+/// Set from the global `--list <name>` flag before dispatch.
+static LIST_OVERRIDE: Mutex<Option<String>> = Mutex::new(None);
+
+/// Makes `name` the list commands act on, above everything
+/// `current_list` looks at, until it is set back. Returns the one before.
+pub fn override_list(name: Option<String>) -> Option<String> {
+    std::mem::replace(&mut *LIST_OVERRIDE.lock().unwrap(), name)
+}
+
+/// File of the default list; named lists live next to it.
+pub fn base_db_path() -> PathBuf {
+    if let Ok(path) = env::var("TODO_DB") {
+        PathBuf::from(path)
+    } else {
+        PathBuf::from("todos.json")
+    }
+}
+
+pub fn db_path() -> PathBuf {
+    list_path(&current_list())
+}
+
+/// Stem and extension of a DB file, e.g. `("todos", "json")`.
+pub fn stem_and_ext(path: &Path) -> (String, String) {
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "todos".to_string());
+    let ext = path
+        .extension()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "json".to_string());
+    (stem, ext)
+}
+
+/// `todos.json` for the default list, `todos-<name>.json` for the others
+/// (keeping whatever extension, and so storage backend, `TODO_DB` uses).
+pub fn list_path(name: &str) -> PathBuf {
+    list_file(&base_db_path(), name)
+}
+
+pub fn list_file(base: &Path, name: &str) -> PathBuf {
+    if name == DEFAULT_LIST {
+        return base.to_path_buf();
+    }
+    let (stem, ext) = stem_and_ext(base);
+    base.with_file_name(format!("{}-{}.{}", stem, name, ext))
+}
+
+/// Where `use <list>` remembers the selected list.
+pub fn current_list_file() -> PathBuf {
+    base_db_path().with_extension("current")
+}
+
+/// The list commands act on: `--list`, then `TODO_LIST`, then the one
+/// picked with `use`, then the default list. A name that isn't valid is
+/// skipped with a warning, so it can't point at a file outside the DB's
+/// directory.
+pub fn current_list() -> String {
+    let candidates = [
+        (LIST_OVERRIDE.lock().unwrap().clone(), "--list".to_string()),
+        (env::var("TODO_LIST").ok(), "TODO_LIST".to_string()),
+        (
+            fs::read_to_string(current_list_file()).ok(),
+            current_list_file().display().to_string(),
+        ),
+    ];
+    let (name, skipped) = first_valid_list(candidates);
+    if !skipped.is_empty() && !WARNED_LIST.swap(true, Ordering::Relaxed) {
+        for warning in skipped {
+            eprintln!("Warning: {}", warning);
+        }
+    }
+    name
+}
+
+/// Set once an invalid list name has been reported, since `current_list`
+/// runs many times per command.
+static WARNED_LIST: AtomicBool = AtomicBool::new(false);
+
+/// The first non-empty, valid name among `(name, where it came from)`, or
+/// the default list, with a line for each invalid one passed over.
+fn first_valid_list(
+    candidates: impl IntoIterator<Item = (Option<String>, String)>,
+) -> (String, Vec<String>) {
+    let mut skipped = Vec::new();
+    for (name, source) in candidates {
+        let Some(name) = name.map(|n| n.trim().to_string()).filter(|n| !n.is_empty()) else {
+            continue;
+        };
+        match validate_list_name(&name) {
+            Ok(()) => return (name, skipped),
+            Err(e) => skipped.push(format!("{} (from {}); ignoring it", e, source)),
+        }
+    }
+    (DEFAULT_LIST.to_string(), skipped)
+}
+
+pub fn validate_list_name(name: &str) -> Result<(), String> {
+    if !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        Ok(())
+    } else {
+        Err(format!(
+            "Invalid list name '{}': use letters, digits, '-' or '_'",
+            name
+        ))
+    }
+}
+
+/// Names of all lists that have a file, default first.
+pub fn all_lists() -> Vec<String> {
+    // Lists created in an interactive session may not be written yet.
+    let unwritten = session::cached_paths()
+        .iter()
+        .filter_map(|p| p.file_name()?.to_str().map(|f| f.to_string()))
+        .collect();
+    lists_at(&base_db_path(), unwritten)
+}
+
+/// Names of the lists whose files are next to `base` or among `more` file
+/// names, default first.
+pub fn lists_at(base: &Path, more: Vec<String>) -> Vec<String> {
+    let (stem, ext) = stem_and_ext(base);
+    let prefix = format!("{}-", stem);
+    let suffix = format!(".{}", ext);
+    let archive_suffix = format!(".archive.{}", ext);
+    let dir = match base.parent() {
+        Some(p) if !p.as_os_str().is_empty() => p.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+    let mut files: Vec<String> = fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .filter_map(|e| e.ok())
+                .filter_map(|e| e.file_name().into_string().ok())
+                .collect()
+        })
+        .unwrap_or_default();
+    files.extend(more);
+    let mut names: Vec<String> = files
+        .iter()
+        .filter(|f| !f.ends_with(&archive_suffix))
+        .filter_map(|f| {
+            f.strip_prefix(&prefix)
+                .and_then(|rest| rest.strip_suffix(&suffix))
+                .map(|n| n.to_string())
+        })
+        .filter(|n| validate_list_name(n).is_ok())
+        .collect();
+    names.sort();
+    names.dedup();
+    names.insert(0, DEFAULT_LIST.to_string());
+    names
+}
+
+/// The single archive file older versions kept next to a list:
+/// `todos.json` -> `todos.archive.json`. It is still read, never written.
+pub fn archive_file(path: &Path) -> PathBuf {
+    let (stem, ext) = stem_and_ext(path);
+    path.with_file_name(format!("{}.archive.{}", stem, ext))
+}
+
+/// Completed todos moved out by `archive` go to a file per month they were
+/// completed in, in a directory next to the list: `todos.json` ->
+/// `todos.archive/2025-06.json`.
+pub fn archive_dir(path: &Path) -> PathBuf {
+    let (stem, _) = stem_and_ext(path);
+    path.with_file_name(format!("{}.archive", stem))
+}
+
+pub fn archive_month_file(path: &Path, month: NaiveDate) -> PathBuf {
+    let (_, ext) = stem_and_ext(path);
+    archive_dir(path).join(format!("{}.{}", month.format("%Y-%m"), ext))
+}
+
+/// The month `archive` files `t` under: the one it was completed in, or
+/// this one when that isn't known.
+pub fn archive_month(t: &Todo) -> NaiveDate {
+    let day = t.completed_at.map_or_else(today, |c| c.date());
+    day.with_day(1).unwrap_or(day)
+}
+
+/// First days of the months the list at `path` has archive files for,
+/// oldest first.
+pub fn archive_months(path: &Path) -> Vec<NaiveDate> {
+    let (_, ext) = stem_and_ext(path);
+    let Ok(entries) = fs::read_dir(archive_dir(path)) else {
+        return Vec::new();
+    };
+    let mut months: Vec<NaiveDate> = entries
+        .filter_map(|e| e.ok()?.file_name().into_string().ok())
+        .filter_map(|f| {
+            let month = f.strip_suffix(&format!(".{}", ext))?;
+            NaiveDate::parse_from_str(&format!("{}-01", month), "%Y-%m-%d").ok()
+        })
+        .collect();
+    months.sort();
+    months
+}
+
+/// Every archive file of the list at `path` that exists: the old single
+/// file, then the monthly ones.
+pub fn archive_files(path: &Path) -> Vec<PathBuf> {
+    let old = Some(archive_file(path)).filter(|f| f.exists());
+    old.into_iter()
+        .chain(
+            archive_months(path)
+                .into_iter()
+                .map(|m| archive_month_file(path, m)),
+        )
+        .collect()
+}
+
+/// Reads the todos stored at `path`, giving ids to those without one. A
+/// file that can't be read is an error rather than an empty list, so
+/// nothing saves over it.
+pub fn load(path: &Path) -> Result<Vec<Todo>, String> {
+    if let Some(todos) = session::cached(path) {
+        return Ok(todos);
+    }
+    let mut todos = storage::open(path).load()?;
+    raise_last_id(stored_last_id(path));
+    assign_missing_ids(&mut todos);
+    session::remember(path, &todos, false);
+    Ok(todos)
+}
+
+pub fn assign_missing_ids(todos: &mut [Todo]) {
+    let fresh = next_id(todos)..;
+    for (id, t) in fresh.zip(todos.iter_mut().filter(|t| t.id == 0)) {
+        t.id = id;
+    }
+}
+
+/// The id for a new todo in `todos`: past the largest there, and past
+/// every id the lists read or saved in this run ever used, so a removed or
+/// archived todo's id is never handed out again. Ids may skip numbers when
+/// several lists were read; they are never reused.
+pub fn next_id(todos: &[Todo]) -> u64 {
+    let largest = todos.iter().map(|t| t.id).max().unwrap_or(0);
+    largest.max(*LAST_ID.lock().unwrap()) + 1
+}
+
+/// The highest id in the lists this run has read or saved, with those
+/// that were removed since.
+static LAST_ID: Mutex<u64> = Mutex::new(0);
+
+fn raise_last_id(id: u64) {
+    let mut last = LAST_ID.lock().unwrap();
+    *last = (*last).max(id);
+}
+
+/// Where the highest id a list has ever used is kept: `todos.json` ->
+/// `todos.lastid`.
+pub fn last_id_file(path: &Path) -> PathBuf {
+    path.with_extension("lastid")
+}
+
+/// The highest id the list at `path` has ever used. Lists saved before
+/// that was kept fall back to the largest id in their archives.
+pub fn stored_last_id(path: &Path) -> u64 {
+    if let Some(id) = fs::read_to_string(last_id_file(path))
+        .ok()
+        .and_then(|s| s.trim().parse().ok())
+    {
+        return id;
+    }
+    archive_files(path)
+        .iter()
+        .filter_map(|f| storage::open(f).load().ok())
+        .flatten()
+        .map(|t| t.id)
+        .max()
+        .unwrap_or(0)
+}
+
+/// Writes `todos` to the list file at `path`, keeping the previous version
+/// as a backup.
+pub fn write_list(path: &Path, todos: &[Todo]) -> Result<(), String> {
+    backup::rotate(path)?;
+    storage::open(path).save(todos)?;
+    keep_last_id(path, todos)
+}
+
+/// Raises the highest id kept for the list at `path` to the largest in
+/// `todos`.
+fn keep_last_id(path: &Path, todos: &[Todo]) -> Result<(), String> {
+    let largest = todos.iter().map(|t| t.id).max().unwrap_or(0);
+    let last = stored_last_id(path);
+    if largest > last {
+        fs::write(last_id_file(path), format!("{}\n", largest))
+            .map_err(|e| format!("Failed to write {}: {}", last_id_file(path).display(), e))?;
+    }
+    Ok(())
+}
+
+pub fn position_of(todos: &[Todo], id: u64) -> Option<usize> {
+    todos.iter().position(|t| t.id == id)
+}
+
+/// Ids of `id` and everything nested below it.
+pub fn with_descendants(todos: &[Todo], id: u64) -> Vec<u64> {
+    let mut ids = vec![id];
+    let mut i = 0;
+    while i < ids.len() {
+        let cur = ids[i];
+        ids.extend(todos.iter().filter(|t| t.parent == Some(cur)).map(|t| t.id));
+        i += 1;
+    }
+    ids
+}
+
+/// Ids of the todos `t` waits for that are still open. Blockers that were
+/// removed or archived don't count.
+pub fn open_blockers(todos: &[Todo], t: &Todo) -> Vec<u64> {
+    t.blocked_by
+        .iter()
+        .copied()
+        .filter(|&b| position_of(todos, b).is_some_and(|i| !todos[i].completed))
+        .collect()
+}
+
+/// Whether `id` waits for `other`, directly or through other blockers.
+pub fn depends_on(todos: &[Todo], id: u64, other: u64) -> bool {
+    let mut seen = vec![id];
+    let mut i = 0;
+    while i < seen.len() {
+        let Some(p) = position_of(todos, seen[i]) else {
+            i += 1;
+            continue;
+        };
+        for &b in &todos[p].blocked_by {
+            if b == other {
+                return true;
+            }
+            if !seen.contains(&b) {
+                seen.push(b);
+            }
+        }
+        i += 1;
+    }
+    false
+}
+
+/// Moves `todos[idx]` to `position` (from 1) among its siblings, or after
+/// the last of them when past it. Subtasks stay under their parent wherever
+/// they are stored. Returns the position it ends up at.
+pub fn move_to(todos: &mut Vec<Todo>, idx: usize, position: usize) -> usize {
+    let parent = todos[idx].parent;
+    let t = todos.remove(idx);
+    let others: Vec<usize> = (0..todos.len())
+        .filter(|&i| todos[i].parent == parent)
+        .collect();
+    let position = position.clamp(1, others.len() + 1);
+    let at = match others.get(position - 1) {
+        Some(&i) => i,
+        None => others.last().map_or(idx, |&i| i + 1),
+    };
+    todos.insert(at, t);
+    position
+}
+
+/// After a subtask is completed, completes its ancestors whose subtasks are
+/// now all done. Returns the indices that changed.
+pub fn complete_finished_parents(todos: &mut [Todo], idx: usize) -> Vec<usize> {
+    let mut changed = Vec::new();
+    let mut cur = todos[idx].parent;
+    while let Some(pid) = cur {
+        let Some(p) = position_of(todos, pid) else {
+            break;
+        };
+        let all_done = todos
+            .iter()
+            .filter(|t| t.parent == Some(pid))
+            .all(|t| t.completed);
+        if !all_done || todos[p].completed {
+            break;
+        }
+        todos[p].completed = true;
+        todos[p].completed_at = Some(now());
+        changed.push(p);
+        cur = todos[p].parent;
+    }
+    changed
+}
+
+/// What `complete` did besides marking the todo itself as done.
+pub struct Completion {
+    /// Index of the next occurrence scheduled for a recurring todo.
+    pub next: Option<usize>,
+    /// Subtasks of the todo that are still open.
+    pub pending: usize,
+    /// Indices of ancestors completed because all their subtasks are done.
+    pub parents_done: Vec<usize>,
+}
+
+/// A todo that can't be completed while the todos it waits for are open.
+#[derive(Debug)]
+pub struct Blocked {
+    pub id: u64,
+    pub by: Vec<u64>,
+}
+
+impl Blocked {
+    /// The open blockers, as `#1, #2`.
+    pub fn ids(&self) -> String {
+        let ids: Vec<String> = self.by.iter().map(|b| format!("#{}", b)).collect();
+        ids.join(", ")
+    }
+}
+
+impl std::fmt::Display for Blocked {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "#{} is blocked by {}; finish those first.",
+            self.id,
+            self.ids()
+        )
+    }
+}
+
+/// Completes `todos[idx]`, unless some todo it waits for is still open.
+pub fn complete(todos: &mut Vec<Todo>, idx: usize) -> Result<Completion, Blocked> {
+    let by = open_blockers(todos, &todos[idx]);
+    if !by.is_empty() {
+        return Err(Blocked {
+            id: todos[idx].id,
+            by,
+        });
+    }
+    Ok(force_complete(todos, idx))
+}
+
+/// Completes `todos[idx]` whether or not it is blocked, for `done --force`.
+pub fn force_complete(todos: &mut Vec<Todo>, idx: usize) -> Completion {
+    todos[idx].completed = true;
+    todos[idx].completed_at = Some(now());
+    todos[idx].in_progress = false;
+    track::stop_timer(todos, idx);
+    // A recurring todo is completed as usual and a fresh copy is
+    // scheduled for its next date; only the copy keeps repeating.
+    let next = todos[idx].repeat.take().map(|r| {
+        let mut next = todos[idx].clone();
+        next.id = next_id(todos);
+        next.completed = false;
+        next.repeat = Some(r);
+        next.due = Some(r.next_due(todos[idx].due, today()));
+        next.snoozed = None;
+        next.created_at = Some(now());
+        next.completed_at = None;
+        next.time = Vec::new();
+        next.pomodoros = 0;
+        todos.push(next);
+        todos.len() - 1
+    });
+    let id = todos[idx].id;
+    let pending = todos
+        .iter()
+        .filter(|t| t.parent == Some(id) && !t.completed)
+        .count();
+    let parents_done = complete_finished_parents(todos, idx);
+    Completion {
+        next,
+        pending,
+        parents_done,
+    }
+}
+
+/// Removes the todo `id` with everything nested below it, and drops them
+/// from what the remaining todos wait for. Returns the removed todos.
+pub fn remove_todo(todos: &mut Vec<Todo>, id: u64) -> Vec<Todo> {
+    let ids = with_descendants(todos, id);
+    let (gone, kept): (Vec<Todo>, Vec<Todo>) = std::mem::take(todos)
+        .into_iter()
+        .partition(|t| ids.contains(&t.id));
+    *todos = kept;
+    for t in todos.iter_mut() {
+        t.blocked_by.retain(|b| !ids.contains(b));
+    }
+    gone
+}
+
+/// Copies `todos[idx]`, and with `with_subtasks` everything nested below
+/// it, to the end of `todos` with fresh ids. Parents and dependencies
+/// within the copied todos point at the copies; `due` moves the copy's due
+/// date and its subtasks' by as much. Progress isn't copied: the copies
+/// start as if just added. Returns how many were added.
+pub fn duplicate(
+    todos: &mut Vec<Todo>,
+    idx: usize,
+    with_subtasks: bool,
+    due: Option<NaiveDate>,
+) -> usize {
+    let ids = if with_subtasks {
+        with_descendants(todos, todos[idx].id)
+    } else {
+        vec![todos[idx].id]
+    };
+    let first = next_id(todos);
+    let new_id = |id: u64| ids.iter().position(|&i| i == id).map(|n| first + n as u64);
+    let shift = due.zip(todos[idx].due).map(|(new, old)| new - old);
+    let mut copies = Vec::new();
+    for (n, &id) in ids.iter().enumerate() {
+        let Some(original) = position_of(todos, id).map(|i| &todos[i]) else {
+            continue;
+        };
+        let mut t = original.clone();
+        t.id = first + n as u64;
+        if n == 0 {
+            t.due = due.or(original.due);
+        } else {
+            t.parent = original.parent.and_then(new_id);
+            t.due = original.due.map(|d| shift.map_or(d, |s| d + s));
+        }
+        t.blocked_by = original
+            .blocked_by
+            .iter()
+            .map(|&b| new_id(b).unwrap_or(b))
+            .collect();
+        t.completed = false;
+        t.completed_at = None;
+        t.created_at = Some(now());
+        t.snoozed = None;
+        t.time = Vec::new();
+        t.pomodoros = 0;
+        t.in_progress = false;
+        t.notes = Vec::new();
+        copies.push(t);
+    }
+    let added = copies.len();
+    todos.extend(copies);
+    added
+}
+
+/// Ids of the completed todos `archive` moves out. A completed parent
+/// stays while any of its subtasks is still open, so the open ones don't
+/// lose their place in the tree.
+pub fn archivable(todos: &[Todo]) -> Vec<u64> {
+    todos
+        .iter()
+        .filter(|t| t.completed)
+        .filter(|t| {
+            with_descendants(todos, t.id)
+                .iter()
+                .all(|id| position_of(todos, *id).is_some_and(|i| todos[i].completed))
+        })
+        .map(|t| t.id)
+        .collect()
+}
+
+/// Marks a todo as not done, reopening its ancestors too since a parent
+/// can't stay done with an open subtask. Returns the reopened indices.
+pub fn reopen(todos: &mut [Todo], idx: usize) -> Vec<usize> {
+    todos[idx].completed = false;
+    todos[idx].completed_at = None;
+    let mut reopened = Vec::new();
+    let mut cur = todos[idx].parent;
+    while let Some(p) = cur.and_then(|pid| position_of(todos, pid)) {
+        if !todos[p].completed {
+            break;
+        }
+        todos[p].completed = false;
+        todos[p].completed_at = None;
+        reopened.push(p);
+        cur = todos[p].parent;
+    }
+    reopened
+}
+
+pub fn save_db(todos: &[Todo]) -> Result<(), String> {
+    save_list(&current_list(), todos)
+}
+
+/// Saves `todos` as list `name`, recording the change in its history and
+/// running the hooks, like `save_db` does for the current list.
+pub fn save_list(name: &str, todos: &[Todo]) -> Result<(), String> {
+    let path = list_path(name);
+    let before = load(&path)?;
+    save_to(&path, todos)?;
+    audit::record(name, &before, todos);
+    hooks::run(name, &before, todos);
+    Ok(())
+}
+
+pub fn save_to(path: &Path, todos: &[Todo]) -> Result<(), String> {
+    raise_last_id(todos.iter().map(|t| t.id).max().unwrap_or(0));
+    if session::remember(path, todos, true) {
+        return Ok(());
+    }
+    write_list(path, todos)
+}
+
+/// Saves `todos` whose last entry is new; JSON Lines files just append it.
+pub fn append_db(todos: &[Todo]) -> Result<(), String> {
+    let path = db_path();
+    let before = load(&path)?;
+    if !session::remember(&path, todos, true) {
+        backup::rotate(&path)?;
+        storage::open(&path).append(todos)?;
+        keep_last_id(&path, todos)?;
+    }
+    raise_last_id(todos.iter().map(|t| t.id).max().unwrap_or(0));
+    audit::record(&current_list(), &before, todos);
+    hooks::run(&current_list(), &before, todos);
+    Ok(())
+}
+
+pub fn today() -> NaiveDate {
+    Local::now().date_naive()
+}
+
+/// The local time, to the second, for timestamps stored in the DB.
+pub fn now() -> NaiveDateTime {
+    Local::now().naive_local().trunc_subsecs(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An open todo with nothing but an id and a title.
+    pub fn todo(id: u64, title: &str) -> Todo {
+        Todo {
+            id,
+            title: title.to_string(),
+            description: String::new(),
+            completed: false,
+            due: None,
+            priority: None,
+            parent: None,
+            repeat: None,
+            tags: Vec::new(),
+            contexts: Vec::new(),
+            snoozed: None,
+            created_at: None,
+            completed_at: None,
+            time: Vec::new(),
+            pomodoros: 0,
+            in_progress: false,
+            pinned: false,
+            estimate: None,
+            blocked_by: Vec::new(),
+            notes: Vec::new(),
+            meta: BTreeMap::new(),
+        }
+    }
+
+    pub fn child(id: u64, title: &str, parent: u64) -> Todo {
+        Todo {
+            parent: Some(parent),
+            ..todo(id, title)
+        }
+    }
+
+    pub fn date(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+    }
+
+    fn ids(todos: &[Todo]) -> Vec<u64> {
+        todos.iter().map(|t| t.id).collect()
+    }
+
+    #[test]
+    fn tags_and_contexts_are_normalized() {
+        assert_eq!(normalize_tag(" #Work ").unwrap(), "work");
+        assert_eq!(normalize_context("@Home").unwrap(), "home");
+        assert!(normalize_tag("two words").is_err());
+        assert!(normalize_context("@").is_err());
+    }
+
+    #[test]
+    fn missing_ids_follow_the_largest() {
+        let mut todos = vec![todo(0, "a"), todo(4, "b"), todo(0, "c")];
+        assign_missing_ids(&mut todos);
+        assert_eq!(ids(&todos), [5, 4, 6]);
+        assert_eq!(next_id(&todos), 7);
+        assert_eq!(next_id(&[]), 1);
+    }
+
+    #[test]
+    fn invalid_list_names_are_skipped() {
+        let from = |name: &str, source: &str| (Some(name.to_string()), source.to_string());
+        let (name, skipped) = first_valid_list([
+            (None, "--list".to_string()),
+            from("../../etc/x", "TODO_LIST"),
+            from(" work\n", "todos.current"),
+        ]);
+        assert_eq!(name, "work");
+        assert_eq!(skipped.len(), 1);
+        assert!(skipped[0].contains("TODO_LIST"), "{}", skipped[0]);
+        let (name, skipped) = first_valid_list([from("  ", "TODO_LIST"), from("a/b", "x")]);
+        assert_eq!(name, DEFAULT_LIST);
+        assert_eq!(skipped.len(), 1);
+    }
+
+    #[test]
+    fn the_last_id_only_grows() {
+        let dir = env::temp_dir().join(format!("todo_core-lastid-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("todos.json");
+        storage::open(&archive_file(&path))
+            .save(&[todo(12, "archived")])
+            .unwrap();
+        assert_eq!(stored_last_id(&path), 12);
+        keep_last_id(&path, &[todo(1, "a"), todo(9, "b")]).unwrap();
+        assert_eq!(stored_last_id(&path), 12);
+        keep_last_id(&path, &[todo(15, "c")]).unwrap();
+        keep_last_id(&path, &[todo(1, "a")]).unwrap();
+        assert_eq!(stored_last_id(&path), 15);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn descendants_include_every_level() {
+        let todos = vec![
+            todo(1, "trip"),
+            child(2, "pack", 1),
+            child(3, "socks", 2),
+            todo(4, "other"),
+        ];
+        assert_eq!(with_descendants(&todos, 1), [1, 2, 3]);
+        assert_eq!(with_descendants(&todos, 4), [4]);
+    }
+
+    #[test]
+    fn blockers_are_open_and_followed_through() {
+        let mut todos = vec![todo(1, "a"), todo(2, "b"), todo(3, "c")];
+        todos[1].blocked_by = vec![1];
+        todos[2].blocked_by = vec![2];
+        assert_eq!(open_blockers(&todos, &todos[1]), [1]);
+        assert!(depends_on(&todos, 3, 1));
+        assert!(!depends_on(&todos, 1, 3));
+        assert!(!Filter::Ready.matches(&todos, &todos[1]));
+        todos[0].completed = true;
+        assert!(open_blockers(&todos, &todos[1]).is_empty());
+        assert!(Filter::Ready.matches(&todos, &todos[1]));
+        assert!(Filter::Done.matches(&todos, &todos[0]));
+        assert!(!Filter::Pending.matches(&todos, &todos[0]));
+    }
+
+    #[test]
+    fn blocked_todos_are_not_completed() {
+        let mut todos = vec![todo(1, "a"), todo(2, "b"), todo(3, "c")];
+        todos[2].blocked_by = vec![1, 2];
+        let blocked = complete(&mut todos, 2).err().unwrap();
+        assert_eq!(
+            blocked.to_string(),
+            "#3 is blocked by #1, #2; finish those first."
+        );
+        assert!(!todos[2].completed);
+        force_complete(&mut todos, 2);
+        assert!(todos[2].completed);
+    }
+
+    #[test]
+    fn removed_todos_take_their_subtasks_and_stop_blocking() {
+        let mut todos = vec![todo(1, "trip"), child(2, "pack", 1), todo(3, "drive")];
+        todos[2].blocked_by = vec![2, 9];
+        let gone = remove_todo(&mut todos, 1);
+        assert_eq!(ids(&gone), [1, 2]);
+        assert_eq!(ids(&todos), [3]);
+        assert_eq!(todos[0].blocked_by, [9]);
+    }
+
+    #[test]
+    fn completing_the_last_subtask_completes_the_parent() {
+        let mut todos = vec![todo(1, "trip"), child(2, "pack", 1), child(3, "book", 1)];
+        let first = complete(&mut todos, 1).unwrap();
+        assert!(first.parents_done.is_empty());
+        assert!(!todos[0].completed);
+        let second = complete(&mut todos, 2).unwrap();
+        assert_eq!(second.parents_done, [0]);
+        assert!(todos[0].completed && todos[0].completed_at.is_some());
+
+        let reopened = reopen(&mut todos, 1);
+        assert_eq!(reopened, [0]);
+        assert!(!todos[0].completed && !todos[1].completed);
+        assert!(todos[2].completed);
+    }
+
+    #[test]
+    fn completing_a_recurring_todo_schedules_the_next() {
+        let mut todos = vec![Todo {
+            repeat: Some(Repeat::Days(1)),
+            due: Some(today()),
+            ..todo(1, "water plants")
+        }];
+        let done = complete(&mut todos, 0).unwrap();
+        let next = &todos[done.next.unwrap()];
+        assert_eq!(next.id, 2);
+        assert!(!next.completed);
+        assert_eq!(next.repeat, Some(Repeat::Days(1)));
+        assert!(next.due > Some(today()));
+        assert!(todos[0].completed && todos[0].repeat.is_none());
+    }
+
+    #[test]
+    fn moving_stays_among_siblings() {
+        let mut todos = vec![
+            todo(1, "a"),
+            child(2, "a1", 1),
+            todo(3, "b"),
+            child(4, "a2", 1),
+            todo(5, "c"),
+        ];
+        assert_eq!(move_to(&mut todos, 4, 1), 1);
+        assert_eq!(ids(&todos), [5, 1, 2, 3, 4]);
+        assert_eq!(move_to(&mut todos, 4, 1), 1);
+        assert_eq!(ids(&todos), [5, 1, 4, 2, 3]);
+        assert_eq!(move_to(&mut todos, 0, 99), 3);
+        assert_eq!(ids(&todos), [1, 4, 2, 3, 5]);
+    }
+
+    #[test]
+    fn duplicating_remaps_subtasks_and_shifts_due_dates() {
+        let mut todos = vec![
+            Todo {
+                due: Some(date("2026-03-10")),
+                completed: true,
+                ..todo(1, "trip")
+            },
+            Todo {
+                due: Some(date("2026-03-08")),
+                blocked_by: vec![3],
+                ..child(2, "pack", 1)
+            },
+            child(3, "book", 1),
+        ];
+        let added = duplicate(&mut todos, 0, true, Some(date("2026-04-10")));
+        assert_eq!(added, 3);
+        let copies = &todos[3..];
+        assert_eq!(ids(copies), [4, 5, 6]);
+        assert!(!copies[0].completed);
+        assert_eq!(copies[0].due, Some(date("2026-04-10")));
+        assert_eq!(copies[1].parent, Some(4));
+        assert_eq!(copies[1].due, Some(date("2026-04-08")));
+        assert_eq!(copies[1].blocked_by, [6]);
+
+        assert_eq!(duplicate(&mut todos, 1, false, None), 1);
+        assert_eq!(todos[6].parent, Some(1));
+        assert_eq!(todos[6].blocked_by, [3]);
+    }
+
+    #[test]
+    fn parents_with_open_subtasks_are_not_archived() {
+        let mut todos = vec![todo(1, "a"), child(2, "a1", 1), todo(3, "b")];
+        todos[0].completed = true;
+        todos[2].completed = true;
+        assert_eq!(archivable(&todos), [3]);
+        todos[1].completed = true;
+        assert_eq!(archivable(&todos), [1, 2, 3]);
+    }
+
+    #[test]
+    fn archived_todos_go_by_completion_month() {
+        let mut t = todo(1, "done");
+        t.completed_at = date("2025-06-30").and_hms_opt(23, 59, 0);
+        assert_eq!(archive_month(&t), date("2025-06-01"));
+    }
+
+    #[test]
+    fn list_files_sit_next_to_the_default() {
+        let base = Path::new("/data/todos.db");
+        assert_eq!(list_file(base, DEFAULT_LIST), base);
+        assert_eq!(list_file(base, "work"), Path::new("/data/todos-work.db"));
+        assert_eq!(
+            archive_file(Path::new("/data/todos-work.db")),
+            Path::new("/data/todos-work.archive.db")
+        );
+        assert_eq!(
+            archive_month_file(Path::new("/data/todos-work.db"), date("2025-06-01")),
+            Path::new("/data/todos-work.archive/2025-06.db")
+        );
+        assert!(validate_list_name("home_2").is_ok());
+        assert!(validate_list_name("../x").is_err());
+    }
+}
@@ -0,0 +1,114 @@
+use crate::base_db_path;
+use std::env;
+use std::fmt;
+use std::fs::{File, OpenOptions, TryLockError};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How long a command waits for another one to release the database.
+const WAIT: Duration = Duration::from_secs(5);
+
+/// Set while this process holds the lock, so commands run from `tui` or
+/// `interactive` don't wait on their own session.
+static HELD: AtomicBool = AtomicBool::new(false);
+
+/// An advisory lock on `todos.lock` next to the DB, covering every list and
+/// archive. Released when dropped.
+pub struct DbLock {
+    file: Option<File>,
+}
+
+impl Drop for DbLock {
+    fn drop(&mut self) {
+        if let Some(file) = self.file.take() {
+            let _ = file.unlock();
+            HELD.store(false, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Why the lock couldn't be taken.
+#[derive(Debug)]
+pub enum LockError {
+    /// Another command held it for longer than we wait.
+    Busy(String),
+    /// The lock file couldn't be opened or locked.
+    Failed(String),
+}
+
+impl fmt::Display for LockError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LockError::Busy(m) | LockError::Failed(m) => f.write_str(m),
+        }
+    }
+}
+
+impl From<LockError> for String {
+    fn from(e: LockError) -> String {
+        e.to_string()
+    }
+}
+
+fn lock_path() -> PathBuf {
+    base_db_path().with_extension("lock")
+}
+
+/// For commands that change todos; waits for readers and other writers.
+pub fn exclusive() -> Result<DbLock, LockError> {
+    acquire(true)
+}
+
+/// For commands that only read; other readers may run at the same time.
+pub fn shared() -> Result<DbLock, LockError> {
+    acquire(false)
+}
+
+fn acquire(exclusive: bool) -> Result<DbLock, LockError> {
+    // Hooks run while the command that triggered them holds the lock, so
+    // the todo commands they run share it.
+    if HELD.load(Ordering::Relaxed) || env::var_os("TODO_HOOK").is_some() {
+        return Ok(DbLock { file: None });
+    }
+    let path = lock_path();
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent).map_err(|e| LockError::Failed(e.to_string()))?;
+    }
+    let file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&path)
+        .map_err(|e| LockError::Failed(format!("Failed to open {}: {}", path.display(), e)))?;
+    let started = Instant::now();
+    loop {
+        let attempt = if exclusive {
+            file.try_lock()
+        } else {
+            file.try_lock_shared()
+        };
+        match attempt {
+            Ok(()) => break,
+            Err(TryLockError::WouldBlock) if started.elapsed() < WAIT => {
+                thread::sleep(Duration::from_millis(50));
+            }
+            Err(TryLockError::WouldBlock) => {
+                return Err(LockError::Busy(format!(
+                    "Database is locked by another todo command ({}); try again later.",
+                    path.display()
+                )));
+            }
+            Err(TryLockError::Error(e)) => {
+                return Err(LockError::Failed(format!(
+                    "Failed to lock {}: {}",
+                    path.display(),
+                    e
+                )));
+            }
+        }
+    }
+    HELD.store(true, Ordering::Relaxed);
+    Ok(DbLock { file: Some(file) })
+}
@@ -0,0 +1,316 @@
+use crate::{audit, next_id, position_of, Todo};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{BTreeSet, HashMap};
+
+/// A todo both files have, changed differently.
+pub struct Conflict {
+    /// Its id here.
+    pub id: u64,
+    /// Its id in the other file.
+    pub theirs: u64,
+    pub title: String,
+    pub fields: Vec<String>,
+}
+
+/// What `merge` did, by id in the merged list.
+#[derive(Default)]
+pub struct Outcome {
+    pub added: Vec<u64>,
+    /// Todos the other file has exactly as they are here.
+    pub skipped: Vec<u64>,
+    pub conflicts: Vec<Conflict>,
+}
+
+/// Whether `a` and `b` are the same todo: the same title, and the same id
+/// or creation time. Ids alone don't tell, since two files number their
+/// todos independently.
+fn same(a: &Todo, b: &Todo) -> bool {
+    a.title.to_lowercase() == b.title.to_lowercase()
+        && (a.id == b.id || (a.created_at.is_some() && a.created_at == b.created_at))
+}
+
+/// Adds the todos of `incoming` that `todos` doesn't have, with fresh ids
+/// and their parent and dependency links pointing at the merged todos.
+/// Todos both have are kept as they are here, or replaced by the other
+/// file's version when `theirs` is set, and reported if they differ.
+pub fn merge(todos: &mut Vec<Todo>, incoming: Vec<Todo>, theirs: bool) -> Outcome {
+    let mut ids: HashMap<u64, u64> = HashMap::new();
+    let mut matched = Vec::new();
+    let mut next = next_id(todos);
+    for t in &incoming {
+        let found = todos
+            .iter()
+            .find(|l| l.id == t.id && same(l, t))
+            .or_else(|| todos.iter().find(|l| same(l, t)));
+        let id = match found {
+            Some(l) => l.id,
+            None => {
+                next += 1;
+                next - 1
+            }
+        };
+        if t.id != 0 {
+            ids.insert(t.id, id);
+        }
+        matched.push((id, found.is_some()));
+    }
+    let mut outcome = Outcome::default();
+    for (mut t, (id, found)) in incoming.into_iter().zip(matched) {
+        let theirs_id = t.id;
+        t.id = id;
+        t.parent = t.parent.and_then(|p| ids.get(&p).copied());
+        t.blocked_by = t
+            .blocked_by
+            .iter()
+            .filter_map(|b| ids.get(b).copied())
+            .collect();
+        if !found {
+            todos.push(t);
+            outcome.added.push(id);
+            continue;
+        }
+        let Some(idx) = position_of(todos, id) else {
+            continue;
+        };
+        let changes = audit::compare(&todos[idx..=idx], std::slice::from_ref(&t));
+        let Some((_, _, fields)) = changes.into_iter().next() else {
+            outcome.skipped.push(id);
+            continue;
+        };
+        outcome.conflicts.push(Conflict {
+            id,
+            theirs: theirs_id,
+            title: todos[idx].title.clone(),
+            fields: fields.into_keys().collect(),
+        });
+        if theirs {
+            todos[idx] = t;
+        }
+    }
+    outcome
+}
+
+/// A field two copies of a todo changed differently since their common
+/// version. Values are as in the JSON file, `Null` when unset.
+#[derive(Serialize, Clone, Debug)]
+pub struct FieldConflict {
+    pub id: u64,
+    pub field: String,
+    pub ours: Value,
+    pub theirs: Value,
+}
+
+/// Merges two copies of a todo field by field: a field changed on one side
+/// since `base` takes that side's value. A field the two changed
+/// differently keeps `ours` and is returned as a conflict; without a
+/// `base` every field they disagree on is one.
+pub fn merge3(base: Option<&Todo>, ours: &Todo, theirs: &Todo) -> (Todo, Vec<FieldConflict>) {
+    let base = base.map(audit::fields);
+    let (mut merged, theirs_fields) = (audit::fields(ours), audit::fields(theirs));
+    let mut conflicts = Vec::new();
+    let names: BTreeSet<String> = merged.keys().chain(theirs_fields.keys()).cloned().collect();
+    for name in names {
+        let (o, t) = (merged.get(&name), theirs_fields.get(&name));
+        let b = base.as_ref().map(|base| base.get(&name));
+        if o == t || b == Some(t) {
+            continue;
+        }
+        if b == Some(o) {
+            match t {
+                Some(value) => merged.insert(name, value.clone()),
+                None => merged.remove(&name),
+            };
+        } else {
+            conflicts.push(FieldConflict {
+                id: ours.id,
+                ours: o.cloned().unwrap_or(Value::Null),
+                theirs: t.cloned().unwrap_or(Value::Null),
+                field: name,
+            });
+        }
+    }
+    let merged = serde_json::from_value(Value::Object(merged.into_iter().collect()))
+        .unwrap_or_else(|_| ours.clone());
+    (merged, conflicts)
+}
+
+fn unchanged(a: &Todo, b: &Todo) -> bool {
+    audit::fields(a) == audit::fields(b)
+}
+
+/// Merges two versions of a list that both started from `base`, in our
+/// order with the todos only they have at the end. Todos are matched by
+/// id and merged with `merge3`. One deleted on one side stays deleted
+/// unless the other side changed it; two different todos added with the
+/// same id on both sides are both kept, theirs with a fresh id.
+pub fn merge_lists(
+    base: &[Todo],
+    ours: &[Todo],
+    theirs: &[Todo],
+) -> (Vec<Todo>, Vec<FieldConflict>) {
+    let find = |list: &'_ [Todo], id: u64| list.iter().find(|t| t.id == id).cloned();
+    let mut merged = Vec::new();
+    let mut conflicts = Vec::new();
+    let mut clashes = Vec::new();
+    for o in ours {
+        match (find(base, o.id), find(theirs, o.id)) {
+            (Some(b), Some(t)) => {
+                let (t, mut found) = merge3(Some(&b), o, &t);
+                merged.push(t);
+                conflicts.append(&mut found);
+            }
+            (Some(b), None) if unchanged(&b, o) => {}
+            (None, Some(t)) if !unchanged(&t, o) => {
+                merged.push(o.clone());
+                clashes.push(t);
+            }
+            _ => merged.push(o.clone()),
+        }
+    }
+    for t in theirs.iter().filter(|t| !ours.iter().any(|o| o.id == t.id)) {
+        match find(base, t.id) {
+            Some(b) if unchanged(&b, t) => {}
+            _ => merged.push(t.clone()),
+        }
+    }
+    // Their todos whose ids we used for others move to fresh ids, and
+    // their links follow.
+    let mut moved: HashMap<u64, u64> = HashMap::new();
+    for t in &clashes {
+        moved.insert(t.id, next_id(&merged) + moved.len() as u64);
+    }
+    let first = merged.len();
+    merged.extend(clashes);
+    let theirs_only: Vec<u64> = theirs
+        .iter()
+        .filter(|t| !ours.iter().any(|o| o.id == t.id))
+        .map(|t| t.id)
+        .collect();
+    for (n, t) in merged.iter_mut().enumerate() {
+        let from_theirs = n >= first || theirs_only.contains(&t.id);
+        if n >= first {
+            t.id = moved[&t.id];
+        }
+        if from_theirs {
+            t.parent = t.parent.map(|p| moved.get(&p).copied().unwrap_or(p));
+            for b in t.blocked_by.iter_mut() {
+                *b = moved.get(b).copied().unwrap_or(*b);
+            }
+        }
+    }
+    (merged, conflicts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{child, date, todo};
+    use crate::Priority;
+
+    #[test]
+    fn adds_new_todos_and_reports_differences() {
+        let mut ours = vec![todo(1, "Buy milk"), todo(2, "Trip"), todo(3, "Local")];
+        let created = crate::now();
+        let theirs = vec![
+            Todo {
+                priority: Some(Priority::High),
+                ..todo(1, "buy milk")
+            },
+            todo(2, "Trip"),
+            // Same id as one of ours, but another todo.
+            Todo {
+                created_at: Some(created),
+                ..todo(3, "Remote")
+            },
+            Todo {
+                created_at: Some(created),
+                ..child(4, "Pack", 3)
+            },
+        ];
+        let outcome = merge(&mut ours, theirs.clone(), false);
+        assert_eq!(outcome.skipped, [2]);
+        assert_eq!(outcome.added, [4, 5]);
+        assert_eq!(outcome.conflicts.len(), 1);
+        assert_eq!(outcome.conflicts[0].id, 1);
+        assert_eq!(outcome.conflicts[0].fields, ["priority", "title"]);
+        assert_eq!(ours[0].priority, None);
+        assert_eq!(ours[4].parent, Some(4));
+
+        // Merged again, the added todos are found by title and creation time.
+        let outcome = merge(&mut ours, theirs, true);
+        assert!(outcome.added.is_empty());
+        assert_eq!(outcome.skipped, [2, 4, 5]);
+        assert_eq!(ours[0].priority, Some(Priority::High));
+    }
+
+    #[test]
+    fn three_way_merge_takes_each_sides_changes() {
+        let base = todo(1, "Pay rent");
+        let ours = Todo {
+            priority: Some(Priority::High),
+            due: Some(date("2026-05-01")),
+            ..base.clone()
+        };
+        let theirs = Todo {
+            tags: vec!["home".to_string()],
+            due: Some(date("2026-05-03")),
+            ..base.clone()
+        };
+        let (merged, conflicts) = merge3(Some(&base), &ours, &theirs);
+        assert_eq!(merged.priority, Some(Priority::High));
+        assert_eq!(merged.tags, ["home"]);
+        assert_eq!(merged.due, ours.due);
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].field, "due");
+        assert_eq!(conflicts[0].theirs, "2026-05-03");
+
+        // Without the common version nothing tells who changed what.
+        let (merged, conflicts) = merge3(None, &ours, &theirs);
+        assert_eq!(merged.tags, Vec::<String>::new());
+        let fields: Vec<&str> = conflicts.iter().map(|c| c.field.as_str()).collect();
+        assert_eq!(fields, ["due", "priority", "tags"]);
+    }
+
+    #[test]
+    fn merging_lists_keeps_additions_and_deletions_of_both() {
+        let base = vec![
+            todo(1, "Keep"),
+            todo(2, "Deleted here"),
+            todo(3, "Deleted there"),
+        ];
+        let ours = vec![
+            Todo {
+                completed: true,
+                ..todo(1, "Keep")
+            },
+            todo(3, "Deleted there, edited here"),
+            todo(4, "Added here"),
+        ];
+        let theirs = vec![
+            Todo {
+                priority: Some(Priority::Low),
+                ..todo(1, "Keep")
+            },
+            todo(2, "Deleted here"),
+            todo(4, "Added there"),
+            child(5, "Under the one added there", 4),
+        ];
+        let (merged, conflicts) = merge_lists(&base, &ours, &theirs);
+        assert!(conflicts.is_empty());
+        let titles: Vec<(u64, &str)> = merged.iter().map(|t| (t.id, t.title.as_str())).collect();
+        assert_eq!(
+            titles,
+            [
+                (1, "Keep"),
+                (3, "Deleted there, edited here"),
+                (4, "Added here"),
+                (5, "Under the one added there"),
+                (6, "Added there"),
+            ]
+        );
+        assert!(merged[0].completed);
+        assert_eq!(merged[0].priority, Some(Priority::Low));
+        assert_eq!(merged[3].parent, Some(6));
+    }
+}
@@ -0,0 +1,512 @@
+use crate::due::parse_due;
+use crate::{normalize_meta_key, today, Priority, Todo};
+use chrono::NaiveDate;
+use std::cmp::Ordering;
+
+/// A parsed `list --where` expression, e.g.
+/// `priority >= high AND tag = work AND due < "next week"`.
+///
+/// Fields are compared with values (`=`, `!=`, `<`, `<=`, `>`, `>=`, and `~`
+/// for "contains") and comparisons combined with `AND`, `OR`, `NOT` and
+/// parentheses. Values are words or quoted strings; words in a row make one
+/// value, so `due < next week` works without quotes. `meta.<key>` is the
+/// value a todo was given with `--meta key=value`.
+#[derive(Clone, Debug)]
+pub enum Query {
+    And(Box<Query>, Box<Query>),
+    Or(Box<Query>, Box<Query>),
+    Not(Box<Query>),
+    Compare(Field, Op, Value),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Field {
+    Id,
+    Title,
+    Description,
+    Status,
+    Priority,
+    Due,
+    Created,
+    Completed,
+    Tag,
+    Context,
+    Parent,
+    /// `meta.<key>`, a field set with `--meta key=value`.
+    Meta(String),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Contains,
+}
+
+/// A value already parsed for the field it is compared with; `None` is
+/// written `none` and matches a missing field.
+#[derive(Clone, Debug)]
+pub enum Value {
+    None,
+    Text(String),
+    Number(u64),
+    Date(NaiveDate),
+    Priority(Priority),
+    Done(bool),
+}
+
+const FIELDS: &str = "id, title, description, status, priority, due, created, completed, tag, \
+                      context, parent or meta.<key>";
+
+impl Field {
+    fn parse(s: &str) -> Result<Field, String> {
+        if let Some(key) = s.to_lowercase().strip_prefix("meta.") {
+            return normalize_meta_key(key).map(Field::Meta);
+        }
+        Ok(match s.to_lowercase().as_str() {
+            "id" => Field::Id,
+            "title" => Field::Title,
+            "description" => Field::Description,
+            "status" => Field::Status,
+            "priority" => Field::Priority,
+            "due" => Field::Due,
+            "created" => Field::Created,
+            "completed" => Field::Completed,
+            "tag" | "tags" => Field::Tag,
+            "context" | "contexts" => Field::Context,
+            "parent" => Field::Parent,
+            _ => return Err(format!("Unknown field '{}': use {}", s, FIELDS)),
+        })
+    }
+
+    fn name(&self) -> String {
+        let name = match self {
+            Field::Id => "id",
+            Field::Title => "title",
+            Field::Description => "description",
+            Field::Status => "status",
+            Field::Priority => "priority",
+            Field::Due => "due",
+            Field::Created => "created",
+            Field::Completed => "completed",
+            Field::Tag => "tag",
+            Field::Context => "context",
+            Field::Parent => "parent",
+            Field::Meta(key) => return format!("meta.{}", key),
+        };
+        name.to_string()
+    }
+
+    /// Reads `s` as a value of this field.
+    fn value(&self, s: &str) -> Result<Value, String> {
+        let text = matches!(self, Field::Title | Field::Description);
+        if s.eq_ignore_ascii_case("none") && !text {
+            return Ok(Value::None);
+        }
+        match self {
+            Field::Title | Field::Description | Field::Meta(_) => Ok(Value::Text(s.to_lowercase())),
+            Field::Tag => Ok(Value::Text(s.trim_start_matches('#').to_lowercase())),
+            Field::Context => Ok(Value::Text(s.trim_start_matches('@').to_lowercase())),
+            Field::Id | Field::Parent => s
+                .trim_start_matches('#')
+                .parse()
+                .map(Value::Number)
+                .map_err(|_| format!("Invalid {} '{}': expected a number", self.name(), s)),
+            Field::Status => match s.to_lowercase().as_str() {
+                "open" | "pending" => Ok(Value::Done(false)),
+                "done" | "completed" => Ok(Value::Done(true)),
+                _ => Err(format!("Invalid status '{}': use open or done", s)),
+            },
+            Field::Priority => Priority::parse(s).map(Value::Priority),
+            Field::Due | Field::Created | Field::Completed => {
+                parse_due(s, today()).map(Value::Date)
+            }
+        }
+    }
+
+    /// Operators that make sense for this field.
+    fn allows(&self, op: Op) -> bool {
+        match self {
+            Field::Title | Field::Description | Field::Tag | Field::Context => {
+                matches!(op, Op::Eq | Op::Ne | Op::Contains)
+            }
+            Field::Status => matches!(op, Op::Eq | Op::Ne),
+            Field::Meta(_) => true,
+            _ => op != Op::Contains,
+        }
+    }
+}
+
+impl Op {
+    fn symbol(self) -> &'static str {
+        match self {
+            Op::Eq => "=",
+            Op::Ne => "!=",
+            Op::Lt => "<",
+            Op::Le => "<=",
+            Op::Gt => ">",
+            Op::Ge => ">=",
+            Op::Contains => "~",
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Token {
+    Word(String),
+    Quoted(String),
+    Op(Op),
+    Open,
+    Close,
+}
+
+impl Token {
+    fn describe(&self) -> String {
+        match self {
+            Token::Word(w) => format!("'{}'", w),
+            Token::Quoted(s) => format!("\"{}\"", s),
+            Token::Op(op) => format!("'{}'", op.symbol()),
+            Token::Open => "'('".to_string(),
+            Token::Close => "')'".to_string(),
+        }
+    }
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        match c {
+            _ if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' | ')' => {
+                chars.next();
+                tokens.push(if c == '(' { Token::Open } else { Token::Close });
+            }
+            '"' | '\'' => {
+                chars.next();
+                let mut text = String::new();
+                loop {
+                    match chars.next() {
+                        Some(q) if q == c => break,
+                        Some(ch) => text.push(ch),
+                        None => return Err(format!("Missing closing {} in the query", c)),
+                    }
+                }
+                tokens.push(Token::Quoted(text));
+            }
+            '=' | '!' | '<' | '>' | '~' => {
+                chars.next();
+                let eq = chars.next_if_eq(&'=').is_some();
+                let op = match (c, eq) {
+                    ('=', _) => Op::Eq,
+                    ('!', true) => Op::Ne,
+                    ('<', false) => Op::Lt,
+                    ('<', true) => Op::Le,
+                    ('>', false) => Op::Gt,
+                    ('>', true) => Op::Ge,
+                    ('~', false) => Op::Contains,
+                    _ => return Err(format!("Unknown operator near '{}' in the query", c)),
+                };
+                tokens.push(Token::Op(op));
+            }
+            _ => {
+                let mut word = String::new();
+                while let Some(&ch) = chars
+                    .peek()
+                    .filter(|ch| !ch.is_whitespace() && !"()\"'=!<>~".contains(**ch))
+                {
+                    word.push(ch);
+                    chars.next();
+                }
+                tokens.push(Token::Word(word));
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+fn is_keyword(token: Option<&Token>, keyword: &str) -> bool {
+    matches!(token, Some(Token::Word(w)) if w.eq_ignore_ascii_case(keyword))
+}
+
+/// Recursive descent over the tokens: `OR` binds loosest, then `AND`, then
+/// `NOT`.
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<&Token> {
+        self.pos += 1;
+        self.tokens.get(self.pos - 1)
+    }
+
+    fn or(&mut self) -> Result<Query, String> {
+        let mut left = self.and()?;
+        while is_keyword(self.peek(), "or") {
+            self.pos += 1;
+            left = Query::Or(Box::new(left), Box::new(self.and()?));
+        }
+        Ok(left)
+    }
+
+    fn and(&mut self) -> Result<Query, String> {
+        let mut left = self.not()?;
+        while is_keyword(self.peek(), "and") {
+            self.pos += 1;
+            left = Query::And(Box::new(left), Box::new(self.not()?));
+        }
+        Ok(left)
+    }
+
+    fn not(&mut self) -> Result<Query, String> {
+        if is_keyword(self.peek(), "not") {
+            self.pos += 1;
+            return Ok(Query::Not(Box::new(self.not()?)));
+        }
+        if self.peek() == Some(&Token::Open) {
+            self.pos += 1;
+            let inner = self.or()?;
+            if self.next() != Some(&Token::Close) {
+                return Err("Missing ')' in the query".to_string());
+            }
+            return Ok(inner);
+        }
+        self.comparison()
+    }
+
+    fn comparison(&mut self) -> Result<Query, String> {
+        let field = match self.next() {
+            Some(Token::Word(w)) => Field::parse(w)?,
+            Some(t) => return Err(format!("Expected a field name, found {}", t.describe())),
+            None => return Err("The query ends too early".to_string()),
+        };
+        let op = match self.next() {
+            Some(Token::Op(op)) => *op,
+            _ => {
+                return Err(format!(
+                    "Expected an operator (=, !=, <, <=, >, >=, ~) after '{}'",
+                    field.name()
+                ))
+            }
+        };
+        if !field.allows(op) {
+            return Err(format!(
+                "'{}' can't be compared with {}",
+                field.name(),
+                op.symbol()
+            ));
+        }
+        let value = match self.next() {
+            Some(Token::Quoted(s)) => s.clone(),
+            Some(Token::Word(w)) if !["and", "or", "not"].contains(&w.to_lowercase().as_str()) => {
+                let mut words = vec![w.clone()];
+                while let Some(Token::Word(w)) = self.peek() {
+                    if ["and", "or", "not"].contains(&w.to_lowercase().as_str()) {
+                        break;
+                    }
+                    words.push(w.clone());
+                    self.pos += 1;
+                }
+                words.join(" ")
+            }
+            _ => return Err(format!("Expected a value after '{}'", field.name())),
+        };
+        let value = field.value(&value)?;
+        Ok(Query::Compare(field, op, value))
+    }
+}
+
+/// Parses a `--where` expression.
+pub fn parse(input: &str) -> Result<Query, String> {
+    let mut parser = Parser {
+        tokens: tokenize(input)?,
+        pos: 0,
+    };
+    if parser.tokens.is_empty() {
+        return Err("The query is empty".to_string());
+    }
+    let query = parser.or()?;
+    if let Some(t) = parser.peek() {
+        return Err(format!(
+            "Unexpected {} in the query; join conditions with AND or OR",
+            t.describe()
+        ));
+    }
+    Ok(query)
+}
+
+fn compare<T: Ord>(actual: Option<T>, op: Op, wanted: Option<T>) -> bool {
+    match (actual, wanted) {
+        (a, None) => match op {
+            Op::Eq => a.is_none(),
+            Op::Ne => a.is_some(),
+            _ => false,
+        },
+        // A missing field only matches `!=`.
+        (None, Some(_)) => op == Op::Ne,
+        (Some(a), Some(w)) => holds(a.cmp(&w), op),
+    }
+}
+
+fn holds(ord: Ordering, op: Op) -> bool {
+    match op {
+        Op::Eq | Op::Contains => ord == Ordering::Equal,
+        Op::Ne => ord != Ordering::Equal,
+        Op::Lt => ord == Ordering::Less,
+        Op::Le => ord != Ordering::Greater,
+        Op::Gt => ord == Ordering::Greater,
+        Op::Ge => ord != Ordering::Less,
+    }
+}
+
+fn compare_text(actual: &str, op: Op, wanted: &str) -> bool {
+    let actual = actual.to_lowercase();
+    match op {
+        Op::Eq => actual == wanted,
+        Op::Ne => actual != wanted,
+        _ => actual.contains(wanted),
+    }
+}
+
+/// Metadata compares as numbers when both sides are numbers and as text
+/// otherwise, so `meta.cost > 30` and `meta.sprint >= 2026-10` both work.
+fn compare_meta(actual: &str, op: Op, wanted: &str) -> bool {
+    if matches!(op, Op::Eq | Op::Ne | Op::Contains) {
+        return compare_text(actual, op, wanted);
+    }
+    let ord = match (actual.trim().parse::<f64>(), wanted.trim().parse::<f64>()) {
+        (Ok(a), Ok(w)) => a.total_cmp(&w),
+        _ => actual.to_lowercase().as_str().cmp(wanted),
+    };
+    holds(ord, op)
+}
+
+/// `context = name`, as `list --context` filters.
+pub fn context(name: &str) -> Query {
+    Query::Compare(Field::Context, Op::Eq, Value::Text(name.to_string()))
+}
+
+impl Query {
+    pub fn matches(&self, t: &Todo) -> bool {
+        match self {
+            Query::And(a, b) => a.matches(t) && b.matches(t),
+            Query::Or(a, b) => a.matches(t) || b.matches(t),
+            Query::Not(q) => !q.matches(t),
+            Query::Compare(field, op, value) => compare_field(t, field, *op, value),
+        }
+    }
+}
+
+fn compare_field(t: &Todo, field: &Field, op: Op, value: &Value) -> bool {
+    match (field, value) {
+        (Field::Title, Value::Text(s)) => compare_text(&t.title, op, s),
+        (Field::Description, Value::Text(s)) => compare_text(&t.description, op, s),
+        (Field::Tag, Value::None) => compare(t.tags.first(), op, None),
+        // `tag != x` means no tag is x.
+        (Field::Tag, Value::Text(s)) if op == Op::Ne => {
+            !t.tags.iter().any(|tag| compare_text(tag, Op::Eq, s))
+        }
+        (Field::Tag, Value::Text(s)) => t.tags.iter().any(|tag| compare_text(tag, op, s)),
+        (Field::Context, Value::None) => compare(t.contexts.first(), op, None),
+        (Field::Context, Value::Text(s)) if op == Op::Ne => {
+            !t.contexts.iter().any(|c| compare_text(c, Op::Eq, s))
+        }
+        (Field::Context, Value::Text(s)) => t.contexts.iter().any(|c| compare_text(c, op, s)),
+        (Field::Status, Value::Done(done)) => compare(Some(t.completed), op, Some(*done)),
+        (Field::Id, Value::Number(n)) => compare(Some(t.id), op, Some(*n)),
+        (Field::Parent, Value::Number(n)) => compare(t.parent, op, Some(*n)),
+        (Field::Parent, Value::None) => compare(t.parent, op, None),
+        (Field::Priority, Value::Priority(p)) => compare(t.priority, op, Some(*p)),
+        (Field::Priority, Value::None) => compare(t.priority, op, None),
+        (Field::Meta(key), Value::Text(s)) => match t.meta.get(key) {
+            Some(actual) => compare_meta(actual, op, s),
+            None => op == Op::Ne,
+        },
+        (Field::Meta(key), Value::None) => compare(t.meta.get(key), op, None),
+        (Field::Due | Field::Created | Field::Completed, v) => {
+            let actual = match field {
+                Field::Due => t.due,
+                Field::Created => t.created_at.map(|c| c.date()),
+                _ => t.completed_at.map(|c| c.date()),
+            };
+            let wanted = match v {
+                Value::Date(d) => Some(*d),
+                _ => None,
+            };
+            compare(actual, op, wanted)
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{date, todo};
+
+    fn matches(query: &str, t: &Todo) -> bool {
+        parse(query).unwrap().matches(t)
+    }
+
+    #[test]
+    fn comparisons_and_logic() {
+        let t = Todo {
+            priority: Some(Priority::High),
+            due: Some(date("2026-03-20")),
+            tags: vec!["work".to_string()],
+            contexts: vec!["office".to_string()],
+            ..todo(7, "Write the Report")
+        };
+        assert!(matches("priority >= medium", &t));
+        assert!(matches("tag = work AND NOT status = done", &t));
+        assert!(matches("title ~ report", &t));
+        assert!(matches("due < 2026-04-01 OR id = 1", &t));
+        assert!(matches("(id = 1 OR id = 7) AND parent = none", &t));
+        assert!(matches("title = \"write the report\"", &t));
+        assert!(!matches("tag = home", &t));
+        assert!(!matches("due > 2026-03-20", &t));
+        assert!(context("office").matches(&t));
+        assert!(!context("home").matches(&t));
+    }
+
+    #[test]
+    fn metadata() {
+        let mut t = todo(1, "Fix login");
+        t.meta.insert("ticket".to_string(), "JIRA-123".to_string());
+        t.meta.insert("cost".to_string(), "40".to_string());
+        assert!(matches("meta.ticket = jira-123", &t));
+        assert!(matches("meta.ticket ~ jira", &t));
+        assert!(matches("meta.cost > 9 AND meta.cost <= 40", &t));
+        assert!(matches("meta.owner = none AND meta.owner != ann", &t));
+        assert!(!matches("meta.cost < 100.5 AND meta.ticket = none", &t));
+        assert!(parse("meta. = x").is_err());
+    }
+
+    #[test]
+    fn missing_values_only_match_none() {
+        let t = todo(1, "bare");
+        assert!(matches("due = none", &t));
+        assert!(!matches("due < 2030-01-01", &t));
+        assert!(!matches("priority >= low", &t));
+    }
+
+    #[test]
+    fn bad_queries_are_errors() {
+        assert!(parse("").is_err());
+        assert!(parse("colour = red").is_err());
+        assert!(parse("priority = urgent").is_err());
+        assert!(parse("(tag = a").is_err());
+    }
+}
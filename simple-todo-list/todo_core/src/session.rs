@@ -0,0 +1,117 @@
+use crate::{write_list, Todo};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+/// A list loaded during an interactive session.
+struct Cached {
+    todos: Vec<Todo>,
+    dirty: bool,
+}
+
+/// Lists kept in memory during a session (`interactive`), so each command
+/// doesn't reload and rewrite the DB. `None` outside a session.
+static SESSION: Mutex<Option<HashMap<PathBuf, Cached>>> = Mutex::new(None);
+
+/// Set while a `--dry-run` session runs: nothing is written back, and no
+/// history or hooks follow the changes.
+static DRY_RUN: AtomicBool = AtomicBool::new(false);
+
+/// The todos stored at `path` if this session has already loaded them.
+pub fn cached(path: &Path) -> Option<Vec<Todo>> {
+    let session = SESSION.lock().unwrap();
+    session.as_ref()?.get(path).map(|c| c.todos.clone())
+}
+
+/// Files this session holds in memory, written or not.
+pub fn cached_paths() -> Vec<PathBuf> {
+    let session = SESSION.lock().unwrap();
+    session
+        .as_ref()
+        .map(|lists| lists.keys().cloned().collect())
+        .unwrap_or_default()
+}
+
+/// Keeps `todos` as the content of `path` for the rest of the session;
+/// `changed` marks it to be written back. Returns false outside a session,
+/// when the caller has to touch the file itself.
+pub fn remember(path: &Path, todos: &[Todo], changed: bool) -> bool {
+    let mut session = SESSION.lock().unwrap();
+    let Some(lists) = session.as_mut() else {
+        return false;
+    };
+    let entry = lists.entry(path.to_path_buf()).or_insert(Cached {
+        todos: Vec::new(),
+        dirty: false,
+    });
+    entry.todos = todos.to_vec();
+    entry.dirty |= changed;
+    true
+}
+
+/// Writes every changed list back to its file; does nothing in a dry run.
+pub fn flush() -> Result<(), String> {
+    let mut session = SESSION.lock().unwrap();
+    let Some(lists) = session.as_mut() else {
+        return Ok(());
+    };
+    if dry_run() {
+        return Ok(());
+    }
+    for (path, cached) in lists.iter_mut().filter(|(_, c)| c.dirty) {
+        write_list(path, &cached.todos)?;
+        cached.dirty = false;
+    }
+    Ok(())
+}
+
+/// Drops the lists held in memory so they are read again from disk, e.g.
+/// after `sync` pulled new versions. Call `flush` first.
+pub fn reload() {
+    if let Some(lists) = SESSION.lock().unwrap().as_mut() {
+        lists.clear();
+    }
+}
+
+/// Starts keeping the lists that are loaded and saved in memory.
+pub fn start() {
+    *SESSION.lock().unwrap() = Some(HashMap::new());
+}
+
+/// Starts a session whose changes are never written, for `--dry-run`.
+pub fn start_dry_run() {
+    start();
+    DRY_RUN.store(true, Ordering::Relaxed);
+}
+
+/// Whether this is a `--dry-run` session.
+pub fn dry_run() -> bool {
+    DRY_RUN.load(Ordering::Relaxed)
+}
+
+/// Whether a session is going, dry or not.
+pub fn active() -> bool {
+    SESSION.lock().unwrap().is_some()
+}
+
+/// Ends a dry run, handing back each list that would have been written
+/// with what it would have held.
+pub fn discard() -> Vec<(PathBuf, Vec<Todo>)> {
+    let lists = SESSION.lock().unwrap().take().unwrap_or_default();
+    DRY_RUN.store(false, Ordering::Relaxed);
+    let mut changed: Vec<(PathBuf, Vec<Todo>)> = lists
+        .into_iter()
+        .filter(|(_, c)| c.dirty)
+        .map(|(path, c)| (path, c.todos))
+        .collect();
+    changed.sort_by(|a, b| a.0.cmp(&b.0));
+    changed
+}
+
+/// Writes every changed list back and stops keeping them.
+pub fn end() -> Result<(), String> {
+    let result = flush();
+    *SESSION.lock().unwrap() = None;
+    result
+}
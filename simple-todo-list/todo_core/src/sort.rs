@@ -0,0 +1,210 @@
+use crate::{config, Todo};
+use chrono::NaiveDate;
+use std::cmp::Ordering;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum SortKey {
+    Title,
+    Created,
+    Completed,
+    Due,
+    Priority,
+    /// Open todos past their due date.
+    Overdue,
+}
+
+impl SortKey {
+    fn parse(s: &str) -> Result<SortKey, String> {
+        match s.to_lowercase().as_str() {
+            "title" => Ok(SortKey::Title),
+            "created" => Ok(SortKey::Created),
+            "completed" => Ok(SortKey::Completed),
+            "due" => Ok(SortKey::Due),
+            "priority" => Ok(SortKey::Priority),
+            "overdue" => Ok(SortKey::Overdue),
+            _ => Err(format!(
+                "Invalid sort key '{}': use title, created, completed, due, priority or overdue",
+                s
+            )),
+        }
+    }
+
+    /// Priority puts the highest first and overdue the overdue todos; the
+    /// other keys go from smallest to largest unless `desc` is given.
+    fn descending(self) -> bool {
+        matches!(self, SortKey::Priority | SortKey::Overdue)
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Step {
+    key: SortKey,
+    descending: bool,
+}
+
+/// The order `list` shows todos in, e.g. `overdue, priority desc, due`:
+/// each key breaks the ties of the one before, and todos equal on all of
+/// them keep their insertion order. Empty means insertion order.
+#[derive(Clone, Default)]
+pub struct Order(Vec<Step>);
+
+impl Order {
+    /// Reads keys separated by commas, each optionally followed by `asc` or
+    /// `desc`; `none` is insertion order.
+    pub fn parse(s: &str) -> Result<Order, String> {
+        if s.trim().eq_ignore_ascii_case("none") {
+            return Ok(Order::default());
+        }
+        let mut steps = Vec::new();
+        for part in s.split(',') {
+            let words: Vec<&str> = part.split_whitespace().collect();
+            let step = match words[..] {
+                [key] => {
+                    let key = SortKey::parse(key)?;
+                    Step {
+                        key,
+                        descending: key.descending(),
+                    }
+                }
+                [key, dir] => Step {
+                    key: SortKey::parse(key)?,
+                    descending: match dir.to_lowercase().as_str() {
+                        "asc" => false,
+                        "desc" => true,
+                        _ => return Err(format!("Invalid direction '{}': use asc or desc", dir)),
+                    },
+                },
+                _ => {
+                    return Err(format!(
+                    "Invalid sort '{}': use keys separated by commas, e.g. 'due, priority desc'",
+                    s.trim()
+                ))
+                }
+            };
+            steps.push(step);
+        }
+        Ok(Order(steps))
+    }
+
+    /// Soonest due first, then highest priority: how the `today`, `week`
+    /// and `overdue` views are ordered.
+    pub fn by_due() -> Order {
+        Order(vec![
+            Step {
+                key: SortKey::Due,
+                descending: false,
+            },
+            Step {
+                key: SortKey::Priority,
+                descending: true,
+            },
+        ])
+    }
+
+    /// The `sort` from the config file, or insertion order. An invalid one is
+    /// reported and ignored.
+    pub fn configured() -> Order {
+        let Some(s) = &config::get().sort else {
+            return Order::default();
+        };
+        Order::parse(s).unwrap_or_else(|e| {
+            eprintln!("Ignoring the configured sort: {}", e);
+            Order::default()
+        })
+    }
+
+    /// Sorts `todos` in this order; the sort is stable.
+    pub fn sort(&self, todos: &mut [&Todo], today: NaiveDate) {
+        if self.0.is_empty() {
+            return;
+        }
+        todos.sort_by(|a, b| {
+            self.0
+                .iter()
+                .map(|step| compare(step, a, b, today))
+                .find(|o| o.is_ne())
+                .unwrap_or(Ordering::Equal)
+        });
+    }
+}
+
+/// Missing values sort last whichever the direction.
+fn missing_last<T: Ord>(a: Option<T>, b: Option<T>, descending: bool) -> Ordering {
+    match (a, b) {
+        (Some(a), Some(b)) if descending => b.cmp(&a),
+        (Some(a), Some(b)) => a.cmp(&b),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    }
+}
+
+fn compare(step: &Step, a: &Todo, b: &Todo, today: NaiveDate) -> Ordering {
+    let descending = step.descending;
+    let ordering = match step.key {
+        SortKey::Completed => return missing_last(a.completed_at, b.completed_at, descending),
+        SortKey::Due => return missing_last(a.due, b.due, descending),
+        SortKey::Priority => return missing_last(a.priority, b.priority, descending),
+        SortKey::Title => a.title.to_lowercase().cmp(&b.title.to_lowercase()),
+        // Todos from before creation times were recorded sort as the oldest.
+        SortKey::Created => a.created_at.cmp(&b.created_at),
+        SortKey::Overdue => {
+            let overdue = |t: &Todo| !t.completed && t.due.is_some_and(|d| d < today);
+            overdue(a).cmp(&overdue(b))
+        }
+    };
+    if descending {
+        ordering.reverse()
+    } else {
+        ordering
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{date, todo};
+    use crate::Priority;
+
+    fn sorted(order: &str, todos: &[Todo], today: NaiveDate) -> Vec<u64> {
+        let mut shown: Vec<&Todo> = todos.iter().collect();
+        Order::parse(order).unwrap().sort(&mut shown, today);
+        shown.iter().map(|t| t.id).collect()
+    }
+
+    #[test]
+    fn keys_break_ties_in_order() {
+        let today = date("2026-03-16");
+        let todos = vec![
+            Todo {
+                due: Some(date("2026-03-20")),
+                ..todo(1, "b")
+            },
+            Todo {
+                priority: Some(Priority::High),
+                due: Some(date("2026-03-01")),
+                ..todo(2, "a")
+            },
+            Todo {
+                priority: Some(Priority::Low),
+                ..todo(3, "c")
+            },
+            Todo {
+                priority: Some(Priority::High),
+                ..todo(4, "d")
+            },
+        ];
+        assert_eq!(sorted("due", &todos, today), [2, 1, 3, 4]);
+        assert_eq!(sorted("due desc", &todos, today), [1, 2, 3, 4]);
+        assert_eq!(sorted("priority, title", &todos, today), [2, 4, 3, 1]);
+        assert_eq!(sorted("overdue, title desc", &todos, today), [2, 4, 3, 1]);
+        assert_eq!(sorted("none", &todos, today), [1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn invalid_orders() {
+        assert!(Order::parse("urgency").is_err());
+        assert!(Order::parse("due sideways").is_err());
+        assert!(Order::parse("due asc desc").is_err());
+    }
+}
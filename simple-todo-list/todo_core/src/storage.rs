@@ -0,0 +1,463 @@
+use crate::{crypto, todotxt, Todo};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+/// Where a list of todos is persisted. Commands load the whole list, change
+/// it in memory and hand it back to `save`.
+pub trait Storage {
+    fn load(&self) -> Result<Vec<Todo>, String>;
+    fn save(&self, todos: &[Todo]) -> Result<(), String>;
+
+    /// Saves `todos` after its last entry was just added. Backends that can
+    /// append write only that entry; the rest rewrite everything.
+    fn append(&self, todos: &[Todo]) -> Result<(), String> {
+        self.save(todos)
+    }
+}
+
+/// Picks the backend from the file extension: `.db`/`.sqlite`/`.sqlite3`
+/// use SQLite, `.jsonl`/`.ndjson` JSON Lines, `.txt` the todo.txt format,
+/// `.toml` and `.yaml`/`.yml` a document in that format, anything else a
+/// JSON array.
+pub fn open(path: &Path) -> Box<dyn Storage> {
+    open_as(path, path)
+}
+
+/// Opens `path` with the backend `like` would get, e.g. a backup file
+/// whose own extension is `.bak.1`.
+pub fn open_as(path: &Path, like: &Path) -> Box<dyn Storage> {
+    match like.extension().and_then(|e| e.to_str()) {
+        Some("jsonl" | "ndjson") => Box::new(JsonLinesStorage {
+            path: path.to_path_buf(),
+        }),
+        Some("db" | "sqlite" | "sqlite3") => Box::new(SqliteStorage {
+            path: path.to_path_buf(),
+        }),
+        Some("txt") => Box::new(TodoTxtStorage {
+            path: path.to_path_buf(),
+        }),
+        _ => Box::new(DocumentStorage {
+            path: path.to_path_buf(),
+            format: Format::of(like),
+        }),
+    }
+}
+
+/// How `DocumentStorage` writes a list out.
+#[derive(Clone, Copy)]
+pub enum Format {
+    /// A pretty-printed array.
+    Json,
+    /// A `[[todo]]` table per todo, since TOML documents are tables.
+    Toml,
+    /// A sequence of mappings.
+    Yaml,
+}
+
+#[derive(Serialize)]
+struct TomlOut<'a> {
+    todo: &'a [Todo],
+}
+
+#[derive(Deserialize)]
+struct TomlIn {
+    #[serde(default)]
+    todo: Vec<Todo>,
+}
+
+impl Format {
+    /// The format of a file with `path`'s extension, JSON unless it is
+    /// `.toml`, `.yaml` or `.yml`.
+    pub fn of(path: &Path) -> Format {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("toml") => Format::Toml,
+            Some("yaml" | "yml") => Format::Yaml,
+            _ => Format::Json,
+        }
+    }
+
+    pub fn serialize(self, todos: &[Todo]) -> Result<Vec<u8>, String> {
+        match self {
+            Format::Json => serde_json::to_vec_pretty(todos).map_err(|e| e.to_string()),
+            Format::Toml => toml::to_string(&TomlOut { todo: todos })
+                .map(String::into_bytes)
+                .map_err(|e| e.to_string()),
+            Format::Yaml => serde_yaml::to_string(todos)
+                .map(String::into_bytes)
+                .map_err(|e| e.to_string()),
+        }
+    }
+
+    pub fn deserialize(self, data: &[u8]) -> Result<Vec<Todo>, String> {
+        match self {
+            Format::Json => serde_json::from_slice(data).map_err(|e| e.to_string()),
+            Format::Toml => {
+                let text = std::str::from_utf8(data).map_err(|e| e.to_string())?;
+                toml::from_str::<TomlIn>(text)
+                    .map(|doc| doc.todo)
+                    .map_err(|e| e.to_string())
+            }
+            Format::Yaml => serde_yaml::from_slice(data).map_err(|e| e.to_string()),
+        }
+    }
+}
+
+fn create_parent_dir(path: &Path) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        if !parent.as_os_str().is_empty() {
+            if let Err(e) = fs::create_dir_all(parent) {
+                return Err(format!("Failed to create directory: {}", e));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Replaces `path` with what `write` produces without ever leaving a
+/// half-written file: the data goes to a temporary file in the same
+/// directory, is synced to disk, and is then renamed over the original.
+pub fn write_atomic(
+    path: &Path,
+    write: impl FnOnce(&mut File) -> std::io::Result<()>,
+) -> Result<(), String> {
+    create_parent_dir(path)?;
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let tmp = path.with_file_name(format!(".{}.tmp-{}", name, std::process::id()));
+    let result = File::create(&tmp)
+        .and_then(|mut file| {
+            write(&mut file)?;
+            file.sync_all()
+        })
+        .and_then(|()| fs::rename(&tmp, path));
+    if let Err(e) = result {
+        let _ = fs::remove_file(&tmp);
+        return Err(format!("Failed to write {}: {}", path.display(), e));
+    }
+    // Make the rename itself durable; not every platform can open a
+    // directory, so this is best effort.
+    if let Some(dir) = path.parent() {
+        let dir = if dir.as_os_str().is_empty() {
+            Path::new(".")
+        } else {
+            dir
+        };
+        if let Ok(dir) = File::open(dir) {
+            let _ = dir.sync_all();
+        }
+    }
+    Ok(())
+}
+
+/// The whole list as one JSON, TOML or YAML document, encrypted once the DB
+/// has been through `todo encrypt`.
+pub struct DocumentStorage {
+    path: PathBuf,
+    format: Format,
+}
+
+impl Storage for DocumentStorage {
+    fn load(&self) -> Result<Vec<Todo>, String> {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+        let mut file = File::open(&self.path).map_err(|e| e.to_string())?;
+        let mut content = Vec::new();
+        file.read_to_end(&mut content).map_err(|e| e.to_string())?;
+        if let Some(salt) = crypto::file_salt(&self.path) {
+            content = crypto::decrypt(&content, &crypto::key_for(&salt)?)
+                .map_err(|e| format!("{}: {}", self.path.display(), e))?;
+        }
+        if content.trim_ascii().is_empty() {
+            return Ok(Vec::new());
+        }
+        self.format.deserialize(&content)
+    }
+
+    fn save(&self, todos: &[Todo]) -> Result<(), String> {
+        let mut data = self.format.serialize(todos)?;
+        // An encrypted file stays encrypted, and new lists of an encrypted
+        // DB are encrypted too.
+        if let Some(salt) = crypto::file_salt(&self.path).or_else(crypto::db_salt) {
+            data = crypto::encrypt(&data, &salt, &crypto::key_for(&salt)?)?;
+        }
+        write_atomic(&self.path, |file| file.write_all(&data))
+    }
+}
+
+/// One todo per line. Loading reads the file line by line and `add` only
+/// appends a line, so big lists don't get re-serialized on every new item.
+pub struct JsonLinesStorage {
+    path: PathBuf,
+}
+
+impl Storage for JsonLinesStorage {
+    fn load(&self) -> Result<Vec<Todo>, String> {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+        let file = File::open(&self.path).map_err(|e| e.to_string())?;
+        let mut reader = BufReader::new(file);
+        let mut todos = Vec::new();
+        let mut line = String::new();
+        for n in 1.. {
+            line.clear();
+            if reader.read_line(&mut line).map_err(|e| e.to_string())? == 0 {
+                break;
+            }
+            if line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str(&line) {
+                Ok(todo) => todos.push(todo),
+                // A crash in the middle of `append` can leave the last line
+                // cut off; only that todo is lost.
+                Err(_) if !line.ends_with('\n') => break,
+                Err(e) => return Err(format!("{}:{}: {}", self.path.display(), n, e)),
+            }
+        }
+        Ok(todos)
+    }
+
+    fn save(&self, todos: &[Todo]) -> Result<(), String> {
+        let mut lines = Vec::with_capacity(todos.len());
+        for t in todos {
+            lines.push(serde_json::to_string(t).map_err(|e| e.to_string())?);
+        }
+        write_atomic(&self.path, |file| {
+            let mut out = BufWriter::new(file);
+            for line in &lines {
+                writeln!(out, "{}", line)?;
+            }
+            out.flush()
+        })
+    }
+
+    fn append(&self, todos: &[Todo]) -> Result<(), String> {
+        let Some(last) = todos.last() else {
+            return self.save(todos);
+        };
+        // After a torn last line the new one would be glued onto it, so the
+        // file is rewritten instead.
+        if !ends_with_newline(&self.path)? {
+            return self.save(todos);
+        }
+        let line = serde_json::to_string(last).map_err(|e| e.to_string())?;
+        OpenOptions::new()
+            .append(true)
+            .open(&self.path)
+            .and_then(|mut file| {
+                writeln!(file, "{}", line)?;
+                file.sync_data()
+            })
+            .map_err(|e| format!("Failed to write {}: {}", self.path.display(), e))
+    }
+}
+
+/// Whether `path` exists and is empty or ends a line; a file cut off in the
+/// middle of a line doesn't.
+fn ends_with_newline(path: &Path) -> Result<bool, String> {
+    let Ok(mut file) = File::open(path) else {
+        return Ok(false);
+    };
+    let len = file.metadata().map_err(|e| e.to_string())?.len();
+    if len == 0 {
+        return Ok(true);
+    }
+    let mut last = [0];
+    file.seek(SeekFrom::Start(len - 1))
+        .and_then(|_| file.read_exact(&mut last))
+        .map_err(|e| e.to_string())?;
+    Ok(last[0] == b'\n')
+}
+
+/// A todo.txt file, one todo per line, so other todo.txt tools can read and
+/// edit the list too. Descriptions, notes and tracked time can't be kept.
+pub struct TodoTxtStorage {
+    path: PathBuf,
+}
+
+impl Storage for TodoTxtStorage {
+    fn load(&self) -> Result<Vec<Todo>, String> {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+        let content = fs::read_to_string(&self.path)
+            .map_err(|e| format!("{}: {}", self.path.display(), e))?;
+        Ok(content.lines().filter_map(todotxt::parse).collect())
+    }
+
+    fn save(&self, todos: &[Todo]) -> Result<(), String> {
+        for t in todos {
+            let lost = todotxt::unsaved(t);
+            if !lost.is_empty() {
+                eprintln!(
+                    "Warning: todo.txt has no room for the {} of #{}; not saved.",
+                    lost.join(", "),
+                    t.id
+                );
+            }
+        }
+        write_atomic(&self.path, |file| {
+            let mut out = BufWriter::new(file);
+            for t in todos {
+                writeln!(out, "{}", todotxt::format(t))?;
+            }
+            out.flush()
+        })
+    }
+}
+
+/// One row per todo, keyed by its id. Each todo is stored as JSON so new
+/// fields need no schema migration; `save` only writes the rows that
+/// actually changed, so big lists don't get rewritten on every command.
+pub struct SqliteStorage {
+    path: PathBuf,
+}
+
+impl SqliteStorage {
+    fn connect(&self) -> Result<Connection, String> {
+        create_parent_dir(&self.path)?;
+        let conn = Connection::open(&self.path).map_err(|e| e.to_string())?;
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS todos (
+                id       INTEGER PRIMARY KEY,
+                position INTEGER NOT NULL,
+                data     TEXT NOT NULL
+            );",
+        )
+        .map_err(|e| e.to_string())?;
+        Ok(conn)
+    }
+}
+
+impl Storage for SqliteStorage {
+    fn load(&self) -> Result<Vec<Todo>, String> {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+        let conn = self.connect()?;
+        let mut stmt = conn
+            .prepare("SELECT data FROM todos ORDER BY position")
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map([], |row| row.get::<_, String>(0))
+            .map_err(|e| e.to_string())?;
+        let mut todos = Vec::new();
+        for data in rows {
+            let data = data.map_err(|e| e.to_string())?;
+            todos.push(serde_json::from_str(&data).map_err(|e| e.to_string())?);
+        }
+        Ok(todos)
+    }
+
+    fn save(&self, todos: &[Todo]) -> Result<(), String> {
+        let mut conn = self.connect()?;
+        let tx = conn.transaction().map_err(|e| e.to_string())?;
+        let existing: HashMap<i64, (i64, String)> = {
+            let mut stmt = tx
+                .prepare("SELECT id, position, data FROM todos")
+                .map_err(|e| e.to_string())?;
+            let rows = stmt
+                .query_map([], |row| Ok((row.get(0)?, (row.get(1)?, row.get(2)?))))
+                .map_err(|e| e.to_string())?;
+            rows.collect::<Result<_, _>>().map_err(|e| e.to_string())?
+        };
+        let mut keep = Vec::with_capacity(todos.len());
+        for (pos, t) in todos.iter().enumerate() {
+            let id = t.id as i64;
+            let pos = pos as i64;
+            let data = serde_json::to_string(t).map_err(|e| e.to_string())?;
+            keep.push(id);
+            if existing.get(&id) == Some(&(pos, data.clone())) {
+                continue;
+            }
+            tx.execute(
+                "INSERT INTO todos (id, position, data) VALUES (?1, ?2, ?3)
+                 ON CONFLICT(id) DO UPDATE SET position = ?2, data = ?3",
+                params![id, pos, data],
+            )
+            .map_err(|e| e.to_string())?;
+        }
+        for id in existing.keys().filter(|id| !keep.contains(id)) {
+            tx.execute("DELETE FROM todos WHERE id = ?1", params![id])
+                .map_err(|e| e.to_string())?;
+        }
+        tx.commit().map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{child, date, todo};
+
+    #[test]
+    fn every_backend_reads_back_what_it_wrote() {
+        let dir = std::env::temp_dir().join(format!("todo_core-storage-{}", std::process::id()));
+        let todos = vec![
+            Todo {
+                due: Some(date("2026-03-20")),
+                tags: vec!["home".to_string()],
+                ..todo(1, "Clean the garage")
+            },
+            child(2, "Sort tools", 1),
+        ];
+        for ext in ["json", "toml", "yaml", "jsonl", "db", "txt"] {
+            let path = dir.join(format!("todos.{}", ext));
+            let storage = open(&path);
+            assert!(storage.load().unwrap().is_empty(), "{}", ext);
+            storage.save(&todos[..1]).unwrap();
+            storage.append(&todos).unwrap();
+            let back = storage.load().unwrap();
+            let ids: Vec<u64> = back.iter().map(|t| t.id).collect();
+            assert_eq!(ids, [1, 2], "{}", ext);
+            assert_eq!(back[0].title, todos[0].title, "{}", ext);
+            assert_eq!(back[0].due, todos[0].due, "{}", ext);
+            assert_eq!(back[0].tags, todos[0].tags, "{}", ext);
+            assert_eq!(back[1].parent, Some(1), "{}", ext);
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn unreadable_files_are_errors() {
+        let dir = std::env::temp_dir().join(format!("todo_core-corrupt-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("todos.json");
+        fs::write(&path, "[{\"id\": 1, \"tit").unwrap();
+        assert!(open(&path).load().is_err());
+        fs::write(&path, "  \n").unwrap();
+        assert!(open(&path).load().unwrap().is_empty());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn a_torn_last_jsonl_line_is_dropped() {
+        let dir = std::env::temp_dir().join(format!("todo_core-torn-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("todos.jsonl");
+        let storage = open(&path);
+        storage.save(&[todo(1, "Kept")]).unwrap();
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(b"{\"id\": 2, \"tit").unwrap();
+
+        let mut todos = storage.load().unwrap();
+        assert_eq!(todos.len(), 1);
+        todos.push(todo(3, "Added after"));
+        storage.append(&todos).unwrap();
+        let ids: Vec<u64> = storage.load().unwrap().iter().map(|t| t.id).collect();
+        assert_eq!(ids, [1, 3]);
+
+        // A bad line in the middle is still an error.
+        fs::write(&path, "{\"id\": 2, \"tit\n{}\n").unwrap();
+        assert!(storage.load().is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
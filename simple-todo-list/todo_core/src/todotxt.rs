@@ -0,0 +1,305 @@
+use crate::due::Repeat;
+use crate::{normalize_context, normalize_meta_key, normalize_tag, parse_estimate, Priority, Todo};
+use chrono::{NaiveDate, NaiveDateTime};
+use std::collections::BTreeMap;
+
+/// Reads one line of a todo.txt file:
+/// `x 2025-07-02 2025-07-01 (A) Call mom +family @phone due:2025-07-03`.
+/// `+project`s become tags and `@context`s contexts; the
+/// `key:value` pairs this tool writes are read back, any others stay in the
+/// title. Blank lines give `None`.
+pub fn parse(line: &str) -> Option<Todo> {
+    let mut words = line.split_whitespace().peekable();
+    words.peek()?;
+    let mut t = Todo {
+        id: 0,
+        title: String::new(),
+        description: String::new(),
+        completed: false,
+        due: None,
+        priority: None,
+        parent: None,
+        repeat: None,
+        tags: Vec::new(),
+        contexts: Vec::new(),
+        snoozed: None,
+        created_at: None,
+        completed_at: None,
+        time: Vec::new(),
+        pomodoros: 0,
+        in_progress: false,
+        pinned: false,
+        estimate: None,
+        blocked_by: Vec::new(),
+        notes: Vec::new(),
+        meta: BTreeMap::new(),
+    };
+    if words.next_if_eq(&"x").is_some() {
+        t.completed = true;
+        t.completed_at = words.next_if(|w| date(w).is_some()).and_then(midnight);
+    } else if let Some(p) = words.next_if(|w| priority(w).is_some()) {
+        t.priority = priority(p);
+    }
+    // A completed todo can only have a creation date after its completion date.
+    if !t.completed || t.completed_at.is_some() {
+        t.created_at = words.next_if(|w| date(w).is_some()).and_then(midnight);
+    }
+    let mut title = Vec::new();
+    for word in words {
+        if !read_tag(&mut t, word) && !read_field(&mut t, word) {
+            title.push(word);
+        }
+    }
+    t.title = title.join(" ");
+    Some(t)
+}
+
+fn date(s: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(s, "%Y-%m-%d").ok()
+}
+
+fn midnight(s: &str) -> Option<NaiveDateTime> {
+    date(s)?.and_hms_opt(0, 0, 0)
+}
+
+/// `(A)` is high, `(B)` medium and `(C)` to `(Z)` low.
+fn priority(s: &str) -> Option<Priority> {
+    let letter = s.strip_prefix('(')?.strip_suffix(')')?;
+    priority_letter(letter)
+}
+
+fn priority_letter(s: &str) -> Option<Priority> {
+    match s {
+        "A" => Some(Priority::High),
+        "B" => Some(Priority::Medium),
+        _ if s.len() == 1 && s.chars().all(|c| c.is_ascii_uppercase()) => Some(Priority::Low),
+        _ => None,
+    }
+}
+
+fn letter(p: Priority) -> char {
+    match p {
+        Priority::High => 'A',
+        Priority::Medium => 'B',
+        Priority::Low => 'C',
+    }
+}
+
+fn read_tag(t: &mut Todo, word: &str) -> bool {
+    let (found, into) = match word.chars().next() {
+        Some('+') => (normalize_tag(&word[1..]), &mut t.tags),
+        Some('@') if word.len() > 1 => (normalize_context(word), &mut t.contexts),
+        _ => return false,
+    };
+    let Ok(found) = found else {
+        return false;
+    };
+    if !into.contains(&found) {
+        into.push(found);
+    }
+    true
+}
+
+/// `rec:1d`, `rec:2w` or `rec:+3m`, as other todo.txt tools write it.
+fn repeat(s: &str) -> Option<Repeat> {
+    let s = s.strip_prefix('+').unwrap_or(s);
+    let n: u32 = s.get(..s.len().checked_sub(1)?)?.parse().ok()?;
+    match (n, s.chars().last()?) {
+        (0, _) => None,
+        (n, 'd') => Some(Repeat::Days(n)),
+        (n, 'w') => Some(Repeat::Weeks(n)),
+        (n, 'm') => Some(Repeat::Months(n)),
+        _ => None,
+    }
+}
+
+fn read_field(t: &mut Todo, word: &str) -> bool {
+    let Some((key, value)) = word.split_once(':') else {
+        return false;
+    };
+    match key {
+        "due" => date(value).map(|d| t.due = Some(d)).is_some(),
+        "id" => value.parse().map(|id| t.id = id).is_ok(),
+        "parent" => value.parse().map(|p| t.parent = Some(p)).is_ok(),
+        "rec" => repeat(value).map(|r| t.repeat = Some(r)).is_some(),
+        "pri" => priority_letter(value)
+            .map(|p| t.priority = Some(p))
+            .is_some(),
+        "snooze" => NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S")
+            .map(|s| t.snoozed = Some(s))
+            .is_ok(),
+        "blocked" => value
+            .split(',')
+            .map(|id| id.parse())
+            .collect::<Result<Vec<u64>, _>>()
+            .map(|ids| t.blocked_by = ids)
+            .is_ok(),
+        "pomo" => value.parse().map(|n| t.pomodoros = n).is_ok(),
+        "est" => parse_estimate(value).map(|n| t.estimate = Some(n)).is_ok(),
+        "status" if value == "in-progress" => {
+            t.in_progress = true;
+            true
+        }
+        "pin" if value == "yes" => {
+            t.pinned = true;
+            true
+        }
+        // `meta.ticket:JIRA-123`, prefixed so it can't clash with the rest.
+        _ => match key.strip_prefix("meta.").map(normalize_meta_key) {
+            Some(Ok(key)) if fits(value) => {
+                t.meta.insert(key, value.to_string());
+                true
+            }
+            _ => false,
+        },
+    }
+}
+
+/// A metadata value a `key:value` pair can hold.
+fn fits(value: &str) -> bool {
+    !value.is_empty() && !value.contains(char::is_whitespace)
+}
+
+/// Writes `t` as a todo.txt line; see `parse`. Only dates are kept of the
+/// creation and completion times.
+pub fn format(t: &Todo) -> String {
+    let mut words = Vec::new();
+    if t.completed {
+        words.push("x".to_string());
+        if let Some(done) = t.completed_at {
+            words.push(done.format("%Y-%m-%d").to_string());
+            words.extend(t.created_at.map(|c| c.format("%Y-%m-%d").to_string()));
+        }
+    } else {
+        words.extend(t.priority.map(|p| format!("({})", letter(p))));
+        words.extend(t.created_at.map(|c| c.format("%Y-%m-%d").to_string()));
+    }
+    words.extend(t.title.split_whitespace().map(str::to_string));
+    words.extend(t.tags.iter().map(|tag| format!("+{}", tag)));
+    words.extend(t.contexts.iter().map(|c| format!("@{}", c)));
+    words.extend(t.due.map(|d| format!("due:{}", d)));
+    words.extend(t.repeat.map(|r| match r {
+        Repeat::Days(n) => format!("rec:{}d", n),
+        Repeat::Weeks(n) => format!("rec:{}w", n),
+        Repeat::Months(n) => format!("rec:{}m", n),
+    }));
+    if t.completed {
+        words.extend(t.priority.map(|p| format!("pri:{}", letter(p))));
+    }
+    words.push(format!("id:{}", t.id));
+    words.extend(t.parent.map(|p| format!("parent:{}", p)));
+    if !t.blocked_by.is_empty() {
+        let ids: Vec<String> = t.blocked_by.iter().map(|id| id.to_string()).collect();
+        words.push(format!("blocked:{}", ids.join(",")));
+    }
+    words.extend(
+        t.snoozed
+            .map(|s| format!("snooze:{}", s.format("%Y-%m-%dT%H:%M:%S"))),
+    );
+    if t.pomodoros > 0 {
+        words.push(format!("pomo:{}", t.pomodoros));
+    }
+    if t.in_progress {
+        words.push("status:in-progress".to_string());
+    }
+    if t.pinned {
+        words.push("pin:yes".to_string());
+    }
+    words.extend(t.estimate.map(|n| format!("est:{}", n)));
+    for (key, value) in t.meta.iter().filter(|(_, v)| fits(v)) {
+        words.push(format!("meta.{}:{}", key, value));
+    }
+    words.join(" ")
+}
+
+/// What of `t` a todo.txt line has no room for.
+pub fn unsaved(t: &Todo) -> Vec<&'static str> {
+    let mut lost = Vec::new();
+    if !t.description.trim().is_empty() {
+        lost.push("description");
+    }
+    if !t.notes.is_empty() {
+        lost.push("notes");
+    }
+    if !t.time.is_empty() {
+        lost.push("tracked time");
+    }
+    if t.meta.values().any(|v| !fits(v)) {
+        lost.push("metadata with spaces");
+    }
+    lost
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{date, todo};
+
+    #[test]
+    fn reads_the_standard_parts() {
+        let t = parse("x 2025-07-02 2025-07-01 Call mom +Family @phone due:2025-07-03").unwrap();
+        assert!(t.completed);
+        assert_eq!(t.completed_at, midnight("2025-07-02"));
+        assert_eq!(t.created_at, midnight("2025-07-01"));
+        assert_eq!(t.title, "Call mom");
+        assert_eq!(t.tags, ["family"]);
+        assert_eq!(t.contexts, ["phone"]);
+        assert_eq!(t.due, Some(date("2025-07-03")));
+
+        let t = parse("(A) Pay rent key:value").unwrap();
+        assert_eq!(t.priority, Some(Priority::High));
+        assert_eq!(t.title, "Pay rent key:value");
+        assert!(parse("   ").is_none());
+    }
+
+    #[test]
+    fn written_lines_read_back() {
+        let t = Todo {
+            priority: Some(Priority::Medium),
+            due: Some(date("2026-03-20")),
+            repeat: Some(Repeat::Weeks(2)),
+            parent: Some(3),
+            blocked_by: vec![1, 2],
+            tags: vec!["home".to_string()],
+            in_progress: true,
+            pinned: true,
+            estimate: Some(2.5),
+            meta: BTreeMap::from([("ticket".to_string(), "OPS-7".to_string())]),
+            ..todo(9, "Clean the garage")
+        };
+        let back = parse(&format(&t)).unwrap();
+        assert_eq!(back.id, 9);
+        assert_eq!(back.title, t.title);
+        assert_eq!(back.priority, t.priority);
+        assert_eq!(back.due, t.due);
+        assert_eq!(back.repeat, t.repeat);
+        assert_eq!(back.parent, t.parent);
+        assert_eq!(back.blocked_by, t.blocked_by);
+        assert_eq!(back.tags, t.tags);
+        assert!(back.in_progress);
+        assert!(back.pinned);
+        assert_eq!(back.estimate, t.estimate);
+        assert_eq!(back.meta, t.meta);
+        assert!(unsaved(&t).is_empty());
+    }
+
+    #[test]
+    fn a_written_todo_reads_back_the_same() {
+        let t = Todo {
+            completed: true,
+            completed_at: midnight("2026-03-21"),
+            created_at: midnight("2026-03-01"),
+            priority: Some(Priority::Low),
+            tags: vec!["home".to_string(), "@odd".to_string()],
+            contexts: vec!["errands".to_string(), "phone".to_string()],
+            snoozed: midnight("2026-03-10"),
+            pomodoros: 3,
+            ..todo(4, "Buy paint")
+        };
+        let back = parse(&format(&t)).unwrap();
+        assert_eq!(
+            serde_json::to_value(&back).unwrap(),
+            serde_json::to_value(&t).unwrap()
+        );
+    }
+}
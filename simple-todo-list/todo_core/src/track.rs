@@ -0,0 +1,66 @@
+use crate::{now, Todo};
+use chrono::{Duration, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+
+/// Time spent on a todo, from `start` to `stop`; `end` is missing while
+/// the timer runs.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TimeEntry {
+    pub start: NaiveDateTime,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub end: Option<NaiveDateTime>,
+}
+
+impl TimeEntry {
+    /// How long it lasted, or has lasted so far for a running timer.
+    pub fn length(&self, now: NaiveDateTime) -> Duration {
+        (self.end.unwrap_or(now) - self.start).max(Duration::zero())
+    }
+}
+
+/// Whether `t`'s timer is running.
+pub fn is_running(t: &Todo) -> bool {
+    t.time.last().is_some_and(|e| e.end.is_none())
+}
+
+/// Index of the todo whose timer is running, if any.
+pub fn running(todos: &[Todo]) -> Option<usize> {
+    todos.iter().position(is_running)
+}
+
+/// Stops the timer of `todos[idx]` if it runs. Returns the stopped entry's
+/// length.
+pub fn stop_timer(todos: &mut [Todo], idx: usize) -> Option<Duration> {
+    let entry = todos[idx].time.last_mut().filter(|e| e.end.is_none())?;
+    let now = now();
+    entry.end = Some(now);
+    Some(entry.length(now))
+}
+
+/// Starts timing `todos[idx]`, stopping whichever timer ran before. Returns
+/// the index of the todo that was being timed and for how long.
+pub fn start_timer(todos: &mut [Todo], idx: usize) -> Option<(usize, Duration)> {
+    let stopped = running(todos).and_then(|i| stop_timer(todos, i).map(|d| (i, d)));
+    todos[idx].time.push(TimeEntry {
+        start: now(),
+        end: None,
+    });
+    stopped
+}
+
+/// Total time tracked on `t`, including a running timer.
+pub fn total(t: &Todo, now: NaiveDateTime) -> Duration {
+    t.time
+        .iter()
+        .fold(Duration::zero(), |sum, e| sum + e.length(now))
+}
+
+/// e.g. "45m" or "2h 05m".
+pub fn describe(d: Duration) -> String {
+    let minutes = d.num_minutes();
+    if minutes < 60 {
+        format!("{}m", minutes)
+    } else {
+        format!("{}h {:02}m", minutes / 60, minutes % 60)
+    }
+}
@@ -0,0 +1,302 @@
+//! Commands for the people running the bot, named by Telegram user ID in
+//! `ADMIN_IDS`. Anyone else who tries them is told they can't.
+
+use crate::db::{Db, LinkStatus};
+use crate::gateway::{GatewayError, Gateways};
+use crate::inflight::InFlight;
+use crate::{format_date, ipn, status_label, HandlerResult};
+use std::env;
+use std::sync::Arc;
+use std::time::Duration;
+use teloxide::dispatching::UpdateHandler;
+use teloxide::prelude::*;
+use teloxide::types::UserId;
+use teloxide::utils::command::BotCommands;
+
+/// How many links /pending lists.
+const PENDING_LIMIT: u32 = 20;
+/// Between broadcast messages, to stay inside Telegram's limit of about
+/// 30 a second.
+const BROADCAST_PAUSE: Duration = Duration::from_millis(50);
+
+/// Comandos de administración:
+#[derive(BotCommands, Clone)]
+#[command(rename_rule = "lowercase")]
+pub enum AdminCommand {
+    /// Ver cuántos links se han generado y pagado.
+    Stats,
+    /// Ver los links que siguen sin pagar.
+    Pending,
+    /// Enviar un mensaje a todos los usuarios: /broadcast <mensaje>
+    Broadcast(String),
+    /// Reembolsar un pago aprobado: /refund <ticket>
+    Refund(String),
+}
+
+/// The users `ADMIN_IDS` lists, separated by commas.
+pub struct Admins(Vec<UserId>);
+
+impl Admins {
+    pub fn from_env() -> Arc<Admins> {
+        let mut ids = Vec::new();
+        for id in env::var("ADMIN_IDS").unwrap_or_default().split(',') {
+            let id = id.trim();
+            if id.is_empty() {
+                continue;
+            }
+            match id.parse() {
+                Ok(id) => ids.push(UserId(id)),
+                Err(_) => eprintln!("ADMIN_IDS: '{}' is not a user ID", id),
+            }
+        }
+        Arc::new(Admins(ids))
+    }
+
+    pub fn contains(&self, msg: &Message) -> bool {
+        msg.from
+            .as_ref()
+            .is_some_and(|user| self.0.contains(&user.id))
+    }
+}
+
+/// The admin commands, for messages from admins; everyone else gets a
+/// refusal.
+pub fn commands() -> UpdateHandler<anyhow::Error> {
+    let allowed = dptree::filter(|admins: Arc<Admins>, msg: Message| admins.contains(&msg))
+        .branch(dptree::case![AdminCommand::Stats].endpoint(stats))
+        .branch(dptree::case![AdminCommand::Pending].endpoint(pending))
+        .branch(dptree::case![AdminCommand::Broadcast(text)].endpoint(broadcast))
+        .branch(dptree::case![AdminCommand::Refund(ticket)].endpoint(refund));
+
+    teloxide::filter_command::<AdminCommand, _>()
+        .branch(allowed)
+        .branch(dptree::endpoint(refuse))
+}
+
+async fn refuse(bot: Bot, msg: Message) -> HandlerResult {
+    bot.send_message(msg.chat.id, "⛔ Este comando es solo para administradores.")
+        .await?;
+    Ok(())
+}
+
+async fn stats(bot: Bot, db: Db, msg: Message) -> HandlerResult {
+    let stats = db.stats().await?;
+    let conversion = if stats.total == 0 {
+        0.0
+    } else {
+        stats.approved as f64 * 100.0 / stats.total as f64
+    };
+    bot.send_message(
+        msg.chat.id,
+        format!(
+            "📊 Links generados: {}\n\
+             ✅ Pagados: {}\n\
+             ❌ Rechazados: {}\n\
+             ⏳ Pendientes: {}\n\
+             ⌛ Vencidos: {}\n\
+             ↩️ Reembolsados: {}\n\
+             📈 Conversión: {:.1}%\n\
+             👥 Usuarios: {}",
+            stats.total,
+            stats.approved,
+            stats.rejected,
+            stats.pending,
+            stats.expired,
+            stats.refunded,
+            conversion,
+            stats.chats
+        ),
+    )
+    .await?;
+    Ok(())
+}
+
+async fn pending(bot: Bot, db: Db, msg: Message) -> HandlerResult {
+    let links = db.pending_links(PENDING_LIMIT).await?;
+    if links.is_empty() {
+        bot.send_message(msg.chat.id, "No hay links pendientes de pago.")
+            .await?;
+        return Ok(());
+    }
+    let mut text = "⏳ Links sin pagar, del más antiguo:\n".to_string();
+    for link in &links {
+        text.push_str(&format!(
+            "\n{} · {}\nTicket: {} · Chat: {}\n{}\n",
+            link.reference,
+            link.amount_text(),
+            link.ticket,
+            link.chat_id,
+            format_date(link.created_at)
+        ));
+    }
+    bot.send_message(msg.chat.id, text).await?;
+    Ok(())
+}
+
+/// Sends `text` to every chat that has created a link.
+async fn broadcast(bot: Bot, db: Db, msg: Message, text: String) -> HandlerResult {
+    let text = text.trim();
+    if text.is_empty() {
+        bot.send_message(
+            msg.chat.id,
+            "Escribe el mensaje después del comando, por ejemplo: /broadcast Hola a todos",
+        )
+        .await?;
+        return Ok(());
+    }
+    let chats = db.chat_ids().await?;
+    let mut failed = 0;
+    for &chat in &chats {
+        if let Err(e) = bot.send_message(ChatId(chat), text).await {
+            eprintln!("Couldn't broadcast to chat {}: {}", chat, e);
+            failed += 1;
+        }
+        tokio::time::sleep(BROADCAST_PAUSE).await;
+    }
+    bot.send_message(
+        msg.chat.id,
+        format!(
+            "📣 Mensaje enviado a {} de {} chats.",
+            chats.len() - failed,
+            chats.len()
+        ),
+    )
+    .await?;
+    Ok(())
+}
+
+/// Gives back a paid link's amount through the provider that made it. The
+/// chat that paid is told once the provider says the refund is complete,
+/// now or in a later `REFUNDED` callback; until then the admin is told it
+/// is pending.
+async fn refund(
+    bot: Bot,
+    db: Db,
+    gateways: Arc<Gateways>,
+    in_flight: Arc<InFlight>,
+    msg: Message,
+    ticket: String,
+) -> HandlerResult {
+    let ticket = ticket.trim();
+    if ticket.is_empty() {
+        bot.send_message(
+            msg.chat.id,
+            "Escribe el ticket del pago, por ejemplo: /refund T-1001",
+        )
+        .await?;
+        return Ok(());
+    }
+    // Two admins refunding the same payment at once mustn't refund it twice.
+    let Some(_claim) = in_flight.claim(&format!("refund:{}", ticket)) else {
+        bot.send_message(msg.chat.id, "⏳ Ya se está reembolsando ese pago.")
+            .await?;
+        return Ok(());
+    };
+    let Some(link) = db.link_by_ticket(ticket).await? else {
+        bot.send_message(
+            msg.chat.id,
+            format!("🔍 No hay ningún pago con el ticket {}.", ticket),
+        )
+        .await?;
+        return Ok(());
+    };
+    if link.status != LinkStatus::Approved {
+        bot.send_message(
+            msg.chat.id,
+            format!(
+                "Solo se pueden reembolsar pagos aprobados.\nTicket {}: {}",
+                ticket,
+                status_label(link.status)
+            ),
+        )
+        .await?;
+        return Ok(());
+    }
+    if let Some(id) = &link.refund_id {
+        bot.send_message(
+            msg.chat.id,
+            format!(
+                "⏳ Este pago ya tiene un reembolso pendiente en la pasarela.\nTicket: {}\nID del reembolso: {}\nEstado: {}",
+                ticket,
+                id,
+                link.refund_status.as_deref().unwrap_or("?")
+            ),
+        )
+        .await?;
+        return Ok(());
+    }
+    let Some(gateway) = gateways.get(&link.provider) else {
+        bot.send_message(
+            msg.chat.id,
+            format!(
+                "⚠️ La pasarela '{}' que generó este pago ya no está configurada.",
+                link.provider
+            ),
+        )
+        .await?;
+        return Ok(());
+    };
+    let refund = match gateway.refund(ticket).await {
+        Ok(refund) => refund,
+        Err(e) => {
+            eprintln!("Couldn't refund {}: {}", ticket, e);
+            let text = match e {
+                // It may have gone through, so it isn't for trying again.
+                GatewayError::Unavailable => format!(
+                    "⚠️ La pasarela no respondió. Revisa en ella si el pago {} se reembolsó antes de intentarlo de nuevo.",
+                    ticket
+                ),
+                e => format!("❌ No se pudo reembolsar el pago {}: {}", ticket, e),
+            };
+            bot.send_message(msg.chat.id, text).await?;
+            return Ok(());
+        }
+    };
+    println!(
+        "Refund of {} asked as {} ({})",
+        ticket, refund.id, refund.status
+    );
+    // Not recorded, so the payment can still be refunded later.
+    if refund.is_failed() {
+        bot.send_message(
+            msg.chat.id,
+            format!(
+                "❌ La pasarela rechazó el reembolso del pago {}.\nID del reembolso: {}\nEstado: {}\n\nPuedes intentarlo de nuevo con /refund {}.",
+                ticket, refund.id, refund.status, ticket
+            ),
+        )
+        .await?;
+        return Ok(());
+    }
+    let done = refund.is_complete();
+    match db
+        .record_refund(ticket, &refund.id, &refund.status, done)
+        .await
+    {
+        Ok(Some(link)) if done => ipn::tell_chat(&bot, &link).await,
+        // Pending: the chat hears when the callback confirms it.
+        Ok(Some(_)) => {}
+        // A callback may have recorded it meanwhile, and told the chat.
+        Ok(None) => {}
+        Err(e) => eprintln!("Couldn't record the refund of {}: {}", ticket, e),
+    }
+    let heading = if done {
+        "↩️ Reembolso realizado"
+    } else {
+        "⏳ Reembolso pendiente en la pasarela; se avisará al cliente cuando se confirme"
+    };
+    bot.send_message(
+        msg.chat.id,
+        format!(
+            "{}\nTicket: {}\nReferencia: {}\nMonto: {}\nID del reembolso: {}\nEstado: {}",
+            heading,
+            ticket,
+            link.reference,
+            link.amount_text(),
+            refund.id,
+            refund.status
+        ),
+    )
+    .await?;
+    Ok(())
+}
@@ -0,0 +1,219 @@
+//! The settings creating a payment link needs, read and checked once at
+//! startup so a bad one stops the bot there rather than in the middle of
+//! a customer's conversation.
+
+use crate::{ipn, PaymentMethod};
+use reqwest::Url;
+use std::env;
+use std::fmt;
+use std::time::Duration;
+
+/// What the provider set by the `GATEWAY_*` variables without a name is
+/// called.
+pub const DEFAULT_GATEWAY: &str = "main";
+
+const DEFAULT_LINK_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// A payment provider's settings. The default one is read from
+/// `GATEWAY_API_URL`, `GATEWAY_USER`, `GATEWAY_PASSWORD` and
+/// `GATEWAY_TOKEN`; one called e.g. `backup` from `GATEWAY_BACKUP_API_URL`,
+/// `GATEWAY_BACKUP_USER` and so on.
+pub struct GatewaySettings {
+    pub name: String,
+    /// e.g. `https://api.gateway.example`, without the `/api/v1/...` part.
+    pub url: Url,
+    pub user: String,
+    pub password: String,
+    pub token: String,
+}
+
+pub struct Config {
+    /// The default provider, then the ones named in `GATEWAYS`, separated
+    /// by commas.
+    pub gateways: Vec<GatewaySettings>,
+    /// Which provider creates the links for a payment method, as payment
+    /// method code and provider name pairs from `GATEWAY_ROUTES`, e.g.
+    /// `CARD=backup,PSE=backup`. Other methods go to the default.
+    pub gateway_routes: Vec<(String, String)>,
+    /// Where this bot can be reached from outside, e.g.
+    /// `https://bot.example.com`.
+    pub public_url: Url,
+    /// Where the gateway sends the customer after paying.
+    pub redirect_url: Url,
+    /// Where the gateway posts the payment's outcome: `IPN_URL`, or
+    /// `PUBLIC_URL` followed by `IPN_PATH`.
+    pub ipn_url: Url,
+    /// How long a link takes payments when the gateway doesn't say:
+    /// `LINK_TTL_MINUTES`, default a day.
+    pub link_ttl: Duration,
+}
+
+/// Everything wrong with the environment, not just the first thing.
+#[derive(Debug)]
+pub struct ConfigError(Vec<String>);
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "the configuration is not valid:")?;
+        for problem in &self.0 {
+            writeln!(f, "  - {}", problem)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl Config {
+    pub fn from_env() -> Result<Config, ConfigError> {
+        let mut problems = Vec::new();
+        // Read by `Bot::from_env`, which would panic without it.
+        text(&mut problems, "TELOXIDE_TOKEN");
+        let mut gateways = Vec::new();
+        let mut names = vec![DEFAULT_GATEWAY.to_string()];
+        for name in env::var("GATEWAYS").unwrap_or_default().split(',') {
+            let name = name.trim().to_ascii_lowercase();
+            if name.is_empty() {
+                continue;
+            }
+            if !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+                problems.push(format!(
+                    "GATEWAYS: '{}' may only have letters, digits and _",
+                    name
+                ));
+            } else if names.contains(&name) {
+                problems.push(format!("GATEWAYS: '{}' is named twice", name));
+            } else {
+                names.push(name);
+            }
+        }
+        for name in &names {
+            gateways.extend(gateway(&mut problems, name));
+        }
+        let gateway_routes = routes(&mut problems, &names);
+        let public_url = url(&mut problems, "PUBLIC_URL");
+        let redirect_url = url(&mut problems, "REDIRECT_URL");
+        let link_ttl = match env::var("LINK_TTL_MINUTES") {
+            Ok(value) => match value.trim().parse::<u64>() {
+                Ok(minutes) if minutes > 0 => Duration::from_secs(minutes * 60),
+                _ => {
+                    problems.push("LINK_TTL_MINUTES must be a positive whole number".to_string());
+                    Duration::ZERO
+                }
+            },
+            Err(_) => DEFAULT_LINK_TTL,
+        };
+        let ipn_url = match env::var("IPN_URL") {
+            Ok(_) => url(&mut problems, "IPN_URL"),
+            Err(_) => public_url.as_ref().and_then(|public| {
+                let joined = format!("{}{}", public.as_str().trim_end_matches('/'), ipn::path());
+                Url::parse(&joined)
+                    .map_err(|e| problems.push(format!("IPN_PATH: {}", e)))
+                    .ok()
+            }),
+        };
+
+        // A URL that couldn't be read has left a problem behind.
+        let (Some(public_url), Some(redirect_url), Some(ipn_url)) =
+            (public_url, redirect_url, ipn_url)
+        else {
+            return Err(ConfigError(problems));
+        };
+        if !problems.is_empty() {
+            return Err(ConfigError(problems));
+        }
+        Ok(Config {
+            gateways,
+            gateway_routes,
+            public_url,
+            redirect_url,
+            ipn_url,
+            link_ttl,
+        })
+    }
+}
+
+/// The settings of the provider `name`, if they could all be read.
+fn gateway(problems: &mut Vec<String>, name: &str) -> Option<GatewaySettings> {
+    let prefix = if name == DEFAULT_GATEWAY {
+        "GATEWAY".to_string()
+    } else {
+        format!("GATEWAY_{}", name.to_ascii_uppercase())
+    };
+    let url = url(problems, &format!("{}_API_URL", prefix));
+    let user = text(problems, &format!("{}_USER", prefix));
+    let password = text(problems, &format!("{}_PASSWORD", prefix));
+    let token = text(problems, &format!("{}_TOKEN", prefix));
+    Some(GatewaySettings {
+        name: name.to_string(),
+        url: url?,
+        user,
+        password,
+        token,
+    })
+}
+
+/// `GATEWAY_ROUTES`, whose providers must be among `names`.
+fn routes(problems: &mut Vec<String>, names: &[String]) -> Vec<(String, String)> {
+    let mut routes = Vec::new();
+    for route in env::var("GATEWAY_ROUTES").unwrap_or_default().split(',') {
+        let route = route.trim();
+        if route.is_empty() {
+            continue;
+        }
+        let Some((method, name)) = route.split_once('=') else {
+            problems.push(format!("GATEWAY_ROUTES: '{}' is not METHOD=gateway", route));
+            continue;
+        };
+        let method = method.trim().to_ascii_uppercase();
+        let name = name.trim().to_ascii_lowercase();
+        if PaymentMethod::from_code(&method).is_none() {
+            problems.push(format!(
+                "GATEWAY_ROUTES: '{}' is not a payment method",
+                method
+            ));
+        } else if !names.contains(&name) {
+            problems.push(format!(
+                "GATEWAY_ROUTES: there is no gateway called '{}'",
+                name
+            ));
+        } else {
+            routes.push((method, name));
+        }
+    }
+    routes
+}
+
+/// The variable `name`, which must be set and not blank.
+fn text(problems: &mut Vec<String>, name: &str) -> String {
+    match env::var(name) {
+        Ok(value) if !value.trim().is_empty() => value.trim().to_string(),
+        Ok(_) => {
+            problems.push(format!("{} is empty", name));
+            String::new()
+        }
+        Err(_) => {
+            problems.push(format!("{} is not set", name));
+            String::new()
+        }
+    }
+}
+
+/// The variable `name`, which must be an http or https URL.
+fn url(problems: &mut Vec<String>, name: &str) -> Option<Url> {
+    let value = text(problems, name);
+    if value.is_empty() {
+        return None;
+    }
+    match Url::parse(&value) {
+        Ok(url) if matches!(url.scheme(), "http" | "https") => Some(url),
+        Ok(_) => {
+            problems.push(format!("{} must be an http or https URL", name));
+            None
+        }
+        Err(e) => {
+            problems.push(format!("{} is not a valid URL ({}): {}", name, e, value));
+            None
+        }
+    }
+}
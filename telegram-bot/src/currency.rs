@@ -0,0 +1,288 @@
+//! The currencies links can be made in, and the amounts the customer types
+//! in them, checked against the limits set in the environment.
+
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::sync::OnceLock;
+
+/// Amounts are kept in the currency's smallest unit (pesos, cents), which
+/// is also what the gateway is sent.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum Currency {
+    #[default]
+    Cop,
+    Usd,
+}
+
+impl Currency {
+    const ALL: [Currency; 2] = [Currency::Cop, Currency::Usd];
+
+    /// The ISO code, as the gateway and `PAY_CURRENCIES` write it, also used
+    /// as the button's callback data.
+    pub fn code(self) -> &'static str {
+        match self {
+            Currency::Cop => "COP",
+            Currency::Usd => "USD",
+        }
+    }
+
+    pub fn from_code(code: &str) -> Option<Currency> {
+        let code = code.trim();
+        Currency::ALL
+            .into_iter()
+            .find(|c| c.code().eq_ignore_ascii_case(code))
+    }
+
+    /// The currencies offered, from `PAY_CURRENCIES` (default `COP,USD`).
+    pub fn enabled() -> &'static [Currency] {
+        static ENABLED: OnceLock<Vec<Currency>> = OnceLock::new();
+        ENABLED.get_or_init(|| {
+            let Ok(codes) = env::var("PAY_CURRENCIES") else {
+                return Currency::ALL.to_vec();
+            };
+            let mut enabled = Vec::new();
+            for code in codes.split(',').filter(|c| !c.trim().is_empty()) {
+                match Currency::from_code(code) {
+                    Some(c) if !enabled.contains(&c) => enabled.push(c),
+                    Some(_) => {}
+                    None => eprintln!("PAY_CURRENCIES: unknown currency '{}'", code.trim()),
+                }
+            }
+            if enabled.is_empty() {
+                enabled.push(Currency::Cop);
+            }
+            enabled
+        })
+    }
+
+    fn decimals(self) -> u32 {
+        match self {
+            Currency::Cop => 0,
+            Currency::Usd => 2,
+        }
+    }
+
+    /// The thousands and decimal separators written for the currency.
+    fn separators(self) -> (char, char) {
+        match self {
+            Currency::Cop => ('.', ','),
+            Currency::Usd => (',', '.'),
+        }
+    }
+
+    fn default_limits(self) -> (u64, u64) {
+        match self {
+            Currency::Cop => (1_000, 10_000_000),
+            // In cents: US$1.00 to US$5,000.00.
+            Currency::Usd => (100, 500_000),
+        }
+    }
+
+    /// The smallest and largest amount a link can be for, from
+    /// `PAY_MIN_AMOUNT_<CODE>` and `PAY_MAX_AMOUNT_<CODE>`, written as the
+    /// customer would, e.g. `PAY_MAX_AMOUNT_USD=2500.00`.
+    pub fn limits(self) -> (u64, u64) {
+        static LIMITS: OnceLock<Vec<(u64, u64)>> = OnceLock::new();
+        let all = LIMITS.get_or_init(|| {
+            Currency::ALL
+                .iter()
+                .map(|&c| {
+                    let (min, max) = c.default_limits();
+                    (c.limit_from_env("MIN", min), c.limit_from_env("MAX", max))
+                })
+                .collect()
+        });
+        all[Currency::ALL.iter().position(|&c| c == self).unwrap_or(0)]
+    }
+
+    fn limit_from_env(self, which: &str, default: u64) -> u64 {
+        let name = format!("PAY_{}_AMOUNT_{}", which, self.code());
+        match env::var(&name) {
+            Ok(value) => self.read(&value).unwrap_or_else(|| {
+                eprintln!(
+                    "{} is not a valid amount, using {}",
+                    name,
+                    self.format(default)
+                );
+                default
+            }),
+            Err(_) => default,
+        }
+    }
+
+    /// Reads `text` in the smallest unit: `50000`, `50.000`, `$ 50,000` or
+    /// `50.000,00` for pesos, `25`, `25.5` or `1,200.00` for dollars.
+    fn read(self, text: &str) -> Option<u64> {
+        let text: String = text
+            .trim()
+            .trim_start_matches("US")
+            .trim_start_matches('$')
+            .chars()
+            .filter(|&c| c != ' ')
+            .collect();
+        if self.decimals() == 0 {
+            return read_whole(&text);
+        }
+        let (thousands, point) = self.separators();
+        let text: String = text.chars().filter(|&c| c != thousands).collect();
+        let (whole, fraction) = text.split_once(point).unwrap_or((&text, ""));
+        let digits = |s: &str| s.chars().all(|c| c.is_ascii_digit());
+        if whole.is_empty() || !digits(whole) || !digits(fraction) {
+            return None;
+        }
+        if fraction.len() > self.decimals() as usize {
+            return None;
+        }
+        let scale = 10u64.pow(self.decimals());
+        let fraction = format!("{:0<width$}", fraction, width = self.decimals() as usize);
+        let cents: u64 = if fraction.is_empty() {
+            0
+        } else {
+            fraction.parse().ok()?
+        };
+        whole
+            .parse::<u64>()
+            .ok()?
+            .checked_mul(scale)?
+            .checked_add(cents)
+    }
+
+    /// Reads the amount the customer typed and checks it is within limits.
+    pub fn parse(self, text: &str) -> Result<u64, String> {
+        let Some(amount) = self.read(text) else {
+            let example = match self {
+                Currency::Cop => "50000",
+                Currency::Usd => "25.50",
+            };
+            return Err(format!(
+                "❌ Escribe el monto solo con números, por ejemplo {}.",
+                example
+            ));
+        };
+        let (min, max) = self.limits();
+        if amount < min || amount > max {
+            return Err(format!(
+                "❌ El monto debe estar entre {} y {}.",
+                self.format(min),
+                self.format(max)
+            ));
+        }
+        Ok(amount)
+    }
+
+    /// e.g. `$50.000 COP` or `US$1,200.50`.
+    pub fn format(self, amount: u64) -> String {
+        let (thousands, point) = self.separators();
+        let scale = 10u64.pow(self.decimals());
+        let digits = (amount / scale).to_string();
+        let mut grouped = String::new();
+        for (i, c) in digits.chars().enumerate() {
+            if i > 0 && (digits.len() - i).is_multiple_of(3) {
+                grouped.push(thousands);
+            }
+            grouped.push(c);
+        }
+        if self.decimals() > 0 {
+            let width = self.decimals() as usize;
+            grouped = format!("{}{}{:0width$}", grouped, point, amount % scale);
+        }
+        match self {
+            Currency::Cop => format!("${} COP", grouped),
+            Currency::Usd => format!("US${}", grouped),
+        }
+    }
+}
+
+/// An amount in a currency without decimals, written with either separator
+/// for thousands. A separator followed by one or two digits starts
+/// decimals, as in `50.000,00`; they can only be zeros.
+fn read_whole(text: &str) -> Option<u64> {
+    let mut whole = text;
+    if let Some(at) = text.rfind(['.', ',']) {
+        let fraction = &text[at + 1..];
+        if (1..=2).contains(&fraction.len()) {
+            if !fraction.chars().all(|c| c == '0') {
+                return None;
+            }
+            whole = &text[..at];
+            // `50,000,00` has no decimal separator of its own.
+            if whole.contains(&text[at..=at]) {
+                return None;
+            }
+        }
+    }
+    let separators: Vec<char> = whole.chars().filter(|&c| c == '.' || c == ',').collect();
+    let groups: Vec<&str> = whole.split(['.', ',']).collect();
+    let grouped = separators.windows(2).all(|w| w[0] == w[1])
+        && (1..=3).contains(&groups[0].len())
+        && groups[1..].iter().all(|g| g.len() == 3);
+    if !separators.is_empty() && !grouped {
+        return None;
+    }
+    let digits = groups.concat();
+    if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    digits.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_pesos_with_either_separator() {
+        let cop = Currency::Cop;
+        assert_eq!(cop.read("50000"), Some(50_000));
+        assert_eq!(cop.read("50.000"), Some(50_000));
+        assert_eq!(cop.read("$ 50,000"), Some(50_000));
+        assert_eq!(cop.read("1.250.000"), Some(1_250_000));
+        assert_eq!(cop.read("50.000,00"), Some(50_000));
+        assert_eq!(cop.read("50,000.00"), Some(50_000));
+        assert_eq!(cop.read("50000,0"), Some(50_000));
+    }
+
+    #[test]
+    fn rejects_pesos_with_a_fraction_or_odd_grouping() {
+        let cop = Currency::Cop;
+        assert_eq!(cop.read("50000,5"), None);
+        assert_eq!(cop.read("50.000,50"), None);
+        assert_eq!(cop.read("50,000,00"), None);
+        assert_eq!(cop.read("5.0000"), None);
+        assert_eq!(cop.read("50.000.00.0"), None);
+        assert_eq!(cop.read("1.000,000"), None);
+        assert_eq!(cop.read(".500"), None);
+        assert_eq!(cop.read("abc"), None);
+        assert_eq!(cop.read(""), None);
+    }
+
+    #[test]
+    fn reads_dollars_in_cents() {
+        let usd = Currency::Usd;
+        assert_eq!(usd.read("25"), Some(2_500));
+        assert_eq!(usd.read("25.5"), Some(2_550));
+        assert_eq!(usd.read("US$1,200.00"), Some(120_000));
+        assert_eq!(usd.read("25.505"), None);
+        assert_eq!(usd.read("1.200,50"), None);
+        assert_eq!(usd.read("25."), Some(2_500));
+    }
+
+    #[test]
+    fn formats_what_it_reads() {
+        assert_eq!(Currency::Cop.format(50_000), "$50.000 COP");
+        assert_eq!(Currency::Cop.format(999), "$999 COP");
+        assert_eq!(Currency::Usd.format(120_050), "US$1,200.50");
+        assert_eq!(Currency::Usd.format(5), "US$0.05");
+        for amount in [1_000, 1_234_567, 10_000_000] {
+            let text = Currency::Cop.format(amount);
+            assert_eq!(
+                Currency::Cop.read(text.trim_end_matches(" COP")),
+                Some(amount)
+            );
+        }
+        assert_eq!(
+            Currency::Usd.read(&Currency::Usd.format(120_050)),
+            Some(120_050)
+        );
+    }
+}
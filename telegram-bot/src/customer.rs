@@ -0,0 +1,196 @@
+//! Checks for the customer data the payment conversation asks for. Each
+//! returns the value as the gateway wants it, or the message to send back.
+
+use regex::Regex;
+use std::sync::OnceLock;
+
+/// Identity documents the gateway accepts.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DocType {
+    Cc,
+    Ce,
+    Nit,
+    Ti,
+    Pp,
+}
+
+impl DocType {
+    pub const ALL: [DocType; 5] = [
+        DocType::Cc,
+        DocType::Ce,
+        DocType::Nit,
+        DocType::Ti,
+        DocType::Pp,
+    ];
+
+    /// The value of `legal_doc_type`, also used as the button's callback
+    /// data.
+    pub fn code(self) -> &'static str {
+        match self {
+            DocType::Cc => "CC",
+            DocType::Ce => "CE",
+            DocType::Nit => "NIT",
+            DocType::Ti => "TI",
+            DocType::Pp => "PP",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            DocType::Cc => "Cédula de ciudadanía",
+            DocType::Ce => "Cédula de extranjería",
+            DocType::Nit => "NIT",
+            DocType::Ti => "Tarjeta de identidad",
+            DocType::Pp => "Pasaporte",
+        }
+    }
+
+    /// Reads a button's data or a typed code such as `cc`.
+    pub fn from_code(code: &str) -> Option<DocType> {
+        let code = code.trim();
+        DocType::ALL
+            .into_iter()
+            .find(|d| d.code().eq_ignore_ascii_case(code))
+    }
+}
+
+/// At least a first and a last name, letters only, with single spaces.
+pub fn full_name(text: &str) -> Result<String, &'static str> {
+    let words: Vec<&str> = text.split_whitespace().collect();
+    if words.len() < 2 {
+        return Err("❌ Escribe tu nombre y apellido.");
+    }
+    let name = words.join(" ");
+    if name.chars().count() > 80 {
+        return Err("❌ El nombre es demasiado largo (máximo 80 caracteres).");
+    }
+    if !name
+        .chars()
+        .all(|c| c.is_alphabetic() || matches!(c, ' ' | '\'' | '-' | '.'))
+    {
+        return Err("❌ El nombre solo puede tener letras.");
+    }
+    Ok(name)
+}
+
+/// A passport number is 5 to 12 letters and digits; every other document
+/// 5 to 12 digits. Dots, dashes and spaces are dropped.
+pub fn document(doc_type: DocType, text: &str) -> Result<String, &'static str> {
+    let number: String = text
+        .chars()
+        .filter(|c| !matches!(c, '.' | '-' | ' '))
+        .collect();
+    if doc_type == DocType::Pp {
+        if (5..=12).contains(&number.len()) && number.chars().all(|c| c.is_ascii_alphanumeric()) {
+            return Ok(number.to_uppercase());
+        }
+        return Err("❌ El pasaporte debe tener entre 5 y 12 letras o números.");
+    }
+    if (5..=12).contains(&number.len()) && number.chars().all(|c| c.is_ascii_digit()) {
+        return Ok(number);
+    }
+    Err("❌ El número de documento debe tener entre 5 y 12 dígitos.")
+}
+
+pub fn email(text: &str) -> Result<String, &'static str> {
+    static EMAIL: OnceLock<Regex> = OnceLock::new();
+    let email = EMAIL.get_or_init(|| Regex::new(r"^[^\s@]+@[^\s@]+\.[^\s@]{2,}$").unwrap());
+    let text = text.trim();
+    if email.is_match(text) {
+        Ok(text.to_lowercase())
+    } else {
+        Err("❌ Ese correo no es válido. Ejemplo: nombre@correo.com")
+    }
+}
+
+/// A mobile number, with an optional `+code` in front (default 57), as
+/// `(phone_code, phone_number)`.
+pub fn phone(text: &str) -> Result<(String, String), &'static str> {
+    const INVALID: &str =
+        "❌ Ese teléfono no es válido. Ejemplo: 3001234567, o +1 5551234567 si no es de Colombia.";
+    let text = text.trim();
+    let (code, number) = match text.strip_prefix('+') {
+        Some(rest) => rest.split_once([' ', '-']).ok_or(INVALID)?,
+        None => ("57", text),
+    };
+    let number: String = number.chars().filter(|c| !matches!(c, ' ' | '-')).collect();
+    let digits = |s: &str| s.chars().all(|c| c.is_ascii_digit());
+    if !(1..=3).contains(&code.len()) || !digits(code) {
+        return Err(INVALID);
+    }
+    if !(7..=12).contains(&number.len()) || !digits(&number) {
+        return Err(INVALID);
+    }
+    Ok((code.to_string(), number))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_need_two_words_of_letters() {
+        assert_eq!(
+            full_name("  Ana   María  Pérez "),
+            Ok("Ana María Pérez".into())
+        );
+        assert_eq!(
+            full_name("John O'Neil-Smith Jr."),
+            Ok("John O'Neil-Smith Jr.".into())
+        );
+        assert!(full_name("Ana").is_err());
+        assert!(full_name("").is_err());
+        assert!(full_name("Ana P3rez").is_err());
+        assert!(full_name("Ana @perez").is_err());
+        assert!(full_name(&format!("Ana {}", "a".repeat(80))).is_err());
+    }
+
+    #[test]
+    fn documents_are_5_to_12_digits() {
+        assert_eq!(
+            document(DocType::Cc, "1.020.304.050"),
+            Ok("1020304050".into())
+        );
+        assert_eq!(
+            document(DocType::Nit, "900123456-7"),
+            Ok("9001234567".into())
+        );
+        assert!(document(DocType::Cc, "1234").is_err());
+        assert!(document(DocType::Cc, "1234567890123").is_err());
+        assert!(document(DocType::Ti, "12A456").is_err());
+    }
+
+    #[test]
+    fn passports_may_have_letters() {
+        assert_eq!(document(DocType::Pp, "ab 12345"), Ok("AB12345".into()));
+        assert!(document(DocType::Pp, "AB1").is_err());
+        assert!(document(DocType::Pp, "AB12345#").is_err());
+        assert!(document(DocType::Pp, "ñ1234567").is_err());
+    }
+
+    #[test]
+    fn emails_are_trimmed_and_lowercased() {
+        assert_eq!(email(" Ana@Example.COM "), Ok("ana@example.com".into()));
+        assert!(email("ana@example").is_err());
+        assert!(email("ana.example.com").is_err());
+        assert!(email("ana @example.com").is_err());
+        assert!(email("ana@example.c").is_err());
+    }
+
+    #[test]
+    fn phones_default_to_colombia() {
+        assert_eq!(
+            phone("300 123 4567"),
+            Ok(("57".into(), "3001234567".into()))
+        );
+        assert_eq!(
+            phone("+1 555-123-4567"),
+            Ok(("1".into(), "5551234567".into()))
+        );
+        assert!(phone("+15551234567").is_err());
+        assert!(phone("+1234 5551234567").is_err());
+        assert!(phone("300123").is_err());
+        assert!(phone("3001234567890").is_err());
+        assert!(phone("300-abc-4567").is_err());
+    }
+}
@@ -0,0 +1,655 @@
+//! The bot's SQLite database: every payment link created, the orders
+//! references are checked against when `REFERENCE_SOURCE=db`, and the
+//! sessions when `SESSION_STORE=sqlite`.
+
+use crate::currency::Currency;
+use crate::reference::Order;
+use crate::session::{Session, SessionStore};
+use crate::CustomerData;
+use async_trait::async_trait;
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePool, SqlitePoolOptions, SqliteRow};
+use sqlx::Row;
+use std::env;
+use std::io;
+use std::time::{SystemTime, UNIX_EPOCH};
+use teloxide::types::ChatId;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS sessions (
+    chat_id INTEGER PRIMARY KEY,
+    state TEXT NOT NULL,
+    touched INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS transactions (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    chat_id INTEGER NOT NULL,
+    reference TEXT NOT NULL,
+    gateway_reference TEXT NOT NULL,
+    ticket TEXT NOT NULL,
+    amount INTEGER NOT NULL,
+    currency TEXT NOT NULL,
+    payment_method TEXT NOT NULL,
+    payment_url TEXT NOT NULL,
+    status TEXT NOT NULL,
+    created_at INTEGER NOT NULL,
+    updated_at INTEGER NOT NULL
+);
+-- Filled by the merchant. A NULL amount lets the customer type it; payer
+-- columns are all set or all left NULL.
+CREATE TABLE IF NOT EXISTS orders (
+    reference TEXT PRIMARY KEY,
+    amount INTEGER,
+    currency TEXT,
+    full_name TEXT,
+    legal_doc_type TEXT,
+    legal_doc TEXT,
+    email TEXT,
+    phone_code TEXT,
+    phone_number TEXT
+);
+CREATE INDEX IF NOT EXISTS transactions_chat ON transactions (chat_id, created_at);
+CREATE INDEX IF NOT EXISTS transactions_ticket ON transactions (ticket);
+CREATE INDEX IF NOT EXISTS transactions_gateway_reference ON transactions (gateway_reference);
+";
+
+/// Changes to the tables above, made once each and in order; a database's
+/// `user_version` is how many it has had.
+const MIGRATIONS: &[&str] = &[
+    // Links made before there was more than one provider were all made by
+    // the default one.
+    "ALTER TABLE transactions ADD COLUMN provider TEXT NOT NULL DEFAULT 'main';",
+    "ALTER TABLE transactions ADD COLUMN idempotency_key TEXT;
+     CREATE INDEX transactions_idempotency_key ON transactions (idempotency_key);",
+    // Links made before these were kept never expire, nor can be renewed.
+    "ALTER TABLE transactions ADD COLUMN expires_at INTEGER;
+     ALTER TABLE transactions ADD COLUMN customer TEXT;",
+    "ALTER TABLE transactions ADD COLUMN refund_id TEXT;
+     ALTER TABLE transactions ADD COLUMN refund_status TEXT;",
+    // Callbacks find their link by the gateway reference, so no two links
+    // may share one. Links that did, from when it was partly random, keep
+    // the oldest one; the others are set apart by their ID.
+    "UPDATE transactions SET gateway_reference = gateway_reference || '#' || id
+     WHERE id NOT IN (SELECT MIN(id) FROM transactions GROUP BY gateway_reference);
+     DROP INDEX IF EXISTS transactions_gateway_reference;
+     CREATE UNIQUE INDEX transactions_gateway_reference ON transactions (gateway_reference);",
+];
+
+/// A payment link the gateway created, as it is recorded.
+pub struct NewLink<'a> {
+    pub chat_id: i64,
+    /// The order's reference, as the customer typed it.
+    pub reference: &'a str,
+    /// The reference the gateway was sent, which its callbacks repeat.
+    pub gateway_reference: &'a str,
+    pub ticket: &'a str,
+    /// In the currency's smallest unit.
+    pub amount: u64,
+    pub currency: &'a str,
+    pub payment_method: &'a str,
+    pub payment_url: &'a str,
+    /// The name of the payment provider that created it.
+    pub provider: &'a str,
+    /// From `gateway::idempotency_key`.
+    pub idempotency_key: &'a str,
+    /// Seconds since the epoch.
+    pub expires_at: i64,
+    /// Who is paying, kept so the link can be renewed.
+    pub customer: &'a CustomerData,
+}
+
+/// Where a link's payment stands, as the gateway last said.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LinkStatus {
+    Pending,
+    Approved,
+    Rejected,
+    /// Went unpaid past its expiry.
+    Expired,
+    /// Was paid, then given back. No later callback changes it.
+    Refunded,
+}
+
+impl LinkStatus {
+    fn as_str(self) -> &'static str {
+        match self {
+            LinkStatus::Pending => "pending",
+            LinkStatus::Approved => "approved",
+            LinkStatus::Rejected => "rejected",
+            LinkStatus::Expired => "expired",
+            LinkStatus::Refunded => "refunded",
+        }
+    }
+
+    fn from_str(status: &str) -> Option<LinkStatus> {
+        [
+            LinkStatus::Pending,
+            LinkStatus::Approved,
+            LinkStatus::Rejected,
+            LinkStatus::Expired,
+            LinkStatus::Refunded,
+        ]
+        .into_iter()
+        .find(|s| s.as_str() == status)
+    }
+}
+
+/// A recorded link, as its chat is told about it.
+pub struct Link {
+    pub chat_id: i64,
+    pub reference: String,
+    pub ticket: String,
+    /// In the currency's smallest unit.
+    pub amount: u64,
+    pub currency: String,
+    pub status: LinkStatus,
+    /// Seconds since the epoch.
+    pub created_at: i64,
+    pub updated_at: i64,
+    pub provider: String,
+    pub payment_url: String,
+    /// `None` for links made before expiries were kept.
+    pub expires_at: Option<i64>,
+    /// The refund asked for this link, if any, and its status as the
+    /// provider last gave it.
+    pub refund_id: Option<String>,
+    pub refund_status: Option<String>,
+}
+
+impl Link {
+    /// e.g. `$50.000 COP`.
+    pub fn amount_text(&self) -> String {
+        Currency::from_code(&self.currency)
+            .map(|c| c.format(self.amount))
+            .unwrap_or_else(|| format!("{} {}", self.amount, self.currency))
+    }
+}
+
+/// How the links created so far have fared.
+pub struct Stats {
+    pub total: u64,
+    pub approved: u64,
+    pub rejected: u64,
+    pub pending: u64,
+    pub expired: u64,
+    pub refunded: u64,
+    /// Chats that have created at least one link.
+    pub chats: u64,
+}
+
+/// The columns `Link::from_row` reads, in order.
+const LINK_COLUMNS: &str = "chat_id, reference, ticket, amount, currency, status, created_at, \
+                            updated_at, provider, payment_url, expires_at, refund_id, refund_status";
+
+impl Link {
+    fn from_row(row: &SqliteRow) -> sqlx::Result<Link> {
+        let status: String = row.try_get(5)?;
+        Ok(Link {
+            chat_id: row.try_get(0)?,
+            reference: row.try_get(1)?,
+            ticket: row.try_get(2)?,
+            amount: row.try_get::<i64, _>(3)? as u64,
+            currency: row.try_get(4)?,
+            status: LinkStatus::from_str(&status).ok_or_else(|| sqlx::Error::ColumnDecode {
+                index: "status".to_string(),
+                source: format!("unknown status '{}'", status).into(),
+            })?,
+            created_at: row.try_get(6)?,
+            updated_at: row.try_get(7)?,
+            provider: row.try_get(8)?,
+            payment_url: row.try_get(9)?,
+            expires_at: row.try_get(10)?,
+            refund_id: row.try_get(11)?,
+            refund_status: row.try_get(12)?,
+        })
+    }
+}
+
+/// A link to make again: `Db::renewal`.
+pub struct Renewal {
+    pub link: Link,
+    pub payment_method: String,
+    pub customer: Option<CustomerData>,
+}
+
+/// What recording a payment's outcome found.
+pub enum Settlement {
+    /// No link has that ticket or reference.
+    Unknown,
+    /// The link already had that status.
+    Unchanged,
+    Changed(Box<Link>),
+}
+
+/// Makes the `MIGRATIONS` `pool`'s database hasn't had yet.
+async fn migrate(pool: &SqlitePool) -> sqlx::Result<()> {
+    let mut tx = pool.begin().await?;
+    let done: i64 = sqlx::query_scalar("PRAGMA user_version")
+        .fetch_one(&mut *tx)
+        .await?;
+    if done as usize >= MIGRATIONS.len() {
+        return Ok(());
+    }
+    for migration in &MIGRATIONS[done as usize..] {
+        sqlx::raw_sql(migration).execute(&mut *tx).await?;
+    }
+    // PRAGMAs don't take parameters.
+    sqlx::raw_sql(&format!("PRAGMA user_version = {}", MIGRATIONS.len()))
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await
+}
+
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs() as i64)
+}
+
+/// Queries run on a pool of connections, so one waiting on a write
+/// doesn't hold up the others.
+#[derive(Clone)]
+pub struct Db {
+    pool: SqlitePool,
+}
+
+impl Db {
+    /// Opens, or creates, the database at `DATABASE_PATH` (default
+    /// `paylink.db`).
+    pub async fn from_env() -> Result<Db, String> {
+        let path = env::var("DATABASE_PATH").unwrap_or_else(|_| "paylink.db".to_string());
+        Db::open(&path)
+            .await
+            .map_err(|e| format!("Can't open the database {}: {}", path, e))
+    }
+
+    pub async fn open(path: &str) -> sqlx::Result<Db> {
+        let options = SqliteConnectOptions::new()
+            .filename(path)
+            .create_if_missing(true);
+        let pool = SqlitePoolOptions::new().connect_with(options).await?;
+        sqlx::raw_sql(SCHEMA).execute(&pool).await?;
+        migrate(&pool).await?;
+        Ok(Db { pool })
+    }
+
+    /// The order `reference` names. One due in a currency this bot doesn't
+    /// know is an error, not a missing order.
+    pub async fn find_order(&self, reference: &str) -> io::Result<Option<Order>> {
+        let row = sqlx::query(
+            "SELECT amount, currency, full_name, legal_doc_type, legal_doc, email,
+                 phone_code, phone_number
+             FROM orders WHERE reference = ?1",
+        )
+        .bind(reference)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(io::Error::other)?;
+        let Some(row) = row else {
+            return Ok(None);
+        };
+        let payer = (|| {
+            Some(CustomerData {
+                full_name: row.try_get::<Option<_>, _>(2).ok()??,
+                legal_doc_type: row.try_get::<Option<_>, _>(3).ok()??,
+                legal_doc: row.try_get::<Option<_>, _>(4).ok()??,
+                email: row.try_get::<Option<_>, _>(5).ok()??,
+                phone_code: row.try_get::<Option<_>, _>(6).ok()??,
+                phone_number: row.try_get::<Option<_>, _>(7).ok()??,
+            })
+        })();
+        let amount: Option<i64> = row.try_get(0).map_err(io::Error::other)?;
+        let currency: Option<String> = row.try_get(1).map_err(io::Error::other)?;
+        let due = match amount.zip(currency) {
+            Some((amount, code)) => {
+                let currency = Currency::from_code(&code).ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("unknown currency '{}'", code),
+                    )
+                })?;
+                Some((currency, amount as u64))
+            }
+            None => None,
+        };
+        Ok(Some(Order { due, payer }))
+    }
+
+    /// Closes the database's connections once the queries on them are
+    /// done. Every write is committed as it is made, so this is only the
+    /// last step of shutting down, once nothing else writes.
+    pub async fn close(&self) {
+        self.pool.close().await
+    }
+
+    /// Records a link as pending payment.
+    pub async fn record_link(&self, link: NewLink<'_>) -> io::Result<()> {
+        let customer = serde_json::to_string(link.customer).map_err(io::Error::other)?;
+        sqlx::query(
+            "INSERT INTO transactions (chat_id, reference, gateway_reference, ticket, amount,
+                 currency, payment_method, payment_url, status, created_at, updated_at,
+                 provider, idempotency_key, expires_at, customer)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?10, ?11, ?12, ?13, ?14)",
+        )
+        .bind(link.chat_id)
+        .bind(link.reference)
+        .bind(link.gateway_reference)
+        .bind(link.ticket)
+        .bind(link.amount as i64)
+        .bind(link.currency)
+        .bind(link.payment_method)
+        .bind(link.payment_url)
+        .bind(LinkStatus::Pending.as_str())
+        .bind(now())
+        .bind(link.provider)
+        .bind(link.idempotency_key)
+        .bind(link.expires_at)
+        .bind(customer)
+        .execute(&self.pool)
+        .await
+        .map(|_| ())
+        .map_err(io::Error::other)
+    }
+
+    pub async fn stats(&self) -> io::Result<Stats> {
+        let row = sqlx::query(
+            "SELECT COUNT(*),
+                 COALESCE(SUM(status = ?1), 0),
+                 COALESCE(SUM(status = ?2), 0),
+                 COALESCE(SUM(status = ?3), 0),
+                 COALESCE(SUM(status = ?4), 0),
+                 COALESCE(SUM(status = ?5), 0),
+                 COUNT(DISTINCT chat_id)
+             FROM transactions",
+        )
+        .bind(LinkStatus::Approved.as_str())
+        .bind(LinkStatus::Rejected.as_str())
+        .bind(LinkStatus::Pending.as_str())
+        .bind(LinkStatus::Expired.as_str())
+        .bind(LinkStatus::Refunded.as_str())
+        .fetch_one(&self.pool)
+        .await
+        .map_err(io::Error::other)?;
+        let count = |i| {
+            row.try_get::<i64, _>(i)
+                .map(|n| n as u64)
+                .map_err(io::Error::other)
+        };
+        Ok(Stats {
+            total: count(0)?,
+            approved: count(1)?,
+            rejected: count(2)?,
+            pending: count(3)?,
+            expired: count(4)?,
+            refunded: count(5)?,
+            chats: count(6)?,
+        })
+    }
+
+    /// Up to `limit` links still waiting to be paid, oldest first.
+    pub async fn pending_links(&self, limit: u32) -> io::Result<Vec<Link>> {
+        let sql = format!(
+            "SELECT {} FROM transactions WHERE status = ?1
+             ORDER BY created_at, id LIMIT ?2",
+            LINK_COLUMNS
+        );
+        sqlx::query(&sql)
+            .bind(LinkStatus::Pending.as_str())
+            .bind(limit)
+            .try_map(|row| Link::from_row(&row))
+            .fetch_all(&self.pool)
+            .await
+            .map_err(io::Error::other)
+    }
+
+    /// Every chat that has created a link.
+    pub async fn chat_ids(&self) -> io::Result<Vec<i64>> {
+        sqlx::query_scalar("SELECT DISTINCT chat_id FROM transactions")
+            .fetch_all(&self.pool)
+            .await
+            .map_err(io::Error::other)
+    }
+
+    /// Up to `limit` of `chat`'s links, newest first, skipping the first
+    /// `offset`.
+    pub async fn list_links(&self, chat: i64, offset: u32, limit: u32) -> io::Result<Vec<Link>> {
+        let sql = format!(
+            "SELECT {} FROM transactions WHERE chat_id = ?1
+             ORDER BY created_at DESC, id DESC LIMIT ?2 OFFSET ?3",
+            LINK_COLUMNS
+        );
+        sqlx::query(&sql)
+            .bind(chat)
+            .bind(limit)
+            .bind(offset)
+            .try_map(|row| Link::from_row(&row))
+            .fetch_all(&self.pool)
+            .await
+            .map_err(io::Error::other)
+    }
+
+    /// The link made for the same payment, by `gateway::idempotency_key`,
+    /// if it is still waiting to be paid and hasn't expired.
+    pub async fn live_link(&self, idempotency_key: &str) -> io::Result<Option<Link>> {
+        let sql = format!(
+            "SELECT {} FROM transactions
+             WHERE idempotency_key = ?1 AND status = ?2
+                 AND (expires_at IS NULL OR expires_at > ?3)
+             ORDER BY created_at DESC, id DESC LIMIT 1",
+            LINK_COLUMNS
+        );
+        sqlx::query(&sql)
+            .bind(idempotency_key)
+            .bind(LinkStatus::Pending.as_str())
+            .bind(now())
+            .try_map(|row| Link::from_row(&row))
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(io::Error::other)
+    }
+
+    /// How many links have been made for the same payment, paid or not.
+    pub async fn count_links(&self, idempotency_key: &str) -> io::Result<u64> {
+        sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM transactions WHERE idempotency_key = ?1")
+            .bind(idempotency_key)
+            .fetch_one(&self.pool)
+            .await
+            .map(|n| n as u64)
+            .map_err(io::Error::other)
+    }
+
+    /// Marks the pending links past their expiry as expired, and says how
+    /// many there were.
+    pub async fn expire_links(&self) -> io::Result<usize> {
+        sqlx::query(
+            "UPDATE transactions SET status = ?1, updated_at = ?2
+             WHERE status = ?3 AND expires_at <= ?2",
+        )
+        .bind(LinkStatus::Expired.as_str())
+        .bind(now())
+        .bind(LinkStatus::Pending.as_str())
+        .execute(&self.pool)
+        .await
+        .map(|done| done.rows_affected() as usize)
+        .map_err(io::Error::other)
+    }
+
+    /// What a new link for the same order as `chat`'s latest link found by
+    /// `key`, as in `find_link`, needs: the payer is `None` for links made
+    /// before payers were kept.
+    pub async fn renewal(&self, chat: i64, key: &str) -> io::Result<Option<Renewal>> {
+        let sql = format!(
+            "SELECT {}, payment_method, customer FROM transactions
+             WHERE chat_id = ?1 AND (ticket = ?2 OR reference = ?2 OR gateway_reference = ?2)
+             ORDER BY created_at DESC, id DESC LIMIT 1",
+            LINK_COLUMNS
+        );
+        sqlx::query(&sql)
+            .bind(chat)
+            .bind(key)
+            .try_map(|row: SqliteRow| {
+                let customer: Option<String> = row.try_get(14)?;
+                Ok(Renewal {
+                    link: Link::from_row(&row)?,
+                    payment_method: row.try_get(13)?,
+                    customer: customer.and_then(|json| serde_json::from_str(&json).ok()),
+                })
+            })
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(io::Error::other)
+    }
+
+    /// The link with `ticket`, whichever chat made it.
+    pub async fn link_by_ticket(&self, ticket: &str) -> io::Result<Option<Link>> {
+        let sql = format!(
+            "SELECT {} FROM transactions WHERE ticket = ?1
+             ORDER BY created_at DESC, id DESC LIMIT 1",
+            LINK_COLUMNS
+        );
+        sqlx::query(&sql)
+            .bind(ticket)
+            .try_map(|row| Link::from_row(&row))
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(io::Error::other)
+    }
+
+    /// Records the refund asked for the paid link with `ticket`, marking
+    /// the link refunded when the provider says it is `done`. Returns `None`
+    /// if it isn't a paid link.
+    pub async fn record_refund(
+        &self,
+        ticket: &str,
+        refund_id: &str,
+        refund_status: &str,
+        done: bool,
+    ) -> io::Result<Option<Link>> {
+        let sql = format!(
+            "UPDATE transactions
+             SET status = CASE WHEN ?7 THEN ?1 ELSE status END,
+                 refund_id = ?2, refund_status = ?3, updated_at = ?4
+             WHERE ticket = ?5 AND status = ?6
+             RETURNING {}",
+            LINK_COLUMNS
+        );
+        sqlx::query(&sql)
+            .bind(LinkStatus::Refunded.as_str())
+            .bind(refund_id)
+            .bind(refund_status)
+            .bind(now())
+            .bind(ticket)
+            .bind(LinkStatus::Approved.as_str())
+            .bind(done)
+            .try_map(|row| Link::from_row(&row))
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(io::Error::other)
+    }
+
+    /// `chat`'s latest link whose ticket, reference or gateway reference is
+    /// `key`.
+    pub async fn find_link(&self, chat: i64, key: &str) -> io::Result<Option<Link>> {
+        let sql = format!(
+            "SELECT {} FROM transactions
+             WHERE chat_id = ?1 AND (ticket = ?2 OR reference = ?2 OR gateway_reference = ?2)
+             ORDER BY created_at DESC, id DESC LIMIT 1",
+            LINK_COLUMNS
+        );
+        sqlx::query(&sql)
+            .bind(chat)
+            .bind(key)
+            .try_map(|row| Link::from_row(&row))
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(io::Error::other)
+    }
+
+    /// Records that the link with `ticket`, or else with the gateway
+    /// reference `reference`, is now `status`. Only a pending or expired
+    /// link can change, besides an approved one being refunded, so a late
+    /// or replayed callback can't undo an outcome.
+    pub async fn settle_link(
+        &self,
+        ticket: &str,
+        reference: &str,
+        status: LinkStatus,
+    ) -> io::Result<Settlement> {
+        let id: Option<i64> = sqlx::query_scalar(
+            "SELECT id FROM transactions WHERE ticket = ?1
+             UNION ALL
+             SELECT id FROM transactions WHERE gateway_reference = ?2
+             LIMIT 1",
+        )
+        .bind(ticket)
+        .bind(reference)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(io::Error::other)?;
+        let Some(id) = id else {
+            return Ok(Settlement::Unknown);
+        };
+        let sql = format!(
+            "UPDATE transactions SET status = ?2, updated_at = ?3
+             WHERE id = ?1 AND status <> ?2
+               AND (status IN (?4, ?5) OR (status = ?6 AND ?2 = ?7))
+             RETURNING {}",
+            LINK_COLUMNS
+        );
+        let link = sqlx::query(&sql)
+            .bind(id)
+            .bind(status.as_str())
+            .bind(now())
+            .bind(LinkStatus::Pending.as_str())
+            .bind(LinkStatus::Expired.as_str())
+            .bind(LinkStatus::Approved.as_str())
+            .bind(LinkStatus::Refunded.as_str())
+            .try_map(|row| Link::from_row(&row))
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(io::Error::other)?;
+        Ok(link.map_or(Settlement::Unchanged, |l| Settlement::Changed(Box::new(l))))
+    }
+}
+
+#[async_trait]
+impl SessionStore for Db {
+    async fn load(&self, chat: ChatId) -> io::Result<Option<Session>> {
+        let row: Option<(String, i64)> =
+            sqlx::query_as("SELECT state, touched FROM sessions WHERE chat_id = ?1")
+                .bind(chat.0)
+                .fetch_optional(&self.pool)
+                .await
+                .map_err(io::Error::other)?;
+        let Some((state, touched)) = row else {
+            return Ok(None);
+        };
+        Ok(Some(Session {
+            state: serde_json::from_str(&state)?,
+            touched: touched as u64,
+        }))
+    }
+
+    async fn save(&self, chat: ChatId, session: &Session) -> io::Result<()> {
+        let state = serde_json::to_string(&session.state)?;
+        sqlx::query(
+            "INSERT INTO sessions (chat_id, state, touched) VALUES (?1, ?2, ?3)
+             ON CONFLICT (chat_id) DO UPDATE SET state = ?2, touched = ?3",
+        )
+        .bind(chat.0)
+        .bind(state)
+        .bind(session.touched as i64)
+        .execute(&self.pool)
+        .await
+        .map(|_| ())
+        .map_err(io::Error::other)
+    }
+
+    async fn remove(&self, chat: ChatId) -> io::Result<()> {
+        sqlx::query("DELETE FROM sessions WHERE chat_id = ?1")
+            .bind(chat.0)
+            .execute(&self.pool)
+            .await
+            .map(|_| ())
+            .map_err(io::Error::other)
+    }
+}
@@ -0,0 +1,417 @@
+//! The payment providers links are created with. Each is a
+//! `PaymentGateway`; `GatewayClient` is the HTTP API the bot started with,
+//! and `Gateways` picks the provider for a payment method.
+//!
+//! `GatewayClient` is given the base URL to call, so tests can point it at
+//! a server of their own instead.
+
+use async_trait::async_trait;
+use base64::{engine::general_purpose, Engine as _};
+use rand::Rng;
+use reqwest::header::AUTHORIZATION;
+use reqwest::{Method, Url};
+use ring::digest;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Calls to the gateway made before giving up on a transient failure.
+const ATTEMPTS: u32 = 4;
+/// The pause before the first retry, doubled before each next one.
+const RETRY_DELAY: Duration = Duration::from_millis(500);
+const TIMEOUT: Duration = Duration::from_secs(15);
+
+#[derive(Serialize, Debug)]
+pub struct LinkRequest {
+    pub reference: String,
+    pub amount: u64,
+    pub currency: String,
+    pub payment_method: String,
+    pub description: String,
+    pub redirect_url: String,
+    pub ipn_url: String,
+    pub customer_data: CustomerData,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct CustomerData {
+    pub legal_doc: String,
+    pub legal_doc_type: String,
+    pub phone_code: String,
+    pub phone_number: String,
+    pub email: String,
+    pub full_name: String,
+}
+
+impl CustomerData {
+    /// Whether every field is filled, as when the order named its payer.
+    pub fn is_complete(&self) -> bool {
+        [
+            &self.legal_doc,
+            &self.legal_doc_type,
+            &self.phone_code,
+            &self.phone_number,
+            &self.email,
+            &self.full_name,
+        ]
+        .iter()
+        .all(|field| !field.trim().is_empty())
+    }
+}
+
+// `GatewayClient`'s link, as its answer has it; not every field is used.
+#[allow(dead_code)]
+#[derive(Deserialize, Debug)]
+struct Data {
+    ticket: String,
+    date: String,
+    payment_url: String,
+    /// When the link stops taking payments, if the gateway says.
+    #[serde(default, alias = "expiration_date")]
+    expires_at: Option<String>,
+    transaction: Transaction,
+}
+
+#[allow(dead_code)]
+#[derive(Deserialize, Debug)]
+struct Transaction {
+    reference: String,
+    amount: u32,
+    currency: String,
+    payment_method: String,
+    redirect_url: String,
+    ipn_url: String,
+    description: String,
+}
+
+/// The same for every attempt to create a link for the same payment, so
+/// a provider that has already created one can hand it back instead of
+/// making another: a hex SHA-256 of the chat, the order's reference and
+/// the amount, in `currency`'s smallest unit.
+pub fn idempotency_key(chat_id: i64, reference: &str, currency: &str, amount: u64) -> String {
+    let payment = format!("{}:{}:{}:{}", chat_id, reference, currency, amount);
+    hex::encode(digest::digest(&digest::SHA256, payment.as_bytes()))
+}
+
+/// A link a provider created.
+#[derive(Debug, Clone)]
+pub struct CreatedLink {
+    /// The provider's ID for the link, which its callbacks repeat.
+    pub ticket: String,
+    /// The reference the provider was sent, which its callbacks repeat too.
+    pub gateway_reference: String,
+    pub payment_url: String,
+    /// When the link stops taking payments, in seconds since the epoch, if
+    /// the provider says.
+    pub expires_at: Option<i64>,
+}
+
+/// A refund a provider accepted.
+#[derive(Deserialize, Debug, Clone)]
+pub struct Refund {
+    #[serde(alias = "refund_id")]
+    pub id: String,
+    /// As the provider words it, e.g. `PENDING` or `COMPLETED`.
+    pub status: String,
+}
+
+impl Refund {
+    /// Whether the money has gone back; anything else is still pending
+    /// and is confirmed later by a `REFUNDED` callback.
+    pub fn is_complete(&self) -> bool {
+        matches!(
+            self.status.to_ascii_uppercase().as_str(),
+            "COMPLETED" | "REFUNDED" | "SUCCESS" | "APPROVED"
+        )
+    }
+
+    /// Whether the provider turned the refund down, so nothing is pending
+    /// and it can be asked for again.
+    pub fn is_failed(&self) -> bool {
+        matches!(
+            self.status.to_ascii_uppercase().as_str(),
+            "FAILED" | "REJECTED" | "DECLINED" | "CANCELLED" | "CANCELED" | "ERROR"
+        )
+    }
+}
+
+/// A payment provider.
+#[async_trait]
+pub trait PaymentGateway: Send + Sync {
+    /// `idempotency_key` comes from `idempotency_key`.
+    async fn create_link(
+        &self,
+        req: &LinkRequest,
+        idempotency_key: &str,
+    ) -> Result<CreatedLink, GatewayError>;
+
+    /// The payment's status as the provider words it, e.g. `APPROVED`.
+    async fn status(&self, ticket: &str) -> Result<String, GatewayError>;
+
+    /// Gives back the whole of a paid link's amount.
+    async fn refund(&self, ticket: &str) -> Result<Refund, GatewayError>;
+}
+
+/// The providers the bot knows, by name: one is the default, and payment
+/// methods can be routed to others.
+pub struct Gateways {
+    providers: HashMap<String, Arc<dyn PaymentGateway>>,
+    default: String,
+    /// Payment method code to provider name.
+    routes: HashMap<String, String>,
+}
+
+impl Gateways {
+    /// Starts with `gateway`, called `name`, as the default.
+    pub fn new(name: &str, gateway: Arc<dyn PaymentGateway>) -> Gateways {
+        Gateways {
+            providers: HashMap::from([(name.to_string(), gateway)]),
+            default: name.to_string(),
+            routes: HashMap::new(),
+        }
+    }
+
+    pub fn add(mut self, name: &str, gateway: Arc<dyn PaymentGateway>) -> Gateways {
+        self.providers.insert(name.to_string(), gateway);
+        self
+    }
+
+    /// Sends links for `method` to the provider `name`, which must have been
+    /// added.
+    pub fn route(mut self, method: &str, name: &str) -> Gateways {
+        assert!(
+            self.providers.contains_key(name),
+            "no gateway called '{}'",
+            name
+        );
+        self.routes.insert(method.to_string(), name.to_string());
+        self
+    }
+
+    /// The provider links for `method` are created with, and its name.
+    pub fn for_method(&self, method: &str) -> (&str, &dyn PaymentGateway) {
+        let name = self.routes.get(method).unwrap_or(&self.default);
+        (name, self.providers[name].as_ref())
+    }
+
+    /// The provider called `name`, e.g. the one that created a link.
+    pub fn get(&self, name: &str) -> Option<&dyn PaymentGateway> {
+        self.providers.get(name).map(|g| g.as_ref())
+    }
+}
+
+/// Every answer from `GatewayClient`'s API wraps what was asked for in
+/// `data`.
+#[derive(Deserialize)]
+struct Answer<T> {
+    data: T,
+}
+
+#[derive(Deserialize)]
+struct StatusData {
+    status: String,
+}
+
+#[derive(Debug)]
+pub enum GatewayError {
+    /// Every attempt timed out or met a 5xx.
+    Unavailable,
+    /// The gateway turned the request down with a 4xx, which trying again
+    /// won't change.
+    Rejected { status: u16, body: String },
+    /// The gateway answered 2xx with something that couldn't be read.
+    InvalidResponse(String),
+    /// The request couldn't be sent at all, e.g. a refused connection.
+    Http(reqwest::Error),
+}
+
+impl fmt::Display for GatewayError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GatewayError::Unavailable => write!(f, "gateway temporarily unavailable"),
+            GatewayError::Rejected { status, body } => {
+                write!(f, "the gateway answered {}: {}", status, body)
+            }
+            GatewayError::InvalidResponse(e) => {
+                write!(f, "the gateway's answer couldn't be read: {}", e)
+            }
+            GatewayError::Http(e) => write!(f, "couldn't reach the gateway: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for GatewayError {}
+
+pub struct GatewayClient {
+    http: reqwest::Client,
+    base_url: Url,
+    user: String,
+    password: String,
+    token: String,
+    attempts: u32,
+    retry_delay: Duration,
+}
+
+impl GatewayClient {
+    /// A client for the gateway at `base_url`, e.g.
+    /// `https://api.gateway.example`, without the `/api/v1/...` part.
+    pub fn new(base_url: Url, user: &str, password: &str, token: &str) -> GatewayClient {
+        GatewayClient {
+            http: http_client(TIMEOUT),
+            base_url,
+            user: user.to_string(),
+            password: password.to_string(),
+            token: token.to_string(),
+            attempts: ATTEMPTS,
+            retry_delay: RETRY_DELAY,
+        }
+    }
+
+    /// How long one call may take before it counts as timed out (default
+    /// 15 seconds).
+    pub fn timeout(mut self, timeout: Duration) -> GatewayClient {
+        self.http = http_client(timeout);
+        self
+    }
+
+    /// How many calls to make (default 4) and the pause before the first
+    /// retry (default 500ms).
+    pub fn retries(mut self, attempts: u32, delay: Duration) -> GatewayClient {
+        self.attempts = attempts.max(1);
+        self.retry_delay = delay;
+        self
+    }
+
+    /// A signed request to `path` under the base URL.
+    fn request(&self, method: Method, path: &str) -> reqwest::RequestBuilder {
+        let url = format!("{}{}", self.base_url.as_str().trim_end_matches('/'), path);
+        let credentials = format!("{}:{}", self.user, self.password);
+        self.http
+            .request(method, url)
+            .header("Token-Top", &self.token)
+            .header(
+                AUTHORIZATION,
+                format!("Basic {}", general_purpose::STANDARD.encode(credentials)),
+            )
+    }
+
+    /// Sends `request`, and again after a growing, jittered pause each time
+    /// it times out or the gateway answers 5xx, up to `attempts` times.
+    /// Other failures aren't going to go away, so they are returned at once.
+    /// The answer's `data` is returned.
+    async fn send_with_retry<T: DeserializeOwned>(
+        &self,
+        request: reqwest::RequestBuilder,
+        attempts: u32,
+    ) -> Result<T, GatewayError> {
+        let res = self.send(request, attempts).await?;
+        let body = res.text().await.map_err(GatewayError::Http)?;
+        serde_json::from_str::<Answer<T>>(&body)
+            .map(|answer| answer.data)
+            .map_err(|e| GatewayError::InvalidResponse(format!("{} (body: {})", e, body)))
+    }
+
+    async fn send(
+        &self,
+        request: reqwest::RequestBuilder,
+        attempts: u32,
+    ) -> Result<reqwest::Response, GatewayError> {
+        let mut delay = self.retry_delay;
+        for attempt in 1..=attempts {
+            // Only a streamed body can't be cloned, and these are JSON.
+            let this_try = request
+                .try_clone()
+                .expect("gateway requests have JSON bodies");
+            let failure = match this_try.send().await {
+                Ok(res) if res.status().is_server_error() => format!("answered {}", res.status()),
+                Ok(res) if res.status().is_client_error() => {
+                    let status = res.status().as_u16();
+                    let body = res.text().await.unwrap_or_default();
+                    return Err(GatewayError::Rejected { status, body });
+                }
+                Ok(res) => return Ok(res),
+                Err(e) if e.is_timeout() => e.to_string(),
+                Err(e) => return Err(GatewayError::Http(e)),
+            };
+            eprintln!(
+                "Gateway attempt {} of {} failed: {}",
+                attempt, attempts, failure
+            );
+            if attempt < attempts {
+                // Half the delay, plus up to as much again at random, so
+                // clients that failed together don't retry together.
+                let half = delay / 2;
+                let jitter = rand::thread_rng().gen_range(0..=half.as_millis() as u64);
+                tokio::time::sleep(half + Duration::from_millis(jitter)).await;
+                delay *= 2;
+            }
+        }
+        Err(GatewayError::Unavailable)
+    }
+}
+
+/// Links are created with a POST to `/api/v1/payin` carrying an
+/// `Idempotency-Key` header, looked up with a GET to
+/// `/api/v1/payin/{ticket}` and refunded with a POST to
+/// `/api/v1/payin/{ticket}/refund`.
+#[async_trait]
+impl PaymentGateway for GatewayClient {
+    async fn create_link(
+        &self,
+        req: &LinkRequest,
+        idempotency_key: &str,
+    ) -> Result<CreatedLink, GatewayError> {
+        let request = self
+            .request(Method::POST, "/api/v1/payin")
+            .header("Idempotency-Key", idempotency_key)
+            .json(req);
+        let data: Data = self.send_with_retry(request, self.attempts).await?;
+        println!("API response body: {:?}", data);
+        Ok(CreatedLink {
+            ticket: data.ticket,
+            gateway_reference: data.transaction.reference,
+            payment_url: data.payment_url,
+            expires_at: data.expires_at.as_deref().and_then(parse_time),
+        })
+    }
+
+    async fn status(&self, ticket: &str) -> Result<String, GatewayError> {
+        let request = self.request(Method::GET, &format!("/api/v1/payin/{}", ticket));
+        let data: StatusData = self.send_with_retry(request, self.attempts).await?;
+        Ok(data.status)
+    }
+
+    /// Sent once: a refund that timed out may still have been made.
+    async fn refund(&self, ticket: &str) -> Result<Refund, GatewayError> {
+        let request = self.request(Method::POST, &format!("/api/v1/payin/{}/refund", ticket));
+        self.send_with_retry(request, 1).await
+    }
+}
+
+/// An RFC 3339 time, or one written `2024-05-01 10:00:00`, taken as UTC.
+fn parse_time(time: &str) -> Option<i64> {
+    let time = time.trim();
+    let parsed = chrono::DateTime::parse_from_rfc3339(time)
+        .map(|t| t.timestamp())
+        .or_else(|_| {
+            chrono::NaiveDateTime::parse_from_str(time, "%Y-%m-%d %H:%M:%S")
+                .map(|t| t.and_utc().timestamp())
+        });
+    match parsed {
+        Ok(secs) => Some(secs),
+        Err(_) => {
+            eprintln!("Couldn't read the link's expiry '{}'", time);
+            None
+        }
+    }
+}
+
+fn http_client(timeout: Duration) -> reqwest::Client {
+    reqwest::Client::builder()
+        .timeout(timeout)
+        .build()
+        .expect("the HTTP client's settings are fixed")
+}
@@ -0,0 +1,39 @@
+//! Payments whose link is being created right now, so a second confirm
+//! that arrives meanwhile, e.g. from a double tap, doesn't make another.
+
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+
+/// Idempotency keys, from `gateway::idempotency_key`, with a link on the
+/// way.
+#[derive(Default)]
+pub struct InFlight(Mutex<HashSet<String>>);
+
+impl InFlight {
+    pub fn new() -> Arc<InFlight> {
+        Arc::new(InFlight::default())
+    }
+
+    /// Marks `key` as on its way until the `Claim` is dropped, or `None` if
+    /// it already is.
+    pub fn claim(self: &Arc<Self>, key: &str) -> Option<Claim> {
+        if !self.0.lock().unwrap().insert(key.to_string()) {
+            return None;
+        }
+        Some(Claim {
+            keys: self.clone(),
+            key: key.to_string(),
+        })
+    }
+}
+
+pub struct Claim {
+    keys: Arc<InFlight>,
+    key: String,
+}
+
+impl Drop for Claim {
+    fn drop(&mut self) {
+        self.keys.0.lock().unwrap().remove(&self.key);
+    }
+}
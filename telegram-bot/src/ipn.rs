@@ -0,0 +1,248 @@
+//! The HTTP side of the bot: the endpoint the gateway calls (IPN) when a
+//! link is paid or rejected, which tells the chat that created the link.
+//! Callbacks must be signed with the secret shared with the gateway.
+
+use crate::db::{Db, Link, LinkStatus, Settlement};
+use axum::body::Bytes;
+use axum::extract::{ConnectInfo, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::routing::post;
+use axum::Router;
+use ring::hmac;
+use serde::Deserialize;
+use std::env;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use teloxide::prelude::*;
+
+/// The path the gateway is given, from `IPN_PATH` (default `/ipn`).
+pub fn path() -> String {
+    let path = env::var("IPN_PATH").unwrap_or_else(|_| "/ipn".to_string());
+    if path.starts_with('/') {
+        path
+    } else {
+        format!("/{}", path)
+    }
+}
+
+/// Where the HTTP server listens, from `HTTP_ADDR` (default
+/// `0.0.0.0:8080`).
+pub fn addr() -> String {
+    env::var("HTTP_ADDR").unwrap_or_else(|_| "0.0.0.0:8080".to_string())
+}
+
+/// What the gateway posts. A link is found by its ticket or, failing that,
+/// the reference the gateway was sent.
+#[derive(Deserialize, Debug)]
+struct Notification {
+    ticket: Option<String>,
+    reference: Option<String>,
+    status: String,
+}
+
+/// What a payment status from the gateway means for the link. Statuses
+/// the customer isn't told about, such as a payment still pending, come
+/// back as `None`.
+pub fn outcome(status: &str) -> Option<LinkStatus> {
+    match status.to_ascii_uppercase().as_str() {
+        "APPROVED" | "PAID" | "SUCCESS" | "COMPLETED" => Some(LinkStatus::Approved),
+        "REJECTED" | "DECLINED" | "FAILED" | "CANCELLED" => Some(LinkStatus::Rejected),
+        "EXPIRED" => Some(LinkStatus::Expired),
+        "REFUNDED" => Some(LinkStatus::Refunded),
+        _ => None,
+    }
+}
+
+/// How callbacks prove they come from the gateway: a hex HMAC-SHA256 of
+/// the body, keyed with `IPN_SECRET`, in the header `IPN_SIGNATURE_HEADER`
+/// (default `X-Signature`), optionally written `sha256=<hex>`.
+struct Signature {
+    /// Without a secret nothing can be verified, so every callback is
+    /// turned away.
+    key: Option<hmac::Key>,
+    header: String,
+}
+
+impl Signature {
+    fn from_env() -> Signature {
+        let key = env::var("IPN_SECRET")
+            .ok()
+            .filter(|s| !s.is_empty())
+            .map(|s| hmac::Key::new(hmac::HMAC_SHA256, s.as_bytes()));
+        if key.is_none() {
+            eprintln!("IPN_SECRET is not set, so every IPN callback will be rejected");
+        }
+        Signature {
+            key,
+            header: env::var("IPN_SIGNATURE_HEADER").unwrap_or_else(|_| "X-Signature".to_string()),
+        }
+    }
+
+    /// Why `body` can't be trusted, if it can't.
+    fn check(&self, headers: &HeaderMap, body: &[u8]) -> Result<(), &'static str> {
+        let key = self.key.as_ref().ok_or("no IPN_SECRET to check it with")?;
+        let value = headers
+            .get(&self.header)
+            .ok_or("no signature header")?
+            .to_str()
+            .map_err(|_| "unreadable signature header")?
+            .trim();
+        let value = value.strip_prefix("sha256=").unwrap_or(value);
+        let signature = hex::decode(value).map_err(|_| "signature is not hex")?;
+        hmac::verify(key, body, &signature).map_err(|_| "signature doesn't match")
+    }
+}
+
+#[derive(Clone)]
+struct IpnState {
+    bot: Bot,
+    db: Db,
+    signature: Arc<Signature>,
+}
+
+/// Serve with `into_make_service_with_connect_info::<SocketAddr>()`, so
+/// rejected callbacks can be logged with where they came from.
+pub fn router(bot: Bot, db: Db) -> Router {
+    let signature = Arc::new(Signature::from_env());
+    Router::new()
+        .route(&path(), post(receive))
+        .with_state(IpnState { bot, db, signature })
+}
+
+/// Answers 401 to a callback that isn't signed with the shared secret, 404
+/// for a link the bot didn't create, so the gateway can tell a misrouted
+/// callback from a handled one, and 200 otherwise, including for a
+/// repeated callback, which isn't passed on to the chat twice.
+async fn receive(
+    State(state): State<IpnState>,
+    ConnectInfo(from): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> StatusCode {
+    if let Err(reason) = state.signature.check(&headers, &body) {
+        eprintln!(
+            "Rejected an IPN from {}: {} (body: {})",
+            from,
+            reason,
+            String::from_utf8_lossy(&body)
+        );
+        return StatusCode::UNAUTHORIZED;
+    }
+    let ipn: Notification = match serde_json::from_slice(&body) {
+        Ok(ipn) => ipn,
+        Err(e) => {
+            eprintln!("Couldn't read an IPN from {}: {}", from, e);
+            return StatusCode::BAD_REQUEST;
+        }
+    };
+    println!("IPN received: {:?}", ipn);
+    let Some(status) = outcome(&ipn.status) else {
+        return StatusCode::OK;
+    };
+    let ticket = ipn.ticket.as_deref().unwrap_or("");
+    let reference = ipn.reference.as_deref().unwrap_or("");
+    let link = match state.db.settle_link(ticket, reference, status).await {
+        Ok(Settlement::Changed(link)) => *link,
+        Ok(Settlement::Unchanged) => return StatusCode::OK,
+        Ok(Settlement::Unknown) => return StatusCode::NOT_FOUND,
+        Err(e) => {
+            eprintln!(
+                "Couldn't record the IPN for {} {}: {}",
+                ticket, reference, e
+            );
+            return StatusCode::INTERNAL_SERVER_ERROR;
+        }
+    };
+    tell_chat(&state.bot, &link).await;
+    StatusCode::OK
+}
+
+/// Tells the chat that made `link` what its status now is. A failure is
+/// only logged.
+pub async fn tell_chat(bot: &Bot, link: &Link) {
+    let amount = link.amount_text();
+    let text = match link.status {
+        LinkStatus::Approved => format!(
+            "✅ Pago confirmado\nReferencia: {}\nMonto: {}",
+            link.reference, amount
+        ),
+        LinkStatus::Refunded => format!(
+            "↩️ Pago reembolsado\nReferencia: {}\nMonto: {}",
+            link.reference, amount
+        ),
+        LinkStatus::Expired => format!(
+            "⌛ El link de pago venció sin pagarse\nReferencia: {}\nMonto: {}\n\nUsa /renew {} para generar uno nuevo.",
+            link.reference, amount, link.reference
+        ),
+        LinkStatus::Rejected | LinkStatus::Pending => format!(
+            "❌ Pago rechazado\nReferencia: {}\nMonto: {}\n\nUsa /pay para generar un nuevo link.",
+            link.reference, amount
+        ),
+    };
+    if let Err(e) = bot.send_message(ChatId(link.chat_id), text).await {
+        eprintln!(
+            "Couldn't tell chat {} about its payment: {}",
+            link.chat_id, e
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BODY: &[u8] = br#"{"ticket":"T-1","status":"APPROVED"}"#;
+
+    fn signature(secret: Option<&str>) -> Signature {
+        Signature {
+            key: secret.map(|s| hmac::Key::new(hmac::HMAC_SHA256, s.as_bytes())),
+            header: "X-Signature".to_string(),
+        }
+    }
+
+    fn signed(value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-signature", value.parse().unwrap());
+        headers
+    }
+
+    fn sign(secret: &str, body: &[u8]) -> String {
+        let key = hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes());
+        hex::encode(hmac::sign(&key, body))
+    }
+
+    #[test]
+    fn accepts_the_shared_secrets_signature() {
+        let check = signature(Some("s3cret"));
+        let hex = sign("s3cret", BODY);
+        assert_eq!(check.check(&signed(&hex), BODY), Ok(()));
+        let prefixed = format!("sha256={}", hex.to_uppercase());
+        assert_eq!(check.check(&signed(&prefixed), BODY), Ok(()));
+    }
+
+    #[test]
+    fn rejects_anything_else() {
+        let check = signature(Some("s3cret"));
+        let hex = sign("s3cret", BODY);
+        assert_eq!(
+            check.check(&signed(&sign("other", BODY)), BODY),
+            Err("signature doesn't match")
+        );
+        assert_eq!(
+            check.check(&signed(&hex), br#"{"ticket":"T-2","status":"APPROVED"}"#),
+            Err("signature doesn't match")
+        );
+        assert_eq!(
+            check.check(&signed("not hex"), BODY),
+            Err("signature is not hex")
+        );
+        assert_eq!(
+            check.check(&HeaderMap::new(), BODY),
+            Err("no signature header")
+        );
+        assert_eq!(
+            signature(None).check(&signed(&hex), BODY),
+            Err("no IPN_SECRET to check it with")
+        );
+    }
+}
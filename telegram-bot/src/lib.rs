@@ -0,0 +1,4 @@
+//! The parts of the bot that don't need Telegram, kept in a library so the
+//! tests under `tests/` can reach them.
+
+pub mod gateway;
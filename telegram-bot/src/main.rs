@@ -1,210 +1,1477 @@
+mod admin;
+mod config;
+mod currency;
+mod customer;
+mod db;
+mod inflight;
+mod ipn;
+mod qr;
+mod redis;
+mod reference;
+mod session;
+mod throttle;
+
+use admin::{AdminCommand, Admins};
 use anyhow::Result;
+use config::{Config, GatewaySettings};
+use currency::Currency;
+use customer::DocType;
+use db::{Db, Link, LinkStatus, NewLink, Settlement};
+use gateway::{
+    CreatedLink, CustomerData, GatewayClient, GatewayError, Gateways, LinkRequest, PaymentGateway,
+};
+use inflight::InFlight;
+use reference::{Order, ReferenceRepository};
 use serde::{Deserialize, Serialize};
+use session::SessionStorage;
 use std::env;
+use std::future::Future;
+use std::net::SocketAddr;
 use std::sync::Arc;
+use std::time::Duration;
+use teloxide::dispatching::dialogue;
+use teloxide::dispatching::UpdateHandler;
 use teloxide::prelude::*;
-use reqwest::header::{AUTHORIZATION, HeaderValue};
-use base64::{engine::general_purpose, Engine as _};
-use rand::Rng;
-use dashmap::DashMap;
+use teloxide::types::{InlineKeyboardButton, InlineKeyboardMarkup, InputFile};
+use teloxide::update_listeners::webhooks;
+use teloxide::utils::command::BotCommands;
+use tg_paylink_bot::gateway;
+use throttle::Throttle;
+use tokio::sync::watch;
+
+/// Comandos disponibles:
+#[derive(BotCommands, Clone)]
+#[command(rename_rule = "lowercase")]
+enum Command {
+    /// Empezar a usar el bot.
+    Start,
+    /// Generar un link de pago.
+    Pay,
+    /// Mostrar esta ayuda.
+    #[command(alias = "ayuda")]
+    Help,
+    /// Cancelar el pago en curso.
+    Cancel,
+    /// Consultar un pago: /status <ticket o referencia>
+    Status(String),
+    /// Ver los links de pago generados.
+    History,
+    /// Generar un nuevo link para un pago vencido: /renew <referencia>
+    Renew(String),
+}
 
-#[derive(Debug, Clone)]
+/// The step of the payment conversation a chat is in.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 enum UserState {
+    #[default]
     Idle,
+    /// Left by `SessionStorage` when a conversation went quiet for too
+    /// long; the chat's next message is told so.
+    Expired,
     WaitingReference,
     ReferenceNotFound,
+    ChoosingCurrency {
+        draft: PaymentDraft,
+    },
+    ReceiveAmount {
+        draft: PaymentDraft,
+    },
+    ChoosingMethod {
+        draft: PaymentDraft,
+    },
+    ReceiveFullName {
+        draft: PaymentDraft,
+    },
+    ReceiveDocType {
+        draft: PaymentDraft,
+    },
+    ReceiveDocNumber {
+        draft: PaymentDraft,
+    },
+    ReceiveEmail {
+        draft: PaymentDraft,
+    },
+    ReceivePhone {
+        draft: PaymentDraft,
+    },
+    Confirming {
+        draft: PaymentDraft,
+    },
 }
 
-#[derive(Serialize)]
-struct LinkRequest {
+/// What the conversation has gathered for the link so far.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct PaymentDraft {
     reference: String,
+    currency: Currency,
+    /// In the currency's smallest unit.
     amount: u64,
-    currency: String,
-    payment_method: String,
-    description: String,
-    redirect_url: String,
-    ipn_url: String,
-    customer_data: CustomerData,
-}
-
-#[derive(Serialize)]
-struct CustomerData {
-    legal_doc: String,
-    legal_doc_type: String,
-    phone_code: String,
-    phone_number: String,
-    email: String,
-    full_name: String,
-}
-
-#[derive(Deserialize)]
-struct LinkResponse {
-    code: String,
-    status: String,
-    message: String,
-    data: Data,
-}
-
-#[derive(Deserialize, Debug)]
-struct Data {
-    ticket: String,
-    date: String,
-    payment_url: String,
-    transaction: Transaction,
-}
-
-#[derive(Deserialize, Debug)]
-struct Transaction {
-    reference: String,
-    amount: u32,
-    currency: String,
-    payment_method: String,
-    redirect_url: String,
-    ipn_url: String,
-    description: String,
+    method: PaymentMethod,
+    customer: CustomerData,
+}
+
+impl PaymentDraft {
+    fn summary(&self) -> String {
+        let c = &self.customer;
+        format!(
+            "📋 Confirma los datos del pago:\n\
+             Referencia: {}\n\
+             Monto: {}\n\
+             Método de pago: {}\n\
+             Nombre: {}\n\
+             Documento: {} {}\n\
+             Correo: {}\n\
+             Teléfono: +{} {}",
+            self.reference,
+            self.currency.format(self.amount),
+            self.method.label(),
+            c.full_name,
+            c.legal_doc_type,
+            c.legal_doc,
+            c.email,
+            c.phone_code,
+            c.phone_number
+        )
+    }
+}
+
+/// How the customer can pay, offered as buttons once the amount is known.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+enum PaymentMethod {
+    Pse,
+    Card,
+    Cash,
+    #[default]
+    All,
+}
+
+impl PaymentMethod {
+    const ALL: [PaymentMethod; 4] = [
+        PaymentMethod::Pse,
+        PaymentMethod::Card,
+        PaymentMethod::Cash,
+        PaymentMethod::All,
+    ];
+
+    /// The value the gateway expects in `payment_method`, also used as the
+    /// button's callback data.
+    fn code(self) -> &'static str {
+        match self {
+            PaymentMethod::Pse => "PSE",
+            PaymentMethod::Card => "CARD",
+            PaymentMethod::Cash => "CASH",
+            PaymentMethod::All => "ALL_METHODS",
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            PaymentMethod::Pse => "🏦 PSE",
+            PaymentMethod::Card => "💳 Tarjeta",
+            PaymentMethod::Cash => "💵 Efectivo",
+            PaymentMethod::All => "Todos los métodos",
+        }
+    }
+
+    fn from_code(code: &str) -> Option<PaymentMethod> {
+        PaymentMethod::ALL.into_iter().find(|m| m.code() == code)
+    }
+
+    fn keyboard() -> InlineKeyboardMarkup {
+        let buttons =
+            PaymentMethod::ALL.map(|m| InlineKeyboardButton::callback(m.label(), m.code()));
+        InlineKeyboardMarkup::new(buttons.chunks(2).map(|row| row.to_vec()))
+    }
 }
 
+/// How often links past their expiry are marked as expired.
+const EXPIRY_SWEEP: Duration = Duration::from_secs(60);
+
+/// How long a shutdown waits for the updates and callbacks in progress.
+const SHUTDOWN_DEADLINE: Duration = Duration::from_secs(30);
+
+type PayDialogue = Dialogue<UserState, SessionStorage>;
+type HandlerResult = Result<()>;
+
 #[tokio::main]
 async fn main() -> Result<()> {
     println!("Starting tg-paylink-bot");
 
     dotenvy::dotenv().ok();
+    let config = Arc::new(Config::from_env()?);
+    let gateways = Arc::new(gateways(&config));
     let bot = Bot::from_env();
+    bot.set_my_commands(Command::bot_commands()).await?;
+    let db = Db::from_env().await.map_err(anyhow::Error::msg)?;
+    let sessions = SessionStorage::from_env(&db).map_err(anyhow::Error::msg)?;
+    let references = reference::from_env(&db).map_err(anyhow::Error::msg)?;
+    let throttle = Throttle::from_env();
+    let admins = Admins::from_env();
+    tokio::spawn(sweep_expired(db.clone()));
+
+    let addr: SocketAddr = ipn::addr()
+        .parse()
+        .map_err(|e| anyhow::anyhow!("HTTP_ADDR: {}", e))?;
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    let app = ipn::router(bot.clone(), db.clone());
+
+    let mut dispatcher = Dispatcher::builder(bot.clone(), schema())
+        .dependencies(dptree::deps![
+            sessions,
+            db.clone(),
+            references,
+            throttle,
+            admins,
+            config.clone(),
+            gateways,
+            InFlight::new()
+        ])
+        .build();
+
+    // Flipped to true when a signal asks the bot to stop.
+    let (stop_tx, stopping) = watch::channel(false);
+    let token = dispatcher.shutdown_token();
+    tokio::spawn(async move {
+        shutdown_signal().await;
+        println!("Shutting down, finishing what is in progress...");
+        stop_tx.send_replace(true);
+        // Not dispatching yet means nothing is in progress either.
+        if token.shutdown().is_err() {
+            std::process::exit(0);
+        }
+    });
+
+    // The dispatcher returns once the handlers it started have finished,
+    // gateway calls included, and the server once its callbacks have.
+    let server_stop = stopped(stopping.clone());
+    let run = async {
+        match webhook_options(addr, &config)? {
+            None => {
+                let server = tokio::spawn(serve(listener, app, server_stop));
+                dispatcher.dispatch().await;
+                server.await?;
+            }
+            Some(options) => {
+                println!("Receiving updates at {}", options.url);
+                let (updates, stop, telegram) = webhooks::axum_to_router(bot, options).await?;
+                // Stopping the dispatcher stops the server, which then
+                // deletes the webhook.
+                let server = tokio::spawn(serve(listener, app.merge(telegram), stop));
+                dispatcher
+                    .dispatch_with_listener(
+                        updates,
+                        LoggingErrorHandler::with_custom_text("An error from the webhook"),
+                    )
+                    .await;
+                server.await?;
+            }
+        }
+        anyhow::Ok(())
+    };
+    tokio::select! {
+        result = run => result?,
+        _ = async {
+            stopped(stopping).await;
+            tokio::time::sleep(SHUTDOWN_DEADLINE).await;
+        } => eprintln!("Gave up waiting for the work in progress after {:?}", SHUTDOWN_DEADLINE),
+    }
+
+    db.close().await;
+    println!("Stopped");
+    Ok(())
+}
 
-    let sessions = Arc::new(DashMap::<i64, UserState>::new());
-
-    Dispatcher::builder(
-        bot.clone(),
-        Update::filter_message().endpoint({
-            let sessions = sessions.clone();
-            move |bot: Bot, msg: Message| {
-                let sessions = sessions.clone();
-                async move {
-                    let chat_id = msg.chat.id.0;
-                    let text = msg.text().unwrap_or("").trim().to_string();
-
-                    match text.as_str() {
-                        "/start" | "ayuda" => {
-                            bot.send_message(msg.chat.id,
-                                "🔗 Envíame: /pay para iniciar el proceso de pago"
-                            ).await?;
-                        }
-                        "/pay" => {
-                            sessions.insert(chat_id, UserState::WaitingReference);
-                            bot.send_message(msg.chat.id, "🔗 Ingresa la referencia de pago:").await?;
-                        }
-                        _ => {
-                            match sessions.get(&chat_id).map(|r| r.clone()).unwrap_or(UserState::Idle) {
-                                UserState::WaitingReference => {
-                                    let reference = text.clone();
-                                    bot.send_message(msg.chat.id, format!("🔍 Buscando pago para referencia: {}", reference)).await?;
-                                    
-                                    // Simular verificación de referencia (aquí puedes conectar con tu base de datos)
-                                    if reference.to_lowercase() == "abc" {
-                                        // Referencia no existe
-                                        bot.send_message(msg.chat.id, "❌ Referencia 'ABC' no encontrada en el sistema.\n\n🔗 Por favor, ingresa una referencia válida:").await?;
-                                        sessions.insert(chat_id, UserState::ReferenceNotFound);
-                                    } else {
-                                        // Referencia válida, generar pago
-                                        let amount = rand::thread_rng().gen_range(10000..100000);
-                                        
-                                        match create_pay_link(amount, &reference, chat_id).await {
-                                            Ok(url) => {
-                                                bot.send_message(msg.chat.id, format!("✅ Link de pago generado:\n💰 Monto: ${} COP\n🔗 Link: {}", amount, url)).await?;
-                                            }
-                                            Err(e) => {
-                                                bot.send_message(msg.chat.id, format!("❌ Error al generar el link: {}", e)).await?;
-                                            }
-                                        }
-                                        sessions.insert(chat_id, UserState::Idle);
-                                    }
-                                }
-                                UserState::ReferenceNotFound => {
-                                    let reference = text.clone();
-                                    bot.send_message(msg.chat.id, format!("🔍 Verificando nueva referencia: {}", reference)).await?;
-                                    
-                                    // Verificar la nueva referencia
-                                    if reference.to_lowercase() == "abc" {
-                                        // Sigue siendo inválida
-                                        bot.send_message(msg.chat.id, "❌ La referencia 'ABC' sigue siendo inválida.\n\n🔗 Por favor, ingresa una referencia diferente:").await?;
-                                        // Mantener en el mismo estado
-                                    } else {
-                                        // Nueva referencia válida
-                                        let amount = rand::thread_rng().gen_range(10000..100000);
-                                        
-                                        match create_pay_link(amount, &reference, chat_id).await {
-                                            Ok(url) => {
-                                                bot.send_message(msg.chat.id, format!("✅ ¡Perfecto! Link de pago generado:\n💰 Monto: ${} COP\n🔗 Link: {}", amount, url)).await?;
-                                            }
-                                            Err(e) => {
-                                                bot.send_message(msg.chat.id, format!("❌ Error al generar el link: {}", e)).await?;
-                                            }
-                                        }
-                                        sessions.insert(chat_id, UserState::Idle);
-                                    }
-                                }
-                                UserState::Idle => {
-                                    bot.send_message(msg.chat.id, "Usa /pay para iniciar el proceso de pago.").await?;
-                                }
-                            }
-                        }
-                    }
-
-                    Ok::<(), anyhow::Error>(())
+/// Ctrl-C, or SIGTERM where there is one, as sent by service managers and
+/// container runtimes.
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                tokio::select! {
+                    _ = tokio::signal::ctrl_c() => {}
+                    _ = terminate.recv() => {}
                 }
+                return;
             }
-        }),
+            Err(e) => eprintln!("Can't listen for SIGTERM: {}", e),
+        }
+    }
+    if let Err(e) = tokio::signal::ctrl_c().await {
+        eprintln!("Can't listen for Ctrl-C: {}", e);
+        std::future::pending::<()>().await;
+    }
+}
+
+/// Resolves once shutting down has begun.
+async fn stopped(mut stopping: watch::Receiver<bool>) {
+    // The sender is only dropped after sending.
+    let _ = stopping.wait_for(|&stop| stop).await;
+}
+
+/// Marks links as expired once they are, every `EXPIRY_SWEEP`.
+async fn sweep_expired(db: Db) {
+    let mut every = tokio::time::interval(EXPIRY_SWEEP);
+    loop {
+        every.tick().await;
+        match db.expire_links().await {
+            Ok(0) => {}
+            Ok(n) => println!("{} payment links expired", n),
+            Err(e) => eprintln!("Couldn't mark the expired links: {}", e),
+        }
+    }
+}
+
+/// A `GatewayClient` for each provider in `config`, routed as it says.
+fn gateways(config: &Config) -> Gateways {
+    let client = |settings: &GatewaySettings| -> Arc<dyn PaymentGateway> {
+        Arc::new(GatewayClient::new(
+            settings.url.clone(),
+            &settings.user,
+            &settings.password,
+            &settings.token,
+        ))
+    };
+    // `Config::from_env` reads the default provider first.
+    let (default, others) = config.gateways.split_first().expect("a default gateway");
+    let mut gateways = Gateways::new(&default.name, client(default));
+    for settings in others {
+        gateways = gateways.add(&settings.name, client(settings));
+    }
+    for (method, name) in &config.gateway_routes {
+        gateways = gateways.route(method, name);
+    }
+    gateways
+}
+
+/// Long polling, unless `BOT_MODE=webhook`: then Telegram posts updates to
+/// `PUBLIC_URL` followed by `WEBHOOK_PATH` (default `/telegram`), which the
+/// IPN's server answers too. `WEBHOOK_SECRET` sets the token Telegram
+/// sends with them; without it a random one is made at each start.
+fn webhook_options(addr: SocketAddr, config: &Config) -> Result<Option<webhooks::Options>> {
+    match env::var("BOT_MODE").as_deref() {
+        Err(_) | Ok("polling") => return Ok(None),
+        Ok("webhook") => {}
+        Ok(other) => anyhow::bail!("BOT_MODE must be polling or webhook, not '{}'", other),
+    }
+    let path = env::var("WEBHOOK_PATH").unwrap_or_else(|_| "/telegram".to_string());
+    let path = format!("/{}", path.trim_start_matches('/'));
+    if path == ipn::path() {
+        anyhow::bail!("WEBHOOK_PATH and IPN_PATH can't be the same path");
+    }
+    let public_url = config.public_url.as_str().trim_end_matches('/');
+    let url = reqwest::Url::parse(&format!("{}{}", public_url, path))?;
+    let mut options = webhooks::Options::new(addr, url).path(path);
+    if let Ok(secret) = env::var("WEBHOOK_SECRET") {
+        options = options.secret_token(secret);
+    }
+    Ok(Some(options))
+}
+
+/// Runs the HTTP server until `stop` resolves.
+async fn serve(
+    listener: tokio::net::TcpListener,
+    app: axum::Router,
+    stop: impl Future<Output = ()> + Send + 'static,
+) {
+    let app = app.into_make_service_with_connect_info::<SocketAddr>();
+    if let Err(e) = axum::serve(listener, app)
+        .with_graceful_shutdown(stop)
+        .await
+    {
+        eprintln!("The HTTP server stopped: {}", e);
+    }
+}
+
+/// Routes each message to the handler for its command or, failing that,
+/// for the step of the conversation the chat is in; button presses go to
+/// the step that showed the buttons.
+fn schema() -> UpdateHandler<anyhow::Error> {
+    let commands = teloxide::filter_command::<Command, _>()
+        .branch(dptree::case![Command::Start].endpoint(start))
+        .branch(dptree::case![Command::Pay].endpoint(pay))
+        .branch(dptree::case![Command::Help].endpoint(help))
+        .branch(dptree::case![Command::Cancel].endpoint(cancel))
+        .branch(dptree::case![Command::Status(key)].endpoint(status))
+        .branch(dptree::case![Command::History].endpoint(history))
+        .branch(dptree::case![Command::Renew(key)].endpoint(renew));
+
+    let messages = Update::filter_message()
+        .branch(commands)
+        .branch(admin::commands())
+        .branch(dptree::case![UserState::WaitingReference].endpoint(receive_reference))
+        .branch(dptree::case![UserState::ReferenceNotFound].endpoint(retry_reference))
+        .branch(dptree::case![UserState::ChoosingCurrency { draft }].endpoint(type_currency))
+        .branch(dptree::case![UserState::ReceiveAmount { draft }].endpoint(receive_amount))
+        .branch(dptree::case![UserState::ChoosingMethod { draft }].endpoint(choose_method))
+        .branch(dptree::case![UserState::ReceiveFullName { draft }].endpoint(receive_full_name))
+        .branch(dptree::case![UserState::ReceiveDocType { draft }].endpoint(type_doc_type))
+        .branch(dptree::case![UserState::ReceiveDocNumber { draft }].endpoint(receive_doc_number))
+        .branch(dptree::case![UserState::ReceiveEmail { draft }].endpoint(receive_email))
+        .branch(dptree::case![UserState::ReceivePhone { draft }].endpoint(receive_phone))
+        .branch(dptree::case![UserState::Confirming { draft }].endpoint(choose_confirmation))
+        .branch(dptree::case![UserState::Expired].endpoint(expired))
+        .branch(dptree::case![UserState::Idle].endpoint(idle));
+
+    let callbacks = Update::filter_callback_query()
+        .branch(dptree::filter_map(history_button).endpoint(history_page))
+        .branch(dptree::case![UserState::ChoosingCurrency { draft }].endpoint(receive_currency))
+        .branch(dptree::case![UserState::ChoosingMethod { draft }].endpoint(receive_method))
+        .branch(dptree::case![UserState::ReceiveDocType { draft }].endpoint(receive_doc_type))
+        .branch(dptree::case![UserState::Confirming { draft }].endpoint(receive_confirmation))
+        .branch(dptree::case![UserState::Expired].endpoint(expired_button))
+        .branch(dptree::endpoint(stale_button));
+
+    dialogue::enter::<Update, SessionStorage, UserState, _>()
+        .branch(messages)
+        .branch(callbacks)
+}
+
+fn message_text(msg: &Message) -> String {
+    msg.text().unwrap_or("").trim().to_string()
+}
+
+async fn start(bot: Bot, msg: Message) -> HandlerResult {
+    bot.send_message(
+        msg.chat.id,
+        "🔗 Envíame: /pay para iniciar el proceso de pago",
     )
-    .enable_ctrlc_handler()
-    .build()
-    .dispatch()
-    .await;
+    .await?;
+    Ok(())
+}
 
+/// Admins are shown their commands too.
+async fn help(bot: Bot, admins: Arc<Admins>, msg: Message) -> HandlerResult {
+    let mut text = Command::descriptions().to_string();
+    if admins.contains(&msg) {
+        text = format!("{}\n\n{}", text, AdminCommand::descriptions());
+    }
+    bot.send_message(msg.chat.id, text).await?;
     Ok(())
 }
 
-async fn create_pay_link(amount: u64, _reference: &str, _chat_id: i64) -> Result<String> {
-    let api_url = env::var("GATEWAY_API_URL")?;
-    let user = env::var("GATEWAY_USER")?;
-    let password = env::var("GATEWAY_PASSWORD")?;
-    let token = env::var("GATEWAY_TOKEN")?;
+/// Looks up one of the chat's own links; the conversation, if any, carries
+/// on where it was.
+async fn status(
+    bot: Bot,
+    db: Db,
+    gateways: Arc<Gateways>,
+    msg: Message,
+    key: String,
+) -> HandlerResult {
+    let key = key.trim();
+    if key.is_empty() {
+        bot.send_message(
+            msg.chat.id,
+            "Escribe el ticket o la referencia del pago, por ejemplo: /status ABC123",
+        )
+        .await?;
+        return Ok(());
+    }
+    let text = match db.find_link(msg.chat.id.0, key).await {
+        Ok(Some(link)) => link_details(&refresh(&db, &gateways, link).await),
+        Ok(None) => format!("🔍 No encontramos ningún pago tuyo con '{}'.", key),
+        Err(e) => {
+            eprintln!("Couldn't look up the link {}: {}", key, e);
+            "⚠️ No pudimos consultar el pago en este momento. Intenta de nuevo en unos minutos."
+                .to_string()
+        }
+    };
+    bot.send_message(msg.chat.id, text).await?;
+    Ok(())
+}
 
+/// `link` as its provider has it now, if it was pending: the callback
+/// with its outcome may not have arrived, or may never.
+async fn refresh(db: &Db, gateways: &Gateways, link: Link) -> Link {
+    if link.status != LinkStatus::Pending {
+        return link;
+    }
+    let Some(gateway) = gateways.get(&link.provider) else {
+        eprintln!(
+            "The link {} was made by the gateway '{}', which isn't configured",
+            link.ticket, link.provider
+        );
+        return link;
+    };
+    let status = match gateway.status(&link.ticket).await {
+        Ok(status) => status,
+        Err(e) => {
+            eprintln!("Couldn't ask the gateway about {}: {}", link.ticket, e);
+            return link;
+        }
+    };
+    let Some(outcome) = ipn::outcome(&status) else {
+        return link;
+    };
+    match db.settle_link(&link.ticket, "", outcome).await {
+        Ok(Settlement::Changed(settled)) => *settled,
+        Ok(_) => link,
+        Err(e) => {
+            eprintln!("Couldn't record the status of {}: {}", link.ticket, e);
+            link
+        }
+    }
+}
+
+/// Makes a new link for the order of one of the chat's links, with the
+/// same amount, method and payer, e.g. once it has expired. A link for it
+/// that can still be paid is sent again instead.
+#[allow(clippy::too_many_arguments)]
+async fn renew(
+    bot: Bot,
+    db: Db,
+    config: Arc<Config>,
+    gateways: Arc<Gateways>,
+    in_flight: Arc<InFlight>,
+    throttle: Arc<Throttle>,
+    msg: Message,
+    key: String,
+) -> HandlerResult {
+    let chat = msg.chat.id;
+    let key = key.trim();
+    if key.is_empty() {
+        bot.send_message(
+            chat,
+            "Escribe la referencia del pago, por ejemplo: /renew ABC123",
+        )
+        .await?;
+        return Ok(());
+    }
+    let renewal = match db.renewal(chat.0, key).await {
+        Ok(Some(renewal)) => renewal,
+        Ok(None) => {
+            bot.send_message(
+                chat,
+                format!("🔍 No encontramos ningún pago tuyo con '{}'.", key),
+            )
+            .await?;
+            return Ok(());
+        }
+        Err(e) => {
+            eprintln!("Couldn't look up the link {} to renew: {}", key, e);
+            bot.send_message(
+                chat,
+                "⚠️ No pudimos consultar el pago en este momento. Intenta de nuevo en unos minutos.",
+            )
+            .await?;
+            return Ok(());
+        }
+    };
+    let link = renewal.link;
+    if link.status == LinkStatus::Approved {
+        bot.send_message(
+            chat,
+            format!(
+                "✅ El pago {} ya fue aprobado, no hace falta un nuevo link.",
+                link.reference
+            ),
+        )
+        .await?;
+        return Ok(());
+    }
+    let (Some(currency), Some(method), Some(customer)) = (
+        Currency::from_code(&link.currency),
+        PaymentMethod::from_code(&renewal.payment_method),
+        renewal.customer,
+    ) else {
+        bot.send_message(
+            chat,
+            "No podemos repetir este pago con los mismos datos. Usa /pay para generar un nuevo link.",
+        )
+        .await?;
+        return Ok(());
+    };
+    if let Err(wait) = throttle.take(chat) {
+        bot.send_message(chat, cooldown_text(wait)).await?;
+        return Ok(());
+    }
+    let draft = PaymentDraft {
+        reference: link.reference,
+        currency,
+        amount: link.amount,
+        method,
+        customer,
+    };
+    send_pay_link(&bot, &db, &config, &gateways, &in_flight, chat, &draft).await
+}
+
+fn status_label(status: LinkStatus) -> &'static str {
+    match status {
+        LinkStatus::Pending => "⏳ Pendiente",
+        LinkStatus::Approved => "✅ Aprobado",
+        LinkStatus::Rejected => "❌ Rechazado",
+        LinkStatus::Expired => "⌛ Vencido",
+        LinkStatus::Refunded => "↩️ Reembolsado",
+    }
+}
+
+/// e.g. `16/10/2026 14:05`, in the server's time zone.
+fn format_date(secs: i64) -> String {
+    chrono::DateTime::from_timestamp(secs, 0)
+        .map(|d| {
+            d.with_timezone(&chrono::Local)
+                .format("%d/%m/%Y %H:%M")
+                .to_string()
+        })
+        .unwrap_or_default()
+}
+
+fn link_details(link: &Link) -> String {
+    let mut text = format!(
+        "📄 Pago {}\n\
+         Ticket: {}\n\
+         Estado: {}\n\
+         Monto: {}\n\
+         Creado: {}",
+        link.reference,
+        link.ticket,
+        status_label(link.status),
+        link.amount_text(),
+        format_date(link.created_at)
+    );
+    match (link.status, link.expires_at) {
+        (LinkStatus::Pending, Some(at)) => {
+            text.push_str(&format!("\nVence: {}", format_date(at)));
+        }
+        (LinkStatus::Pending, None) => {}
+        _ => text.push_str(&format!("\nActualizado: {}", format_date(link.updated_at))),
+    }
+    if link.status == LinkStatus::Expired {
+        text.push_str(&format!(
+            "\n\nUsa /renew {} para generar un nuevo link.",
+            link.reference
+        ));
+    }
+    text
+}
+
+/// How many links /history shows at a time, from `HISTORY_PAGE_SIZE`
+/// (default 5).
+fn history_page_size() -> u32 {
+    env::var("HISTORY_PAGE_SIZE")
+        .ok()
+        .and_then(|n| n.trim().parse().ok())
+        .filter(|&n| n > 0)
+        .unwrap_or(5)
+}
+
+/// The page of /history a "next"/"prev" button asks for; these work from
+/// any step of the conversation.
+fn history_button(q: CallbackQuery) -> Option<u32> {
+    q.data?.strip_prefix("history:")?.parse().ok()
+}
+
+/// Page `page` of the chat's links, and the buttons to the pages around
+/// it, or `None` if the page is empty.
+async fn history_text(
+    db: &Db,
+    chat: ChatId,
+    page: u32,
+) -> Result<Option<(String, InlineKeyboardMarkup)>> {
+    let size = history_page_size();
+    // One more than fits tells whether there is a next page.
+    let mut links = db.list_links(chat.0, page * size, size + 1).await?;
+    if links.is_empty() {
+        return Ok(None);
+    }
+    let more = links.len() > size as usize;
+    links.truncate(size as usize);
+    let mut text = format!("🧾 Tus links de pago (página {}):\n", page + 1);
+    for link in &links {
+        text.push_str(&format!(
+            "\n{} · {}\n{} · {}\n",
+            link.reference,
+            link.amount_text(),
+            status_label(link.status),
+            format_date(link.created_at)
+        ));
+    }
+    let mut buttons = Vec::new();
+    if page > 0 {
+        buttons.push(InlineKeyboardButton::callback(
+            "⬅️ Anterior",
+            format!("history:{}", page - 1),
+        ));
+    }
+    if more {
+        buttons.push(InlineKeyboardButton::callback(
+            "Siguiente ➡️",
+            format!("history:{}", page + 1),
+        ));
+    }
+    Ok(Some((text, InlineKeyboardMarkup::new([buttons]))))
+}
+
+async fn history(bot: Bot, db: Db, msg: Message) -> HandlerResult {
+    match history_text(&db, msg.chat.id, 0).await {
+        Ok(Some((text, buttons))) => {
+            bot.send_message(msg.chat.id, text)
+                .reply_markup(buttons)
+                .await?;
+        }
+        Ok(None) => {
+            bot.send_message(
+                msg.chat.id,
+                "Todavía no has generado links de pago. Usa /pay para crear uno.",
+            )
+            .await?;
+        }
+        Err(e) => {
+            eprintln!("Couldn't list the links of chat {}: {}", msg.chat.id, e);
+            bot.send_message(
+                msg.chat.id,
+                "⚠️ No pudimos consultar tus pagos en este momento. Intenta de nuevo en unos minutos.",
+            )
+            .await?;
+        }
+    }
+    Ok(())
+}
+
+/// Turns the /history message to another page.
+async fn history_page(bot: Bot, db: Db, q: CallbackQuery, page: u32) -> HandlerResult {
+    let Some(message) = q.regular_message() else {
+        bot.answer_callback_query(q.id.clone()).await?;
+        return Ok(());
+    };
+    match history_text(&db, message.chat.id, page).await {
+        Ok(Some((text, buttons))) => {
+            bot.answer_callback_query(q.id.clone()).await?;
+            bot.edit_message_text(message.chat.id, message.id, text)
+                .reply_markup(buttons)
+                .await?;
+        }
+        Ok(None) => {
+            bot.answer_callback_query(q.id.clone())
+                .text("No hay más pagos.")
+                .await?;
+        }
+        Err(e) => {
+            eprintln!("Couldn't list the links of chat {}: {}", message.chat.id, e);
+            bot.answer_callback_query(q.id.clone())
+                .text("⚠️ No pudimos consultar tus pagos. Intenta de nuevo.")
+                .await?;
+        }
+    }
+    Ok(())
+}
+
+/// Drops whatever the chat was in the middle of, from any step.
+async fn cancel(bot: Bot, dialogue: PayDialogue, state: UserState, msg: Message) -> HandlerResult {
+    if matches!(state, UserState::Idle | UserState::Expired) {
+        bot.send_message(msg.chat.id, "No hay ningún pago en curso.")
+            .await?;
+        return Ok(());
+    }
+    dialogue.exit().await?;
+    bot.send_message(
+        msg.chat.id,
+        "🚫 Pago cancelado. Usa /pay para empezar de nuevo.",
+    )
+    .await?;
+    Ok(())
+}
+
+const EXPIRED: &str = "⌛ Tu sesión expiró por inactividad. Empieza de nuevo con /pay.";
+
+async fn expired(bot: Bot, dialogue: PayDialogue, msg: Message) -> HandlerResult {
+    dialogue.exit().await?;
+    bot.send_message(msg.chat.id, EXPIRED).await?;
+    Ok(())
+}
+
+async fn expired_button(bot: Bot, dialogue: PayDialogue, q: CallbackQuery) -> HandlerResult {
+    dialogue.exit().await?;
+    bot.answer_callback_query(q.id.clone()).await?;
+    close_buttons(&bot, &q, EXPIRED.to_string()).await
+}
+
+/// A button from a step that is over, e.g. after /cancel.
+async fn stale_button(bot: Bot, q: CallbackQuery) -> HandlerResult {
+    bot.answer_callback_query(q.id.clone())
+        .text("Este botón ya no está activo. Usa /pay para empezar de nuevo.")
+        .await?;
+    close_buttons(&bot, &q, "🚫 Opción vencida.".to_string()).await
+}
+
+/// The reply to a chat that has created as many links as it may for now.
+fn cooldown_text(wait: Duration) -> String {
+    let minutes = wait.as_secs().div_ceil(60).max(1);
+    format!(
+        "⏳ Has generado varios links de pago en poco tiempo. Podrás crear otro en {} {}.",
+        minutes,
+        if minutes == 1 { "minuto" } else { "minutos" }
+    )
+}
+
+async fn pay(
+    bot: Bot,
+    dialogue: PayDialogue,
+    throttle: Arc<Throttle>,
+    msg: Message,
+) -> HandlerResult {
+    // Said now rather than after all the questions.
+    if let Err(wait) = throttle.check(msg.chat.id) {
+        bot.send_message(msg.chat.id, cooldown_text(wait)).await?;
+        return Ok(());
+    }
+    dialogue.update(UserState::WaitingReference).await?;
+    bot.send_message(
+        msg.chat.id,
+        "🔗 Ingresa la referencia de pago (o /cancel para salir):",
+    )
+    .await?;
+    Ok(())
+}
+
+async fn receive_reference(
+    bot: Bot,
+    db: Db,
+    dialogue: PayDialogue,
+    references: Arc<dyn ReferenceRepository>,
+    msg: Message,
+) -> HandlerResult {
+    let reference = message_text(&msg);
+    bot.send_message(
+        msg.chat.id,
+        format!("🔍 Buscando pago para referencia: {}", reference),
+    )
+    .await?;
+
+    match references.find(&reference).await {
+        Ok(Some(order)) => {
+            warn_if_expired(&bot, &db, msg.chat.id, &reference).await?;
+            start_payment(&bot, &dialogue, reference, order).await?
+        }
+        Ok(None) => {
+            bot.send_message(
+                msg.chat.id,
+                format!("❌ Referencia '{}' no encontrada en el sistema.\n\n🔗 Por favor, ingresa una referencia válida, o /cancel para salir:", reference),
+            )
+            .await?;
+            dialogue.update(UserState::ReferenceNotFound).await?;
+        }
+        Err(e) => lookup_failed(&bot, msg.chat.id, &reference, e).await?,
+    }
+    Ok(())
+}
+
+/// Tells the chat when the last link it made for `reference` went unpaid
+/// past its expiry, since it may have come back to pay that one.
+async fn warn_if_expired(bot: &Bot, db: &Db, chat: ChatId, reference: &str) -> HandlerResult {
+    let link = match db.find_link(chat.0, reference).await {
+        Ok(Some(link)) => link,
+        Ok(None) => return Ok(()),
+        Err(e) => {
+            eprintln!("Couldn't look up the links for {}: {}", reference, e);
+            return Ok(());
+        }
+    };
+    let Some(expires_at) = link.expires_at else {
+        return Ok(());
+    };
+    // The sweep may not have got to it yet.
+    let lapsed = link.status == LinkStatus::Pending && expires_at <= chrono::Utc::now().timestamp();
+    if link.status == LinkStatus::Expired || lapsed {
+        bot.send_message(
+            chat,
+            format!(
+                "⌛ El link que generaste para esta referencia venció el {} y ya no acepta pagos. \
+                 Sigue aquí para generar uno nuevo, o usa /renew {} para repetirlo con los mismos datos.",
+                format_date(expires_at),
+                reference
+            ),
+        )
+        .await?;
+    }
+    Ok(())
+}
+
+async fn retry_reference(
+    bot: Bot,
+    db: Db,
+    dialogue: PayDialogue,
+    references: Arc<dyn ReferenceRepository>,
+    msg: Message,
+) -> HandlerResult {
+    let reference = message_text(&msg);
+    bot.send_message(
+        msg.chat.id,
+        format!("🔍 Verificando nueva referencia: {}", reference),
+    )
+    .await?;
+
+    match references.find(&reference).await {
+        Ok(Some(order)) => {
+            warn_if_expired(&bot, &db, msg.chat.id, &reference).await?;
+            start_payment(&bot, &dialogue, reference, order).await?
+        }
+        Ok(None) => {
+            // Se mantiene en el mismo estado
+            bot.send_message(
+                msg.chat.id,
+                format!("❌ La referencia '{}' tampoco se encontró.\n\n🔗 Por favor, ingresa una referencia diferente, o /cancel para salir:", reference),
+            )
+            .await?;
+        }
+        Err(e) => lookup_failed(&bot, msg.chat.id, &reference, e).await?,
+    }
+    Ok(())
+}
+
+/// The reference couldn't be checked at all; the chat stays where it is so
+/// it can send it again.
+async fn lookup_failed(
+    bot: &Bot,
+    chat: ChatId,
+    reference: &str,
+    error: anyhow::Error,
+) -> HandlerResult {
+    eprintln!("Couldn't look up reference {}: {}", reference, error);
+    bot.send_message(
+        chat,
+        "⚠️ No pudimos verificar la referencia en este momento. Escríbela de nuevo en unos minutos, o /cancel para salir.",
+    )
+    .await?;
+    Ok(())
+}
+
+/// Starts the draft from what the order says: its amount, if it has one,
+/// skips straight to the payment method, and a known payer skips the
+/// customer data.
+async fn start_payment(
+    bot: &Bot,
+    dialogue: &PayDialogue,
+    reference: String,
+    order: Order,
+) -> HandlerResult {
+    let draft = PaymentDraft {
+        reference,
+        customer: order.payer.unwrap_or_default(),
+        ..PaymentDraft::default()
+    };
+    match order.due {
+        Some((currency, amount)) => {
+            let draft = PaymentDraft {
+                currency,
+                amount,
+                ..draft
+            };
+            ask_method(bot, dialogue, draft).await
+        }
+        None => ask_currency(bot, dialogue, draft).await,
+    }
+}
+
+/// Offers the configured currencies as buttons, or goes on to the amount
+/// when there is only one.
+async fn ask_currency(bot: &Bot, dialogue: &PayDialogue, draft: PaymentDraft) -> HandlerResult {
+    if let [currency] = Currency::enabled() {
+        return ask_amount(bot, dialogue, draft, *currency).await;
+    }
+    bot.send_message(dialogue.chat_id(), "💱 Elige la moneda:")
+        .reply_markup(currency_keyboard())
+        .await?;
+    dialogue
+        .update(UserState::ChoosingCurrency { draft })
+        .await?;
+    Ok(())
+}
+
+fn currency_keyboard() -> InlineKeyboardMarkup {
+    InlineKeyboardMarkup::new([Currency::enabled()
+        .iter()
+        .map(|c| InlineKeyboardButton::callback(c.code(), c.code()))])
+}
+
+async fn receive_currency(
+    bot: Bot,
+    dialogue: PayDialogue,
+    draft: PaymentDraft,
+    q: CallbackQuery,
+) -> HandlerResult {
+    bot.answer_callback_query(q.id.clone()).await?;
+    let Some(currency) = q.data.as_deref().and_then(Currency::from_code) else {
+        return Ok(());
+    };
+    close_buttons(&bot, &q, format!("💱 Moneda: {}", currency.code())).await?;
+    ask_amount(&bot, &dialogue, draft, currency).await
+}
+
+/// A typed currency code, e.g. `usd`, counts like a button press.
+async fn type_currency(
+    bot: Bot,
+    dialogue: PayDialogue,
+    draft: PaymentDraft,
+    msg: Message,
+) -> HandlerResult {
+    match Currency::from_code(&message_text(&msg)).filter(|c| Currency::enabled().contains(c)) {
+        Some(currency) => ask_amount(&bot, &dialogue, draft, currency).await,
+        None => {
+            bot.send_message(msg.chat.id, "👆 Elige la moneda con uno de los botones.")
+                .reply_markup(currency_keyboard())
+                .await?;
+            Ok(())
+        }
+    }
+}
+
+async fn ask_amount(
+    bot: &Bot,
+    dialogue: &PayDialogue,
+    mut draft: PaymentDraft,
+    currency: Currency,
+) -> HandlerResult {
+    let (min, max) = currency.limits();
+    bot.send_message(
+        dialogue.chat_id(),
+        format!(
+            "💰 Escribe el monto a pagar (entre {} y {}):",
+            currency.format(min),
+            currency.format(max)
+        ),
+    )
+    .await?;
+    draft.currency = currency;
+    dialogue.update(UserState::ReceiveAmount { draft }).await?;
+    Ok(())
+}
+
+async fn receive_amount(
+    bot: Bot,
+    dialogue: PayDialogue,
+    mut draft: PaymentDraft,
+    msg: Message,
+) -> HandlerResult {
+    match draft.currency.parse(&message_text(&msg)) {
+        Ok(amount) => {
+            draft.amount = amount;
+            ask_method(&bot, &dialogue, draft).await?;
+        }
+        Err(e) => {
+            bot.send_message(msg.chat.id, e).await?;
+        }
+    }
+    Ok(())
+}
+
+async fn ask_method(bot: &Bot, dialogue: &PayDialogue, draft: PaymentDraft) -> HandlerResult {
+    bot.send_message(
+        dialogue.chat_id(),
+        format!(
+            "💰 Monto: {}\n\n💳 Elige el método de pago:",
+            draft.currency.format(draft.amount)
+        ),
+    )
+    .reply_markup(PaymentMethod::keyboard())
+    .await?;
+    dialogue.update(UserState::ChoosingMethod { draft }).await?;
+    Ok(())
+}
+
+/// Text sent while the buttons are waiting for a press.
+async fn choose_method(bot: Bot, msg: Message) -> HandlerResult {
+    bot.send_message(
+        msg.chat.id,
+        "👆 Elige el método de pago con uno de los botones.",
+    )
+    .reply_markup(PaymentMethod::keyboard())
+    .await?;
+    Ok(())
+}
+
+async fn receive_method(
+    bot: Bot,
+    dialogue: PayDialogue,
+    mut draft: PaymentDraft,
+    q: CallbackQuery,
+) -> HandlerResult {
+    bot.answer_callback_query(q.id.clone()).await?;
+    let Some(method) = q.data.as_deref().and_then(PaymentMethod::from_code) else {
+        return Ok(());
+    };
+    close_buttons(&bot, &q, format!("💳 Método de pago: {}", method.label())).await?;
+    draft.method = method;
+    if draft.customer.is_complete() {
+        return ask_confirmation(&bot, &dialogue, draft).await;
+    }
+    ask_full_name(&bot, &dialogue, draft).await
+}
+
+/// Replaces the message whose button was pressed with `text`, dropping the
+/// buttons so the choice can't be made twice.
+async fn close_buttons(bot: &Bot, q: &CallbackQuery, text: String) -> HandlerResult {
+    if let Some(message) = q.regular_message() {
+        bot.edit_message_text(message.chat.id, message.id, text)
+            .await?;
+    }
+    Ok(())
+}
+
+async fn ask_full_name(bot: &Bot, dialogue: &PayDialogue, draft: PaymentDraft) -> HandlerResult {
+    bot.send_message(dialogue.chat_id(), "👤 Escribe tu nombre completo:")
+        .await?;
+    dialogue
+        .update(UserState::ReceiveFullName { draft })
+        .await?;
+    Ok(())
+}
+
+async fn receive_full_name(
+    bot: Bot,
+    dialogue: PayDialogue,
+    mut draft: PaymentDraft,
+    msg: Message,
+) -> HandlerResult {
+    match customer::full_name(&message_text(&msg)) {
+        Ok(name) => {
+            draft.customer.full_name = name;
+            bot.send_message(msg.chat.id, "🪪 Elige el tipo de documento:")
+                .reply_markup(doc_type_keyboard())
+                .await?;
+            dialogue.update(UserState::ReceiveDocType { draft }).await?;
+        }
+        Err(e) => {
+            bot.send_message(msg.chat.id, e).await?;
+        }
+    }
+    Ok(())
+}
+
+fn doc_type_keyboard() -> InlineKeyboardMarkup {
+    InlineKeyboardMarkup::new(
+        DocType::ALL.map(|d| [InlineKeyboardButton::callback(d.label(), d.code())]),
+    )
+}
+
+async fn receive_doc_type(
+    bot: Bot,
+    dialogue: PayDialogue,
+    draft: PaymentDraft,
+    q: CallbackQuery,
+) -> HandlerResult {
+    bot.answer_callback_query(q.id.clone()).await?;
+    let Some(doc_type) = q.data.as_deref().and_then(DocType::from_code) else {
+        return Ok(());
+    };
+    close_buttons(&bot, &q, format!("🪪 Documento: {}", doc_type.label())).await?;
+    ask_doc_number(&bot, &dialogue, draft, doc_type).await
+}
+
+/// A typed document type, e.g. `CC`, counts like a button press.
+async fn type_doc_type(
+    bot: Bot,
+    dialogue: PayDialogue,
+    draft: PaymentDraft,
+    msg: Message,
+) -> HandlerResult {
+    match DocType::from_code(&message_text(&msg)) {
+        Some(doc_type) => ask_doc_number(&bot, &dialogue, draft, doc_type).await,
+        None => {
+            bot.send_message(
+                msg.chat.id,
+                "👆 Elige el tipo de documento con uno de los botones.",
+            )
+            .reply_markup(doc_type_keyboard())
+            .await?;
+            Ok(())
+        }
+    }
+}
+
+async fn ask_doc_number(
+    bot: &Bot,
+    dialogue: &PayDialogue,
+    mut draft: PaymentDraft,
+    doc_type: DocType,
+) -> HandlerResult {
+    draft.customer.legal_doc_type = doc_type.code().to_string();
+    bot.send_message(dialogue.chat_id(), "🔢 Escribe el número de documento:")
+        .await?;
+    dialogue
+        .update(UserState::ReceiveDocNumber { draft })
+        .await?;
+    Ok(())
+}
+
+async fn receive_doc_number(
+    bot: Bot,
+    dialogue: PayDialogue,
+    mut draft: PaymentDraft,
+    msg: Message,
+) -> HandlerResult {
+    // The type was set from a `DocType` code in the step before.
+    let doc_type = DocType::from_code(&draft.customer.legal_doc_type).unwrap_or(DocType::Cc);
+    match customer::document(doc_type, &message_text(&msg)) {
+        Ok(number) => {
+            draft.customer.legal_doc = number;
+            bot.send_message(msg.chat.id, "📧 Escribe tu correo electrónico:")
+                .await?;
+            dialogue.update(UserState::ReceiveEmail { draft }).await?;
+        }
+        Err(e) => {
+            bot.send_message(msg.chat.id, e).await?;
+        }
+    }
+    Ok(())
+}
+
+async fn receive_email(
+    bot: Bot,
+    dialogue: PayDialogue,
+    mut draft: PaymentDraft,
+    msg: Message,
+) -> HandlerResult {
+    match customer::email(&message_text(&msg)) {
+        Ok(email) => {
+            draft.customer.email = email;
+            bot.send_message(
+                msg.chat.id,
+                "📱 Escribe tu número de celular (con +código si no es de Colombia):",
+            )
+            .await?;
+            dialogue.update(UserState::ReceivePhone { draft }).await?;
+        }
+        Err(e) => {
+            bot.send_message(msg.chat.id, e).await?;
+        }
+    }
+    Ok(())
+}
+
+async fn receive_phone(
+    bot: Bot,
+    dialogue: PayDialogue,
+    mut draft: PaymentDraft,
+    msg: Message,
+) -> HandlerResult {
+    match customer::phone(&message_text(&msg)) {
+        Ok((code, number)) => {
+            draft.customer.phone_code = code;
+            draft.customer.phone_number = number;
+            ask_confirmation(&bot, &dialogue, draft).await?;
+        }
+        Err(e) => {
+            bot.send_message(msg.chat.id, e).await?;
+        }
+    }
+    Ok(())
+}
+
+async fn ask_confirmation(bot: &Bot, dialogue: &PayDialogue, draft: PaymentDraft) -> HandlerResult {
+    bot.send_message(dialogue.chat_id(), draft.summary())
+        .reply_markup(confirmation_keyboard())
+        .await?;
+    dialogue.update(UserState::Confirming { draft }).await?;
+    Ok(())
+}
+
+fn confirmation_keyboard() -> InlineKeyboardMarkup {
+    InlineKeyboardMarkup::new([[
+        InlineKeyboardButton::callback("✅ Confirmar", "confirm"),
+        InlineKeyboardButton::callback("✏️ Corregir", "restart"),
+    ]])
+}
+
+/// Text sent while the summary waits for a button press.
+async fn choose_confirmation(bot: Bot, draft: PaymentDraft, msg: Message) -> HandlerResult {
+    bot.send_message(msg.chat.id, draft.summary())
+        .reply_markup(confirmation_keyboard())
+        .await?;
+    Ok(())
+}
+
+// Every argument is a dependency the dispatcher injects.
+#[allow(clippy::too_many_arguments)]
+async fn receive_confirmation(
+    bot: Bot,
+    db: Db,
+    throttle: Arc<Throttle>,
+    config: Arc<Config>,
+    gateways: Arc<Gateways>,
+    in_flight: Arc<InFlight>,
+    dialogue: PayDialogue,
+    mut draft: PaymentDraft,
+    q: CallbackQuery,
+) -> HandlerResult {
+    bot.answer_callback_query(q.id.clone()).await?;
+    match q.data.as_deref() {
+        Some("confirm") => {
+            // The draft is kept, so the chat can confirm again once the
+            // wait is over.
+            if let Err(wait) = throttle.take(dialogue.chat_id()) {
+                bot.send_message(dialogue.chat_id(), cooldown_text(wait))
+                    .await?;
+                return Ok(());
+            }
+            close_buttons(&bot, &q, draft.summary()).await?;
+            send_pay_link(
+                &bot,
+                &db,
+                &config,
+                &gateways,
+                &in_flight,
+                dialogue.chat_id(),
+                &draft,
+            )
+            .await?;
+            dialogue.update(UserState::Idle).await?;
+        }
+        Some("restart") => {
+            close_buttons(&bot, &q, "✏️ Volvamos a tomar los datos.".to_string()).await?;
+            draft.customer = CustomerData::default();
+            ask_full_name(&bot, &dialogue, draft).await?;
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+async fn idle(bot: Bot, msg: Message) -> HandlerResult {
+    bot.send_message(msg.chat.id, "Usa /pay para iniciar el proceso de pago.")
+        .await?;
+    Ok(())
+}
+
+/// Creates a link for `draft` with the provider for its payment method,
+/// records it, and sends it to `chat`, or the provider's error.
+async fn send_pay_link(
+    bot: &Bot,
+    db: &Db,
+    config: &Config,
+    gateways: &Gateways,
+    in_flight: &Arc<InFlight>,
+    chat: ChatId,
+    draft: &PaymentDraft,
+) -> HandlerResult {
+    let key = gateway::idempotency_key(
+        chat.0,
+        &draft.reference,
+        draft.currency.code(),
+        draft.amount,
+    );
+    let Some(_claim) = in_flight.claim(&key) else {
+        bot.send_message(chat, "⏳ Ya estamos generando el link de este pago.")
+            .await?;
+        return Ok(());
+    };
+    // A link already made for this payment is sent again rather than
+    // making a second one.
+    match db.live_link(&key).await {
+        Ok(Some(link)) => {
+            bot.send_message(
+                chat,
+                format!(
+                    "ℹ️ Ya tienes un link de pago para esta referencia:
+💰 Monto: {}
+🔗 Link: {}",
+                    link.amount_text(),
+                    link.payment_url
+                ),
+            )
+            .await?;
+            send_qr(bot, chat, &link.payment_url).await;
+            return Ok(());
+        }
+        Ok(None) => {}
+        // The gateway is sent the key too, so it can still catch a repeat.
+        Err(e) => eprintln!("Couldn't look for a link with the key {}: {}", key, e),
+    }
+    // Once a link has been paid or rejected, paying again is a new payment,
+    // which the gateway mustn't answer with the old link.
+    let earlier = db.count_links(&key).await.unwrap_or_else(|e| {
+        eprintln!("Couldn't count the links with the key {}: {}", key, e);
+        0
+    });
+    let gateway_key = format!("{}-{}", key, earlier);
+    let (provider, gateway) = gateways.for_method(draft.method.code());
+    match create_pay_link(config, gateway, &gateway_key, draft).await {
+        Ok(link) => {
+            let expires_at = link.expires_at.unwrap_or_else(|| {
+                chrono::Utc::now().timestamp() + config.link_ttl.as_secs() as i64
+            });
+            let record = NewLink {
+                chat_id: chat.0,
+                reference: &draft.reference,
+                gateway_reference: &link.gateway_reference,
+                ticket: &link.ticket,
+                amount: draft.amount,
+                currency: draft.currency.code(),
+                payment_method: draft.method.code(),
+                payment_url: &link.payment_url,
+                provider,
+                idempotency_key: &key,
+                expires_at,
+                customer: &draft.customer,
+            };
+            // The customer still gets a link that couldn't be recorded.
+            if let Err(e) = db.record_link(record).await {
+                eprintln!("Couldn't record the link {}: {}", link.ticket, e);
+            }
+            bot.send_message(
+                chat,
+                format!(
+                    "✅ Link de pago generado:\n💰 Monto: {}\n🔗 Link: {}\n⏰ Vence: {}",
+                    draft.currency.format(draft.amount),
+                    link.payment_url,
+                    format_date(expires_at)
+                ),
+            )
+            .await?;
+            send_qr(bot, chat, &link.payment_url).await;
+        }
+        Err(GatewayError::Unavailable) => {
+            bot.send_message(
+                chat,
+                "⚠️ La pasarela de pagos no está disponible en este momento. Intenta de nuevo en unos minutos con /pay.",
+            )
+            .await?;
+        }
+        Err(e) => {
+            bot.send_message(chat, format!("❌ Error al generar el link: {}", e))
+                .await?;
+        }
+    }
+    Ok(())
+}
+
+/// The link as a QR code, to scan from another device. The link itself
+/// has already been sent, so a failure here is only logged.
+async fn send_qr(bot: &Bot, chat: ChatId, url: &str) {
+    let Some(code) = qr::QrCode::encode(url) else {
+        eprintln!("The link {} is too long for a QR code", url);
+        return;
+    };
+    let photo = InputFile::memory(code.to_png()).file_name("pago.png");
+    if let Err(e) = bot
+        .send_photo(chat, photo)
+        .caption("📱 Escanea este código para pagar desde otro dispositivo.")
+        .await
+    {
+        eprintln!("Couldn't send the QR code to chat {}: {}", chat, e);
+    }
+}
+
+/// Asks `gateway` for a link for `draft`. `idempotency_key` is unique to
+/// this attempt, so it is the link's gateway reference too.
+async fn create_pay_link(
+    config: &Config,
+    gateway: &dyn PaymentGateway,
+    idempotency_key: &str,
+    draft: &PaymentDraft,
+) -> Result<CreatedLink, GatewayError> {
     let req = LinkRequest {
-        reference: String::from(format!("0123456789abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ{}", rand::thread_rng().gen_range(1..1000000))),
-        amount: amount,
-        currency: String::from("COP"),
-        payment_method: String::from("ALL_METHODS"),
+        reference: idempotency_key.to_string(),
+        amount: draft.amount,
+        currency: draft.currency.code().to_string(),
+        payment_method: draft.method.code().to_string(),
         description: String::from("Payment from telegram user"),
-        redirect_url: String::from("https://google.com/"),
-        ipn_url: String::from("https://google.com/"),
-        customer_data: CustomerData {
-            legal_doc: String::from("1102184491"),
-            legal_doc_type: String::from("CC"),
-            phone_code: String::from("57"),
-            phone_number: String::from("3133243232"),
-            email: String::from("John-Doe@test.com"),
-            full_name: String::from("John Doe"),
-        },
-    };
-
-    let credentials = format!("{}:{}", user, password);
-    let encoded_credentials = general_purpose::STANDARD.encode(credentials);
-    let auth_header_value = format!("Basic {}", encoded_credentials);
-    let auth_header = HeaderValue::from_str(&auth_header_value)?;
-    
-    let client = reqwest::Client::new();
-    let res = client
-        .post(format!("{}/api/v1/payin", api_url))
-        .header("Token-Top", token)
-        .header("Content-Type", "application/json")
-        .header(AUTHORIZATION, auth_header)
-        .json(&req)
-        .send()
-        .await?
-        .error_for_status()?;
-
-    let data: LinkResponse = res.json().await?;
-    println!("API response body: {:?}", data.data);
-
-    Ok(data.data.payment_url)
+        redirect_url: config.redirect_url.to_string(),
+        ipn_url: config.ipn_url.to_string(),
+        customer_data: draft.customer.clone(),
+    };
+
+    gateway.create_link(&req, idempotency_key).await
 }
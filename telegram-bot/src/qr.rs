@@ -0,0 +1,57 @@
+//! Draws payment links as QR codes, in PNG, for the customer to scan from
+//! another device. Text is encoded at error correction level M, in the
+//! smallest version that holds it.
+
+use image::{ImageFormat, Luma};
+use qrcode::EcLevel;
+use std::io::Cursor;
+
+/// Pixels per module in the PNG.
+const SCALE: u32 = 8;
+
+/// A QR code's modules.
+pub struct QrCode(qrcode::QrCode);
+
+impl QrCode {
+    /// `None` if `text` is too long for even the largest version.
+    pub fn encode(text: &str) -> Option<QrCode> {
+        qrcode::QrCode::with_error_correction_level(text, EcLevel::M)
+            .ok()
+            .map(QrCode)
+    }
+
+    /// The code as a black on white PNG, with the quiet zone around it.
+    pub fn to_png(&self) -> Vec<u8> {
+        let image = self
+            .0
+            .render::<Luma<u8>>()
+            .module_dimensions(SCALE, SCALE)
+            .build();
+        let mut png = Vec::new();
+        // Writing to a Vec can't fail.
+        image
+            .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+            .unwrap();
+        png
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_link_is_drawn_as_a_png_of_whole_modules() {
+        let png = QrCode::encode("https://pagos.example.com/link/abc123")
+            .unwrap()
+            .to_png();
+        let image = image::load_from_memory_with_format(&png, ImageFormat::Png).unwrap();
+        assert_eq!(image.width(), image.height());
+        assert_eq!(image.width() % SCALE, 0);
+    }
+
+    #[test]
+    fn text_too_long_for_a_code_is_refused() {
+        assert!(QrCode::encode(&"a".repeat(3000)).is_none());
+    }
+}
@@ -0,0 +1,80 @@
+//! A `SessionStore` on a Redis server, so sessions outlive a restart and
+//! replicas of the bot share them. Commands go over one multiplexed
+//! connection, which the `redis` crate reopens when it breaks.
+
+use crate::session::{Session, SessionStore};
+use ::redis::aio::ConnectionManager;
+use ::redis::{AsyncCommands, Client};
+use async_trait::async_trait;
+use std::io;
+use std::time::Duration;
+use teloxide::types::ChatId;
+use tokio::sync::OnceCell;
+
+const KEY_PREFIX: &str = "tg-paylink:session:";
+
+pub struct RedisStore {
+    client: Client,
+    /// How long an untouched session stays on the server.
+    keep: Duration,
+    conn: OnceCell<ConnectionManager>,
+}
+
+impl RedisStore {
+    /// `url` as in `redis://[[user]:password@]host[:port][/db]`. Nothing is
+    /// connected until the first session is read or written.
+    pub fn new(url: &str, keep: Duration) -> Result<RedisStore, String> {
+        let client = Client::open(url).map_err(|e| format!("REDIS_URL: {}", e))?;
+        Ok(RedisStore {
+            client,
+            keep,
+            conn: OnceCell::new(),
+        })
+    }
+
+    /// The connection, made on first use. Clones share it.
+    async fn conn(&self) -> io::Result<ConnectionManager> {
+        self.conn
+            .get_or_try_init(|| ConnectionManager::new(self.client.clone()))
+            .await
+            .cloned()
+            .map_err(io::Error::other)
+    }
+
+    fn key(chat: ChatId) -> String {
+        format!("{}{}", KEY_PREFIX, chat.0)
+    }
+}
+
+#[async_trait]
+impl SessionStore for RedisStore {
+    async fn load(&self, chat: ChatId) -> io::Result<Option<Session>> {
+        let data: Option<Vec<u8>> = self
+            .conn()
+            .await?
+            .get(Self::key(chat))
+            .await
+            .map_err(io::Error::other)?;
+        match data {
+            Some(data) => Ok(Some(serde_json::from_slice(&data)?)),
+            None => Ok(None),
+        }
+    }
+
+    async fn save(&self, chat: ChatId, session: &Session) -> io::Result<()> {
+        let data = serde_json::to_vec(session)?;
+        self.conn()
+            .await?
+            .set_ex(Self::key(chat), data, self.keep.as_secs().max(1))
+            .await
+            .map_err(io::Error::other)
+    }
+
+    async fn remove(&self, chat: ChatId) -> io::Result<()> {
+        self.conn()
+            .await?
+            .del(Self::key(chat))
+            .await
+            .map_err(io::Error::other)
+    }
+}
@@ -0,0 +1,122 @@
+//! Where the references customers type are looked up: the merchant's
+//! orders, each with the amount expected and, when known, who pays it.
+
+use crate::currency::Currency;
+use crate::db::Db;
+use crate::CustomerData;
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use reqwest::{StatusCode, Url};
+use serde::Deserialize;
+use std::env;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// An order waiting to be paid.
+pub struct Order {
+    /// The amount expected, in the currency's smallest unit, or `None` to
+    /// let the customer type it.
+    pub due: Option<(Currency, u64)>,
+    pub payer: Option<CustomerData>,
+}
+
+#[async_trait]
+pub trait ReferenceRepository: Send + Sync {
+    /// The order `reference` names, or `None` if there is no such order.
+    async fn find(&self, reference: &str) -> Result<Option<Order>>;
+}
+
+/// The `orders` table of the bot's database.
+pub struct DbReferences {
+    db: Db,
+}
+
+#[async_trait]
+impl ReferenceRepository for DbReferences {
+    async fn find(&self, reference: &str) -> Result<Option<Order>> {
+        Ok(self.db.find_order(reference).await?)
+    }
+}
+
+/// What the merchant's backend answers for a known reference.
+#[derive(Deserialize)]
+struct OrderResponse {
+    amount: Option<u64>,
+    currency: Option<String>,
+    payer: Option<CustomerData>,
+}
+
+/// The merchant's backend, asked with `GET {base}/{reference}`; a 404 means
+/// the reference is unknown.
+pub struct HttpReferences {
+    base: Url,
+    token: Option<String>,
+    client: reqwest::Client,
+}
+
+impl HttpReferences {
+    pub fn new(base: &str, token: Option<String>) -> Result<HttpReferences, String> {
+        let base = Url::parse(base).map_err(|e| format!("REFERENCE_API_URL: {}", e))?;
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(10))
+            .build()
+            .map_err(|e| e.to_string())?;
+        Ok(HttpReferences {
+            base,
+            token,
+            client,
+        })
+    }
+}
+
+#[async_trait]
+impl ReferenceRepository for HttpReferences {
+    async fn find(&self, reference: &str) -> Result<Option<Order>> {
+        let mut url = self.base.clone();
+        url.path_segments_mut()
+            .map_err(|_| anyhow!("REFERENCE_API_URL can't take a path"))?
+            .pop_if_empty()
+            .push(reference);
+        let mut request = self.client.get(url);
+        if let Some(token) = &self.token {
+            request = request.bearer_auth(token);
+        }
+        let res = request.send().await?;
+        if res.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        let order: OrderResponse = res.error_for_status()?.json().await?;
+        let due = match (order.amount, order.currency) {
+            (Some(amount), Some(code)) => {
+                let currency = Currency::from_code(&code)
+                    .ok_or_else(|| anyhow!("unknown currency '{}'", code))?;
+                Some((currency, amount))
+            }
+            (None, _) => None,
+            (Some(_), None) => return Err(anyhow!("the order's amount has no currency")),
+        };
+        Ok(Some(Order {
+            due,
+            payer: order.payer,
+        }))
+    }
+}
+
+/// The repository `REFERENCE_SOURCE` names: `db` (the default), the
+/// `orders` table, or `http`, the backend at `REFERENCE_API_URL`, sent
+/// `REFERENCE_API_TOKEN` as a bearer token if set.
+pub fn from_env(db: &Db) -> Result<Arc<dyn ReferenceRepository>, String> {
+    match env::var("REFERENCE_SOURCE").as_deref() {
+        Err(_) | Ok("db") => Ok(Arc::new(DbReferences { db: db.clone() })),
+        Ok("http") => {
+            let base = env::var("REFERENCE_API_URL")
+                .map_err(|_| "REFERENCE_SOURCE=http needs REFERENCE_API_URL".to_string())?;
+            let token = env::var("REFERENCE_API_TOKEN").ok();
+            Ok(Arc::new(HttpReferences::new(&base, token)?))
+        }
+        Ok(other) => Err(format!(
+            "REFERENCE_SOURCE must be db or http, not '{}'",
+            other
+        )),
+    }
+}
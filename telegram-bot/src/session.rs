@@ -0,0 +1,167 @@
+//! Where each chat's step of the conversation is kept between messages.
+
+use crate::db::Db;
+use crate::redis::RedisStore;
+use crate::UserState;
+use async_trait::async_trait;
+use futures::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::env;
+use std::io;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use teloxide::dispatching::dialogue::Storage;
+use teloxide::types::ChatId;
+
+const DEFAULT_TTL_MINUTES: u64 = 15;
+
+/// A chat's step and when the chat was last heard from, in seconds since
+/// the epoch so it survives a restart.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Session {
+    pub state: UserState,
+    pub touched: u64,
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+/// A place to keep sessions: this process's memory, or a server every
+/// replica of the bot shares.
+#[async_trait]
+pub trait SessionStore: Send + Sync {
+    async fn load(&self, chat: ChatId) -> io::Result<Option<Session>>;
+    async fn save(&self, chat: ChatId, session: &Session) -> io::Result<()>;
+    async fn remove(&self, chat: ChatId) -> io::Result<()>;
+}
+
+/// Sessions that last as long as the process.
+#[derive(Default)]
+pub struct MemoryStore {
+    sessions: Mutex<HashMap<ChatId, Session>>,
+}
+
+#[async_trait]
+impl SessionStore for MemoryStore {
+    async fn load(&self, chat: ChatId) -> io::Result<Option<Session>> {
+        Ok(self.sessions.lock().unwrap().get(&chat).cloned())
+    }
+
+    async fn save(&self, chat: ChatId, session: &Session) -> io::Result<()> {
+        self.sessions.lock().unwrap().insert(chat, session.clone());
+        Ok(())
+    }
+
+    async fn remove(&self, chat: ChatId) -> io::Result<()> {
+        self.sessions.lock().unwrap().remove(&chat);
+        Ok(())
+    }
+}
+
+/// Keeps the conversations in a `SessionStore`. One that hears nothing
+/// for `ttl` is dropped, and the chat's next message finds
+/// `UserState::Expired`. Chats that aren't paying aren't kept at all.
+pub struct SessionStorage {
+    store: Box<dyn SessionStore>,
+    ttl: Duration,
+}
+
+impl SessionStorage {
+    pub fn new(store: Box<dyn SessionStore>, ttl: Duration) -> Arc<Self> {
+        Arc::new(SessionStorage { store, ttl })
+    }
+
+    /// The store `SESSION_STORE` names: `memory` (the default), `sqlite`,
+    /// in the bot's database, or `redis`, at `REDIS_URL` (default
+    /// `redis://127.0.0.1:6379`).
+    pub fn from_env(db: &Db) -> Result<Arc<Self>, String> {
+        let ttl = ttl_from_env();
+        let store: Box<dyn SessionStore> = match env::var("SESSION_STORE").as_deref() {
+            Err(_) | Ok("memory") => Box::new(MemoryStore::default()),
+            Ok("sqlite") => Box::new(db.clone()),
+            Ok("redis") => {
+                let url =
+                    env::var("REDIS_URL").unwrap_or_else(|_| "redis://127.0.0.1:6379".to_string());
+                // Kept a day past the TTL, so a chat coming back late is
+                // still told its session expired.
+                let keep = ttl + Duration::from_secs(24 * 60 * 60);
+                Box::new(RedisStore::new(&url, keep)?)
+            }
+            Ok(other) => {
+                return Err(format!(
+                    "SESSION_STORE must be memory, sqlite or redis, not '{}'",
+                    other
+                ))
+            }
+        };
+        Ok(SessionStorage::new(store, ttl))
+    }
+}
+
+/// How long a conversation may go without messages, from
+/// `SESSION_TTL_MINUTES` (default 15).
+fn ttl_from_env() -> Duration {
+    let minutes = match env::var("SESSION_TTL_MINUTES") {
+        Ok(value) => value.trim().parse().unwrap_or_else(|_| {
+            eprintln!(
+                "SESSION_TTL_MINUTES is not a whole number, using {}",
+                DEFAULT_TTL_MINUTES
+            );
+            DEFAULT_TTL_MINUTES
+        }),
+        Err(_) => DEFAULT_TTL_MINUTES,
+    };
+    Duration::from_secs(minutes * 60)
+}
+
+impl Storage<UserState> for SessionStorage {
+    type Error = io::Error;
+
+    fn remove_dialogue(
+        self: Arc<Self>,
+        chat_id: ChatId,
+    ) -> BoxFuture<'static, Result<(), Self::Error>> {
+        Box::pin(async move { self.store.remove(chat_id).await })
+    }
+
+    fn update_dialogue(
+        self: Arc<Self>,
+        chat_id: ChatId,
+        state: UserState,
+    ) -> BoxFuture<'static, Result<(), Self::Error>> {
+        Box::pin(async move {
+            if matches!(state, UserState::Idle) {
+                return self.store.remove(chat_id).await;
+            }
+            let session = Session {
+                state,
+                touched: now(),
+            };
+            self.store.save(chat_id, &session).await
+        })
+    }
+
+    /// Every update reads the chat's state first, so this is where
+    /// inactivity is measured and expiry noticed.
+    fn get_dialogue(
+        self: Arc<Self>,
+        chat_id: ChatId,
+    ) -> BoxFuture<'static, Result<Option<UserState>, Self::Error>> {
+        Box::pin(async move {
+            let Some(mut session) = self.store.load(chat_id).await? else {
+                return Ok(None);
+            };
+            if now().saturating_sub(session.touched) > self.ttl.as_secs() {
+                self.store.remove(chat_id).await?;
+                return Ok(Some(UserState::Expired));
+            }
+            session.touched = now();
+            self.store.save(chat_id, &session).await?;
+            Ok(Some(session.state))
+        })
+    }
+}
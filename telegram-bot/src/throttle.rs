@@ -0,0 +1,138 @@
+//! How many payment links each chat may create, so nobody can hammer the
+//! gateway through the bot.
+
+use std::collections::HashMap;
+use std::env;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use teloxide::types::ChatId;
+
+const DEFAULT_LINKS: u32 = 3;
+const DEFAULT_MINUTES: u64 = 10;
+
+/// A token bucket per chat: it holds up to `capacity` links and refills
+/// at `capacity` per `period`, so a chat can make a burst of `capacity`
+/// and then one every `period / capacity`.
+pub struct Throttle {
+    capacity: f64,
+    per_second: f64,
+    buckets: Mutex<HashMap<ChatId, Bucket>>,
+}
+
+struct Bucket {
+    tokens: f64,
+    at: Instant,
+}
+
+impl Throttle {
+    pub fn new(capacity: u32, period: Duration) -> Arc<Throttle> {
+        let capacity = capacity.max(1) as f64;
+        Arc::new(Throttle {
+            capacity,
+            per_second: capacity / period.as_secs_f64().max(1.0),
+            buckets: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// `RATE_LIMIT_LINKS` links (default 3) every `RATE_LIMIT_MINUTES`
+    /// (default 10).
+    pub fn from_env() -> Arc<Throttle> {
+        let links = env_number("RATE_LIMIT_LINKS", DEFAULT_LINKS as u64) as u32;
+        let minutes = env_number("RATE_LIMIT_MINUTES", DEFAULT_MINUTES);
+        Throttle::new(links, Duration::from_secs(minutes * 60))
+    }
+
+    /// The tokens in `bucket` by `now`; no bucket is a full one.
+    fn tokens(&self, bucket: Option<&Bucket>, now: Instant) -> f64 {
+        bucket.map_or(self.capacity, |b| {
+            let refilled = now.duration_since(b.at).as_secs_f64() * self.per_second;
+            (b.tokens + refilled).min(self.capacity)
+        })
+    }
+
+    /// How long until `tokens` grows to a whole link.
+    fn wait(&self, tokens: f64) -> Duration {
+        Duration::from_secs_f64((1.0 - tokens) / self.per_second)
+    }
+
+    /// Whether `chat` may create a link now, or how long until it may.
+    pub fn check(&self, chat: ChatId) -> Result<(), Duration> {
+        let buckets = self.buckets.lock().unwrap();
+        let tokens = self.tokens(buckets.get(&chat), Instant::now());
+        if tokens >= 1.0 {
+            Ok(())
+        } else {
+            Err(self.wait(tokens))
+        }
+    }
+
+    /// Like `check`, but uses up one link if `chat` may create it.
+    pub fn take(&self, chat: ChatId) -> Result<(), Duration> {
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().unwrap();
+        let tokens = self.tokens(buckets.get(&chat), now);
+        if tokens < 1.0 {
+            return Err(self.wait(tokens));
+        }
+        buckets.insert(
+            chat,
+            Bucket {
+                tokens: tokens - 1.0,
+                at: now,
+            },
+        );
+        // A full bucket is the same as none, so those needn't be kept.
+        buckets.retain(|_, b| self.tokens(Some(b), now) < self.capacity);
+        Ok(())
+    }
+}
+
+fn env_number(name: &str, default: u64) -> u64 {
+    match env::var(name) {
+        Ok(value) => match value.trim().parse() {
+            Ok(n) if n > 0 => n,
+            _ => {
+                eprintln!("{} is not a positive whole number, using {}", name, default);
+                default
+            }
+        },
+        Err(_) => default,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_chat_gets_a_burst_then_waits() {
+        let throttle = Throttle::new(2, Duration::from_secs(60));
+        let chat = ChatId(1);
+        assert!(throttle.take(chat).is_ok());
+        assert!(throttle.check(chat).is_ok());
+        assert!(throttle.take(chat).is_ok());
+        let wait = throttle.take(chat).unwrap_err();
+        assert!(wait > Duration::from_secs(29) && wait <= Duration::from_secs(30));
+        assert!(throttle.check(chat).unwrap_err() <= wait);
+        // Other chats have their own bucket.
+        assert!(throttle.take(ChatId(2)).is_ok());
+    }
+
+    #[test]
+    fn buckets_refill_over_the_period() {
+        let throttle = Throttle::new(3, Duration::from_secs(60));
+        let at = Instant::now();
+        let empty = Bucket { tokens: 0.0, at };
+        assert_eq!(throttle.tokens(Some(&empty), at), 0.0);
+        assert_eq!(
+            throttle.tokens(Some(&empty), at + Duration::from_secs(20)),
+            1.0
+        );
+        assert_eq!(
+            throttle.tokens(Some(&empty), at + Duration::from_secs(600)),
+            3.0
+        );
+        assert_eq!(throttle.tokens(None, at), 3.0);
+        assert_eq!(throttle.wait(0.5), Duration::from_secs(10));
+    }
+}
@@ -1,4 +1,85 @@
-use std::{env, fs};
+use clap::{Parser as _, Subcommand, ValueEnum};
+use std::io::{self, Read, Write};
+use std::process::ExitCode;
+use std::{fmt, fs};
+
+/// Half-open range of char offsets into the source.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Span {
+    start: usize,
+    end: usize,
+}
+
+/// A lex, parse or runtime error with a stable code and the source range
+/// it refers to, if any.
+#[derive(Debug)]
+struct Diagnostic {
+    code: &'static str,
+    message: String,
+    span: Option<Span>,
+}
+impl Diagnostic {
+    fn new(code: &'static str, message: impl Into<String>, span: Span) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            span: Some(span),
+        }
+    }
+
+    fn to_json(&self, src: &str) -> String {
+        let span = match self.span {
+            Some(sp) => {
+                let (line, column) = line_col(src, sp.start);
+                format!(
+                    "{{\"start\":{},\"end\":{},\"line\":{line},\"column\":{column}}}",
+                    sp.start, sp.end
+                )
+            }
+            None => "null".to_string(),
+        };
+        format!(
+            "{{\"code\":\"{}\",\"severity\":\"error\",\"message\":\"{}\",\"span\":{span}}}",
+            self.code,
+            json_escape(&self.message)
+        )
+    }
+}
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+/// 1-based line and column of a char offset.
+fn line_col(src: &str, offset: usize) -> (usize, usize) {
+    let mut line = 1;
+    let mut column = 1;
+    for c in src.chars().take(offset) {
+        if c == '\n' {
+            line += 1;
+            column = 1;
+        } else {
+            column += 1;
+        }
+    }
+    (line, column)
+}
+
+fn json_escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out
+}
 
 #[derive(Debug, Clone, PartialEq)]
 enum Token {
@@ -24,10 +105,18 @@ struct Lexer {
 }
 impl Lexer {
     fn new(s: &str) -> Self {
-        Self {
+        let mut lx = Self {
             src: s.chars().collect(),
             i: 0,
+        };
+        // Skip a `#!/usr/bin/env mini_x` line so scripts can be executable.
+        // Offsets keep counting from the start of the file.
+        if lx.src.starts_with(&['#', '!']) {
+            while matches!(lx.peek(), Some(c) if c != '\n') {
+                lx.i += 1;
+            }
         }
+        lx
     }
     fn peek(&self) -> Option<char> {
         self.src.get(self.i).copied()
@@ -43,26 +132,46 @@ impl Lexer {
         }
     }
 
-    fn string(&mut self) -> Result<String, String> {
+    fn string(&mut self) -> Result<String, Diagnostic> {
         // assumes opening quote already consumed
+        let start = self.i - 1;
         let mut out = String::new();
         while let Some(c) = self.bump() {
             match c {
                 '"' => return Ok(out),
                 '\\' => {
-                    let esc = self.bump().ok_or("Unfinished escape in string")?;
+                    let esc = self.bump().ok_or_else(|| {
+                        Diagnostic::new(
+                            "E003",
+                            "Unfinished escape in string",
+                            Span { start, end: self.i },
+                        )
+                    })?;
                     out.push(match esc {
                         'n' => '\n',
                         't' => '\t',
                         '"' => '"',
                         '\\' => '\\',
-                        _ => return Err(format!("Unsupported escape: \\{esc}")),
+                        _ => {
+                            return Err(Diagnostic::new(
+                                "E003",
+                                format!("Unsupported escape: \\{esc}"),
+                                Span {
+                                    start: self.i - 2,
+                                    end: self.i,
+                                },
+                            ));
+                        }
                     });
                 }
                 _ => out.push(c),
             }
         }
-        Err("Unterminated string".into())
+        Err(Diagnostic::new(
+            "E002",
+            "Unterminated string",
+            Span { start, end: self.i },
+        ))
     }
 
     fn ident_or_kw(&mut self, first: char) -> String {
@@ -79,12 +188,13 @@ impl Lexer {
         s
     }
 
-    fn next_token(&mut self) -> Result<Token, String> {
+    fn next_token(&mut self) -> Result<(Token, Span), Diagnostic> {
         self.skip_ws();
+        let start = self.i;
         let Some(c) = self.bump() else {
-            return Ok(Token::Eof);
+            return Ok((Token::Eof, Span { start, end: start }));
         };
-        Ok(match c {
+        let tok = match c {
             '(' => Token::LParen,
             ')' => Token::RParen,
             '{' => Token::LBrace,
@@ -102,31 +212,90 @@ impl Lexer {
                     "string" => Token::Type(s),
                     "int" => Token::Type(s),
                     "bool" => Token::Type(s),
+                    "float" => Token::Type(s),
                     _ => Token::Ident(s),
                 }
             }
-            _ => return Err(format!("Unexpected char: '{c}' at {}", self.i - 1)),
-        })
+            _ => {
+                return Err(Diagnostic::new(
+                    "E001",
+                    format!("Unexpected char: '{c}' at {}", self.i - 1),
+                    Span { start, end: self.i },
+                ));
+            }
+        };
+        Ok((tok, Span { start, end: self.i }))
     }
 
-    fn tokenize(mut self) -> Result<Vec<Token>, String> {
+    fn tokenize(mut self) -> Result<(Vec<Token>, Vec<Span>), Diagnostic> {
         let mut ts = Vec::new();
+        let mut spans = Vec::new();
         loop {
-            let t = self.next_token()?;
+            let (t, span) = self.next_token()?;
             let end = t == Token::Eof;
             ts.push(t);
+            spans.push(span);
             if end {
                 break;
             }
         }
-        Ok(ts)
+        Ok((ts, spans))
+    }
+}
+
+#[derive(Debug)]
+struct Expr {
+    kind: ExprKind,
+    span: Span,
+}
+
+#[derive(Debug)]
+enum ExprKind {
+    Str(String),
+    Var(String),
+    Call { func: String, args: Vec<Expr> },
+}
+impl Expr {
+    /// Names of all variables read by this expression.
+    fn vars(&self, out: &mut Vec<String>) {
+        match &self.kind {
+            ExprKind::Str(_) => {}
+            ExprKind::Var(name) => out.push(name.clone()),
+            ExprKind::Call { args, .. } => args.iter().for_each(|a| a.vars(out)),
+        }
+    }
+
+    /// Whether evaluating this could raise an error: a variable may be
+    /// undefined, and a call may get the wrong arguments or name no builtin.
+    /// Only a string, or a builtin called with one string, is known to be safe.
+    fn can_fail(&self) -> bool {
+        match &self.kind {
+            ExprKind::Str(_) => false,
+            ExprKind::Var(_) => true,
+            ExprKind::Call { func, args } => match args.as_slice() {
+                [arg] if matches!(arg.kind, ExprKind::Str(_)) => {
+                    !matches!(func.as_str(), "parse_int" | "parse_float")
+                }
+                _ => true,
+            },
+        }
     }
 }
 
 #[derive(Debug)]
 enum Stmt {
-    Let { name: String, #[allow(dead_code)] type_annotation: Option<String>, value: String },
-    Print { format: String, args: Vec<String> },
+    Let {
+        name: String,
+        #[allow(dead_code)]
+        type_annotation: Option<String>,
+        value: Expr,
+        span: Span,
+    },
+    Print {
+        format: String,
+        args: Vec<String>,
+        span: Span,
+    },
 }
 
 #[derive(Debug)]
@@ -136,30 +305,46 @@ struct Program {
 
 struct Parser {
     ts: Vec<Token>,
+    spans: Vec<Span>,
     i: usize,
 }
 impl Parser {
-    fn new(ts: Vec<Token>) -> Self {
-        Self { ts, i: 0 }
+    fn new(ts: Vec<Token>, spans: Vec<Span>) -> Self {
+        Self { ts, spans, i: 0 }
     }
     fn peek(&self) -> &Token {
         self.ts.get(self.i).unwrap_or(&Token::Eof)
     }
+    fn span_at(&self, i: usize) -> Span {
+        let last = Span { start: 0, end: 0 };
+        *self.spans.get(i).or(self.spans.last()).unwrap_or(&last)
+    }
+    /// Span of the next token to be consumed.
+    fn peek_span(&self) -> Span {
+        self.span_at(self.i)
+    }
+    /// Span of the most recently consumed token.
+    fn prev_span(&self) -> Span {
+        self.span_at(self.i.saturating_sub(1))
+    }
+    fn error(&self, message: String) -> Diagnostic {
+        Diagnostic::new("E100", message, self.prev_span())
+    }
     fn bump(&mut self) -> &Token {
         let t = self.peek() as *const Token;
         self.i += 1;
         unsafe { &*t }
     }
-    fn expect(&mut self, expected: &Token) -> Result<(), String> {
+    fn expect(&mut self, expected: &Token) -> Result<(), Diagnostic> {
         let t = self.bump().clone();
         if &t == expected {
             Ok(())
         } else {
-            Err(format!("Expected {:?}, found {:?}", expected, t))
+            Err(self.error(format!("Expected {:?}, found {:?}", expected, t)))
         }
     }
 
-    fn parse(&mut self) -> Result<Program, String> {
+    fn parse(&mut self) -> Result<Program, Diagnostic> {
         let mut body = Vec::new();
         while !matches!(self.peek(), Token::RBrace | Token::Eof) {
             body.push(self.parse_stmt()?);
@@ -167,51 +352,90 @@ impl Parser {
         Ok(Program { body })
     }
 
-    fn parse_stmt(&mut self) -> Result<Stmt, String> {
+    fn parse_stmt(&mut self) -> Result<Stmt, Diagnostic> {
         match self.peek() {
             Token::Let => self.parse_let(),
             Token::Print => self.parse_print(),
-            other => Err(format!("Unexpected token in statement: {:?}", other)),
+            other => Err(Diagnostic::new(
+                "E100",
+                format!("Unexpected token in statement: {:?}", other),
+                self.peek_span(),
+            )),
         }
     }
 
-    fn parse_let(&mut self) -> Result<Stmt, String> {
+    fn parse_let(&mut self) -> Result<Stmt, Diagnostic> {
+        let start = self.peek_span().start;
         self.expect(&Token::Let)?;
         let name = match self.bump().clone() {
             Token::Ident(s) => s,
-            t => return Err(format!("Expected identifier after let, got {:?}", t)),
+            t => return Err(self.error(format!("Expected identifier after let, got {:?}", t))),
         };
-        
+
         // Check for optional type annotation
         let type_annotation = if matches!(self.peek(), Token::Colon) {
             self.expect(&Token::Colon)?;
             match self.bump().clone() {
                 Token::Type(s) => Some(s),
-                t => return Err(format!("Expected type after ':', got {:?}", t)),
+                t => return Err(self.error(format!("Expected type after ':', got {:?}", t))),
             }
         } else {
             None
         };
-        
+
         self.expect(&Token::Equal)?;
-        let value = match self.bump().clone() {
-            Token::Str(s) => s,
-            t => return Err(format!("Expected string literal after '=', got {:?}", t)),
-        };
+        let value = self.parse_expr()?;
         self.expect(&Token::Semicolon)?;
-        Ok(Stmt::Let { name, type_annotation, value })
+        let span = Span {
+            start,
+            end: self.prev_span().end,
+        };
+        Ok(Stmt::Let {
+            name,
+            type_annotation,
+            value,
+            span,
+        })
+    }
+
+    fn parse_expr(&mut self) -> Result<Expr, Diagnostic> {
+        let start = self.peek_span().start;
+        let kind = match self.bump().clone() {
+            Token::Str(s) => ExprKind::Str(s),
+            Token::Ident(name) if matches!(self.peek(), Token::LParen) => {
+                self.expect(&Token::LParen)?;
+                let mut args = Vec::new();
+                if !matches!(self.peek(), Token::RParen) {
+                    args.push(self.parse_expr()?);
+                    while matches!(self.peek(), Token::Comma) {
+                        self.expect(&Token::Comma)?;
+                        args.push(self.parse_expr()?);
+                    }
+                }
+                self.expect(&Token::RParen)?;
+                ExprKind::Call { func: name, args }
+            }
+            Token::Ident(name) => ExprKind::Var(name),
+            t => return Err(self.error(format!("Expected expression, got {:?}", t))),
+        };
+        let span = Span {
+            start,
+            end: self.prev_span().end,
+        };
+        Ok(Expr { kind, span })
     }
 
-    fn parse_print(&mut self) -> Result<Stmt, String> {
+    fn parse_print(&mut self) -> Result<Stmt, Diagnostic> {
+        let start = self.peek_span().start;
         self.expect(&Token::Print)?;
         self.expect(&Token::LParen)?;
         let format = match self.bump().clone() {
             Token::Str(s) => s,
             t => {
-                return Err(format!(
+                return Err(self.error(format!(
                     "Expected string literal in print(...), got {:?}",
                     t
-                ));
+                )));
             }
         };
 
@@ -220,20 +444,103 @@ impl Parser {
             self.expect(&Token::Comma)?;
             match self.bump().clone() {
                 Token::Ident(s) => args.push(s),
-                t => return Err(format!("Expected identifier as print arg, got {:?}", t)),
+                t => {
+                    return Err(
+                        self.error(format!("Expected identifier as print arg, got {:?}", t))
+                    );
+                }
             }
         }
 
         self.expect(&Token::RParen)?;
         self.expect(&Token::Semicolon)?;
-        Ok(Stmt::Print { format, args })
+        let span = Span {
+            start,
+            end: self.prev_span().end,
+        };
+        Ok(Stmt::Print { format, args, span })
+    }
+}
+
+/// Builtins that fail on bad input return `Value::Null` rather than aborting,
+/// so programs can check what they got back.
+fn call_builtin(func: &str, args: Vec<Value>) -> Result<Value, (&'static str, String)> {
+    let [arg] = <[Value; 1]>::try_from(args).map_err(|args| {
+        (
+            "E203",
+            format!("{func}: expected 1 argument, got {}", args.len()),
+        )
+    })?;
+    let Value::Str(s) = arg else {
+        return Err(("E204", format!("{func}: expected a string, got {arg}")));
+    };
+    match func {
+        "parse_int" => Ok(s.trim().parse().map_or(Value::Null, Value::Int)),
+        "parse_float" => Ok(s.trim().parse().map_or(Value::Null, Value::Float)),
+        _ => Err(("E202", format!("Unknown function: {func}"))),
     }
 }
 
-use std::collections::HashMap;
+#[derive(Debug, Default)]
+struct OptStats {
+    stmts_before: usize,
+    stmts_after: usize,
+    unused_lets: usize,
+}
+
+/// Dead-code elimination. Walks the program backwards tracking which names
+/// are still read later on; a `let` whose name is not live at that point is
+/// never observed and can be dropped, unless evaluating its value could
+/// fail, since the error is the one thing a reader would see.
+fn eliminate_dead_code(prog: Program, stats: &mut OptStats) -> Program {
+    stats.stmts_before = prog.body.len();
+    let mut live: HashSet<String> = HashSet::new();
+    let mut body = Vec::with_capacity(prog.body.len());
+    for s in prog.body.into_iter().rev() {
+        match &s {
+            Stmt::Let { name, value, .. } => {
+                // Builtins have no side effects, so an unused binding can go
+                // as long as its value can't raise an error.
+                if !live.remove(name) && !value.can_fail() {
+                    stats.unused_lets += 1;
+                    continue;
+                }
+                let mut reads = Vec::new();
+                value.vars(&mut reads);
+                live.extend(reads);
+            }
+            Stmt::Print { args, .. } => live.extend(args.iter().cloned()),
+        }
+        body.push(s);
+    }
+    body.reverse();
+    stats.stmts_after = body.len();
+    Program { body }
+}
+
+use std::collections::{HashMap, HashSet};
+
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    Str(String),
+    Int(i64),
+    Float(f64),
+    /// Result of a builtin that could not produce a value, e.g. `parse_int("x")`.
+    Null,
+}
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Str(s) => f.write_str(s),
+            Value::Int(n) => write!(f, "{n}"),
+            Value::Float(x) => write!(f, "{x}"),
+            Value::Null => f.write_str("null"),
+        }
+    }
+}
 
 struct Interpreter {
-    env: HashMap<String, String>,
+    env: HashMap<String, Value>,
 }
 impl Interpreter {
     fn new() -> Self {
@@ -242,21 +549,49 @@ impl Interpreter {
         }
     }
 
-    fn run(&mut self, prog: Program) -> Result<(), String> {
+    fn run(&mut self, prog: Program) -> Result<(), Diagnostic> {
         for s in prog.body {
             match s {
-                Stmt::Let { name, type_annotation: _, value } => {
+                Stmt::Let {
+                    name,
+                    type_annotation: _,
+                    value,
+                    span,
+                } => {
                     // For now, we'll just store the value without type checking
                     // In a more complete implementation, we would validate the type
+                    let value = self
+                        .eval(&value)
+                        .map_err(|(code, message)| Diagnostic::new(code, message, span))?;
                     self.env.insert(name, value);
                 }
-                Stmt::Print { format, args } => self.exec_print(format, args)?,
+                Stmt::Print { format, args, span } => self
+                    .exec_print(format, args)
+                    .map_err(|(code, message)| Diagnostic::new(code, message, span))?,
             }
         }
         Ok(())
     }
 
-    fn exec_print(&self, format: String, args: Vec<String>) -> Result<(), String> {
+    fn eval(&self, expr: &Expr) -> Result<Value, (&'static str, String)> {
+        match &expr.kind {
+            ExprKind::Str(s) => Ok(Value::Str(s.clone())),
+            ExprKind::Var(name) => self
+                .env
+                .get(name)
+                .cloned()
+                .ok_or_else(|| ("E200", format!("Undefined variable: {name}"))),
+            ExprKind::Call { func, args } => {
+                let args = args
+                    .iter()
+                    .map(|a| self.eval(a))
+                    .collect::<Result<Vec<_>, _>>()?;
+                call_builtin(func, args)
+            }
+        }
+    }
+
+    fn exec_print(&self, format: String, args: Vec<String>) -> Result<(), (&'static str, String)> {
         let mut out = String::new();
         let mut fmt = format.as_str();
         let mut remaining_args = args.iter();
@@ -265,14 +600,15 @@ impl Interpreter {
             match fmt.find("{}") {
                 Some(pos) => {
                     out.push_str(&fmt[..pos]);
-                    let name = remaining_args
-                        .next()
-                        .ok_or_else(|| "print: missing arguments for placeholders".to_string())?;
+                    let name = remaining_args.next().ok_or((
+                        "E201",
+                        "print: missing arguments for placeholders".to_string(),
+                    ))?;
                     let val = self
                         .env
                         .get(name)
-                        .ok_or_else(|| format!("Undefined variable: {name}"))?;
-                    out.push_str(val);
+                        .ok_or_else(|| ("E200", format!("Undefined variable: {name}")))?;
+                    out.push_str(&val.to_string());
                     fmt = &fmt[pos + 2..];
                 }
                 None => {
@@ -283,7 +619,10 @@ impl Interpreter {
         }
 
         if remaining_args.next().is_some() {
-            return Err("print: too many arguments for placeholders".to_string());
+            return Err((
+                "E201",
+                "print: too many arguments for placeholders".to_string(),
+            ));
         }
 
         println!("{out}");
@@ -291,18 +630,397 @@ impl Interpreter {
     }
 }
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let path = env::args().nth(1).ok_or("usage: mini_x <file.x>")?;
-    let code = fs::read_to_string(&path)?;
-    let tokens = Lexer::new(&code)
-        .tokenize()
-        .map_err(|e| format!("Lex error: {e}"))?;
-    println!("Tokens: {:?}", tokens);
-    let program = Parser::new(tokens)
+/// Canonical source text for a program, one statement per line, after the
+/// `#!` line of `src` if it has one.
+fn format_program(prog: &Program, src: &str) -> String {
+    let mut out = String::new();
+    if src.starts_with("#!") {
+        out.push_str(src.lines().next().unwrap_or_default().trim_end());
+        out.push('\n');
+    }
+    for s in &prog.body {
+        match s {
+            Stmt::Let {
+                name,
+                type_annotation,
+                value,
+                ..
+            } => {
+                out.push_str("let ");
+                out.push_str(name);
+                if let Some(ty) = type_annotation {
+                    out.push_str(": ");
+                    out.push_str(ty);
+                }
+                out.push_str(&format!(" = {};\n", format_expr(value)));
+            }
+            Stmt::Print { format, args, .. } => {
+                out.push_str("print(");
+                out.push_str(&quote(format));
+                for a in args {
+                    out.push_str(", ");
+                    out.push_str(a);
+                }
+                out.push_str(");\n");
+            }
+        }
+    }
+    out
+}
+
+fn format_expr(expr: &Expr) -> String {
+    match &expr.kind {
+        ExprKind::Str(s) => quote(s),
+        ExprKind::Var(name) => name.clone(),
+        ExprKind::Call { func, args } => {
+            let args: Vec<String> = args.iter().map(format_expr).collect();
+            format!("{func}({})", args.join(", "))
+        }
+    }
+}
+
+/// Renders the parse tree as an indented diagram for learners: one node per
+/// line with its kind, source range (`line:col-line:col`) and literal values.
+fn pretty_ast(prog: &Program, src: &str) -> String {
+    let range = |sp: Span| {
+        let (l1, c1) = line_col(src, sp.start);
+        let (l2, c2) = line_col(src, sp.end);
+        format!("{l1}:{c1}-{l2}:{c2}")
+    };
+    let mut out = String::from("Program\n");
+    let n = prog.body.len();
+    for (i, s) in prog.body.iter().enumerate() {
+        let last = i + 1 == n;
+        let branch = if last { "└── " } else { "├── " };
+        let indent = if last { "    " } else { "│   " };
+        match s {
+            Stmt::Let {
+                name,
+                type_annotation,
+                value,
+                span,
+            } => {
+                let ty = type_annotation
+                    .as_deref()
+                    .map(|t| format!(" : {t}"))
+                    .unwrap_or_default();
+                out.push_str(&format!("{branch}Let {name}{ty}  @{}\n", range(*span)));
+                pretty_expr(value, indent, true, &range, &mut out);
+            }
+            Stmt::Print { format, args, span } => {
+                out.push_str(&format!("{branch}Print  @{}\n", range(*span)));
+                let n_args = args.len();
+                let b = if n_args == 0 {
+                    "└── "
+                } else {
+                    "├── "
+                };
+                out.push_str(&format!("{indent}{b}Format {}\n", quote(format)));
+                for (j, a) in args.iter().enumerate() {
+                    let b = if j + 1 == n_args {
+                        "└── "
+                    } else {
+                        "├── "
+                    };
+                    out.push_str(&format!("{indent}{b}Arg {a}\n"));
+                }
+            }
+        }
+    }
+    out
+}
+
+fn pretty_expr(
+    expr: &Expr,
+    indent: &str,
+    last: bool,
+    range: &dyn Fn(Span) -> String,
+    out: &mut String,
+) {
+    let branch = if last { "└── " } else { "├── " };
+    let at = range(expr.span);
+    match &expr.kind {
+        ExprKind::Str(s) => out.push_str(&format!("{indent}{branch}Str {}  @{at}\n", quote(s))),
+        ExprKind::Var(name) => out.push_str(&format!("{indent}{branch}Var {name}  @{at}\n")),
+        ExprKind::Call { func, args } => {
+            out.push_str(&format!("{indent}{branch}Call {func}  @{at}\n"));
+            let child = format!("{indent}{}", if last { "    " } else { "│   " });
+            for (j, a) in args.iter().enumerate() {
+                pretty_expr(a, &child, j + 1 == args.len(), range, out);
+            }
+        }
+    }
+}
+
+/// Inverse of `Lexer::string`.
+fn quote(s: &str) -> String {
+    let mut out = String::from('"');
+    for c in s.chars() {
+        match c {
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[derive(clap::Parser)]
+#[command(
+    name = "mini_x",
+    version,
+    about = "Interpreter for the .x toy language"
+)]
+struct Cli {
+    /// How lex/parse/runtime errors are reported
+    #[arg(long, value_enum, global = true, default_value_t = ErrorFormat::Text)]
+    error_format: ErrorFormat,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum ErrorFormat {
+    Text,
+    Json,
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum DumpAst {
+    None,
+    /// Token list and `{:?}` of the program
+    Debug,
+    /// Indented tree with spans, for reading
+    Pretty,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Lex, parse and run a program
+    Run {
+        /// Source file, or `-` to read from stdin
+        path: String,
+        /// How to print the tokens/AST before running
+        #[arg(long, value_enum, default_value_t = DumpAst::Debug)]
+        dump_ast: DumpAst,
+        /// Run the optimizer before executing
+        #[arg(long)]
+        opt: bool,
+        /// Print optimizer statistics
+        #[arg(short, long)]
+        verbose: bool,
+    },
+    /// Lex and parse a program without running it
+    Check {
+        /// Source file, or `-` to read from stdin
+        path: String,
+        /// Print the AST after a successful parse
+        #[arg(long, value_enum, default_value_t = DumpAst::None)]
+        dump_ast: DumpAst,
+    },
+    /// Print a program in canonical formatting
+    Fmt {
+        /// Source file, or `-` to read from stdin
+        path: String,
+        /// Rewrite the file in place instead of printing it
+        #[arg(short, long)]
+        write: bool,
+    },
+    /// Read and run statements interactively
+    Repl,
+}
+
+/// Exit codes: 1 for errors in the program itself, 2 for bad usage (clap),
+/// 3 when the source could not be read or written.
+const EXIT_PROGRAM_ERROR: u8 = 1;
+const EXIT_IO_ERROR: u8 = 3;
+
+fn read_source(path: &str) -> io::Result<String> {
+    if path == "-" {
+        let mut code = String::new();
+        io::stdin().read_to_string(&mut code)?;
+        Ok(code)
+    } else {
+        fs::read_to_string(path)
+    }
+}
+
+/// Lexes and parses `code`, tagging a failure with the stage it came from.
+fn parse_source(code: &str, dump: DumpAst) -> Result<Program, (&'static str, Diagnostic)> {
+    let (tokens, spans) = Lexer::new(code).tokenize().map_err(|e| ("Lex", e))?;
+    if dump == DumpAst::Debug {
+        println!("Tokens: {:?}", tokens);
+    }
+    let program = Parser::new(tokens, spans)
         .parse()
-        .map_err(|e| format!("Parse error: {e}"))?;
-    println!("Program: {:?}", program);
+        .map_err(|e| ("Parse", e))?;
+    match dump {
+        DumpAst::None => {}
+        DumpAst::Debug => println!("Program: {:?}", program),
+        DumpAst::Pretty => print!("{}", pretty_ast(&program, code)),
+    }
+    Ok(program)
+}
+
+fn report(format: ErrorFormat, code: &str, stage: &str, d: Diagnostic) {
+    match format {
+        ErrorFormat::Json => eprintln!("{}", d.to_json(code)),
+        ErrorFormat::Text => eprintln!("{stage} error: {d}"),
+    }
+}
+
+fn repl(format: ErrorFormat) -> io::Result<()> {
     let mut vm = Interpreter::new();
-    vm.run(program).map_err(|e| format!("Runtime error: {e}"))?;
-    Ok(())
+    let mut buf = String::new();
+    let stdin = io::stdin();
+    loop {
+        print!("{}", if buf.is_empty() { "> " } else { ". " });
+        io::stdout().flush()?;
+        let mut line = String::new();
+        if stdin.read_line(&mut line)? == 0 {
+            println!();
+            return Ok(());
+        }
+        if buf.is_empty() && matches!(line.trim(), ":q" | ":quit") {
+            return Ok(());
+        }
+        buf.push_str(&line);
+        // Keep reading until the statement is terminated.
+        if !buf.trim_end().ends_with(';') {
+            continue;
+        }
+        let result = parse_source(&buf, DumpAst::None)
+            .and_then(|prog| vm.run(prog).map_err(|e| ("Runtime", e)));
+        if let Err((stage, d)) = result {
+            report(format, &buf, stage, d);
+        }
+        buf.clear();
+    }
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let path = match &cli.command {
+        Command::Run { path, .. } | Command::Check { path, .. } | Command::Fmt { path, .. } => {
+            path.clone()
+        }
+        Command::Repl => {
+            return match repl(cli.error_format) {
+                Ok(()) => ExitCode::SUCCESS,
+                Err(e) => {
+                    eprintln!("error: {e}");
+                    ExitCode::from(EXIT_IO_ERROR)
+                }
+            };
+        }
+    };
+    let code = match read_source(&path) {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("error: cannot read {path}: {e}");
+            return ExitCode::from(EXIT_IO_ERROR);
+        }
+    };
+    let dump = match cli.command {
+        Command::Run { dump_ast, .. } | Command::Check { dump_ast, .. } => dump_ast,
+        _ => DumpAst::None,
+    };
+    let program = match parse_source(&code, dump) {
+        Ok(program) => program,
+        Err((stage, d)) => {
+            report(cli.error_format, &code, stage, d);
+            return ExitCode::from(EXIT_PROGRAM_ERROR);
+        }
+    };
+
+    match cli.command {
+        Command::Run { opt, verbose, .. } => {
+            let program = if opt {
+                let mut stats = OptStats::default();
+                let program = eliminate_dead_code(program, &mut stats);
+                if verbose {
+                    println!(
+                        "Optimizer: removed {} unused let binding(s), {} -> {} statements",
+                        stats.unused_lets, stats.stmts_before, stats.stmts_after
+                    );
+                }
+                program
+            } else {
+                program
+            };
+            let mut vm = Interpreter::new();
+            if let Err(e) = vm.run(program) {
+                report(cli.error_format, &code, "Runtime", e);
+                return ExitCode::from(EXIT_PROGRAM_ERROR);
+            }
+        }
+        Command::Check { .. } => println!("{path}: ok ({} statements)", program.body.len()),
+        Command::Fmt { write, .. } => {
+            let formatted = format_program(&program, &code);
+            if !write || path == "-" {
+                print!("{formatted}");
+            } else if let Err(e) = fs::write(&path, formatted) {
+                eprintln!("error: cannot write {path}: {e}");
+                return ExitCode::from(EXIT_IO_ERROR);
+            }
+        }
+        Command::Repl => unreachable!("handled above"),
+    }
+    ExitCode::SUCCESS
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(src: &str) -> Program {
+        let (tokens, spans) = Lexer::new(src).tokenize().unwrap();
+        Parser::new(tokens, spans).parse().unwrap()
+    }
+
+    fn optimize(src: &str) -> (Program, OptStats) {
+        let mut stats = OptStats::default();
+        let program = eliminate_dead_code(parse(src), &mut stats);
+        (program, stats)
+    }
+
+    #[test]
+    fn unused_lets_that_cannot_fail_are_removed() {
+        let (program, stats) =
+            optimize("let a = \"x\"; let b = parse_int(\"7\"); let c = \"y\"; print(\"{}\", c);");
+        assert_eq!(stats.unused_lets, 2);
+        assert_eq!(program.body.len(), 2);
+    }
+
+    #[test]
+    fn unused_lets_that_fail_still_fail_when_optimized() {
+        for (src, code) in [
+            ("let a = missing; print(\"ok\");", "E200"),
+            ("let a = nope(\"1\"); print(\"ok\");", "E202"),
+            ("let a = parse_int(\"1\", \"2\"); print(\"ok\");", "E203"),
+            (
+                "let n = parse_int(\"1\"); let a = parse_int(n); print(\"ok\");",
+                "E204",
+            ),
+        ] {
+            let (program, stats) = optimize(src);
+            assert_eq!(stats.unused_lets, 0, "{src}");
+            let err = Interpreter::new().run(program).unwrap_err();
+            assert_eq!(err.code, code, "{src}");
+        }
+    }
+
+    #[test]
+    fn fmt_keeps_the_shebang() {
+        let src = "#!/usr/bin/env mini_x\nlet  a=\"x\" ;print(\"{}\",a);\n";
+        assert_eq!(
+            format_program(&parse(src), src),
+            "#!/usr/bin/env mini_x\nlet a = \"x\";\nprint(\"{}\", a);\n"
+        );
+        let src = "print(\"hi\");";
+        assert_eq!(format_program(&parse(src), src), "print(\"hi\");\n");
+    }
 }